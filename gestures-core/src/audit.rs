@@ -0,0 +1,206 @@
+//! Keeping a record of every gesture that was recognized, and of what the daemon did about it.
+//!
+//! When an action runs that nobody meant to run, the audit log says which gesture set it off, on
+//! which device, and which binding it came from. Each gesture is logged with the time (in
+//! milliseconds) of the frame that finished it, which is the same clock as the one in trace files,
+//! so a report can be matched up with a trace that was recorded at the same time. The log is off
+//! unless the config file has an `[audit]` section:
+//!
+//! ```toml
+//! [audit]
+//! # Where the log goes. A relative path is relative to the data directory (usually
+//! # ~/.local/share/gestures). The default is "audit.log".
+//! path = "audit.log"
+//! # Once the log is bigger than this many kilobytes, it is moved to audit.log.1 (and the older
+//! # ones to audit.log.2, and so on). The default is 1024.
+//! max-size-kb = 1024
+//! # How many of the old logs to keep. The default is 3.
+//! keep = 3
+//! ```
+
+use app_dirs::{app_root, AppDataType};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use APP_INFO;
+
+/// An `[audit]` section of the config file.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct AuditParsed {
+    path: Option<String>,
+    #[serde(rename = "max-size-kb")]
+    max_size_kb: Option<u64>,
+    keep: Option<u32>,
+}
+
+impl AuditParsed {
+    pub fn to_audit(&self) -> Result<AuditConfig, String> {
+        let max_size_kb = self.max_size_kb.unwrap_or(1024);
+        if max_size_kb == 0 {
+            return Err("audit.max-size-kb must be at least 1".to_owned());
+        }
+        Ok(AuditConfig {
+            path: PathBuf::from(self.path.as_ref().map_or("audit.log", |p| p.as_str())),
+            max_bytes: max_size_kb * 1024,
+            keep: self.keep.unwrap_or(3),
+        })
+    }
+}
+
+/// Where the audit log goes, and when it is rotated.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuditConfig {
+    /// The log file. If this is relative, it is relative to the data directory.
+    pub path: PathBuf,
+    /// The log is rotated before it gets bigger than this.
+    pub max_bytes: u64,
+    /// How many rotated logs are kept.
+    pub keep: u32,
+}
+
+/// An open audit log.
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: u32,
+    file: File,
+    // How big the file is, as far as we know.
+    size: u64,
+}
+
+// Returns the path of the `n`th old log.
+fn rotated(path: &Path, n: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+fn open_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+// Turns a number of days since 1970-01-01 into a year, month and day. This is Howard Hinnant's
+// `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = (if z >= 0 { z } else { z - 146_096 }) / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// Formats a time as UTC, like "2017-11-05T14:03:27.250Z".
+fn timestamp(t: SystemTime) -> String {
+    let since = t.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since.as_secs() as i64;
+    let (year, month, day) = civil_from_days(secs / 86_400);
+    let rem = secs % 86_400;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z", year, month, day, rem / 3600,
+            rem / 60 % 60, rem % 60, since.subsec_nanos() / 1_000_000)
+}
+
+impl AuditLog {
+    /// Opens the log that `config` describes, creating it if it isn't there.
+    pub fn open(config: &AuditConfig) -> Result<AuditLog, String> {
+        let path = if config.path.is_absolute() {
+            config.path.clone()
+        } else {
+            let dir = app_root(AppDataType::UserData, &APP_INFO)
+                .map_err(|e| format!("couldn't find the data directory: {}", e))?;
+            dir.join(&config.path)
+        };
+        let file = open_file(&path)
+            .map_err(|e| format!("couldn't open the audit log {}: {}", path.display(), e))?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(AuditLog {
+            path: path,
+            max_bytes: config.max_bytes,
+            keep: config.keep,
+            file: file,
+            size: size,
+        })
+    }
+
+    // Moves the log out of the way, deleting the oldest one, and starts a new one.
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                let from = rotated(&self.path, n);
+                if from.exists() {
+                    fs::rename(&from, rotated(&self.path, n + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated(&self.path, 1))?;
+        }
+        self.file = open_file(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    /// Adds a line to the log, after the current time.
+    ///
+    /// Failing to write to the log isn't worth stopping for, so errors are only logged.
+    pub fn record(&mut self, line: &str) {
+        let line = format!("{} {}\n", timestamp(SystemTime::now()), line);
+        if self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            if let Err(e) = self.rotate() {
+                warn!("couldn't rotate the audit log {}: {}", self.path.display(), e);
+            }
+        }
+        match self.file.write_all(line.as_bytes()) {
+            Ok(()) => self.size += line.len() as u64,
+            Err(e) => warn!("couldn't write to the audit log {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libc;
+    use std::env;
+    use std::io::Read;
+    use std::time::Duration;
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let t = UNIX_EPOCH + Duration::from_millis(1_509_890_607_250);
+        assert_eq!(timestamp(t), "2017-11-05T14:03:27.250Z");
+        // A leap day.
+        let t = UNIX_EPOCH + Duration::from_secs(951_782_400);
+        assert_eq!(timestamp(t), "2000-02-29T00:00:00.000Z");
+    }
+
+    #[test]
+    fn rotation() {
+        let dir = env::temp_dir().join(format!("gestures-audit-{}", unsafe { libc::getpid() }));
+        fs::create_dir_all(&dir).unwrap();
+        let config = AuditConfig { path: dir.join("audit.log"), max_bytes: 100, keep: 2 };
+        let mut log = AuditLog::open(&config).unwrap();
+        // Each line is 75 bytes long, so each one ends up in its own file.
+        for c in &["a", "b", "c", "d"] {
+            log.record(&c.repeat(49));
+        }
+        let read = |p: PathBuf| {
+            let mut s = String::new();
+            File::open(p).unwrap().read_to_string(&mut s).unwrap();
+            s
+        };
+        assert!(read(dir.join("audit.log")).contains(&"d".repeat(49)[..]));
+        assert!(read(dir.join("audit.log.1")).contains(&"c".repeat(49)[..]));
+        assert!(read(dir.join("audit.log.2")).contains(&"b".repeat(49)[..]));
+        assert!(!dir.join("audit.log.3").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
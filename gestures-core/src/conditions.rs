@@ -0,0 +1,160 @@
+use input::event::Event;
+use input::event::switch::{Switch, SwitchEvent, SwitchEventTrait, SwitchState};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+use std::io::Read;
+
+/// The names of all the conditions that can be used in a binding.
+pub const KNOWN: &'static [&'static str] = &["tablet-mode", "external-monitor"];
+
+/// A condition that needs to hold in order for a binding to fire.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Condition {
+    pub name: String,
+    /// If true, the binding fires only if the condition *doesn't* hold.
+    pub negated: bool,
+}
+
+impl Condition {
+    /// Parses a condition, like `"tablet-mode"` or `"!external-monitor"`.
+    pub fn parse(s: &str) -> Result<Condition, String> {
+        let s = s.trim();
+        let (name, negated) = if s.starts_with('!') {
+            (s[1..].trim(), true)
+        } else {
+            (s, false)
+        };
+
+        if KNOWN.iter().any(|&k| k == name) {
+            Ok(Condition {
+                name: name.to_owned(),
+                negated: negated,
+            })
+        } else {
+            Err(format!("unknown condition {:?}, expected one of {:?}", name, KNOWN))
+        }
+    }
+
+    /// Parses a comma-separated list of conditions, all of which need to hold.
+    pub fn parse_all(s: &str) -> Result<Vec<Condition>, String> {
+        s.split(',').map(Condition::parse).collect()
+    }
+}
+
+/// Something that knows whether a condition holds.
+pub trait Provider: Debug {
+    /// Updates the provider's state in response to an input event.
+    fn handle_event(&mut self, _ev: &Event) {}
+
+    /// Does the condition currently hold?
+    fn holds(&self) -> bool;
+}
+
+/// Knows whether the device is in tablet mode, by watching for libinput's tablet-mode switch.
+#[derive(Debug, Default)]
+pub struct TabletMode {
+    on: bool,
+}
+
+impl Provider for TabletMode {
+    fn handle_event(&mut self, ev: &Event) {
+        if let &Event::Switch(SwitchEvent::Toggle(ref ev)) = ev {
+            if ev.switch() == Switch::TabletMode {
+                self.on = ev.switch_state() == SwitchState::On;
+                debug!("tablet mode is now {}", self.on);
+            }
+        }
+    }
+
+    fn holds(&self) -> bool {
+        self.on
+    }
+}
+
+/// Knows whether an external monitor is connected, by looking at the DRM connectors in sysfs.
+#[derive(Debug, Default)]
+pub struct ExternalMonitor;
+
+impl ExternalMonitor {
+    /// Is this the name of a connector for a built-in panel?
+    fn is_internal(connector: &str) -> bool {
+        ["eDP", "LVDS", "DSI"].iter().any(|p| connector.contains(p))
+    }
+}
+
+impl Provider for ExternalMonitor {
+    fn holds(&self) -> bool {
+        let entries = match fs::read_dir("/sys/class/drm") {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("couldn't read DRM connectors: {}", e);
+                return false;
+            }
+        };
+
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Connectors are named like "card0-HDMI-A-1"; skip the cards themselves.
+            if !name.contains('-') || ExternalMonitor::is_internal(&name) {
+                continue;
+            }
+
+            let mut status = String::new();
+            let mut path = entry.path();
+            path.push("status");
+            if let Ok(mut f) = fs::File::open(&path) {
+                if f.read_to_string(&mut status).is_ok() && status.trim() == "connected" {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Keeps track of all the conditions that bindings can depend on.
+#[derive(Debug)]
+pub struct Environment {
+    providers: HashMap<String, Box<Provider>>,
+}
+
+impl Environment {
+    /// Creates an `Environment` with all the built-in providers.
+    pub fn new() -> Environment {
+        let mut ret = Environment {
+            providers: HashMap::new(),
+        };
+        ret.register("tablet-mode", TabletMode::default());
+        ret.register("external-monitor", ExternalMonitor);
+        ret
+    }
+
+    /// Adds a provider for the condition called `name`, replacing any previous one.
+    pub fn register<P: Provider + 'static>(&mut self, name: &str, provider: P) {
+        self.providers.insert(name.to_owned(), Box::new(provider));
+    }
+
+    /// Lets all the providers know about an input event.
+    pub fn handle_event(&mut self, ev: &Event) {
+        for p in self.providers.values_mut() {
+            p.handle_event(ev);
+        }
+    }
+
+    /// Does the condition hold?
+    pub fn holds(&self, cond: &Condition) -> bool {
+        match self.providers.get(&cond.name) {
+            Some(p) => p.holds() != cond.negated,
+            None => {
+                warn!("no provider for condition {:?}", cond.name);
+                false
+            }
+        }
+    }
+
+    /// Do all of the conditions hold?
+    pub fn holds_all(&self, conds: &[Condition]) -> bool {
+        conds.iter().all(|c| self.holds(c))
+    }
+}
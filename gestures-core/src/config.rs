@@ -0,0 +1,2284 @@
+use app_dirs::{app_root, AppDataType};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::time::Duration;
+use toml;
+
+use { APP_INFO, Direction, Gesture, TouchGesture };
+use audit::{AuditConfig, AuditParsed};
+use conditions::{Condition, Environment};
+use context::{PauseConfig, PauseParsed};
+use custom::{CustomGesture, CustomName, CustomParsed};
+use desktop::{self, Desktop, Side, Workspace};
+use inject::{self, Backend, KeyCombo};
+use keyboard::Modifiers;
+use levels::{self, Level};
+use libgestures::edge::{Edge, EdgeZones};
+use libgestures::frame::Overflow;
+use libgestures::geom::{DirectionThresholds, Point, ScreenPoint, UAngle};
+use libgestures::gestures::{Fire, OneFinger, Path};
+use libgestures::manager::Rearm;
+use libgestures::params::Params;
+use mouse;
+use notify;
+use plugin::{PluginConfig, PluginParsed};
+use profile;
+use sandbox::{Sandbox, SandboxParsed};
+use session::{SessionConfig, SessionParsed};
+
+fn parse_swipe(mut s: &[&str]) -> Option<Gesture> {
+    if s.is_empty() {
+        return None;
+    }
+
+    let mut num_fingers = 3;
+    if let Ok(n) = s[0].parse::<u8>() {
+        num_fingers = n;
+        s = &s[1..];
+    }
+
+    if s.len() != 1 {
+        return None;
+    }
+    let direction = s[0].parse::<Direction>().ok()?;
+    Some(Gesture::Touch(TouchGesture::Swipe { num_fingers, direction }))
+}
+
+/// The most fingers that a gesture can be made with. This is also the most touches that the daemon
+/// keeps track of on a touchscreen that it grabs.
+pub const MAX_FINGERS: u8 = 16;
+
+/// What the gestures in bindings look like, for error messages.
+pub const GESTURE_FORMS: &'static str =
+    "gestures look like \"swipe [FINGERS] DIRECTION\", \"spread [FINGERS]\", \"grab [FINGERS]\", \
+     \"shake [FINGERS]\", \"tap [FINGERS]\", \"hold [FINGERS]\", \"path [FINGERS] DIRECTION...\", \
+     \"pinch [FINGERS] in|out\", \"rotate [FINGERS] clockwise|counterclockwise\", \
+     \"edge EDGE\", \"rocker BUTTON BUTTON\", \"wheel DIRECTION\", \"custom NAME\" or \
+     \"plugin NAME\"";
+
+// Parses "[fingers] direction", where the number of fingers defaults to `default`.
+fn parse_fingers_and<D: FromStr>(mut s: &[&str], default: u8) -> Option<(u8, D)> {
+    let mut num_fingers = default;
+    if let Some(n) = s.first().and_then(|w| w.parse::<u8>().ok()) {
+        num_fingers = n;
+        s = &s[1..];
+    }
+
+    if s.len() != 1 {
+        return None;
+    }
+    s[0].parse::<D>().ok().map(|d| (num_fingers, d))
+}
+
+// Parses "path [fingers] direction direction...", where the number of fingers defaults to one.
+fn parse_path(mut s: &[&str]) -> Option<Gesture> {
+    let mut num_fingers = 1;
+    if let Some(n) = s.first().and_then(|w| w.parse::<u8>().ok()) {
+        num_fingers = n;
+        s = &s[1..];
+    }
+
+    let directions = s.iter()
+        .map(|w| w.parse::<Direction>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let path = Path::new(&directions)?;
+    Some(Gesture::Touch(TouchGesture::Path { num_fingers, path }))
+}
+
+// Parses an optional number of fingers, which should be the only thing in `s`.
+fn parse_num_fingers(s: &[&str], default: u8) -> Option<u8> {
+    match s.len() {
+        0 => Some(default),
+        1 => s[0].parse::<u8>().ok(),
+        _ => None,
+    }
+}
+
+/// Parses a gesture, written in the same way as in a binding (like "swipe 3 up").
+pub fn parse_gesture(s: &str) -> Option<Gesture> {
+    let parts = s.split_whitespace().collect::<Vec<_>>();
+    match *parts.first()? {
+        "swipe" => {
+            parse_swipe(&parts[1..])
+        },
+        // "rocker left right" means holding the left button and clicking the right one.
+        "rocker" if parts.len() == 3 => {
+            let held = mouse::Button::parse(parts[1])?;
+            let clicked = mouse::Button::parse(parts[2])?;
+            if held == clicked {
+                return None;
+            }
+            Some(Gesture::Rocker { held, clicked })
+        },
+        "spread" => {
+            parse_num_fingers(&parts[1..], 5)
+                .map(|num_fingers| Gesture::Touch(TouchGesture::Spread { num_fingers }))
+        },
+        "grab" => {
+            parse_num_fingers(&parts[1..], 5)
+                .map(|num_fingers| Gesture::Touch(TouchGesture::Grab { num_fingers }))
+        },
+        "path" => {
+            parse_path(&parts[1..])
+        },
+        "shake" => {
+            parse_num_fingers(&parts[1..], 3)
+                .map(|num_fingers| Gesture::Touch(TouchGesture::Shake { num_fingers }))
+        },
+        "tap" => {
+            parse_num_fingers(&parts[1..], 1)
+                .map(|num_fingers| Gesture::Touch(TouchGesture::Tap { num_fingers }))
+        },
+        "hold" => {
+            parse_num_fingers(&parts[1..], 1)
+                .map(|num_fingers| Gesture::Touch(TouchGesture::Hold { num_fingers }))
+        },
+        "pinch" => {
+            parse_fingers_and(&parts[1..], 2).map(|(num_fingers, direction)| {
+                Gesture::Touch(TouchGesture::Pinch { num_fingers, direction })
+            })
+        },
+        "rotate" => {
+            parse_fingers_and(&parts[1..], 2).map(|(num_fingers, direction)| {
+                Gesture::Touch(TouchGesture::Rotate { num_fingers, direction })
+            })
+        },
+        "edge" if parts.len() == 2 => {
+            parts[1].parse::<Edge>().ok()
+                .map(|edge| Gesture::Touch(TouchGesture::EdgeSwipe { edge }))
+        },
+        "wheel" if parts.len() == 2 => {
+            parts[1].parse::<Direction>().ok().map(|direction| Gesture::Wheel { direction })
+        },
+        "custom" if parts.len() == 2 => {
+            CustomName::new(parts[1]).map(Gesture::Custom)
+        },
+        "plugin" if parts.len() == 2 => {
+            CustomName::new(parts[1]).map(Gesture::Plugin)
+        },
+        _ => {
+            error!("unable to parse gesture {:?}", s);
+            None
+        },
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+struct ConfigParsed {
+    #[serde(default)]
+    bindings: Vec<BindingParsed>,
+    /// Gestures that are defined here, instead of being built in.
+    #[serde(default, rename = "gesture")]
+    custom: Vec<CustomParsed>,
+    /// Programs that recognize gestures or run actions.
+    #[serde(default, rename = "plugin")]
+    plugins: Vec<PluginParsed>,
+    edges: Option<EdgesParsed>,
+    /// The most fingers that are tracked at once.
+    #[serde(rename = "max-contacts")]
+    max_contacts: Option<u8>,
+    /// Frames that arrive less than this many milliseconds apart are merged.
+    #[serde(rename = "coalesce-ms")]
+    coalesce_ms: Option<u32>,
+    /// When to start recognizing again after a gesture: "immediately", "after-lift" (once every
+    /// finger has been up for `rearm_ms`) or "after-delay" (once `rearm_ms` have passed since the
+    /// gesture).
+    rearm: Option<String>,
+    /// How long `rearm` waits, in milliseconds. Setting only this means "after-lift".
+    #[serde(rename = "rearm-ms")]
+    rearm_ms: Option<u32>,
+    /// A gesture that another device already recognized less than this many milliseconds ago is
+    /// only fired once.
+    #[serde(rename = "dedup-ms")]
+    dedup_ms: Option<u32>,
+    /// What to do when there are more than `max_contacts` fingers: "ignore", "fail" or "palm".
+    overflow: Option<String>,
+    /// How far (in millimeters) each finger must move in a grab gesture. This overrides
+    /// `tuning.pinch.grab-mm`.
+    #[serde(rename = "grab-distance")]
+    grab_distance: Option<f64>,
+    /// The thresholds for the recognizers. A later config file replaces the whole section.
+    tuning: Option<toml::Value>,
+    /// Makes the gestures easier to make, for people with motor impairments: the thresholds start
+    /// out from `Params::accessible()` instead of the defaults (anything in `tuning` still takes
+    /// precedence), swipes may stray further from each direction, and a swipe may end by holding
+    /// the fingers still instead of flicking them.
+    accessibility: Option<bool>,
+    /// Swipes are recognized as soon as they are long enough, instead of when the fingers lift.
+    /// A binding's `fire` takes precedence over this.
+    #[serde(rename = "early-swipes")]
+    early_swipes: Option<bool>,
+    /// Thresholds for specific devices, which override the ones in `tuning`.
+    #[serde(default, rename = "device")]
+    devices: Vec<DeviceParsed>,
+    #[serde(rename = "swipe-angles")]
+    swipe_angles: Option<SwipeAnglesParsed>,
+    #[serde(rename = "one-finger")]
+    one_finger: Option<OneFingerParsed>,
+    /// The apps that the gestures pause for.
+    pause: Option<PauseParsed>,
+    /// What to do when the session is locked, and whether gestures keep it awake.
+    session: Option<SessionParsed>,
+    /// Where to log the gestures and their actions, if anywhere.
+    audit: Option<AuditParsed>,
+    /// How to send the key presses and clicks of `key` and `click` bindings: "auto" (the
+    /// default), "wayland" (which needs a compositor with virtual keyboards and pointers, like the
+    /// wlroots-based ones) or "x11".
+    #[serde(rename = "input-backend")]
+    input_backend: Option<String>,
+    /// Which desktop the `workspace` and `snap` bindings talk to: "auto" (the default), "sway",
+    /// "i3", "hyprland", "river" or "x11".
+    desktop: Option<String>,
+    /// The name of the output (as `xrandr` calls it) that the touchscreen covers. This overrides
+    /// the output that the compositor says the touchscreen belongs to.
+    output: Option<String>,
+}
+
+/// How far (in degrees) a swipe may stray from each direction. Each is at most 45.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+struct SwipeAnglesParsed {
+    /// The threshold for every direction that doesn't have its own setting.
+    default: Option<f64>,
+    up: Option<f64>,
+    down: Option<f64>,
+    left: Option<f64>,
+    right: Option<f64>,
+}
+
+impl SwipeAnglesParsed {
+    fn to_thresholds(&self) -> Result<DirectionThresholds, String> {
+        let mut ret = DirectionThresholds::default();
+        for &(d, t) in &[(Direction::Up, self.up), (Direction::Down, self.down),
+                         (Direction::Left, self.left), (Direction::Right, self.right)] {
+            if let Some(t) = t.or(self.default) {
+                if t < 0.0 || t > 45.0 {
+                    return Err(format!("swipe angle {} is not between 0 and 45 degrees", t));
+                }
+                ret = ret.threshold(d, UAngle::from_degrees(t));
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Where one-finger gestures on touchscreens may start, and whether to take the touchscreens away
+/// from the other programs.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+struct OneFingerParsed {
+    /// "anywhere", "after-hold" or "edge". The default is "after-hold", unless `grab` is set.
+    start: Option<String>,
+    /// For "after-hold", how long (in milliseconds) the finger must hold still first.
+    #[serde(rename = "hold-ms")]
+    hold_ms: Option<u32>,
+    /// If set, the daemon grabs the touchscreens, and passes on the touches that aren't gestures.
+    grab: Option<bool>,
+}
+
+// How long a finger must hold still before a one-finger gesture, if the config doesn't say.
+const ONE_FINGER_HOLD_MS: u32 = 300;
+
+impl OneFingerParsed {
+    fn to_policy(&self) -> Result<OneFinger, String> {
+        let hold = OneFinger::AfterHold { ms: self.hold_ms.unwrap_or(ONE_FINGER_HOLD_MS) };
+        match self.start.as_ref().map(|s| s.as_str()) {
+            Some("anywhere") => Ok(OneFinger::Anywhere),
+            Some("after-hold") => Ok(hold),
+            Some("edge") => Ok(OneFinger::FromEdges),
+            Some(s) => Err(format!("unknown one-finger start {:?}", s)),
+            // With the touchscreen grabbed, touches that aren't gestures never reach the other
+            // programs by accident, so there's no need to be careful.
+            None if self.grab.unwrap_or(false) => Ok(OneFinger::Anywhere),
+            None => Ok(hold),
+        }
+    }
+}
+
+/// Tuning for the devices that match `name` and `vendor`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+struct DeviceParsed {
+    /// Matches the devices whose names contain this.
+    name: Option<String>,
+    /// Matches the devices with this USB vendor id.
+    vendor: Option<u32>,
+    /// The settings from the top-level `tuning` section that are different for these devices.
+    tuning: Option<toml::Value>,
+    /// If true, touch gestures on these devices are ignored while a mouse or touchpad button is
+    /// held down (like when dragging with a thumb on a clickpad).
+    #[serde(rename = "pause-while-clicking")]
+    pause_while_clicking: Option<bool>,
+}
+
+// Sets the values in `base` that are also in `over`, recursing into tables.
+fn merge_values(base: &mut toml::Value, over: &toml::Value) {
+    match (base, over) {
+        (&mut toml::Value::Table(ref mut base), &toml::Value::Table(ref over)) => {
+            for (k, v) in over {
+                match base.get_mut(k) {
+                    Some(b) => {
+                        merge_values(b, v);
+                        continue;
+                    },
+                    None => {},
+                }
+                base.insert(k.clone(), v.clone());
+            }
+        },
+        (base, over) => *base = over.clone(),
+    }
+}
+
+fn to_params(tuning: toml::Value) -> Result<Params, String> {
+    let ret: Params = tuning.try_into().map_err(|e| format!("invalid tuning: {}", e))?;
+    if ret.pinch.min_scale <= 1.0 || ret.filter.max_spread_scale <= 1.0 {
+        return Err("tuning.pinch.min-scale and tuning.filter.max-spread-scale must be more than 1"
+                   .to_owned());
+    }
+    if ret.shake.min_reversals == 0 {
+        return Err("tuning.shake.min-reversals must be at least 1".to_owned());
+    }
+    if ret.pinch.min_rotation_deg <= 0.0 {
+        return Err("tuning.pinch.min-rotation-deg must be more than 0".to_owned());
+    }
+    Ok(ret)
+}
+
+/// Where edge swipes can start. All the sizes are in millimeters.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+struct EdgesParsed {
+    /// The size of the touch surface.
+    width: f64,
+    height: f64,
+    /// The width of the activation zone along every edge that doesn't have its own setting.
+    zone: Option<f64>,
+    top: Option<f64>,
+    bottom: Option<f64>,
+    left: Option<f64>,
+    right: Option<f64>,
+    exclude: Option<Vec<ExclusionParsed>>,
+}
+
+/// A part of an edge that can't start edge swipes.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+struct ExclusionParsed {
+    edge: String,
+    from: f64,
+    to: f64,
+}
+
+impl EdgesParsed {
+    fn to_edge_zones(self) -> Result<EdgeZones, String> {
+        let mut zones = EdgeZones::new(self.width, self.height);
+        let default = self.zone.unwrap_or(1.0);
+        for &(edge, zone) in &[(Edge::Top, self.top), (Edge::Bottom, self.bottom),
+                               (Edge::Left, self.left), (Edge::Right, self.right)] {
+            zones = zones.zone(edge, zone.unwrap_or(default));
+        }
+        for ex in self.exclude.unwrap_or_else(Vec::new) {
+            let edge = ex.edge.parse::<Edge>()?;
+            zones = zones.exclude(edge, ex.from, ex.to);
+        }
+        Ok(zones)
+    }
+}
+
+/// Combines the contents of several config files into a single `Config`.
+///
+/// The files are given in order of increasing precedence. Settings in later files replace
+/// settings in earlier ones. A binding in a later file replaces any binding in an earlier file
+/// for the same gesture with the same conditions, modifiers, source and profile; apart from that,
+/// the bindings from all the files are combined.
+fn to_config(layers: Vec<ConfigParsed>) -> Result<Config, String> {
+    let mut edges = None;
+    let mut max_contacts = None;
+    let mut coalesce_ms = None;
+    let mut rearm = None;
+    let mut rearm_ms = None;
+    let mut dedup_ms = None;
+    let mut overflow = None;
+    let mut grab_distance = None;
+    let mut tuning = None;
+    let mut accessibility = None;
+    let mut early_swipes = None;
+    let mut devices = Vec::new();
+    let mut swipe_angles = None;
+    let mut one_finger = None;
+    let mut pause = None;
+    let mut session = None;
+    let mut audit = None;
+    let mut input_backend = None;
+    let mut desktop = None;
+    let mut output = None;
+    // The custom gestures, with the layer that each came from.
+    let mut custom: Vec<(usize, CustomParsed)> = Vec::new();
+    // The same for the plugins.
+    let mut plugins: Vec<(usize, PluginParsed)> = Vec::new();
+    // For each binding, we also remember which layer it came from.
+    let mut bindings: HashMap<Gesture, Vec<(usize, Binding)>> = HashMap::new();
+
+    for (layer, c) in layers.into_iter().enumerate() {
+        edges = c.edges.or(edges);
+        max_contacts = c.max_contacts.or(max_contacts);
+        coalesce_ms = c.coalesce_ms.or(coalesce_ms);
+        rearm = c.rearm.or(rearm);
+        rearm_ms = c.rearm_ms.or(rearm_ms);
+        dedup_ms = c.dedup_ms.or(dedup_ms);
+        overflow = c.overflow.or(overflow);
+        grab_distance = c.grab_distance.or(grab_distance);
+        tuning = c.tuning.or(tuning);
+        accessibility = c.accessibility.or(accessibility);
+        early_swipes = c.early_swipes.or(early_swipes);
+        // Devices in later files come first, so that they take precedence.
+        for (i, d) in c.devices.into_iter().enumerate() {
+            if d.name.is_none() && d.vendor.is_none() {
+                return Err("a [[device]] section needs a name or a vendor".to_owned());
+            }
+            devices.insert(i, d);
+        }
+        swipe_angles = c.swipe_angles.or(swipe_angles);
+        one_finger = c.one_finger.or(one_finger);
+        pause = c.pause.or(pause);
+        session = c.session.or(session);
+        audit = c.audit.or(audit);
+        input_backend = c.input_backend.or(input_backend);
+        desktop = c.desktop.or(desktop);
+        output = c.output.or(output);
+        // A gesture in a later file replaces any gesture with the same name in an earlier one.
+        for g in c.custom {
+            match custom.iter().position(|&(_, ref old)| old.name == g.name) {
+                Some(i) if custom[i].0 == layer => {
+                    return Err(format!("gesture {:?} is defined twice", g.name));
+                },
+                Some(i) => custom[i] = (layer, g),
+                None => custom.push((layer, g)),
+            }
+        }
+        for p in c.plugins {
+            match plugins.iter().position(|&(_, ref old)| old.name == p.name) {
+                Some(i) if plugins[i].0 == layer => {
+                    return Err(format!("plugin {:?} is defined twice", p.name));
+                },
+                Some(i) => plugins[i] = (layer, p),
+                None => plugins.push((layer, p)),
+            }
+        }
+
+        for b in c.bindings {
+            let (gesture, binding) = b.to_binding()?;
+            let existing = bindings.entry(gesture).or_insert_with(Vec::new);
+            // The same gesture can be bound more than once, as long as the conditions, modifiers,
+            // sources or profiles differ.
+            let same = existing.iter().position(|&(_, ref b)| {
+                b.when == binding.when && b.modifiers == binding.modifiers
+                    && b.source == binding.source && b.profile == binding.profile
+            });
+            match same {
+                Some(i) if existing[i].0 == layer => {
+                    return Err(format!("duplicate binding for \"{}\"", gesture));
+                },
+                Some(i) => existing[i] = (layer, binding),
+                None => existing.push((layer, binding)),
+            }
+        }
+    }
+
+    let backend = match input_backend {
+        Some(ref b) => Backend::parse(b).ok_or(format!("unknown input backend {:?}", b))?,
+        None => Backend::Auto,
+    };
+    let desktop = match desktop {
+        Some(ref d) => Desktop::parse(d).ok_or(format!("unknown desktop {:?}", d))?,
+        None => Desktop::Auto,
+    };
+    for b in bindings.values_mut().flat_map(|bs| bs.iter_mut()) {
+        b.1.action.set_backend(backend, desktop);
+        for a in &mut b.1.feedback {
+            a.set_backend(backend, desktop);
+        }
+    }
+
+    let accessibility = accessibility.unwrap_or(false);
+    // The accessibility preset goes underneath whatever the config files set.
+    let mut base = if accessibility {
+        toml::Value::try_from(Params::accessible())
+            .map_err(|e| format!("couldn't use the accessible thresholds: {}", e))?
+    } else {
+        toml::Value::Table(toml::value::Table::new())
+    };
+    if let Some(ref over) = tuning {
+        merge_values(&mut base, over);
+    }
+    let mut tuning = base;
+    if let Some(mm) = grab_distance {
+        let mut grab = toml::value::Table::new();
+        grab.insert("grab-mm".to_owned(), toml::Value::Float(mm));
+        let mut over = toml::value::Table::new();
+        over.insert("pinch".to_owned(), toml::Value::Table(grab));
+        merge_values(&mut tuning, &toml::Value::Table(over));
+    }
+    let mut device_params = Vec::new();
+    for d in devices {
+        let mut t = tuning.clone();
+        if let Some(ref over) = d.tuning {
+            merge_values(&mut t, over);
+        }
+        device_params.push(DeviceParams {
+            name: d.name,
+            vendor: d.vendor,
+            params: to_params(t)?,
+            pause_while_clicking: d.pause_while_clicking.unwrap_or(false),
+        });
+    }
+
+    let mut custom_gestures = Vec::new();
+    for (_, g) in custom {
+        let name = CustomName::new(&g.name)
+            .ok_or(format!("{:?} is not a valid gesture name", g.name))?;
+        let params = match g.tuning {
+            Some(ref over) => {
+                let mut t = tuning.clone();
+                merge_values(&mut t, over);
+                Some(to_params(t)?)
+            },
+            None => None,
+        };
+        custom_gestures.push(CustomGesture {
+            name: name,
+            spec: g.to_spec()?,
+            params: params,
+        });
+    }
+
+    let ret = Config {
+        bindings: bindings.into_iter()
+            .map(|(g, bs)| (g, bs.into_iter().map(|(_, b)| b).collect()))
+            .collect(),
+        edges: match edges {
+            Some(e) => Some(e.to_edge_zones()?),
+            None => None,
+        },
+        max_contacts: max_contacts,
+        coalesce_ms: coalesce_ms,
+        rearm: parse_rearm(rearm.as_ref().map(|r| &r[..]), rearm_ms)?,
+        dedup_ms: dedup_ms,
+        overflow: match overflow {
+            Some(ref o) => parse_overflow(o).ok_or(format!("unknown overflow policy {:?}", o))?,
+            None => Overflow::Ignore,
+        },
+        params: to_params(tuning)?,
+        devices: device_params,
+        custom: custom_gestures,
+        plugins: plugins.into_iter()
+            .map(|(_, p)| p.to_plugin())
+            .collect::<Result<Vec<_>, _>>()?,
+        swipe_angles: match swipe_angles {
+            Some(a) => a.to_thresholds()?,
+            None if accessibility => DirectionThresholds::uniform(UAngle::from_degrees(40.0)),
+            None => DirectionThresholds::default(),
+        },
+        accessibility: accessibility,
+        early_swipes: early_swipes.unwrap_or(false),
+        one_finger: match one_finger {
+            Some(ref o) => o.to_policy()?,
+            None => OneFinger::AfterHold { ms: ONE_FINGER_HOLD_MS },
+        },
+        grab_touchscreens: one_finger.as_ref().and_then(|o| o.grab).unwrap_or(false),
+        pause: match pause {
+            Some(p) => Some(p.to_pause()?),
+            None => None,
+        },
+        session: session.map_or(SessionConfig::default(), |s| s.to_session()),
+        audit: match audit {
+            Some(a) => Some(a.to_audit()?),
+            None => None,
+        },
+        output: output,
+    };
+    if ret.max_contacts == Some(0) {
+        return Err("max-contacts must be at least 1".to_owned());
+    }
+    if ret.edges.is_none() && ret.bindings.keys().any(|g| g.is_edge_gesture()) {
+        return Err("edge swipes are bound, but there is no [edges] section".to_owned());
+    }
+    if ret.edges.is_none() && ret.one_finger == OneFinger::FromEdges {
+        return Err("one-finger gestures start from the edges, but there is no [edges] section"
+            .to_owned());
+    }
+    for g in ret.bindings.keys() {
+        match *g {
+            Gesture::Custom(name) if !ret.custom.iter().any(|c| c.name == name) => {
+                return Err(format!("there is no gesture named {:?}", name.as_str()));
+            },
+            Gesture::Plugin(name)
+                if !ret.plugins.iter().any(|p| p.recognizes.contains(&name)) => {
+                return Err(format!("no plugin recognizes {:?}", name.as_str()));
+            },
+            _ => {},
+        }
+    }
+    for (g, bs) in &ret.bindings {
+        let mut fires = bs.iter().filter_map(|b| b.fire);
+        if let Some(f) = fires.next() {
+            if fires.any(|other| other != f) {
+                return Err(format!("the bindings for {} disagree about when it fires", g));
+            }
+        }
+    }
+    for b in ret.bindings.values().flat_map(|bs| bs.iter()) {
+        if let Action::Plugin { ref plugin, .. } = b.action {
+            if !ret.plugins.iter().any(|p| p.name == *plugin) {
+                return Err(format!("there is no plugin called {:?}", plugin));
+            }
+        }
+    }
+    let profiles = ret.profiles();
+    for b in ret.bindings.values().flat_map(|bs| bs.iter()) {
+        if let Action::Internal(Internal::SwitchProfile(ref name)) = b.action {
+            if !profiles.contains(name) {
+                return Err(format!("there are no bindings in profile {:?}", name));
+            }
+        }
+    }
+
+    Ok(ret)
+}
+
+/// Splits a string into words, following (a subset of) the quoting rules of the shell.
+///
+/// Words are separated by whitespace. Single quotes preserve everything up to the next single
+/// quote; double quotes preserve everything up to the next double quote, except that a backslash
+/// escapes the character after it. Outside of quotes, a backslash escapes the character after it.
+fn split_words(s: &str) -> Result<Vec<String>, String> {
+    let mut ret = Vec::new();
+    let mut word = String::new();
+    // Are we in the middle of a word? (This is not the same as `!word.is_empty()`, because of
+    // empty quotes.)
+    let mut in_word = false;
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(format!("unterminated single quote in {:?}", s)),
+                    }
+                }
+            },
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => word.push(c),
+                            None => return Err(format!("unterminated double quote in {:?}", s)),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(format!("unterminated double quote in {:?}", s)),
+                    }
+                }
+            },
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    Some(c) => word.push(c),
+                    None => return Err(format!("trailing backslash in {:?}", s)),
+                }
+            },
+            c if c.is_whitespace() => {
+                if in_word {
+                    ret.push(::std::mem::replace(&mut word, String::new()));
+                    in_word = false;
+                }
+            },
+            c => {
+                in_word = true;
+                word.push(c);
+            },
+        }
+    }
+    if in_word {
+        ret.push(word);
+    }
+    Ok(ret)
+}
+
+/// Expands `~` and environment variables in a word from a command.
+///
+/// A `~` at the start of the word (on its own, or followed by `/`) is replaced by `$HOME`.
+/// `$NAME` and `${NAME}` are replaced by the value of the variable `NAME`, which is looked up
+/// first in `vars` and then in the daemon's environment. It is an error to refer to a variable
+/// that isn't set. A `$` that isn't followed by a variable name (such as `$5`) is left alone.
+///
+/// Since this happens after the command is split into words, quoting doesn't prevent expansion.
+fn expand(word: &str, vars: &BTreeMap<String, String>) -> Result<String, String> {
+    let lookup = |name: &str| -> Result<String, String> {
+        match vars.get(name) {
+            Some(v) => Ok(v.clone()),
+            None => env::var(name)
+                .map_err(|_| format!("environment variable {:?} is not set", name)),
+        }
+    };
+    let is_name_char = |c: char| match c {
+        'a'...'z' | 'A'...'Z' | '0'...'9' | '_' => true,
+        _ => false,
+    };
+
+    let mut ret = String::new();
+    let mut rest = word;
+    if rest == "~" || rest.starts_with("~/") {
+        ret.push_str(&lookup("HOME")?);
+        rest = &rest[1..];
+    }
+    while let Some(i) = rest.find('$') {
+        ret.push_str(&rest[..i]);
+        rest = &rest[(i + 1)..];
+        if rest.starts_with('{') {
+            let end = rest.find('}').ok_or(format!("unterminated ${{ in {:?}", word))?;
+            ret.push_str(&lookup(&rest[1..end])?);
+            rest = &rest[(end + 1)..];
+        } else {
+            let end = rest.find(|c: char| !is_name_char(c)).unwrap_or(rest.len());
+            // Like the shell, we don't support variable names that start with a digit.
+            if end == 0 || rest.starts_with(|c: char| c.is_digit(10)) {
+                ret.push('$');
+                continue;
+            }
+            ret.push_str(&lookup(&rest[..end])?);
+            rest = &rest[end..];
+        }
+    }
+    ret.push_str(rest);
+    Ok(ret)
+}
+
+// Builds a command action, expanding variables in the command, its arguments and its environment.
+fn expand_command(command: String, args: Vec<String>, env: &BTreeMap<String, String>)
+-> Result<Action, String> {
+    let mut expanded_env = Vec::new();
+    for (name, value) in env {
+        expanded_env.push((name.clone(), expand(value, &BTreeMap::new())?));
+    }
+    let vars = expanded_env.iter().cloned().collect::<BTreeMap<_, _>>();
+    Ok(Action::Command {
+        command: expand(&command, &vars)?,
+        args: args.iter().map(|a| expand(a, &vars)).collect::<Result<Vec<_>, _>>()?,
+        env: expanded_env,
+        sandbox: Sandbox::default(),
+    })
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+struct BindingParsed {
+    gesture: String,
+    command: Option<String>,
+    args: Option<Vec<String>>,
+    /// The command and its arguments, as a single string.
+    exec: Option<String>,
+    /// If true, `exec` is passed to `/bin/sh` instead of being split into words.
+    shell: Option<bool>,
+    cooldown_ms: Option<u64>,
+    overlap: Option<String>,
+    /// A comma-separated list of conditions that must hold for the binding to fire.
+    when: Option<String>,
+    /// The modifier keys that must be held down for the binding to fire, like "super+shift".
+    modifiers: Option<String>,
+    /// Where the gesture comes from: "touch" (the default) or "pen".
+    source: Option<String>,
+    /// When the gesture fires: "threshold" (as soon as the fingers have moved far enough),
+    /// "release" (once they lift), or "continuously" (for swipes that change a level, which
+    /// follows the fingers while they move). The default depends on the gesture.
+    fire: Option<String>,
+    /// A desktop notification to show when the binding fires.
+    notify: Option<NotifyParsed>,
+    /// A sound file to play when the binding fires.
+    sound: Option<String>,
+    /// The profile that this binding belongs to. If this is missing, the binding belongs to the
+    /// default profile.
+    profile: Option<String>,
+    /// Instead of running a command, switch to this profile.
+    switch_profile: Option<String>,
+    /// Instead of running a command, tell the daemon to do something: "reload-config",
+    /// "toggle-gestures", "switch-profile NAME" or "quit".
+    internal: Option<String>,
+    /// Instead of running a command, press this key combination, like "ctrl+shift+t".
+    key: Option<String>,
+    /// Instead of running a command, click this mouse button.
+    click: Option<String>,
+    /// Instead of running a command, switch to this workspace: "next", "prev" or a number.
+    workspace: Option<String>,
+    /// Instead of running a command, snap the focused window to this half of the screen: "left"
+    /// or "right".
+    snap: Option<String>,
+    /// Instead of running a command, change the screen brightness by this step, like "+10%".
+    brightness: Option<String>,
+    /// Instead of running a command, change the volume by this step, like "-5%".
+    volume: Option<String>,
+    /// Instead of running a command, send `args` to this plugin.
+    plugin: Option<String>,
+    /// Extra environment variables for the command.
+    env: Option<BTreeMap<String, String>>,
+    /// How to confine the command.
+    sandbox: Option<SandboxParsed>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+struct NotifyParsed {
+    summary: String,
+    body: Option<String>,
+}
+
+fn parse_source(s: &str) -> Option<Source> {
+    match s {
+        "touch" => Some(Source::Touch),
+        "pen" => Some(Source::Pen),
+        "mouse" => Some(Source::Mouse),
+        _ => None,
+    }
+}
+
+fn parse_overflow(s: &str) -> Option<Overflow> {
+    match s {
+        "ignore" => Some(Overflow::Ignore),
+        "fail" => Some(Overflow::Fail),
+        "palm" => Some(Overflow::Palm),
+        _ => None,
+    }
+}
+
+fn parse_rearm(policy: Option<&str>, ms: Option<u32>) -> Result<Rearm, String> {
+    match (policy, ms) {
+        (None, None) | (Some("immediately"), None) => Ok(Rearm::Immediately),
+        (Some("immediately"), Some(_)) => {
+            Err("rearm-ms can't be used with rearm = \"immediately\"".to_owned())
+        },
+        (None, Some(ms)) | (Some("after-lift"), Some(ms)) => Ok(Rearm::AfterLift { ms }),
+        (Some("after-delay"), Some(ms)) => Ok(Rearm::AfterDelay { ms }),
+        (Some(p @ "after-lift"), None) | (Some(p @ "after-delay"), None) => {
+            Err(format!("rearm = {:?} needs rearm-ms", p))
+        },
+        (Some(p), _) => Err(format!("unknown rearm policy {:?}", p)),
+    }
+}
+
+fn parse_overlap(s: &str) -> Option<Overlap> {
+    match s {
+        "allow" => Some(Overlap::Allow),
+        "skip" => Some(Overlap::Skip),
+        "queue" => Some(Overlap::Queue),
+        "kill-previous" => Some(Overlap::KillPrevious),
+        _ => None,
+    }
+}
+
+impl BindingParsed {
+    fn to_binding(self) -> Result<(Gesture, Binding), String> {
+        let mut g = parse_gesture(&self.gesture)
+            .ok_or(format!("unknown gesture {:?}; {}", self.gesture, GESTURE_FORMS))?;
+        let source = match self.source {
+            Some(ref s) => parse_source(s).ok_or(format!("unknown source {:?}", s))?,
+            None if g.is_mouse_gesture() => Source::Mouse,
+            None => Source::Touch,
+        };
+        if g.is_mouse_gesture() != (source == Source::Mouse) {
+            return Err(format!("gesture {:?} can't come from source {:?}", self.gesture, source));
+        }
+        if source == Source::Pen {
+            // A pen only ever has one point of contact, so there's no point in making people
+            // write "swipe 1 up".
+            if let Gesture::Touch(TouchGesture::Swipe { ref mut num_fingers, .. }) = g {
+                *num_fingers = 1;
+            }
+        }
+        let fire = match self.fire {
+            Some(ref f) => Some(f.parse::<Fire>()?),
+            None => None,
+        };
+        if let Some(f) = fire {
+            match g {
+                Gesture::Touch(t) if t.can_fire(f) => {},
+                Gesture::Custom(_) => {
+                    return Err(format!("{:?} is a custom gesture, so it fires according to its \
+                                        `end`", self.gesture));
+                },
+                _ => return Err(format!("gesture {:?} can't fire {:?}", self.gesture,
+                                        f.to_string())),
+            }
+        }
+        let mut feedback = Vec::new();
+        if let Some(n) = self.notify {
+            feedback.push(Action::Notify {
+                summary: n.summary,
+                body: n.body.unwrap_or_else(String::new),
+            });
+        }
+        if let Some(file) = self.sound {
+            feedback.push(Action::Sound { file: file });
+        }
+
+        let internal = match (self.switch_profile, self.internal) {
+            (Some(name), None) => Some(Internal::SwitchProfile(name)),
+            (None, Some(ref i)) => Some(Internal::parse(i)?),
+            (Some(_), Some(_)) => {
+                return Err(format!("binding for {:?} has both internal and switch_profile",
+                                   self.gesture));
+            },
+            (None, None) => None,
+        };
+        // The actions that the daemon knows how to do without running a command.
+        let mut builtin = Vec::new();
+        if let Some(ref k) = self.key {
+            builtin.push(Action::Key {
+                combo: KeyCombo::parse(k)?,
+                backend: Backend::Auto,
+            });
+        }
+        if let Some(ref c) = self.click {
+            builtin.push(Action::Click {
+                button: mouse::Button::parse(c).ok_or(format!("unknown mouse button {:?}", c))?,
+                backend: Backend::Auto,
+            });
+        }
+        if let Some(ref w) = self.workspace {
+            builtin.push(Action::Workspace {
+                workspace: Workspace::parse(w).ok_or(format!("unknown workspace {:?}", w))?,
+                desktop: Desktop::Auto,
+            });
+        }
+        if let Some(ref s) = self.snap {
+            builtin.push(Action::WindowSnap {
+                side: Side::parse(s).ok_or(format!("unknown side {:?}", s))?,
+                desktop: Desktop::Auto,
+            });
+        }
+        if let Some(ref b) = self.brightness {
+            builtin.push(Action::Adjust {
+                level: Level::Brightness,
+                percent: levels::parse_step(b)?,
+            });
+        }
+        if let Some(ref v) = self.volume {
+            builtin.push(Action::Adjust {
+                level: Level::Volume,
+                percent: levels::parse_step(v)?,
+            });
+        }
+        if builtin.len() > 1 {
+            return Err(format!("binding for {:?} has more than one of key, click, workspace, \
+                                snap, brightness and volume", self.gesture));
+        }
+        let input = builtin.pop();
+        let plugin = match self.plugin {
+            Some(ref name) => Some(Action::Plugin {
+                plugin: name.clone(),
+                args: self.args.clone().unwrap_or_else(Vec::new),
+            }),
+            None => None,
+        };
+        if let Some(plugin) = plugin {
+            if internal.is_some() || input.is_some() || self.command.is_some()
+                || self.exec.is_some() {
+                return Err(format!("binding for {:?} has both a plugin and another action",
+                                   self.gesture));
+            }
+            feedback.insert(0, plugin);
+        }
+        if internal.is_some() && input.is_some() {
+            return Err(format!("binding for {:?} has both an internal action and an input action",
+                               self.gesture));
+        }
+        if let Some(internal) = internal {
+            if self.command.is_some() || self.exec.is_some() {
+                return Err(format!("binding for {:?} has both a command and an internal action",
+                                   self.gesture));
+            }
+            feedback.insert(0, Action::Internal(internal));
+        }
+        if let Some(input) = input {
+            if self.command.is_some() || self.exec.is_some() {
+                return Err(format!("binding for {:?} has both a command and an input action",
+                                   self.gesture));
+            }
+            feedback.insert(0, input);
+        }
+
+        if self.shell == Some(true) && self.exec.is_none() {
+            return Err(format!("binding for {:?} has shell but no exec", self.gesture));
+        }
+
+        let env = self.env.unwrap_or_else(BTreeMap::new);
+        let mut action = match (self.command, self.exec) {
+            (Some(command), None) => {
+                let args = self.args.unwrap_or_else(Vec::new);
+                expand_command(command, args, &env)?
+            },
+            (None, Some(exec)) => {
+                if self.args.is_some() {
+                    return Err(format!("binding for {:?} has both exec and args", self.gesture));
+                }
+                if self.shell == Some(true) {
+                    // The shell does its own expansion.
+                    Action::Command {
+                        command: "/bin/sh".to_owned(),
+                        args: vec!["-c".to_owned(), exec],
+                        env: env.into_iter().collect(),
+                        sandbox: Sandbox::default(),
+                    }
+                } else {
+                    let mut words = split_words(&exec)?;
+                    if words.is_empty() {
+                        return Err(format!("binding for {:?} has an empty exec", self.gesture));
+                    }
+                    let command = words.remove(0);
+                    expand_command(command, words, &env)?
+                }
+            },
+            (Some(_), Some(_)) => {
+                return Err(format!("binding for {:?} has both command and exec", self.gesture));
+            },
+            // If there is only feedback (or an internal or input action), it becomes the main
+            // action.
+            (None, None) if !feedback.is_empty() => {
+                if !env.is_empty() {
+                    return Err(format!("binding for {:?} has env but no command", self.gesture));
+                }
+                feedback.remove(0)
+            },
+            (None, None) => {
+                return Err(format!("binding for {:?} has no command", self.gesture));
+            },
+        };
+        if let Some(ref s) = self.sandbox {
+            match action {
+                Action::Command { ref mut sandbox, .. } => *sandbox = s.to_sandbox()?,
+                _ => {
+                    return Err(format!("binding for {:?} has a sandbox, but no command",
+                                       self.gesture));
+                },
+            }
+        }
+        let overlap = match self.overlap {
+            Some(ref o) => parse_overlap(o).ok_or(format!("unknown overlap policy {:?}", o))?,
+            None => Overlap::Allow,
+        };
+        let when = match self.when {
+            Some(ref w) => Condition::parse_all(w)?,
+            None => Vec::new(),
+        };
+        let modifiers = match self.modifiers {
+            Some(ref m) => Some(Modifiers::parse(m)?),
+            None => None,
+        };
+        let binding = Binding {
+            action: action,
+            feedback: feedback,
+            profile: self.profile.unwrap_or_else(|| profile::DEFAULT.to_owned()),
+            cooldown: self.cooldown_ms.map(Duration::from_millis),
+            overlap: overlap,
+            when: when,
+            modifiers: modifiers,
+            source: source,
+            fire: fire,
+        };
+        Ok((g, binding))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// For each gesture, all the bindings for that gesture, in the order that they appeared in the
+    /// config file.
+    pub bindings: HashMap<Gesture, Vec<Binding>>,
+    /// Where edge swipes can start. This is only set if the config file has an `[edges]` section.
+    pub edges: Option<EdgeZones>,
+    /// The most fingers that are tracked at once, if there is a limit.
+    pub max_contacts: Option<u8>,
+    /// If set, frames that arrive less than this many milliseconds apart are merged.
+    pub coalesce_ms: Option<u32>,
+    /// When to start recognizing again after a gesture.
+    pub rearm: Rearm,
+    /// If set, a gesture that one device recognizes is dropped if a different device recognized
+    /// the same gesture less than this many milliseconds before (see the `dedup` module).
+    pub dedup_ms: Option<u32>,
+    /// What to do about fingers beyond `max_contacts`.
+    pub overflow: Overflow,
+    /// The thresholds for the recognizers.
+    pub params: Params,
+    /// Thresholds for specific devices, which take precedence over `params`. If a device matches
+    /// more than one of these, the first one wins.
+    pub devices: Vec<DeviceParams>,
+    /// The gestures defined in the config file.
+    pub custom: Vec<CustomGesture>,
+    /// How far swipes may stray from each direction.
+    pub swipe_angles: DirectionThresholds,
+    /// If set, swipes may also end by holding the fingers still (the thresholds in `params`
+    /// already include the rest of the accessibility preset).
+    pub accessibility: bool,
+    /// If set, swipes fire at the threshold instead of when the fingers lift, unless their
+    /// bindings say otherwise (see [`fire`](#method.fire)).
+    pub early_swipes: bool,
+    /// Where one-finger gestures on touchscreens may start.
+    pub one_finger: OneFinger,
+    /// If set, the daemon takes the touchscreens away from the other programs, and passes on the
+    /// touches that turn out not to be one-finger gestures (see the `forward` module).
+    pub grab_touchscreens: bool,
+    /// If set, the gestures pause while some apps are focused.
+    pub pause: Option<PauseConfig>,
+    /// What to do about the session getting locked or going idle.
+    pub session: SessionConfig,
+    /// If set, where to log every gesture and what was done about it.
+    pub audit: Option<AuditConfig>,
+    /// If set, the output that the touchscreen covers.
+    pub output: Option<String>,
+    /// The plugins that can be started.
+    pub plugins: Vec<PluginConfig>,
+}
+
+impl Binding {
+    /// Returns a copy of this binding, with the placeholders in all of its actions filled in.
+    pub fn with_placeholders(&self, p: &Placeholders) -> Binding {
+        Binding {
+            action: self.action.with_placeholders(p),
+            feedback: self.feedback.iter().map(|a| a.with_placeholders(p)).collect(),
+            ..self.clone()
+        }
+    }
+}
+
+/// The thresholds for the devices that match `name` and `vendor`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceParams {
+    /// If set, only devices whose names contain this match.
+    pub name: Option<String>,
+    /// If set, only devices with this USB vendor id match.
+    pub vendor: Option<u32>,
+    pub params: Params,
+    /// If set, touch gestures on these devices pause while a pointer button is held down.
+    pub pause_while_clicking: bool,
+}
+
+impl DeviceParams {
+    fn matches(&self, name: &str, vendor: u32) -> bool {
+        self.name.as_ref().map_or(true, |n| name.contains(n.as_str()))
+            && self.vendor.map_or(true, |v| v == vendor)
+    }
+}
+
+impl Config {
+    /// Returns the thresholds for the recognizers of a device with the given name and vendor id,
+    /// or the default thresholds if `device` is `None`.
+    pub fn params_for(&self, device: Option<(&str, u32)>) -> Params {
+        device.and_then(|(name, vendor)| self.devices.iter().find(|d| d.matches(name, vendor)))
+            .map_or(self.params, |d| d.params)
+    }
+
+    /// Returns true if the touch gestures on a device with the given name and vendor id should
+    /// pause while a pointer button is held down.
+    pub fn pauses_while_clicking(&self, name: &str, vendor: u32) -> bool {
+        self.devices.iter().find(|d| d.matches(name, vendor))
+            .map_or(false, |d| d.pause_while_clicking)
+    }
+
+    /// Returns the names of all the profiles, which are the default profile and all the profiles
+    /// that have bindings.
+    pub fn profiles(&self) -> BTreeSet<String> {
+        let mut ret = self.bindings.values()
+            .flat_map(|bs| bs.iter())
+            .map(|b| b.profile.clone())
+            .collect::<BTreeSet<_>>();
+        ret.insert(profile::DEFAULT.to_owned());
+        ret
+    }
+
+    /// Returns true if some of the touch recognizers wait for the fingers to hold still, so that
+    /// they need to be told when time passes.
+    pub fn needs_ticks(&self) -> bool {
+        let after_hold = match self.one_finger {
+            OneFinger::AfterHold { .. } => true,
+            _ => false,
+        };
+        self.accessibility || self.bindings.keys().any(|g| match *g {
+            Gesture::Touch(TouchGesture::Hold { .. }) => true,
+            Gesture::Touch(ref t) => after_hold && t.num_fingers() == 1 && !g.is_edge_gesture(),
+            _ => false,
+        })
+    }
+
+    /// Returns how many fingers `gesture` is made with, if it is made with fingers at all.
+    pub fn num_fingers(&self, gesture: &Gesture) -> Option<u8> {
+        match *gesture {
+            Gesture::Touch(ref g) => Some(g.num_fingers()),
+            Gesture::Custom(name) => {
+                self.custom.iter().find(|c| c.name == name).map(|c| c.spec.fingers)
+            },
+            _ => None,
+        }
+    }
+
+    /// Checks the bound gestures against what the recognizers can do, returning a complaint about
+    /// each gesture that could never fire.
+    pub fn unsupported_gestures(&self) -> Vec<String> {
+        let mut ret = Vec::new();
+        for g in self.bindings.keys() {
+            let n = match self.num_fingers(g) {
+                Some(n) => n,
+                None => continue,
+            };
+            let problem = match *g {
+                _ if n == 0 => "it has no fingers".to_owned(),
+                _ if n > MAX_FINGERS => {
+                    format!("it needs {} fingers, but gestures can have at most {}",
+                            n, MAX_FINGERS)
+                },
+                Gesture::Touch(TouchGesture::Spread { .. })
+                    | Gesture::Touch(TouchGesture::Grab { .. }) if n < 2 => {
+                    "spreading and grabbing need at least 2 fingers".to_owned()
+                },
+                Gesture::Touch(TouchGesture::Pinch { .. })
+                    | Gesture::Touch(TouchGesture::Rotate { .. }) if n < 2 => {
+                    "pinching and rotating need at least 2 fingers".to_owned()
+                },
+                _ => match self.max_contacts {
+                    Some(max) if n > max => {
+                        format!("it needs {} fingers, but max-contacts is {}", n, max)
+                    },
+                    _ => continue,
+                },
+            };
+            ret.push(format!("{} is bound, but {}, so it can never fire", g, problem));
+        }
+        ret.sort();
+        ret
+    }
+
+    /// Returns all the gestures that have a binding for `source` in `profile`.
+    pub fn gestures(&self, source: Source, profile: &str) -> Vec<Gesture> {
+        self.bindings.iter()
+            .filter(|&(_, bs)| bs.iter().any(|b| b.source == source && b.profile == profile))
+            .map(|(g, _)| *g)
+            .collect()
+    }
+
+    /// Returns when a touch gesture should fire: what its bindings say, or else at the threshold
+    /// for swipes if `early_swipes` is set, or else the gesture's default.
+    pub fn fire(&self, gesture: &TouchGesture) -> Fire {
+        let bound = self.bindings.get(&Gesture::Touch(*gesture))
+            .and_then(|bs| bs.iter().filter_map(|b| b.fire).next());
+        match (bound, *gesture) {
+            (Some(f), _) => f,
+            (None, TouchGesture::Swipe { .. }) if self.early_swipes => Fire::Threshold,
+            (None, g) => g.default_fire(),
+        }
+    }
+
+    /// Finds the binding in `profile` that should fire for a gesture from `source`, given the
+    /// current environment and the modifier keys that are held down.
+    ///
+    /// Bindings that ask for exactly the modifiers that are held down take precedence over
+    /// bindings that don't mention modifiers at all. Apart from that, if more than one binding's
+    /// conditions hold, the first one wins.
+    ///
+    /// The binding comes with its index among the gesture's bindings, which tells it apart from
+    /// the gesture's other bindings (see
+    /// [`Runner::trigger`](../runner/struct.Runner.html#method.trigger)).
+    pub fn binding(&self, gesture: &Gesture, source: Source, profile: &str, env: &Environment,
+                   mods: Modifiers) -> Option<(usize, &Binding)> {
+        self.bindings.get(gesture).and_then(|bs| {
+            let matches = |&(_, b): &(usize, &Binding)| {
+                b.source == source && b.profile == profile && env.holds_all(&b.when)
+            };
+            bs.iter().enumerate().filter(|&(_, b)| b.modifiers == Some(mods)).find(&matches)
+                .or_else(|| {
+                    bs.iter().enumerate().filter(|&(_, b)| b.modifiers.is_none()).find(&matches)
+                })
+        })
+    }
+}
+
+/// Everything we know about what to do when a gesture is recognized.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Binding {
+    pub action: Action,
+    /// Actions that give feedback about the gesture, like notifications and sounds. These run
+    /// alongside `action` whenever it runs, but they aren't subject to the overlap policy.
+    pub feedback: Vec<Action>,
+    /// The name of the profile that this binding belongs to.
+    pub profile: String,
+    /// If set, the action will not be run again until this much time has passed since the last
+    /// time it ran.
+    pub cooldown: Option<Duration>,
+    /// What to do if the gesture is recognized while the previous action is still running.
+    pub overlap: Overlap,
+    /// The binding only fires if all of these conditions hold.
+    pub when: Vec<Condition>,
+    /// If set, the binding only fires if exactly these modifier keys are held down.
+    pub modifiers: Option<Modifiers>,
+    /// The binding only fires for gestures made with this kind of input device.
+    pub source: Source,
+    /// If set, when the gesture fires. Every binding for the same gesture must agree about this.
+    pub fire: Option<Fire>,
+}
+
+/// The kinds of input devices that gestures can come from.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Source {
+    /// A touchscreen.
+    Touch,
+    /// A pen or stylus on a tablet (including a touchscreen with pen support). The pen tip
+    /// counts as a single finger.
+    Pen,
+    /// A mouse (or a trackpoint, or anything else that libinput treats as a pointer).
+    Mouse,
+}
+
+/// The possible policies for a gesture that is recognized while its action is still running.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Overlap {
+    /// Run the action again anyway.
+    Allow,
+    /// Don't run the action.
+    Skip,
+    /// Run the action again once the previous one finishes. If a few actions are already
+    /// waiting, don't run it.
+    Queue,
+    /// Kill the previous action, and then run the new one.
+    KillPrevious,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Action {
+    Command {
+        command: String,
+        args: Vec<String>,
+        /// Environment variables to set for the command, on top of the daemon's environment.
+        env: Vec<(String, String)>,
+        sandbox: Sandbox,
+    },
+    /// Shows a desktop notification.
+    Notify {
+        summary: String,
+        body: String,
+    },
+    /// Plays a sound file, using PulseAudio's `paplay`.
+    Sound {
+        file: String,
+    },
+    /// Tells the daemon to do something. The daemon does it itself, so starting this action
+    /// doesn't do anything.
+    Internal(Internal),
+    /// Presses a key combination.
+    Key {
+        combo: KeyCombo,
+        backend: Backend,
+    },
+    /// Clicks a mouse button.
+    Click {
+        button: mouse::Button,
+        backend: Backend,
+    },
+    /// Switches to another workspace (see the `desktop` module).
+    Workspace {
+        workspace: Workspace,
+        desktop: Desktop,
+    },
+    /// Makes the focused window fill half of the screen (see the `desktop` module).
+    WindowSnap {
+        side: Side,
+        desktop: Desktop,
+    },
+    /// Turns the brightness or the volume up or down by a percentage (see the `levels` module).
+    Adjust {
+        level: Level,
+        percent: i32,
+    },
+    /// Sends some arguments to a plugin. The daemon does this itself, like `Internal`.
+    Plugin {
+        plugin: String,
+        args: Vec<String>,
+    },
+}
+
+/// Something that the daemon can be told to do by a gesture.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Internal {
+    /// Reads the config files again.
+    ReloadConfig,
+    /// Stops running any actions, or starts running them again. While the actions are stopped,
+    /// only the bindings to `ToggleGestures` still work.
+    ToggleGestures,
+    /// Switches to a different profile.
+    SwitchProfile(String),
+    /// Stops the daemon.
+    Quit,
+}
+
+impl Internal {
+    fn parse(s: &str) -> Result<Internal, String> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        match (words.first().cloned(), words.len()) {
+            (Some("reload-config"), 1) => Ok(Internal::ReloadConfig),
+            (Some("toggle-gestures"), 1) => Ok(Internal::ToggleGestures),
+            (Some("switch-profile"), 2) => Ok(Internal::SwitchProfile(words[1].to_owned())),
+            (Some("quit"), 1) => Ok(Internal::Quit),
+            _ => Err(format!("unknown internal action {:?}", s)),
+        }
+    }
+}
+
+/// What we know about where a gesture happened, for filling in the placeholders in its actions.
+///
+/// In a command and its arguments, and in notifications, `{x}` and `{y}` are replaced by the
+/// position (in millimeters from the top left corner of the touch surface) of the middle of the
+/// fingers when the gesture started. `{x_frac}` and `{y_frac}` are replaced by the same position,
+/// as fractions of the surface's width and height, and `{screen_x}` and `{screen_y}` by the
+/// position on the screen (in pixels) underneath it. A placeholder is left alone if we don't know
+/// its value: for example, mouse gestures don't have positions.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Placeholders {
+    pub pos: Option<Point>,
+    pub fraction: Option<(f64, f64)>,
+    pub screen: Option<ScreenPoint>,
+}
+
+impl Placeholders {
+    fn fill(&self, s: &str) -> String {
+        let mut ret = s.to_owned();
+        if let Some(p) = self.pos {
+            ret = ret.replace("{x}", &format!("{:.1}", p.x))
+                .replace("{y}", &format!("{:.1}", p.y));
+        }
+        if let Some((x, y)) = self.fraction {
+            ret = ret.replace("{x_frac}", &format!("{:.4}", x))
+                .replace("{y_frac}", &format!("{:.4}", y));
+        }
+        if let Some(p) = self.screen {
+            ret = ret.replace("{screen_x}", &format!("{:.0}", p.x))
+                .replace("{screen_y}", &format!("{:.0}", p.y));
+        }
+        ret
+    }
+}
+
+impl Action {
+    /// Returns a copy of this action, with its placeholders filled in.
+    pub fn with_placeholders(&self, p: &Placeholders) -> Action {
+        match *self {
+            Action::Command { ref command, ref args, ref env, ref sandbox } => Action::Command {
+                command: p.fill(command),
+                args: args.iter().map(|a| p.fill(a)).collect(),
+                env: env.clone(),
+                sandbox: sandbox.clone(),
+            },
+            Action::Notify { ref summary, ref body } => Action::Notify {
+                summary: p.fill(summary),
+                body: p.fill(body),
+            },
+            Action::Plugin { ref plugin, ref args } => Action::Plugin {
+                plugin: plugin.clone(),
+                args: args.iter().map(|a| p.fill(a)).collect(),
+            },
+            _ => self.clone(),
+        }
+    }
+
+    /// Starts running this action.
+    ///
+    /// If the action runs in a separate process, returns a handle to it. Otherwise, the action is
+    /// finished by the time this returns.
+    pub fn start(&self) -> io::Result<Option<process::Child>> {
+        match self {
+            &Action::Command { ref command, ref args, ref env, ref sandbox } => {
+                sandbox.command(command, args, env)
+                    .spawn()
+                    .map(Some)
+            },
+            &Action::Notify { ref summary, ref body } => {
+                notify::notify(summary, body)
+                    .map(|_| None)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            },
+            &Action::Sound { ref file } => {
+                process::Command::new("paplay")
+                    .arg(file)
+                    .spawn()
+                    .map(Some)
+            },
+            &Action::Internal(_) | &Action::Plugin { .. } => Ok(None),
+            &Action::Key { ref combo, backend } => inject::press(combo, backend),
+            &Action::Click { button, backend } => inject::click(button, backend),
+            &Action::Workspace { workspace, desktop: d } => {
+                desktop::switch_workspace(workspace, d)
+                    .map(|_| None)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            },
+            &Action::WindowSnap { side, desktop: d } => {
+                desktop::snap_window(side, d)
+                    .map(|_| None)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            },
+            &Action::Adjust { level, percent } => {
+                levels::adjust(level, percent)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            },
+        }
+    }
+
+    /// Returns how long this action may run before it is killed, if there is a limit.
+    pub fn timeout(&self) -> Option<Duration> {
+        match *self {
+            Action::Command { ref sandbox, .. } => sandbox.timeout,
+            _ => None,
+        }
+    }
+
+    /// Changes how this action sends input events or talks to the desktop, if it does either.
+    fn set_backend(&mut self, b: Backend, d: Desktop) {
+        match *self {
+            Action::Key { ref mut backend, .. } | Action::Click { ref mut backend, .. } => {
+                *backend = b;
+            },
+            Action::Workspace { ref mut desktop, .. }
+            | Action::WindowSnap { ref mut desktop, .. } => {
+                *desktop = d;
+            },
+            _ => {},
+        }
+    }
+}
+
+// The system-wide config directory, which is overridden by the user's config directory.
+const SYSTEM_CONFIG_DIR: &'static str = "/etc/gestures";
+
+/// Returns all the config files, in order of increasing precedence.
+///
+/// In each of the system-wide and user config directories, there is a main `bindings.toml` file,
+/// followed by any `*.toml` files in `bindings.toml.d/`, in alphabetical order. Everything in the
+/// user's directory takes precedence over everything in the system-wide one.
+fn config_files() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from(SYSTEM_CONFIG_DIR)];
+    match app_root(AppDataType::UserConfig, &APP_INFO) {
+        Ok(dir) => dirs.push(dir),
+        Err(e) => warn!("couldn't open the user config directory: {}", e),
+    }
+
+    let mut ret = Vec::new();
+    for dir in dirs {
+        let main = dir.join("bindings.toml");
+        if main.is_file() {
+            ret.push(main);
+        }
+        if let Ok(entries) = fs::read_dir(dir.join("bindings.toml.d")) {
+            let mut fragments = entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().map_or(false, |e| e == "toml"))
+                .collect::<Vec<_>>();
+            fragments.sort();
+            ret.extend(fragments);
+        }
+    }
+    ret
+}
+
+/// Returns the path of the main config file in the user's config directory, which might not exist
+/// yet.
+pub fn user_config_file() -> Result<PathBuf, String> {
+    app_root(AppDataType::UserConfig, &APP_INFO)
+        .map(|dir| dir.join("bindings.toml"))
+        .map_err(|e| format!("couldn't open the user config directory: {}", e))
+}
+
+fn parse_config_file(path: &Path, contents: &str) -> Result<ConfigParsed, String> {
+    toml::from_str(contents).map_err(|e| format!("unable to parse {}: {}", path.display(), e))
+}
+
+fn read_config_file(path: &Path) -> Result<ConfigParsed, String> {
+    let mut contents = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut contents))
+        .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+    parse_config_file(path, &contents)
+}
+
+/// Reads all the config files.
+pub fn load_config() -> Result<Config, String> {
+    let files = config_files();
+    if files.is_empty() {
+        return Err("couldn't find any config files".to_owned());
+    }
+    let mut layers = Vec::new();
+    for path in &files {
+        debug!("reading config file {}", path.display());
+        layers.push(read_config_file(path)?);
+    }
+    let config = to_config(layers)?;
+    for w in config.unsupported_gestures() {
+        warn!("{} ({}, with 1 to {} fingers)", w, GESTURE_FORMS, MAX_FINGERS);
+    }
+    Ok(config)
+}
+
+/// Reads all the config files, as if the one at `path` contained `contents`.
+///
+/// This checks a change to a config file before it is written. If there is no file at `path` yet,
+/// `contents` take precedence over all the files.
+pub fn load_config_with(path: &Path, contents: &str) -> Result<Config, String> {
+    let mut layers = Vec::new();
+    let mut found = false;
+    for p in &config_files() {
+        if p == path {
+            found = true;
+            layers.push(parse_config_file(path, contents)?);
+        } else {
+            layers.push(read_config_file(p)?);
+        }
+    }
+    if !found {
+        layers.push(parse_config_file(path, contents)?);
+    }
+    to_config(layers)
+}
+
+pub fn open_config() -> Config {
+    load_config().unwrap_or_else(|e| panic!("{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand, parse_gesture, split_words, to_config, Action, Internal, Placeholders,
+                ONE_FINGER_HOLD_MS};
+    use desktop::{Desktop, Side, Workspace};
+    use inject::{Backend, KeyCombo};
+    use levels::Level;
+    use sandbox::Sandbox;
+    use std::collections::BTreeMap;
+    use std::env;
+    use std::time::Duration;
+    use toml;
+    use {Gesture, TouchGesture};
+    use libgestures::classify::{ContactKind, Kinds};
+    use libgestures::geom::{Direction, DirectionThresholds, Point};
+    use libgestures::gestures::{Fire, OneFinger};
+    use libgestures::manager::Rearm;
+    use libgestures::params::Params;
+
+    #[test]
+    fn later_layers_take_precedence() {
+        let system = toml::from_str(r#"
+            max-contacts = 5
+            dedup-ms = 80
+            [[bindings]]
+            gesture = "swipe up"
+            exec = "system-up"
+            [[bindings]]
+            gesture = "swipe down"
+            exec = "system-down"
+        "#).unwrap();
+        let user = toml::from_str(r#"
+            [[bindings]]
+            gesture = "swipe up"
+            exec = "user-up"
+        "#).unwrap();
+        let config = to_config(vec![system, user]).unwrap();
+
+        let up = Gesture::Touch(TouchGesture::Swipe { num_fingers: 3, direction: Direction::Up });
+        let down = Gesture::Touch(TouchGesture::Swipe { num_fingers: 3,
+                                                        direction: Direction::Down });
+        assert_eq!(config.max_contacts, Some(5));
+        assert_eq!(config.dedup_ms, Some(80));
+        assert_eq!(config.bindings[&up].len(), 1);
+        assert_eq!(config.bindings[&up][0].action,
+                   Action::Command { command: "user-up".to_owned(), args: vec![], env: vec![],
+                                     sandbox: Sandbox::default() });
+        assert_eq!(config.bindings[&down].len(), 1);
+    }
+
+    #[test]
+    fn gesture_names_round_trip() {
+        for name in &["swipe 3 up", "spread 4", "grab 5", "shake 3", "path 1 down right",
+                      "edge top", "rocker left right", "wheel down", "custom flick-up",
+                      "plugin lasso", "tap 1", "hold 2", "pinch 2 in", "pinch 3 out",
+                      "rotate 2 clockwise", "rotate 4 counterclockwise"] {
+            assert_eq!(parse_gesture(name).unwrap().to_string(), *name);
+        }
+    }
+
+    #[test]
+    fn internal_actions() {
+        let c = toml::from_str(r#"
+            [[bindings]]
+            gesture = "spread 5"
+            internal = "toggle-gestures"
+        "#).unwrap();
+        let config = to_config(vec![c]).unwrap();
+        let spread = Gesture::Touch(TouchGesture::Spread { num_fingers: 5 });
+        assert_eq!(config.bindings[&spread][0].action, Action::Internal(Internal::ToggleGestures));
+
+        let c = toml::from_str(r#"
+            [[bindings]]
+            gesture = "spread 5"
+            internal = "explode"
+        "#).unwrap();
+        assert!(to_config(vec![c]).is_err());
+    }
+
+    #[test]
+    fn shell_commands() {
+        let c = toml::from_str(r#"
+            [[bindings]]
+            gesture = "spread 5"
+            exec = "echo $HOME > /tmp/x"
+            shell = true
+        "#).unwrap();
+        let config = to_config(vec![c]).unwrap();
+        let spread = Gesture::Touch(TouchGesture::Spread { num_fingers: 5 });
+        assert_eq!(config.bindings[&spread][0].action,
+                   Action::Command { command: "/bin/sh".to_owned(),
+                                     args: vec!["-c".to_owned(), "echo $HOME > /tmp/x".to_owned()],
+                                     env: vec![], sandbox: Sandbox::default() });
+
+        // Only exec goes through the shell.
+        let c = toml::from_str(r#"
+            [[bindings]]
+            gesture = "spread 5"
+            command = "echo"
+            args = ["hi"]
+            shell = true
+        "#).unwrap();
+        assert!(to_config(vec![c]).is_err());
+    }
+
+    #[test]
+    fn placeholders() {
+        let action = Action::Command {
+            command: "menu".to_owned(),
+            args: vec!["--at={x},{y}".to_owned(), "{x_frac}".to_owned(), "{z}".to_owned()],
+            env: vec![],
+            sandbox: Sandbox::default(),
+        };
+        let p = Placeholders { pos: Some(Point::new(12.0, 34.5)), ..Placeholders::default() };
+        assert_eq!(action.with_placeholders(&p), Action::Command {
+            command: "menu".to_owned(),
+            args: vec!["--at=12.0,34.5".to_owned(), "{x_frac}".to_owned(), "{z}".to_owned()],
+            env: vec![],
+            sandbox: Sandbox::default(),
+        });
+    }
+
+    #[test]
+    fn tuning() {
+        let system = toml::from_str(r#"
+            grab-distance = 12.0
+            [tuning.swipe]
+            min-length-mm = 25.0
+            leading-fingers = 2
+        "#).unwrap();
+        let config = to_config(vec![system]).unwrap();
+        assert_eq!(config.params.swipe.min_length_mm, 25.0);
+        assert_eq!(config.params.swipe.leading_fingers, 2);
+        assert_eq!(config.params.swipe.step_mm, Params::default().swipe.step_mm);
+        assert_eq!(config.params.pinch.grab_mm, 12.0);
+
+        let bad = toml::from_str(r#"
+            [tuning.pinch]
+            min-scale = 0.5
+        "#).unwrap();
+        assert!(to_config(vec![bad]).is_err());
+
+        // Rotations can't be recognized without turning at all.
+        let bad = toml::from_str(r#"
+            [tuning.pinch]
+            min-rotation-deg = 0.0
+        "#).unwrap();
+        assert!(to_config(vec![bad]).is_err());
+    }
+
+    #[test]
+    fn accessibility() {
+        let c = toml::from_str(r#"
+            accessibility = true
+            [tuning.dwell]
+            hold-ms = 1200
+        "#).unwrap();
+        let config = to_config(vec![c]).unwrap();
+        assert!(config.accessibility);
+        assert_eq!(config.params.dwell.hold_ms, 1200);
+        assert_eq!(config.params.swipe, Params::accessible().swipe);
+        assert!(config.swipe_angles != DirectionThresholds::default());
+
+        // A later file can turn it off again.
+        let off = toml::from_str("accessibility = false").unwrap();
+        let c = toml::from_str("accessibility = true").unwrap();
+        let config = to_config(vec![c, off]).unwrap();
+        assert!(!config.accessibility);
+        assert_eq!(config.params, Params::default());
+    }
+
+    #[test]
+    fn one_finger() {
+        let config = to_config(vec![toml::from_str("").unwrap()]).unwrap();
+        assert_eq!(config.one_finger, OneFinger::AfterHold { ms: ONE_FINGER_HOLD_MS });
+        assert!(!config.grab_touchscreens);
+        let tap = Gesture::Touch(TouchGesture::Tap { num_fingers: 1 });
+        assert_eq!(parse_gesture("tap"), Some(tap));
+
+        let c = toml::from_str(r#"
+            [one-finger]
+            grab = true
+        "#).unwrap();
+        let config = to_config(vec![c]).unwrap();
+        assert_eq!(config.one_finger, OneFinger::Anywhere);
+        assert!(config.grab_touchscreens);
+
+        let c = toml::from_str(r#"
+            [one-finger]
+            start = "after-hold"
+            hold-ms = 500
+        "#).unwrap();
+        let config = to_config(vec![c]).unwrap();
+        assert_eq!(config.one_finger, OneFinger::AfterHold { ms: 500 });
+
+        let edge = toml::from_str(r#"
+            [one-finger]
+            start = "edge"
+        "#).unwrap();
+        assert!(to_config(vec![edge]).is_err());
+        let bad = toml::from_str(r#"
+            [one-finger]
+            start = "sometimes"
+        "#).unwrap();
+        assert!(to_config(vec![bad]).is_err());
+    }
+
+    #[test]
+    fn fire() {
+        let c = toml::from_str(r#"
+            [[bindings]]
+            gesture = "swipe 3 up"
+            exec = "up"
+            fire = "threshold"
+            [[bindings]]
+            gesture = "swipe 3 down"
+            exec = "down"
+        "#).unwrap();
+        let config = to_config(vec![c]).unwrap();
+        let up = TouchGesture::Swipe { num_fingers: 3, direction: Direction::Up };
+        let down = TouchGesture::Swipe { num_fingers: 3, direction: Direction::Down };
+        assert_eq!(config.fire(&up), Fire::Threshold);
+        assert_eq!(config.fire(&down), Fire::Release);
+        assert_eq!(config.fire(&TouchGesture::Hold { num_fingers: 2 }), Fire::Threshold);
+
+        let early = toml::from_str("early-swipes = true").unwrap();
+        let config = to_config(vec![early]).unwrap();
+        assert_eq!(config.fire(&down), Fire::Threshold);
+
+        let tap = toml::from_str(r#"
+            [[bindings]]
+            gesture = "tap 2"
+            exec = "tap"
+            fire = "threshold"
+        "#).unwrap();
+        assert!(to_config(vec![tap]).is_err());
+        let slider = toml::from_str(r#"
+            [[bindings]]
+            gesture = "swipe 2 up"
+            brightness = "+30%"
+            fire = "continuously"
+        "#).unwrap();
+        let config = to_config(vec![slider]).unwrap();
+        let up = TouchGesture::Swipe { num_fingers: 2, direction: Direction::Up };
+        assert_eq!(config.fire(&up), Fire::Continuously);
+        let spread = toml::from_str(r#"
+            [[bindings]]
+            gesture = "spread 2"
+            exec = "spread"
+            fire = "continuously"
+        "#).unwrap();
+        assert!(to_config(vec![spread]).is_err());
+        let disagree = toml::from_str(r#"
+            [[bindings]]
+            gesture = "spread 4"
+            exec = "a"
+            fire = "threshold"
+            [[bindings]]
+            gesture = "spread 4"
+            exec = "b"
+            profile = "other"
+            fire = "release"
+        "#).unwrap();
+        assert!(to_config(vec![disagree]).is_err());
+    }
+
+    #[test]
+    fn rearm() {
+        let rearm = |s: &str| to_config(vec![toml::from_str(s).unwrap()]).map(|c| c.rearm);
+        assert_eq!(rearm(""), Ok(Rearm::Immediately));
+        assert_eq!(rearm("rearm = \"immediately\""), Ok(Rearm::Immediately));
+        assert_eq!(rearm("rearm-ms = 200"), Ok(Rearm::AfterLift { ms: 200 }));
+        assert_eq!(rearm("rearm = \"after-lift\"\nrearm-ms = 200"),
+                   Ok(Rearm::AfterLift { ms: 200 }));
+        assert_eq!(rearm("rearm = \"after-delay\"\nrearm-ms = 300"),
+                   Ok(Rearm::AfterDelay { ms: 300 }));
+        assert!(rearm("rearm = \"after-delay\"").is_err());
+        assert!(rearm("rearm = \"immediately\"\nrearm-ms = 200").is_err());
+        assert!(rearm("rearm = \"later\"").is_err());
+    }
+
+    #[test]
+    fn pause() {
+        let c = toml::from_str(r#"
+            [pause]
+            apps = ["xournalpp", "krita"]
+            focus = "sway"
+        "#).unwrap();
+        let pause = to_config(vec![c]).unwrap().pause.unwrap();
+        assert!(pause.pauses_for("krita"));
+        assert_eq!(pause.focus, ::context::FocusSource::Sway);
+        assert_eq!(to_config(vec![toml::from_str("").unwrap()]).unwrap().pause, None);
+    }
+
+    #[test]
+    fn unsupported_gestures() {
+        let c = toml::from_str(r#"
+            max-contacts = 5
+            [[bindings]]
+            gesture = "swipe 11 up"
+            exec = "a"
+            [[bindings]]
+            gesture = "swipe 20 up"
+            exec = "b"
+            [[bindings]]
+            gesture = "grab 1"
+            exec = "c"
+            [[bindings]]
+            gesture = "swipe 3 up"
+            exec = "d"
+            [[bindings]]
+            gesture = "rotate 1 clockwise"
+            exec = "e"
+        "#).unwrap();
+        let problems = to_config(vec![c]).unwrap().unsupported_gestures();
+        assert_eq!(problems.len(), 4);
+        assert!(problems.iter().any(|p| p.contains("max-contacts is 5")));
+        assert!(problems.iter().any(|p| p.contains("at most 16")));
+        assert!(problems.iter().any(|p| p.contains("grabbing need at least 2 fingers")));
+        assert!(problems.iter().any(|p| p.contains("rotating need at least 2 fingers")));
+
+        let c = toml::from_str("[[bindings]]\ngesture = \"swipe 3 sideways\"\nexec = \"a\"");
+        let err = to_config(vec![c.unwrap()]).unwrap_err();
+        assert!(err.contains("gestures look like"));
+    }
+
+    #[test]
+    fn session() {
+        let base = toml::from_str("[session]\nkeep-awake = true").unwrap();
+        let over = toml::from_str("[session]\npause-when-locked = false").unwrap();
+        let session = to_config(vec![base, over]).unwrap().session;
+        // A later file replaces the whole section.
+        assert!(!session.pause_when_locked);
+        assert!(!session.keep_awake);
+        let session = to_config(vec![toml::from_str("").unwrap()]).unwrap().session;
+        assert_eq!(session, ::session::SessionConfig::default());
+    }
+
+    #[test]
+    fn audit() {
+        assert_eq!(to_config(vec![toml::from_str("").unwrap()]).unwrap().audit, None);
+        let c = toml::from_str("[audit]\nmax-size-kb = 10").unwrap();
+        assert_eq!(to_config(vec![c]).unwrap().audit, Some(::audit::AuditConfig {
+            path: "audit.log".into(),
+            max_bytes: 10240,
+            keep: 3,
+        }));
+        let c = toml::from_str("[audit]\nmax-size-kb = 0").unwrap();
+        assert!(to_config(vec![c]).is_err());
+    }
+
+    #[test]
+    fn device_tuning() {
+        let c = toml::from_str(r#"
+            [tuning.swipe]
+            min-length-mm = 25.0
+            step-mm = 4.0
+            [[device]]
+            name = "Magic Trackpad"
+            [device.tuning.swipe]
+            min-length-mm = 15.0
+        "#).unwrap();
+        let config = to_config(vec![c]).unwrap();
+        let trackpad = config.params_for(Some(("Apple Inc. Magic Trackpad 2", 0x05ac)));
+        assert_eq!(trackpad.swipe.min_length_mm, 15.0);
+        assert_eq!(trackpad.swipe.step_mm, 4.0);
+        let other = config.params_for(Some(("ELAN Touchscreen", 0x04f3)));
+        assert_eq!(other.swipe.min_length_mm, 25.0);
+        assert!(!config.pauses_while_clicking("ELAN Touchscreen", 0x04f3));
+
+        let c = toml::from_str(r#"
+            [[device]]
+            vendor = 0x04f3
+            pause-while-clicking = true
+        "#).unwrap();
+        let config = to_config(vec![c]).unwrap();
+        assert!(config.pauses_while_clicking("ELAN Touchscreen", 0x04f3));
+        assert!(!config.pauses_while_clicking("Wacom Touch", 0x056a));
+    }
+
+    #[test]
+    fn key_actions() {
+        let c = toml::from_str(r#"
+            input-backend = "wayland"
+            [[bindings]]
+            gesture = "swipe 3 left"
+            key = "ctrl+shift+Tab"
+        "#).unwrap();
+        let config = to_config(vec![c]).unwrap();
+        let left = Gesture::Touch(TouchGesture::Swipe { num_fingers: 3,
+                                                        direction: Direction::Left });
+        assert_eq!(config.bindings[&left][0].action, Action::Key {
+            combo: KeyCombo::parse("ctrl+shift+Tab").unwrap(),
+            backend: Backend::Wayland,
+        });
+
+        let c = toml::from_str(r#"
+            [[bindings]]
+            gesture = "swipe 3 left"
+            key = "ctrl+Tab"
+            exec = "true"
+        "#).unwrap();
+        assert!(to_config(vec![c]).is_err());
+    }
+
+    #[test]
+    fn desktop_actions() {
+        let c = toml::from_str(r#"
+            desktop = "river"
+            [[bindings]]
+            gesture = "swipe 4 left"
+            workspace = "next"
+            [[bindings]]
+            gesture = "swipe 4 up"
+            workspace = "2"
+            [[bindings]]
+            gesture = "swipe 3 right"
+            snap = "right"
+        "#).unwrap();
+        let config = to_config(vec![c]).unwrap();
+        let swipe = |n, d| Gesture::Touch(TouchGesture::Swipe { num_fingers: n, direction: d });
+        assert_eq!(config.bindings[&swipe(4, Direction::Left)][0].action,
+                   Action::Workspace { workspace: Workspace::Next, desktop: Desktop::River });
+        assert_eq!(config.bindings[&swipe(4, Direction::Up)][0].action,
+                   Action::Workspace { workspace: Workspace::Number(2),
+                                       desktop: Desktop::River });
+        assert_eq!(config.bindings[&swipe(3, Direction::Right)][0].action,
+                   Action::WindowSnap { side: Side::Right, desktop: Desktop::River });
+
+        let c = toml::from_str(r#"
+            [[bindings]]
+            gesture = "swipe 3 left"
+            workspace = "prev"
+            snap = "left"
+        "#).unwrap();
+        assert!(to_config(vec![c]).is_err());
+    }
+
+    #[test]
+    fn level_actions() {
+        let c = toml::from_str(r#"
+            [[bindings]]
+            gesture = "swipe 2 up"
+            brightness = "+10%"
+            [[bindings]]
+            gesture = "swipe 2 down"
+            volume = "-5%"
+        "#).unwrap();
+        let config = to_config(vec![c]).unwrap();
+        let swipe = |d| Gesture::Touch(TouchGesture::Swipe { num_fingers: 2, direction: d });
+        assert_eq!(config.bindings[&swipe(Direction::Up)][0].action,
+                   Action::Adjust { level: Level::Brightness, percent: 10 });
+        assert_eq!(config.bindings[&swipe(Direction::Down)][0].action,
+                   Action::Adjust { level: Level::Volume, percent: -5 });
+
+        let c = toml::from_str(r#"
+            [[bindings]]
+            gesture = "swipe 2 up"
+            volume = "50%"
+        "#).unwrap();
+        assert!(to_config(vec![c]).is_err());
+    }
+
+    #[test]
+    fn sandboxed_commands() {
+        let c = toml::from_str(r#"
+            [[bindings]]
+            gesture = "swipe 3 up"
+            exec = "sync-notes"
+            [bindings.sandbox]
+            nice = 10
+            timeout-ms = 2000
+        "#).unwrap();
+        let config = to_config(vec![c]).unwrap();
+        let up = Gesture::Touch(TouchGesture::Swipe { num_fingers: 3, direction: Direction::Up });
+        let action = &config.bindings[&up][0].action;
+        assert_eq!(*action, Action::Command {
+            command: "sync-notes".to_owned(),
+            args: vec![],
+            env: vec![],
+            sandbox: Sandbox {
+                clear_env: false,
+                nice: Some(10),
+                scope: false,
+                timeout: Some(Duration::from_millis(2000)),
+            },
+        });
+        assert_eq!(action.timeout(), Some(Duration::from_millis(2000)));
+
+        let c = toml::from_str(r#"
+            [[bindings]]
+            gesture = "swipe 3 up"
+            key = "ctrl+Tab"
+            [bindings.sandbox]
+            clear-env = true
+        "#).unwrap();
+        assert!(to_config(vec![c]).is_err());
+    }
+
+    #[test]
+    fn duplicates_in_one_layer() {
+        let c = toml::from_str(r#"
+            [[bindings]]
+            gesture = "swipe up"
+            exec = "a"
+            [[bindings]]
+            gesture = "swipe up"
+            exec = "b"
+        "#).unwrap();
+        assert!(to_config(vec![c]).is_err());
+    }
+
+    #[test]
+    fn custom_gestures() {
+        let system = toml::from_str(r#"
+            [[gesture]]
+            name = "flick"
+            fingers = 3
+            move = "straight"
+            [[bindings]]
+            gesture = "custom flick"
+            exec = "true"
+        "#).unwrap();
+        let user = toml::from_str(r#"
+            [[gesture]]
+            name = "flick"
+            fingers = 4
+            move = "straight"
+            direction = "up"
+            max-duration-ms = 300
+            end = "move"
+            contacts = ["finger", "unknown"]
+            [gesture.tuning.filter]
+            shape-mm = 6.0
+        "#).unwrap();
+        let config = to_config(vec![system, user]).unwrap();
+        assert_eq!(config.custom.len(), 1);
+        let flick = &config.custom[0];
+        assert_eq!(flick.spec.fingers, 4);
+        assert_eq!(flick.spec.direction, Some(Direction::Up));
+        assert_eq!(flick.spec.max_duration_ms, Some(300));
+        let contacts = Kinds::of(&[ContactKind::Finger, ContactKind::Unknown]);
+        assert_eq!(flick.spec.contacts, Some(contacts));
+        assert_eq!(flick.params.unwrap().filter.shape_mm, 6.0);
+
+        let undefined = toml::from_str(r#"
+            [[bindings]]
+            gesture = "custom flick"
+            exec = "true"
+        "#).unwrap();
+        assert!(to_config(vec![undefined]).is_err());
+
+        let nonsense = toml::from_str(r#"
+            [[gesture]]
+            name = "twirl"
+            fingers = 2
+            move = "grab"
+            direction = "left"
+        "#).unwrap();
+        assert!(to_config(vec![nonsense]).is_err());
+    }
+
+    #[test]
+    fn plugins() {
+        let c = toml::from_str(r#"
+            [[plugin]]
+            name = "shapes"
+            command = "gestures-shapes"
+            recognizes = ["lasso"]
+            [[bindings]]
+            gesture = "plugin lasso"
+            plugin = "shapes"
+            args = ["select", "{x}"]
+        "#).unwrap();
+        let config = to_config(vec![c]).unwrap();
+        let lasso = parse_gesture("plugin lasso").unwrap();
+        assert_eq!(config.bindings[&lasso][0].action, Action::Plugin {
+            plugin: "shapes".to_owned(),
+            args: vec!["select".to_owned(), "{x}".to_owned()],
+        });
+
+        let unknown = toml::from_str(r#"
+            [[plugin]]
+            name = "shapes"
+            command = "gestures-shapes"
+            [[bindings]]
+            gesture = "plugin lasso"
+            exec = "true"
+        "#).unwrap();
+        assert!(to_config(vec![unknown]).is_err());
+
+        let both = toml::from_str(r#"
+            [[plugin]]
+            name = "shapes"
+            command = "gestures-shapes"
+            [[bindings]]
+            gesture = "swipe 3 up"
+            plugin = "shapes"
+            exec = "true"
+        "#).unwrap();
+        assert!(to_config(vec![both]).is_err());
+    }
+
+    #[test]
+    fn expansion() {
+        let mut vars = BTreeMap::new();
+        vars.insert("FOO".to_owned(), "foo".to_owned());
+        let home = env::var("HOME").unwrap();
+
+        assert_eq!(expand("~", &vars).unwrap(), home);
+        assert_eq!(expand("~/bin", &vars).unwrap(), format!("{}/bin", home));
+        assert_eq!(expand("a~b", &vars).unwrap(), "a~b");
+        assert_eq!(expand("$HOME/x", &vars).unwrap(), format!("{}/x", home));
+        assert_eq!(expand("${FOO}bar-$FOO.txt", &vars).unwrap(), "foobar-foo.txt");
+        assert_eq!(expand("cost: $5 $", &vars).unwrap(), "cost: $5 $");
+        assert!(expand("$GESTURES_SURELY_NOT_SET", &vars).is_err());
+        assert!(expand("${FOO", &vars).is_err());
+    }
+
+    #[test]
+    fn split_words_quoting() {
+        assert_eq!(split_words("wmctrl -s 2").unwrap(), vec!["wmctrl", "-s", "2"]);
+        assert_eq!(split_words("  a   b  ").unwrap(), vec!["a", "b"]);
+        assert_eq!(split_words("echo 'a b' \"c \\\" d\" e\\ f").unwrap(),
+                   vec!["echo", "a b", "c \" d", "e f"]);
+        assert_eq!(split_words("a '' b").unwrap(), vec!["a", "", "b"]);
+        assert!(split_words("echo 'oops").is_err());
+    }
+}
@@ -0,0 +1,297 @@
+//! Pausing the gestures while a drawing or handwriting app is focused.
+//!
+//! In such an app, every stroke is a touch, and it's annoying when a three-finger smudge switches
+//! workspaces. The `[pause]` section of the config file lists the apps that should have the
+//! touchscreen (and the pen) to themselves:
+//!
+//! ```toml
+//! [pause]
+//! apps = ["xournalpp", "krita", "com.github.maoschanz.drawing"]
+//! # Where to find out which app is focused: "auto" (the default), "sway", "hyprland" or "x11".
+//! focus = "auto"
+//! ```
+//!
+//! Apps are matched by their Wayland app id or their X11 window class, ignoring case. The focus
+//! comes from the compositor: sway's event subscription (through `swaymsg`), Hyprland's event
+//! socket, or `xprop` under X11. Each of these runs in its own thread, and reports the focused app
+//! every time the focus changes. The gestures pause from the next touch after one of the apps
+//! gets the focus, and they resume from the next touch after it loses the focus.
+
+use chan;
+use serde_json;
+use std::env;
+use std::io::{BufRead, BufReader};
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+/// A `[pause]` section of the config file.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct PauseParsed {
+    apps: Vec<String>,
+    focus: Option<String>,
+}
+
+impl PauseParsed {
+    pub fn to_pause(self) -> Result<PauseConfig, String> {
+        let focus = match self.focus {
+            Some(ref f) => FocusSource::parse(f).ok_or(format!("unknown focus source {:?}", f))?,
+            None => FocusSource::Auto,
+        };
+        Ok(PauseConfig {
+            apps: self.apps,
+            focus: focus,
+        })
+    }
+}
+
+/// Which apps the gestures pause for, and how to find out which one is focused.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PauseConfig {
+    pub apps: Vec<String>,
+    pub focus: FocusSource,
+}
+
+impl PauseConfig {
+    /// Returns true if the gestures should pause while `app` is focused.
+    pub fn pauses_for(&self, app: &str) -> bool {
+        let app = app.to_lowercase();
+        self.apps.iter().any(|a| a.to_lowercase() == app)
+    }
+}
+
+/// Where to find out which app is focused.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FocusSource {
+    /// Whichever of the others the environment variables point to.
+    Auto,
+    Sway,
+    Hyprland,
+    X11,
+}
+
+impl FocusSource {
+    pub fn parse(s: &str) -> Option<FocusSource> {
+        match s {
+            "auto" => Some(FocusSource::Auto),
+            "sway" => Some(FocusSource::Sway),
+            "hyprland" => Some(FocusSource::Hyprland),
+            "x11" => Some(FocusSource::X11),
+            _ => None,
+        }
+    }
+
+    // Works out which compositor we're running under.
+    fn detect() -> Option<FocusSource> {
+        if env::var_os("SWAYSOCK").is_some() {
+            Some(FocusSource::Sway)
+        } else if env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+            Some(FocusSource::Hyprland)
+        } else if env::var_os("DISPLAY").is_some() {
+            Some(FocusSource::X11)
+        } else {
+            None
+        }
+    }
+}
+
+// Whatever a watcher's thread is reading from, so that we can make it stop.
+enum Handle {
+    Child(Child),
+    Socket(UnixStream),
+}
+
+/// Sends the focused app to a channel every time it changes, until it is dropped.
+pub struct FocusWatcher {
+    handle: Handle,
+}
+
+impl Drop for FocusWatcher {
+    fn drop(&mut self) {
+        // Either way, the thread's next read fails, and it stops.
+        match self.handle {
+            Handle::Child(ref mut child) => {
+                let _ = child.kill();
+                let _ = child.wait();
+            },
+            Handle::Socket(ref s) => {
+                let _ = s.shutdown(Shutdown::Both);
+            },
+        }
+    }
+}
+
+/// Starts watching the focus with `source`, sending the app id (or window class) of each newly
+/// focused window to `send`.
+pub fn watch(source: FocusSource, send: chan::Sender<String>) -> Result<FocusWatcher, String> {
+    let source = match source {
+        FocusSource::Auto => FocusSource::detect()
+            .ok_or("couldn't tell which compositor is running".to_owned())?,
+        s => s,
+    };
+    debug!("watching the focus with {:?}", source);
+    match source {
+        FocusSource::Sway => {
+            let mut child = Command::new("swaymsg")
+                .args(&["-r", "-m", "-t", "subscribe", r#"["window"]"#])
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("failed to run swaymsg: {}", e))?;
+            let out = BufReader::new(child.stdout.take().unwrap());
+            thread::spawn(move || {
+                for app in out.lines().filter_map(|l| l.ok()).filter_map(|l| parse_sway(&l)) {
+                    send.send(app);
+                }
+                debug!("stopped watching the focus in sway");
+            });
+            Ok(FocusWatcher { handle: Handle::Child(child) })
+        },
+        FocusSource::Hyprland => {
+            let sig = env::var("HYPRLAND_INSTANCE_SIGNATURE")
+                .map_err(|_| "HYPRLAND_INSTANCE_SIGNATURE isn't set".to_owned())?;
+            let stream = UnixStream::connect(hyprland_socket(&sig, ".socket2.sock"))
+                .map_err(|e| format!("failed to connect to Hyprland: {}", e))?;
+            let read = stream.try_clone()
+                .map_err(|e| format!("failed to connect to Hyprland: {}", e))?;
+            thread::spawn(move || {
+                let lines = BufReader::new(read).lines().filter_map(|l| l.ok());
+                for app in lines.filter_map(|l| parse_hyprland(&l)) {
+                    send.send(app);
+                }
+                debug!("stopped watching the focus in Hyprland");
+            });
+            Ok(FocusWatcher { handle: Handle::Socket(stream) })
+        },
+        // (`Auto` was already replaced, above.)
+        FocusSource::X11 | FocusSource::Auto => {
+            let mut child = Command::new("xprop")
+                .args(&["-spy", "-root", "_NET_ACTIVE_WINDOW"])
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("failed to run xprop: {}", e))?;
+            let out = BufReader::new(child.stdout.take().unwrap());
+            thread::spawn(move || {
+                for id in out.lines().filter_map(|l| l.ok()).filter_map(|l| parse_active(&l)) {
+                    if let Some(class) = window_class(&id) {
+                        send.send(class);
+                    }
+                }
+                debug!("stopped watching the focus in X11");
+            });
+            Ok(FocusWatcher { handle: Handle::Child(child) })
+        },
+    }
+}
+
+/// Finds one of the sockets of the Hyprland instance with the signature `sig`: ".socket.sock"
+/// (for commands) or ".socket2.sock" (for events).
+///
+/// Newer versions of Hyprland keep their sockets in the runtime directory, and older ones in /tmp.
+pub fn hyprland_socket(sig: &str, name: &str) -> PathBuf {
+    let runtime = env::var_os("XDG_RUNTIME_DIR").map(|d| {
+        let mut p = PathBuf::from(d);
+        p.push("hypr");
+        p.push(sig);
+        p.push(name);
+        p
+    });
+    match runtime {
+        Some(ref p) if p.exists() => p.clone(),
+        _ => PathBuf::from(format!("/tmp/hypr/{}/{}", sig, name)),
+    }
+}
+
+// Parses one of sway's window events, like
+// {"change": "focus", "container": {"app_id": "xournalpp", ...}}
+// which has a "window_properties" object with a "class" instead of an "app_id" for X11 windows.
+fn parse_sway(line: &str) -> Option<String> {
+    let ev = serde_json::from_str::<serde_json::Value>(line).ok()?;
+    if ev["change"] != "focus" {
+        return None;
+    }
+    let c = &ev["container"];
+    c["app_id"].as_str()
+        .or_else(|| c["window_properties"]["class"].as_str())
+        .map(|s| s.to_owned())
+}
+
+// Parses one of Hyprland's events, like "activewindow>>xournalpp,Untitled".
+fn parse_hyprland(line: &str) -> Option<String> {
+    if !line.starts_with("activewindow>>") {
+        return None;
+    }
+    let class = line["activewindow>>".len()..].split(',').next().unwrap_or("");
+    if class.is_empty() {
+        None
+    } else {
+        Some(class.to_owned())
+    }
+}
+
+// Parses a line like "_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007", returning the id.
+fn parse_active(line: &str) -> Option<String> {
+    let id = line.rsplit(' ').next()?;
+    if id.starts_with("0x") && id != "0x0" {
+        Some(id.to_owned())
+    } else {
+        None
+    }
+}
+
+// Parses a line like `WM_CLASS(STRING) = "xournalpp", "Xournalpp"`, returning the class (which
+// is the second one).
+fn parse_class(line: &str) -> Option<String> {
+    let parts = line.trim().split('"').collect::<Vec<_>>();
+    if parts.len() < 3 || parts[parts.len() - 2].is_empty() {
+        return None;
+    }
+    Some(parts[parts.len() - 2].to_owned())
+}
+
+fn window_class(id: &str) -> Option<String> {
+    let out = Command::new("xprop").args(&["-id", id, "WM_CLASS"]).output().ok()?;
+    parse_class(&String::from_utf8_lossy(&out.stdout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_focus_events() {
+        let sway = r#"{"change": "focus", "container": {"app_id": "xournalpp"}}"#;
+        assert_eq!(parse_sway(sway), Some("xournalpp".to_owned()));
+        let x = r#"{"change": "focus", "container": {"app_id": null,
+                    "window_properties": {"class": "krita"}}}"#;
+        assert_eq!(parse_sway(&x.replace('\n', "")), Some("krita".to_owned()));
+        assert_eq!(parse_sway(r#"{"change": "title", "container": {"app_id": "a"}}"#), None);
+
+        assert_eq!(parse_hyprland("activewindow>>xournalpp,Untitled, 1"),
+                   Some("xournalpp".to_owned()));
+        assert_eq!(parse_hyprland("activewindow>>,"), None);
+        assert_eq!(parse_hyprland("workspace>>2"), None);
+
+        assert_eq!(parse_active("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007"),
+                   Some("0x3a00007".to_owned()));
+        assert_eq!(parse_active("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x0"), None);
+        assert_eq!(parse_class(r#"WM_CLASS(STRING) = "xournalpp", "Xournalpp""#),
+                   Some("Xournalpp".to_owned()));
+    }
+
+    #[test]
+    fn matches_apps() {
+        let p = PauseParsed { apps: vec!["Xournalpp".to_owned()], focus: None }
+            .to_pause()
+            .unwrap();
+        assert_eq!(p.focus, FocusSource::Auto);
+        assert!(p.pauses_for("xournalpp"));
+        assert!(!p.pauses_for("firefox"));
+        assert!(PauseParsed { apps: vec![], focus: Some("wayland".to_owned()) }
+            .to_pause()
+            .is_err());
+    }
+}
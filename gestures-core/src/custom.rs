@@ -0,0 +1,148 @@
+//! Gestures that are defined in the config file, instead of being built in.
+//!
+//! A `[[gesture]]` section describes a gesture with a handful of settings (see
+//! [`Spec`](../../libgestures/gestures/spec/struct.Spec.html)), and gives it a name. Bindings then
+//! refer to it as `"custom NAME"`:
+//!
+//! ```toml
+//! [[gesture]]
+//! name = "flick-up"
+//! fingers = 3
+//! move = "straight"
+//! direction = "up"
+//! min-len-mm = 20.0
+//! max-duration-ms = 300
+//! end = "move"
+//! # Only count the contacts that are fingers, or that nothing could tell apart.
+//! contacts = ["finger", "unknown"]
+//! # Any of the settings from [tuning], just for this gesture.
+//! [gesture.tuning.filter]
+//! shape-mm = 6.0
+//!
+//! [[bindings]]
+//! gesture = "custom flick-up"
+//! exec = "rofi -show run"
+//! ```
+
+use libgestures::classify::Kinds;
+use libgestures::geom::Direction;
+use libgestures::gestures::spec::{End, Motion, Spec};
+use libgestures::manager::Manager;
+use libgestures::params::Params;
+use libgestures::Recognizer;
+use std::fmt;
+use std::str;
+
+use Gesture;
+
+/// The longest name of a custom gesture, in bytes.
+pub const MAX_NAME_LEN: usize = 24;
+
+/// The name of a custom gesture, or of a gesture that a plugin recognizes.
+///
+/// The name is stored inline (like the directions of a
+/// [`Path`](../../libgestures/gestures/struct.Path.html)), so that a `Gesture` can still be
+/// copied around freely.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CustomName {
+    len: u8,
+    // Only the first `len` of these are used; the rest are always zero.
+    bytes: [u8; MAX_NAME_LEN],
+}
+
+impl CustomName {
+    /// Returns `None` if `name` is empty, too long, or contains whitespace.
+    pub fn new(name: &str) -> Option<CustomName> {
+        if name.is_empty() || name.len() > MAX_NAME_LEN || name.contains(char::is_whitespace) {
+            return None;
+        }
+        let mut ret = CustomName {
+            len: name.len() as u8,
+            bytes: [0; MAX_NAME_LEN],
+        };
+        ret.bytes[..name.len()].copy_from_slice(name.as_bytes());
+        Some(ret)
+    }
+
+    pub fn as_str(&self) -> &str {
+        // This came from a `&str`, so it's valid UTF-8.
+        str::from_utf8(&self.bytes[..self.len as usize]).unwrap()
+    }
+}
+
+impl fmt::Display for CustomName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A `[[gesture]]` section of the config file.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct CustomParsed {
+    pub name: String,
+    fingers: u8,
+    /// "straight", "spread", "grab", "shake", or "path" followed by directions (like "path down
+    /// right").
+    #[serde(rename = "move")]
+    motion: String,
+    /// For straight gestures, "up", "down", "left" or "right". If this is missing, the gesture
+    /// may go in any direction.
+    direction: Option<String>,
+    #[serde(rename = "min-len-mm")]
+    min_len_mm: Option<f64>,
+    #[serde(rename = "max-duration-ms")]
+    max_duration_ms: Option<u32>,
+    /// "lift" (the default) or "move".
+    end: Option<String>,
+    /// The kinds of contact that count as fingers: "finger", "thumb", "palm", "pen" or
+    /// "unknown". If this is missing, they all do (except for the ones that are left out
+    /// altogether).
+    contacts: Option<Vec<String>>,
+    /// The settings from the top-level `tuning` section that are different for this gesture.
+    pub tuning: Option<::toml::Value>,
+}
+
+impl CustomParsed {
+    /// Checks the settings, and turns them into a `Spec`.
+    pub fn to_spec(&self) -> Result<Spec, String> {
+        let err = |e: String| format!("in gesture {:?}: {}", self.name, e);
+        let mut spec = Spec::new(self.fingers, self.motion.parse::<Motion>().map_err(&err)?);
+        spec.direction = match self.direction {
+            Some(ref d) => Some(d.parse::<Direction>().map_err(&err)?),
+            None => None,
+        };
+        spec.min_len_mm = self.min_len_mm;
+        spec.max_duration_ms = self.max_duration_ms;
+        if let Some(ref e) = self.end {
+            spec.end = e.parse::<End>().map_err(&err)?;
+        }
+        if let Some(ref c) = self.contacts {
+            spec.contacts = Some(c.join(" ").parse::<Kinds>().map_err(&err)?);
+        }
+        // `Spec::parse` knows which combinations of settings make sense.
+        spec.to_string().parse::<Spec>().map_err(&err)
+    }
+}
+
+/// A gesture defined in the config file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CustomGesture {
+    pub name: CustomName,
+    pub spec: Spec,
+    /// If set, the thresholds for this gesture, which take precedence over the ones for the
+    /// device.
+    pub params: Option<Params>,
+}
+
+impl CustomGesture {
+    /// Adds a recognizer for this gesture to `man`, returning a description of it.
+    ///
+    /// Like the built-in gestures, gestures with more fingers get priority.
+    pub fn push_to(&self, man: &mut Manager<Gesture>, params: Params) -> String {
+        let name = self.name;
+        let rec = self.spec.build(&self.params.unwrap_or(params))
+            .map_outcome(move |_| Gesture::Custom(name));
+        man.push_with_priority(rec, self.spec.fingers as i32);
+        format!("custom {} ({})", name, self.spec)
+    }
+}
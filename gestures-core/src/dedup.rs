@@ -0,0 +1,86 @@
+//! Firing a gesture only once when several devices saw it.
+//!
+//! Some hardware shows up as more than one device. A touchscreen and its pen digitizer, for
+//! example, can both see the same swipe, and since each device gets its own recognizers, the
+//! binding would fire twice. With `dedup-ms` set, a gesture is dropped if a different device
+//! recognized an equal gesture that ended less than that many milliseconds before or after it:
+//!
+//! ```toml
+//! dedup-ms = 100
+//! ```
+//!
+//! The same device recognizing a gesture twice in a row is never a duplicate, so quick repeated
+//! swipes still fire every time.
+
+use Gesture;
+
+/// A gesture that was recently fired.
+#[derive(Clone, Debug)]
+struct Fired {
+    gesture: Gesture,
+    device: String,
+    end_time: u32,
+}
+
+/// Remembers the gestures that were recently fired, and which devices they came from.
+#[derive(Debug, Default)]
+pub struct Dedup {
+    recent: Vec<Fired>,
+}
+
+// How many milliseconds apart `a` and `b` are, allowing for the clock wrapping around.
+fn distance(a: u32, b: u32) -> u32 {
+    a.wrapping_sub(b).min(b.wrapping_sub(a))
+}
+
+impl Dedup {
+    pub fn new() -> Dedup {
+        Dedup::default()
+    }
+
+    /// Returns true if a device other than `device` recognized `gesture` within `window_ms`
+    /// milliseconds of `end_time`.
+    ///
+    /// If it returns false, the gesture is remembered, so that the other devices' copies of it
+    /// count as duplicates.
+    pub fn is_duplicate(&mut self, gesture: &Gesture, device: &str, end_time: u32, window_ms: u32)
+    -> bool {
+        self.recent.retain(|f| distance(f.end_time, end_time) <= window_ms);
+        let dup = self.recent.iter()
+            .any(|f| f.gesture == *gesture && f.device != device);
+        if !dup {
+            self.recent.push(Fired {
+                gesture: *gesture,
+                device: device.to_owned(),
+                end_time: end_time,
+            });
+        }
+        dup
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libgestures::geom::Direction;
+    use TouchGesture;
+
+    #[test]
+    fn only_other_devices_are_duplicates() {
+        let up = Gesture::Touch(TouchGesture::Swipe { num_fingers: 3, direction: Direction::Up });
+        let down = Gesture::Touch(TouchGesture::Swipe { num_fingers: 3,
+                                                        direction: Direction::Down });
+        let mut d = Dedup::new();
+        assert!(!d.is_duplicate(&up, "event5", 1000, 100));
+        // The same device, swiping again.
+        assert!(!d.is_duplicate(&up, "event5", 1020, 100));
+        // The pen digitizer, which saw the same swipe.
+        assert!(d.is_duplicate(&up, "event6", 1050, 100));
+        assert!(!d.is_duplicate(&down, "event6", 1060, 100));
+        // Long enough afterwards, it's a new swipe.
+        assert!(!d.is_duplicate(&up, "event6", 1500, 100));
+        // The clock can wrap around.
+        assert!(!d.is_duplicate(&up, "event5", u32::max_value() - 10, 100));
+        assert!(d.is_duplicate(&up, "event6", 20, 100));
+    }
+}
@@ -0,0 +1,455 @@
+//! Switching workspaces and snapping windows, which are what most people bind their swipes to,
+//! without having to install and configure other tools:
+//!
+//! ```toml
+//! # Which desktop to talk to: "auto" (the default), "sway", "i3", "hyprland", "river" or "x11".
+//! desktop = "auto"
+//!
+//! [[bindings]]
+//! gesture = "swipe 4 left"
+//! # "next", "prev", or the number of a workspace (counting from 1), like "2".
+//! workspace = "next"
+//!
+//! [[bindings]]
+//! gesture = "swipe 3 left"
+//! # "left" or "right".
+//! snap = "left"
+//! ```
+//!
+//! How this happens depends on the desktop, which is normally worked out from the environment
+//! variables:
+//!
+//! - sway and i3 are sent commands over their IPC sockets. Since their windows are usually tiled,
+//!   snapping a window moves it one place to the left or right.
+//! - Hyprland is sent dispatchers over its IPC socket. The next and previous workspaces are the
+//!   ones that have windows, and snapping moves a window like it does in sway.
+//! - river is sent commands with its `river-control` Wayland protocol, which is what `riverctl`
+//!   does. Its workspaces are tags, so switching to workspace 3 shows the windows tagged with the
+//!   third tag. River has no next or previous tag, so only numbered workspaces work. Snapping a
+//!   window swaps it with the previous or next window in the layout.
+//! - Any other X11 window manager is sent the EWMH client messages that `wmctrl` sends. Snapped
+//!   windows fill half of the work area, which covers all the monitors.
+//!
+//! GNOME Shell on X11 is one of those window managers. On Wayland, it has no supported way for
+//! other programs to switch workspaces or move windows (its `Eval` D-Bus method is disabled since
+//! GNOME 41), so these bindings don't work there.
+
+use std::env;
+
+/// Which workspace to switch to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Workspace {
+    Next,
+    Prev,
+    /// The workspace with this number, counting from 1.
+    Number(u32),
+}
+
+impl Workspace {
+    pub fn parse(s: &str) -> Option<Workspace> {
+        match s {
+            "next" => Some(Workspace::Next),
+            "prev" => Some(Workspace::Prev),
+            n => match n.parse() {
+                Ok(0) | Err(_) => None,
+                Ok(n) => Some(Workspace::Number(n)),
+            },
+        }
+    }
+}
+
+/// Which half of the screen to snap a window to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    pub fn parse(s: &str) -> Option<Side> {
+        match s {
+            "left" => Some(Side::Left),
+            "right" => Some(Side::Right),
+            _ => None,
+        }
+    }
+}
+
+/// The desktops that we know how to talk to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Desktop {
+    /// Work out the desktop from the environment when the action runs.
+    Auto,
+    /// sway or i3, which speak the same IPC protocol.
+    I3,
+    Hyprland,
+    River,
+    X11,
+}
+
+impl Desktop {
+    pub fn parse(s: &str) -> Option<Desktop> {
+        match s {
+            "auto" => Some(Desktop::Auto),
+            "sway" | "i3" => Some(Desktop::I3),
+            "hyprland" => Some(Desktop::Hyprland),
+            "river" => Some(Desktop::River),
+            "x11" => Some(Desktop::X11),
+            _ => None,
+        }
+    }
+
+    // River doesn't set a variable of its own, so we only recognize it by the
+    // `XDG_CURRENT_DESKTOP` that some setups set. GNOME on Wayland also sets `DISPLAY` for
+    // XWayland, but its windows don't listen to EWMH messages from X clients.
+    fn resolve(self) -> Result<Desktop, String> {
+        if self != Desktop::Auto {
+            return Ok(self);
+        }
+        let current = env::var("XDG_CURRENT_DESKTOP").unwrap_or_else(|_| String::new());
+        let current = current.split(':').collect::<Vec<_>>();
+        if env::var_os("SWAYSOCK").is_some() || env::var_os("I3SOCK").is_some() {
+            Ok(Desktop::I3)
+        } else if env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+            Ok(Desktop::Hyprland)
+        } else if current.contains(&"river") {
+            Ok(Desktop::River)
+        } else if current.contains(&"GNOME") && env::var_os("WAYLAND_DISPLAY").is_some() {
+            Err("GNOME Shell doesn't let other programs switch workspaces or move windows under \
+                 Wayland".to_owned())
+        } else if env::var_os("DISPLAY").is_some() {
+            Ok(Desktop::X11)
+        } else {
+            Err("couldn't tell which desktop is running".to_owned())
+        }
+    }
+}
+
+/// Switches to another workspace.
+pub fn switch_workspace(w: Workspace, desktop: Desktop) -> Result<(), String> {
+    match desktop.resolve()? {
+        Desktop::I3 => i3::run(&match w {
+            Workspace::Next => "workspace next".to_owned(),
+            Workspace::Prev => "workspace prev".to_owned(),
+            Workspace::Number(n) => format!("workspace number {}", n),
+        }),
+        Desktop::Hyprland => hyprland::dispatch(&match w {
+            Workspace::Next => "workspace e+1".to_owned(),
+            Workspace::Prev => "workspace e-1".to_owned(),
+            Workspace::Number(n) => format!("workspace {}", n),
+        }),
+        Desktop::River => match w {
+            // River has 32 tags.
+            Workspace::Number(n) if n <= 32 => {
+                let tags = (1u32 << (n - 1)).to_string();
+                river::run(&["set-focused-tags", tags.as_str()])
+            },
+            Workspace::Number(n) => Err(format!("river has no tag {}", n)),
+            _ => Err("river can only switch to numbered workspaces".to_owned()),
+        },
+        Desktop::X11 => ewmh::switch_workspace(w),
+        Desktop::Auto => unreachable!(),
+    }
+}
+
+/// Makes the focused window fill one half of the screen.
+pub fn snap_window(side: Side, desktop: Desktop) -> Result<(), String> {
+    match desktop.resolve()? {
+        Desktop::I3 => i3::run(match side {
+            Side::Left => "move left",
+            Side::Right => "move right",
+        }),
+        Desktop::Hyprland => hyprland::dispatch(match side {
+            Side::Left => "movewindow l",
+            Side::Right => "movewindow r",
+        }),
+        Desktop::River => river::run(&["swap", match side {
+            Side::Left => "previous",
+            Side::Right => "next",
+        }]),
+        Desktop::X11 => ewmh::snap_window(side),
+        Desktop::Auto => unreachable!(),
+    }
+}
+
+mod i3 {
+    use serde_json;
+    use std::env;
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+
+    use wayland::{from_bytes, to_bytes};
+
+    const MAGIC: &'static [u8] = b"i3-ipc";
+    const RUN_COMMAND: u32 = 0;
+
+    // The reply to each command in a RUN_COMMAND message.
+    #[derive(Deserialize)]
+    struct Reply {
+        success: bool,
+        error: Option<String>,
+    }
+
+    /// Runs a command, like `swaymsg` does.
+    pub fn run(command: &str) -> Result<(), String> {
+        let path = env::var_os("SWAYSOCK").or_else(|| env::var_os("I3SOCK"))
+            .ok_or("couldn't find the IPC socket".to_owned())?;
+        let mut sock = UnixStream::connect(&path)
+            .map_err(|e| format!("couldn't connect to {:?}: {}", path, e))?;
+        let mut msg = MAGIC.to_vec();
+        msg.extend_from_slice(&to_bytes(command.len() as u32));
+        msg.extend_from_slice(&to_bytes(RUN_COMMAND));
+        msg.extend_from_slice(command.as_bytes());
+        sock.write_all(&msg).map_err(|e| format!("couldn't send {:?}: {}", command, e))?;
+
+        let mut header = [0; 14];
+        sock.read_exact(&mut header).map_err(|e| format!("no reply to {:?}: {}", command, e))?;
+        let mut body = vec![0; from_bytes(&header[MAGIC.len()..]) as usize];
+        sock.read_exact(&mut body).map_err(|e| format!("no reply to {:?}: {}", command, e))?;
+        let replies: Vec<Reply> = serde_json::from_slice(&body)
+            .map_err(|e| format!("bad reply to {:?}: {}", command, e))?;
+        match replies.into_iter().find(|r| !r.success) {
+            Some(r) => Err(format!("{:?} failed: {}", command,
+                                   r.error.unwrap_or_else(|| "no reason given".to_owned()))),
+            None => Ok(()),
+        }
+    }
+}
+
+mod hyprland {
+    use context;
+    use std::env;
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+
+    /// Runs a dispatcher, like `hyprctl dispatch` does.
+    pub fn dispatch(args: &str) -> Result<(), String> {
+        let sig = env::var("HYPRLAND_INSTANCE_SIGNATURE")
+            .map_err(|_| "HYPRLAND_INSTANCE_SIGNATURE isn't set".to_owned())?;
+        let mut sock = UnixStream::connect(context::hyprland_socket(&sig, ".socket.sock"))
+            .map_err(|e| format!("failed to connect to Hyprland: {}", e))?;
+        sock.write_all(format!("dispatch {}", args).as_bytes())
+            .map_err(|e| format!("couldn't send {:?} to Hyprland: {}", args, e))?;
+        // Hyprland closes the connection after it replies.
+        let mut reply = String::new();
+        sock.read_to_string(&mut reply)
+            .map_err(|e| format!("no reply to {:?} from Hyprland: {}", args, e))?;
+        match reply.trim() {
+            "ok" => Ok(()),
+            e => Err(format!("Hyprland couldn't run {:?}: {}", args, e)),
+        }
+    }
+}
+
+// Runs river commands with its `zriver_control_v1` global.
+mod river {
+    use wayland::{self, Arg, Args, Conn};
+
+    // The opcodes of the requests and events that we use.
+    const CONTROL_ADD_ARGUMENT: u16 = 1;
+    const CONTROL_RUN_COMMAND: u16 = 2;
+    const CALLBACK_SUCCESS: u16 = 0;
+
+    /// Runs a command, like `riverctl` does.
+    pub fn run(command: &[&str]) -> Result<(), String> {
+        let mut conn = Conn::open()?;
+        let globals = conn.globals()?;
+        let control = wayland::find(&globals, "zriver_control_v1")
+            .ok_or("the compositor isn't river".to_owned())?;
+        let seat = wayland::find(&globals, "wl_seat")
+            .ok_or("the compositor has no seat".to_owned())?;
+
+        let control = conn.bind(control, 1)?;
+        let seat = conn.bind(seat, 1)?;
+        for a in command {
+            conn.send(control, CONTROL_ADD_ARGUMENT, &[Arg::Str(a)])?;
+        }
+        let callback = conn.new_id();
+        conn.send(control, CONTROL_RUN_COMMAND, &[Arg::Uint(seat), Arg::Uint(callback)])?;
+        loop {
+            let (object, opcode, body) = conn.recv()?;
+            if object == callback {
+                return if opcode == CALLBACK_SUCCESS {
+                    Ok(())
+                } else {
+                    let msg = Args(&body).string().unwrap_or_else(String::new);
+                    Err(format!("river couldn't run {:?}: {}", command.join(" "), msg))
+                };
+            }
+        }
+    }
+}
+
+#[cfg(feature = "x11")]
+mod ewmh {
+    use libc::{c_int, c_long, c_uchar, c_ulong};
+    use std::ffi::CString;
+    use std::mem;
+    use std::ptr;
+    use std::slice;
+    use x11::xlib;
+
+    use super::{Side, Workspace};
+
+    // A connection to the X server, which is closed when this is dropped.
+    struct Display(*mut xlib::Display);
+
+    impl Display {
+        fn open() -> Result<Display, String> {
+            let d = unsafe { xlib::XOpenDisplay(ptr::null()) };
+            if d.is_null() {
+                Err("couldn't connect to the X server".to_owned())
+            } else {
+                Ok(Display(d))
+            }
+        }
+
+        fn root(&self) -> xlib::Window {
+            unsafe { xlib::XDefaultRootWindow(self.0) }
+        }
+
+        fn atom(&self, name: &str) -> xlib::Atom {
+            let name = CString::new(name).unwrap();
+            unsafe { xlib::XInternAtom(self.0, name.as_ptr(), xlib::False) }
+        }
+
+        // Reads a property that is a list of 32-bit numbers (which Xlib gives us as longs).
+        fn property(&self, w: xlib::Window, name: &str) -> Result<Vec<c_ulong>, String> {
+            let mut actual_type = 0;
+            let mut format: c_int = 0;
+            let mut len: c_ulong = 0;
+            let mut remaining: c_ulong = 0;
+            let mut data: *mut c_uchar = ptr::null_mut();
+            let status = unsafe {
+                xlib::XGetWindowProperty(self.0, w, self.atom(name), 0, 1024, xlib::False, 0,
+                                         &mut actual_type, &mut format, &mut len, &mut remaining,
+                                         &mut data)
+            };
+            if status != 0 || data.is_null() {
+                return Err(format!("the window manager doesn't set {}", name));
+            }
+            let ret = if format == 32 {
+                unsafe { slice::from_raw_parts(data as *const c_ulong, len as usize).to_vec() }
+            } else {
+                Vec::new()
+            };
+            unsafe { xlib::XFree(data as *mut _); }
+            if ret.is_empty() {
+                Err(format!("the window manager doesn't set {}", name))
+            } else {
+                Ok(ret)
+            }
+        }
+
+        fn property_value(&self, w: xlib::Window, name: &str) -> Result<c_ulong, String> {
+            self.property(w, name).map(|p| p[0])
+        }
+
+        // Asks the window manager to do something to `w`.
+        fn send(&self, w: xlib::Window, name: &str, data: [c_long; 5]) {
+            let mut ev: xlib::XClientMessageEvent = unsafe { mem::zeroed() };
+            ev.type_ = xlib::ClientMessage;
+            ev.send_event = xlib::True;
+            ev.display = self.0;
+            ev.window = w;
+            ev.message_type = self.atom(name);
+            ev.format = 32;
+            for (i, &x) in data.iter().enumerate() {
+                ev.data.set_long(i, x);
+            }
+            let mut ev = xlib::XEvent::from(ev);
+            unsafe {
+                xlib::XSendEvent(self.0, self.root(), xlib::False,
+                                 xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask,
+                                 &mut ev);
+            }
+        }
+    }
+
+    impl Drop for Display {
+        fn drop(&mut self) {
+            unsafe {
+                xlib::XFlush(self.0);
+                xlib::XCloseDisplay(self.0);
+            }
+        }
+    }
+
+    pub fn switch_workspace(w: Workspace) -> Result<(), String> {
+        let d = Display::open()?;
+        let root = d.root();
+        let last = d.property_value(root, "_NET_NUMBER_OF_DESKTOPS")?.saturating_sub(1);
+        let current = d.property_value(root, "_NET_CURRENT_DESKTOP")?;
+        let target = match w {
+            Workspace::Next => (current + 1).min(last),
+            Workspace::Prev => current.saturating_sub(1),
+            Workspace::Number(n) => (n as c_ulong - 1).min(last),
+        };
+        // The last field is the timestamp, which we don't know.
+        d.send(root, "_NET_CURRENT_DESKTOP", [target as c_long, 0, 0, 0, 0]);
+        Ok(())
+    }
+
+    pub fn snap_window(side: Side) -> Result<(), String> {
+        // Says that the request comes from a pager (or something like it), so that window
+        // managers don't ignore it.
+        const SOURCE: c_long = 2;
+        // Says that the x, y, width and height are all set, and that the gravity is the window's.
+        const MOVE_RESIZE_FLAGS: c_long = 0xf00 | (SOURCE << 12);
+        const REMOVE: c_long = 0;
+
+        let d = Display::open()?;
+        let root = d.root();
+        let window = d.property_value(root, "_NET_ACTIVE_WINDOW")?;
+        if window == 0 {
+            return Err("no window is focused".to_owned());
+        }
+        // The work area is given for each workspace.
+        let current = d.property_value(root, "_NET_CURRENT_DESKTOP").unwrap_or(0) as usize;
+        let areas = d.property(root, "_NET_WORKAREA")?;
+        let area = match areas.chunks(4).nth(current).or_else(|| areas.chunks(4).next()) {
+            Some(a) if a.len() == 4 => a,
+            _ => return Err("the window manager doesn't set _NET_WORKAREA".to_owned()),
+        };
+        let (x, y, width, height) = (area[0] as c_long, area[1] as c_long, area[2] as c_long,
+                                     area[3] as c_long);
+
+        let vert = d.atom("_NET_WM_STATE_MAXIMIZED_VERT") as c_long;
+        let horz = d.atom("_NET_WM_STATE_MAXIMIZED_HORZ") as c_long;
+        d.send(window, "_NET_WM_STATE", [REMOVE, vert, horz, SOURCE, 0]);
+        let x = match side {
+            Side::Left => x,
+            Side::Right => x + width / 2,
+        };
+        d.send(window, "_NET_MOVERESIZE_WINDOW", [MOVE_RESIZE_FLAGS, x, y, width / 2, height]);
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "x11"))]
+mod ewmh {
+    use super::{Side, Workspace};
+
+    pub fn switch_workspace(_: Workspace) -> Result<(), String> {
+        Err("gestures was built without X11 support".to_owned())
+    }
+
+    pub fn snap_window(_: Side) -> Result<(), String> {
+        Err("gestures was built without X11 support".to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(Workspace::parse("next"), Some(Workspace::Next));
+        assert_eq!(Workspace::parse("3"), Some(Workspace::Number(3)));
+        assert_eq!(Workspace::parse("0"), None);
+        assert_eq!(Workspace::parse("last"), None);
+        assert_eq!(Side::parse("right"), Some(Side::Right));
+        assert_eq!(Side::parse("up"), None);
+    }
+}
@@ -0,0 +1,281 @@
+//! Changing the bindings in a config file, for `gestures bind` and for config editors.
+//!
+//! The file is edited in place, so its comments and formatting survive. Only bindings written as
+//! `[[bindings]]` tables can be edited; the ones in an inline array are left alone.
+//!
+//! ```no_run
+//! # use gestures_core::edit::ConfigFile;
+//! let mut file = ConfigFile::open_user().unwrap();
+//! file.bind("swipe 3 up", &["wmctrl".to_owned(), "-s".to_owned(), "1".to_owned()], None)
+//!     .unwrap();
+//! file.save().unwrap();
+//! ```
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use toml_edit::{value, ArrayOfTables, Document, Item, Table, Value};
+
+use Gesture;
+use config;
+use profile;
+
+// The keys that say what a binding does, in the order that `describe` looks for them.
+const ACTION_KEYS: &'static [&'static str] =
+    &["exec", "command", "key", "click", "workspace", "snap", "brightness", "volume", "internal",
+      "switch_profile", "plugin"];
+
+// The keys that make a binding fire only some of the time.
+const CONDITION_KEYS: &'static [&'static str] = &["when", "modifiers", "source"];
+
+/// A binding in a config file, as [`ConfigFile::bindings`](struct.ConfigFile.html) lists it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BindingEntry {
+    /// The gesture, as it is written in the file.
+    pub gesture: String,
+    pub profile: String,
+    /// What the binding does, like "command wmctrl -s 1" or "key ctrl+t".
+    pub action: String,
+}
+
+/// A config file, ready to be edited.
+pub struct ConfigFile {
+    path: PathBuf,
+    doc: Document,
+}
+
+impl ConfigFile {
+    /// Opens the main config file in the user's config directory. If it doesn't exist yet, it
+    /// starts out empty.
+    pub fn open_user() -> Result<ConfigFile, String> {
+        ConfigFile::open(&config::user_config_file()?)
+    }
+
+    /// Opens the config file at `path`, which might not exist yet.
+    pub fn open(path: &Path) -> Result<ConfigFile, String> {
+        let mut contents = String::new();
+        if path.exists() {
+            File::open(path)
+                .and_then(|mut f| f.read_to_string(&mut contents))
+                .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+        }
+        ConfigFile::parse(path, &contents)
+    }
+
+    /// Makes a config file that will be saved to `path`, and contains `contents` for now.
+    pub fn parse(path: &Path, contents: &str) -> Result<ConfigFile, String> {
+        let doc = contents.parse::<Document>()
+            .map_err(|e| format!("unable to parse {}: {}", path.display(), e))?;
+        Ok(ConfigFile {
+            path: path.to_owned(),
+            doc: doc,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Lists the bindings in this file, in order.
+    pub fn bindings(&self) -> Vec<BindingEntry> {
+        let tables = self.doc.as_table().get("bindings").and_then(|b| b.as_array_of_tables());
+        let tables = match tables {
+            Some(t) => t,
+            None => return Vec::new(),
+        };
+        tables.iter()
+            .map(|t| BindingEntry {
+                gesture: get_str(t, "gesture").unwrap_or("").to_owned(),
+                profile: get_str(t, "profile").unwrap_or(profile::DEFAULT).to_owned(),
+                action: describe(t),
+            })
+            .collect()
+    }
+
+    // The `[[bindings]]` tables, which are added if there aren't any.
+    fn tables_mut(&mut self) -> Result<&mut ArrayOfTables, String> {
+        if self.doc["bindings"].is_none() {
+            self.doc["bindings"] = Item::ArrayOfTables(ArrayOfTables::new());
+        }
+        let path = &self.path;
+        self.doc["bindings"].as_array_of_tables_mut().ok_or_else(|| {
+            format!("the bindings in {} aren't [[bindings]] tables, so they can't be edited",
+                    path.display())
+        })
+    }
+
+    /// Binds `gesture` (written like "swipe 3 up") in `profile` (or in the default profile) to
+    /// run `command`, whose first element is the program to run.
+    ///
+    /// This replaces any other binding in this file for the same gesture in the same profile,
+    /// unless it only fires under some conditions. Returns how many bindings were replaced.
+    pub fn bind(&mut self, gesture: &str, command: &[String], profile: Option<&str>)
+    -> Result<usize, String> {
+        let g = config::parse_gesture(gesture).ok_or(format!("unknown gesture {:?}", gesture))?;
+        let (program, args) = command.split_first()
+            .ok_or(format!("there is no command to bind {:?} to", gesture))?;
+        let profile = profile.unwrap_or(profile::DEFAULT);
+        let tables = self.tables_mut()?;
+
+        let replaced = (0..tables.len())
+            .filter(|&i| {
+                let t = tables.get(i).unwrap();
+                belongs_to(t, &g, profile) && !CONDITION_KEYS.iter().any(|k| t.contains_key(k))
+            })
+            .collect::<Vec<_>>();
+        for &i in replaced.iter().rev() {
+            tables.remove(i);
+        }
+
+        let mut t = Table::new();
+        t["gesture"] = value(g.to_string());
+        t["command"] = value(program.as_str());
+        if !args.is_empty() {
+            t["args"] = Item::Value(args.iter().map(|a| a.as_str()).collect::<Value>());
+        }
+        if profile != profile::DEFAULT {
+            t["profile"] = value(profile);
+        }
+        tables.append(t);
+        Ok(replaced.len())
+    }
+
+    /// Removes all the bindings in this file for `gesture` in `profile` (or in the default
+    /// profile), returning how many there were.
+    pub fn unbind(&mut self, gesture: &str, profile: Option<&str>) -> Result<usize, String> {
+        let g = config::parse_gesture(gesture).ok_or(format!("unknown gesture {:?}", gesture))?;
+        let profile = profile.unwrap_or(profile::DEFAULT);
+        if self.doc.as_table().get("bindings").is_none() {
+            return Ok(0);
+        }
+        let tables = self.tables_mut()?;
+        let removed = (0..tables.len())
+            .filter(|&i| belongs_to(tables.get(i).unwrap(), &g, profile))
+            .collect::<Vec<_>>();
+        for &i in removed.iter().rev() {
+            tables.remove(i);
+        }
+        Ok(removed.len())
+    }
+
+    /// Checks that the daemon would accept the config files, with this one changed.
+    pub fn check(&self) -> Result<(), String> {
+        config::load_config_with(&self.path, &self.doc.to_string()).map(|_| ())
+    }
+
+    /// Checks the changes, and then writes them to the file.
+    ///
+    /// The new contents go to a temporary file first, so that a daemon that is reading the file
+    /// at the same time sees either the old contents or the new ones.
+    pub fn save(&self) -> Result<(), String> {
+        self.check()?;
+        let err = |e: io::Error| format!("couldn't write {}: {}", self.path.display(), e);
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(&err)?;
+        }
+        let tmp = self.path.with_extension("toml.new");
+        File::create(&tmp)
+            .and_then(|mut f| f.write_all(self.doc.to_string().as_bytes()))
+            .and_then(|_| fs::rename(&tmp, &self.path))
+            .map_err(&err)
+    }
+}
+
+/// Writes the file's contents, as `save` would.
+impl fmt::Display for ConfigFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.doc)
+    }
+}
+
+fn get_str<'a>(t: &'a Table, key: &str) -> Option<&'a str> {
+    t.get(key).and_then(|i| i.as_str())
+}
+
+// Is `t` a binding for `gesture` in `profile`?
+fn belongs_to(t: &Table, gesture: &Gesture, profile: &str) -> bool {
+    get_str(t, "gesture").and_then(config::parse_gesture).as_ref() == Some(gesture)
+        && get_str(t, "profile").unwrap_or(profile::DEFAULT) == profile
+}
+
+// Describes what the binding `t` does, like "command wmctrl -s 1".
+fn describe(t: &Table) -> String {
+    let key = match ACTION_KEYS.iter().find(|k| t.contains_key(k)) {
+        Some(k) => k,
+        None => return "nothing".to_owned(),
+    };
+    let mut ret = format!("{} {}", key, get_str(t, key).unwrap_or("?"));
+    if let Some(args) = t.get("args").and_then(|a| a.as_array()) {
+        for a in args.iter().filter_map(|a| a.as_str()) {
+            ret.push(' ');
+            ret.push_str(a);
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &'static str = r#"# Workspaces
+[[bindings]]
+gesture = "swipe 3 up"
+exec = "wmctrl -s 0"  # the first one
+
+[[bindings]]
+gesture = "swipe 3 up"
+exec = "wmctrl -s 2"
+modifiers = "super"
+
+[[bindings]]
+gesture = "tap 2"
+key = "ctrl+t"
+profile = "browsing"
+"#;
+
+    fn file() -> ConfigFile {
+        ConfigFile::parse(Path::new("bindings.toml"), CONFIG).unwrap()
+    }
+
+    #[test]
+    fn lists_bindings() {
+        let b = file().bindings();
+        assert_eq!(b.len(), 3);
+        assert_eq!(b[0].action, "exec wmctrl -s 0");
+        assert_eq!(b[2], BindingEntry {
+            gesture: "tap 2".to_owned(),
+            profile: "browsing".to_owned(),
+            action: "key ctrl+t".to_owned(),
+        });
+        assert!(ConfigFile::parse(Path::new("empty.toml"), "").unwrap().bindings().is_empty());
+    }
+
+    #[test]
+    fn binds_and_unbinds() {
+        let mut f = file();
+        let command = vec!["wmctrl".to_owned(), "-s".to_owned(), "1".to_owned()];
+        // The binding with a modifier stays.
+        assert_eq!(f.bind("swipe 3 up", &command, None), Ok(1));
+        let out = f.to_string();
+        assert!(out.starts_with("# Workspaces\n"));
+        assert!(!out.contains("the first one"));
+        let b = f.bindings();
+        assert_eq!(b.len(), 3);
+        assert_eq!(b[2].action, "command wmctrl -s 1");
+
+        assert_eq!(f.bind("tap 2", &command[..1], Some("browsing")), Ok(1));
+        assert!(f.bind("swipe sideways", &command, None).is_err());
+        assert!(f.bind("tap 2", &[], None).is_err());
+
+        assert_eq!(f.unbind("swipe 3 up", None), Ok(2));
+        assert_eq!(f.unbind("tap 2", None), Ok(0));
+        assert_eq!(f.bindings().len(), 1);
+
+        let mut empty = ConfigFile::parse(Path::new("empty.toml"), "").unwrap();
+        assert_eq!(empty.unbind("tap 2", None), Ok(0));
+        assert_eq!(empty.bind("tap 2", &command, None), Ok(0));
+        assert_eq!(empty.bindings().len(), 1);
+    }
+}
@@ -0,0 +1,549 @@
+//! Synthesizing key presses and mouse clicks, for bindings that just want to press a shortcut.
+//!
+//! Under Wayland, the events go through the compositor, using the `zwp_virtual_keyboard_v1` and
+//! `zwlr_virtual_pointer_v1` protocols that wlroots-based compositors support. Unlike writing to
+//! `/dev/uinput`, this doesn't need any special permissions. The daemon speaks those protocols
+//! itself (see the `wayland` module), so nothing else needs to be installed. A virtual keyboard
+//! brings its own keymap, so each key press sends a tiny keymap with just that key on it: "ctrl+z"
+//! sends a "z" with Control held down, whatever the layout, and "question" doesn't need Shift.
+//! This backend is built with the `wayland` feature, which is on by default.
+//!
+//! Under X11, the events are injected with the XTest extension, straight from the daemon. Key
+//! names are looked up in the keyboard layout that is active when the key is pressed, so "ctrl+z"
+//! presses whichever key makes a "z", even on a French or a Dvorak keyboard. If that takes Shift
+//! or AltGr (like "question" does on most layouts), they are held down too. If no key in the
+//! layout makes that symbol at all, a spare key is borrowed for it until it has been pressed.
+
+use std::env;
+use std::io;
+use std::process::Child;
+
+use keyboard::Modifiers;
+use mouse::Button;
+
+/// A key, together with some modifiers to hold down while pressing it.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct KeyCombo {
+    pub modifiers: Modifiers,
+    /// The name of the key, as an XKB keysym name like "z" or "Page_Down".
+    pub key: String,
+}
+
+impl KeyCombo {
+    /// Parses a key combination like "ctrl+shift+t", where the last part is the key and the others
+    /// are modifiers.
+    pub fn parse(s: &str) -> Result<KeyCombo, String> {
+        let (mods, key) = match s.rfind('+') {
+            Some(i) => (&s[..i], &s[(i + 1)..]),
+            None => ("", s),
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(format!("missing key in {:?}", s));
+        }
+        let modifiers = if mods.is_empty() {
+            Modifiers::default()
+        } else {
+            Modifiers::parse(mods)?
+        };
+        Ok(KeyCombo {
+            modifiers: modifiers,
+            key: key.to_owned(),
+        })
+    }
+}
+
+/// How to send synthetic input events.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Backend {
+    /// Work out the backend from the environment when the event is sent.
+    Auto,
+    /// Use the Wayland compositor's virtual keyboard and pointer.
+    Wayland,
+    /// Use the X server's XTest extension.
+    X11,
+}
+
+impl Backend {
+    pub fn parse(s: &str) -> Option<Backend> {
+        match s {
+            "auto" => Some(Backend::Auto),
+            "wayland" => Some(Backend::Wayland),
+            "x11" => Some(Backend::X11),
+            _ => None,
+        }
+    }
+
+    // Under XWayland, both displays are set. Events that go through the X server only reach X
+    // clients then, so Wayland wins.
+    fn resolve(self) -> io::Result<Backend> {
+        match self {
+            Backend::Auto => {
+                if env::var_os("WAYLAND_DISPLAY").is_some() {
+                    Ok(Backend::Wayland)
+                } else if env::var_os("DISPLAY").is_some() {
+                    Ok(Backend::X11)
+                } else {
+                    Err(io::Error::new(io::ErrorKind::NotFound,
+                                       "couldn't find a display to send input events to"))
+                }
+            },
+            b => Ok(b),
+        }
+    }
+}
+
+#[cfg(feature = "wayland")]
+mod virtual_input {
+    use std::env;
+    use std::fs::{self, File, OpenOptions};
+    use std::io::{self, Write};
+    use std::os::unix::io::AsRawFd;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use libc;
+
+    use keyboard::Modifiers;
+    use mouse::Button;
+    use super::KeyCombo;
+    use wayland::{self, Arg, Conn};
+
+    // The opcodes of the requests that we use.
+    const KEYBOARD_MANAGER_CREATE: u16 = 0;
+    const KEYBOARD_KEYMAP: u16 = 0;
+    const KEYBOARD_KEY: u16 = 1;
+    const KEYBOARD_MODIFIERS: u16 = 2;
+    const KEYBOARD_DESTROY: u16 = 3;
+    const POINTER_MANAGER_CREATE: u16 = 0;
+    const POINTER_BUTTON: u16 = 2;
+    const POINTER_FRAME: u16 = 4;
+    const POINTER_DESTROY: u16 = 8;
+
+    // The keymap format that means an XKB keymap in text form.
+    const XKB_V1: u32 = 1;
+    // The only key in our keymaps, as an evdev key code. (XKB key codes are 8 more.)
+    const KEY: u32 = 1;
+    const PRESSED: u32 = 1;
+    const RELEASED: u32 = 0;
+
+    // The masks of the real modifiers, which are the same in every XKB keymap.
+    const SHIFT_MASK: u32 = 1 << 0;
+    const CTRL_MASK: u32 = 1 << 2;
+    const ALT_MASK: u32 = 1 << 3;
+    const SUPER_MASK: u32 = 1 << 6;
+
+    // The evdev codes of the mouse buttons.
+    const BTN_LEFT: u32 = 0x110;
+    const BTN_RIGHT: u32 = 0x111;
+    const BTN_MIDDLE: u32 = 0x112;
+
+    fn error(msg: String) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, msg)
+    }
+
+    // The timestamps only need to go up, so the clock doesn't matter.
+    fn now_ms() -> u32 {
+        let t = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        (t.as_secs() * 1000 + (t.subsec_nanos() / 1_000_000) as u64) as u32
+    }
+
+    // Turns a key name into the name of a keysym in a keymap. Names of keysyms (like "z" or
+    // "Page_Down") are used as they are, and any other single character means the keysym that
+    // types it.
+    pub fn keysym_name(key: &str) -> Option<String> {
+        let plain = key.chars().all(|c| match c {
+            'a'...'z' | 'A'...'Z' | '0'...'9' | '_' => true,
+            _ => false,
+        });
+        let mut chars = key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(_), _) if plain => Some(key.to_owned()),
+            (Some(c), None) => Some(format!("U{:04X}", c as u32)),
+            _ => None,
+        }
+    }
+
+    // A keymap with only one key, which makes `keysym`.
+    pub fn keymap(keysym: &str) -> String {
+        format!("xkb_keymap {{\n\
+                 xkb_keycodes \"(unnamed)\" {{ minimum = 8; maximum = {code}; <K1> = {code}; }};\n\
+                 xkb_types \"(unnamed)\" {{ include \"complete\" }};\n\
+                 xkb_compatibility \"(unnamed)\" {{ include \"complete\" }};\n\
+                 xkb_symbols \"(unnamed)\" {{ key <K1> {{ [ {sym} ] }}; }};\n\
+                 }};\n", code = KEY + 8, sym = keysym)
+    }
+
+    fn modifier_mask(mods: &Modifiers) -> u32 {
+        mods.names().into_iter()
+            .map(|m| match m {
+                "shift" => SHIFT_MASK,
+                "ctrl" => CTRL_MASK,
+                "alt" => ALT_MASK,
+                _ => SUPER_MASK,
+            })
+            .fold(0, |a, b| a | b)
+    }
+
+    // The compositor maps the keymap from a file, which can go as soon as it's open.
+    fn keymap_file(keymap: &str) -> io::Result<File> {
+        let dir = env::var_os("XDG_RUNTIME_DIR").map_or_else(env::temp_dir, |d| d.into());
+        let path = dir.join(format!("gestures-keymap-{}", unsafe { libc::getpid() }));
+        let mut f = OpenOptions::new().read(true).write(true).create(true).truncate(true)
+            .open(&path)?;
+        fs::remove_file(&path)?;
+        f.write_all(keymap.as_bytes())?;
+        // The size that we send includes a terminating zero.
+        f.write_all(&[0])?;
+        Ok(f)
+    }
+
+    // Connects to the compositor, and makes a new object with the `create` request of the global
+    // `manager`, for the first seat.
+    fn connect(manager: &str, create: u16) -> Result<(Conn, u32), String> {
+        let mut conn = Conn::open()?;
+        let globals = conn.globals()?;
+        let man = wayland::find(&globals, manager)
+            .ok_or(format!("the compositor doesn't support {}", manager))?;
+        let seat = wayland::find(&globals, "wl_seat")
+            .ok_or("the compositor has no seat".to_owned())?;
+        let man = conn.bind(man, 1)?;
+        let seat = conn.bind(seat, 1)?;
+        let obj = conn.new_id();
+        conn.send(man, create, &[Arg::Uint(seat), Arg::Uint(obj)])?;
+        Ok((conn, obj))
+    }
+
+    fn press(combo: &KeyCombo) -> Result<(), String> {
+        let sym = keysym_name(&combo.key).ok_or(format!("unknown key {:?}", combo.key))?;
+        let map = keymap(&sym);
+        let file = keymap_file(&map).map_err(|e| format!("couldn't write the keymap: {}", e))?;
+        let (mut conn, kb) = connect("zwp_virtual_keyboard_manager_v1", KEYBOARD_MANAGER_CREATE)?;
+        conn.send_fd(kb, KEYBOARD_KEYMAP, &[Arg::Uint(XKB_V1), Arg::Uint(map.len() as u32 + 1)],
+                     file.as_raw_fd())?;
+        // The compositor has to have the keymap before the key is pressed.
+        conn.roundtrip()?;
+
+        let mods = modifier_mask(&combo.modifiers);
+        let time = now_ms();
+        conn.send(kb, KEYBOARD_MODIFIERS, &[Arg::Uint(mods), Arg::Uint(0), Arg::Uint(0),
+                                            Arg::Uint(0)])?;
+        conn.send(kb, KEYBOARD_KEY, &[Arg::Uint(time), Arg::Uint(KEY), Arg::Uint(PRESSED)])?;
+        conn.send(kb, KEYBOARD_KEY, &[Arg::Uint(time), Arg::Uint(KEY), Arg::Uint(RELEASED)])?;
+        conn.send(kb, KEYBOARD_MODIFIERS, &[Arg::Uint(0), Arg::Uint(0), Arg::Uint(0),
+                                            Arg::Uint(0)])?;
+        conn.send(kb, KEYBOARD_DESTROY, &[])?;
+        conn.roundtrip()
+    }
+
+    fn press_button(button: Button) -> Result<(), String> {
+        let code = match button {
+            Button::Left => BTN_LEFT,
+            Button::Right => BTN_RIGHT,
+            Button::Middle => BTN_MIDDLE,
+        };
+        let (mut conn, ptr) = connect("zwlr_virtual_pointer_manager_v1", POINTER_MANAGER_CREATE)?;
+        let time = now_ms();
+        for &state in &[PRESSED, RELEASED] {
+            conn.send(ptr, POINTER_BUTTON, &[Arg::Uint(time), Arg::Uint(code), Arg::Uint(state)])?;
+            conn.send(ptr, POINTER_FRAME, &[])?;
+        }
+        conn.send(ptr, POINTER_DESTROY, &[])?;
+        conn.roundtrip()
+    }
+
+    pub fn key(combo: &KeyCombo) -> io::Result<()> {
+        press(combo).map_err(error)
+    }
+
+    pub fn click(button: Button) -> io::Result<()> {
+        press_button(button).map_err(error)
+    }
+}
+
+#[cfg(feature = "wayland")]
+fn wayland_key(combo: &KeyCombo) -> io::Result<Option<Child>> {
+    virtual_input::key(combo).map(|_| None)
+}
+
+#[cfg(feature = "wayland")]
+fn wayland_click(button: Button) -> io::Result<Option<Child>> {
+    virtual_input::click(button).map(|_| None)
+}
+
+#[cfg(not(feature = "wayland"))]
+fn wayland_key(_: &KeyCombo) -> io::Result<Option<Child>> {
+    Err(io::Error::new(io::ErrorKind::Other, "gestures was built without Wayland support"))
+}
+
+#[cfg(not(feature = "wayland"))]
+fn wayland_click(_: Button) -> io::Result<Option<Child>> {
+    Err(io::Error::new(io::ErrorKind::Other, "gestures was built without Wayland support"))
+}
+
+#[cfg(feature = "x11")]
+mod xtest {
+    use libc::{c_int, c_uint};
+    use std::ffi::CString;
+    use std::io;
+    use std::mem;
+    use std::ptr;
+    use x11::{xlib, xtest};
+
+    use mouse::Button;
+    use super::KeyCombo;
+
+    // Asks XKB about the core keyboard, instead of some other device.
+    const USE_CORE_KBD: c_uint = 0x0100;
+    // Unicode characters that aren't in Latin-1 have keysyms of their own, from here on.
+    const UNICODE_KEYSYMS: xlib::KeySym = 0x0100_0000;
+
+    fn error(msg: String) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, msg)
+    }
+
+    // How to type a keysym with the active layout.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    enum Typed {
+        // The key, and the shift level that it makes the keysym at (0 is unshifted, 1 takes
+        // Shift, 2 takes AltGr, and 3 takes both).
+        Key(c_uint, c_int),
+        // No key makes the keysym, so it needs to be put on a spare key.
+        Missing(xlib::KeySym),
+    }
+
+    // A connection to the X server, which is closed when this is dropped.
+    struct Display(*mut xlib::Display);
+
+    impl Display {
+        fn open() -> io::Result<Display> {
+            let d = unsafe { xlib::XOpenDisplay(ptr::null()) };
+            if d.is_null() {
+                return Err(error("couldn't connect to the X server".to_owned()));
+            }
+            let d = Display(d);
+            let (mut event, mut error_base, mut major, mut minor) = (0, 0, 0, 0);
+            let ok = unsafe {
+                xtest::XTestQueryExtension(d.0, &mut event, &mut error_base, &mut major,
+                                           &mut minor)
+            };
+            if ok == 0 {
+                return Err(error("the X server doesn't support XTest".to_owned()));
+            }
+            Ok(d)
+        }
+
+        // Turns a key name into a keysym. Besides the names of keysyms, a single character
+        // means the keysym that types it.
+        fn keysym(name: &str) -> io::Result<xlib::KeySym> {
+            let c_name = CString::new(name)
+                .map_err(|_| error(format!("bad key name {:?}", name)))?;
+            let sym = unsafe { xlib::XStringToKeysym(c_name.as_ptr()) };
+            let mut chars = name.chars();
+            match (sym, chars.next(), chars.next()) {
+                (0, Some(c), None) if (c as u32) < 0x100 => Ok(c as xlib::KeySym),
+                (0, Some(c), None) => Ok(UNICODE_KEYSYMS + c as xlib::KeySym),
+                (0, _, _) => Err(error(format!("unknown key {:?}", name))),
+                (sym, _, _) => Ok(sym),
+            }
+        }
+
+        // Finds the key that makes `sym` in the active group of the layout, preferring the
+        // lowest shift level.
+        fn lookup(&self, sym: xlib::KeySym) -> Typed {
+            let (mut min, mut max) = (0, 0);
+            let group = unsafe {
+                xlib::XDisplayKeycodes(self.0, &mut min, &mut max);
+                let mut state: xlib::XkbStateRec = mem::zeroed();
+                xlib::XkbGetState(self.0, USE_CORE_KBD, &mut state);
+                state.group as c_int
+            };
+            for level in 0..4 {
+                for code in min..(max + 1) {
+                    let s = unsafe {
+                        xlib::XkbKeycodeToKeysym(self.0, code as xlib::KeyCode, group, level)
+                    };
+                    if s == sym {
+                        return Typed::Key(code as c_uint, level);
+                    }
+                }
+            }
+            Typed::Missing(sym)
+        }
+
+        fn keycode(&self, name: &str) -> io::Result<c_uint> {
+            match self.lookup(Display::keysym(name)?) {
+                Typed::Key(code, _) => Ok(code),
+                Typed::Missing(_) => Err(error(format!("the keyboard has no key {:?}", name))),
+            }
+        }
+
+        // Finds a key that doesn't make anything, which we can borrow.
+        fn spare_keycode(&self) -> io::Result<c_uint> {
+            let (mut min, mut max) = (0, 0);
+            unsafe { xlib::XDisplayKeycodes(self.0, &mut min, &mut max); }
+            let mut per_code = 0;
+            let count = max - min + 1;
+            let syms = unsafe {
+                xlib::XGetKeyboardMapping(self.0, min as xlib::KeyCode, count, &mut per_code)
+            };
+            if syms.is_null() {
+                return Err(error("couldn't read the keyboard mapping".to_owned()));
+            }
+            let spare = (0..count).rev().find(|&i| {
+                (0..per_code).all(|j| unsafe { *syms.offset((i * per_code + j) as isize) } == 0)
+            });
+            unsafe { xlib::XFree(syms as *mut _); }
+            spare.map(|i| (min + i) as c_uint)
+                .ok_or(error("there is no spare key to type with".to_owned()))
+        }
+
+        // Puts `sym` on the key `code` (or removes whatever is on it, if `sym` is 0).
+        fn remap(&self, code: c_uint, sym: xlib::KeySym) {
+            let mut syms = [sym];
+            unsafe {
+                xlib::XChangeKeyboardMapping(self.0, code as c_int, 1, syms.as_mut_ptr(), 1);
+                xlib::XSync(self.0, xlib::False);
+            }
+        }
+
+        fn key(&self, code: c_uint, pressed: bool) {
+            unsafe { xtest::XTestFakeKeyEvent(self.0, code, pressed as c_int, 0); }
+        }
+
+        fn button(&self, button: c_uint, pressed: bool) {
+            unsafe { xtest::XTestFakeButtonEvent(self.0, button, pressed as c_int, 0); }
+        }
+    }
+
+    impl Drop for Display {
+        fn drop(&mut self) {
+            unsafe {
+                xlib::XFlush(self.0);
+                xlib::XCloseDisplay(self.0);
+            }
+        }
+    }
+
+    pub fn key(combo: &KeyCombo) -> io::Result<()> {
+        let d = Display::open()?;
+        let mut names = combo.modifiers.names().into_iter()
+            .map(|m| match m {
+                "shift" => "Shift_L",
+                "ctrl" => "Control_L",
+                "alt" => "Alt_L",
+                _ => "Super_L",
+            })
+            .collect::<Vec<_>>();
+        let (key, borrowed) = match d.lookup(Display::keysym(&combo.key)?) {
+            Typed::Key(code, level) => {
+                // The modifiers that choose the shift level go last, right next to the key.
+                if level & 1 != 0 && !names.contains(&"Shift_L") {
+                    names.push("Shift_L");
+                }
+                if level & 2 != 0 {
+                    names.push("ISO_Level3_Shift");
+                }
+                (code, false)
+            },
+            Typed::Missing(sym) => {
+                let code = d.spare_keycode()?;
+                debug!("borrowing key {} to type {:?}", code, combo.key);
+                d.remap(code, sym);
+                (code, true)
+            },
+        };
+        let mut mods = Vec::new();
+        for m in names {
+            mods.push(d.keycode(m)?);
+        }
+
+        for &m in &mods {
+            d.key(m, true);
+        }
+        d.key(key, true);
+        d.key(key, false);
+        for &m in mods.iter().rev() {
+            d.key(m, false);
+        }
+        if borrowed {
+            // The key has to stay mapped until the X server has sent the presses on.
+            unsafe { xlib::XSync(d.0, xlib::False); }
+            d.remap(key, 0);
+        }
+        Ok(())
+    }
+
+    pub fn click(button: Button) -> io::Result<()> {
+        let d = Display::open()?;
+        let b = match button {
+            Button::Left => 1,
+            Button::Middle => 2,
+            Button::Right => 3,
+        };
+        d.button(b, true);
+        d.button(b, false);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "x11")]
+fn x11_key(combo: &KeyCombo) -> io::Result<Option<Child>> {
+    xtest::key(combo).map(|_| None)
+}
+
+#[cfg(feature = "x11")]
+fn x11_click(button: Button) -> io::Result<Option<Child>> {
+    xtest::click(button).map(|_| None)
+}
+
+#[cfg(not(feature = "x11"))]
+fn x11_key(_: &KeyCombo) -> io::Result<Option<Child>> {
+    Err(io::Error::new(io::ErrorKind::Other, "gestures was built without X11 support"))
+}
+
+#[cfg(not(feature = "x11"))]
+fn x11_click(_: Button) -> io::Result<Option<Child>> {
+    Err(io::Error::new(io::ErrorKind::Other, "gestures was built without X11 support"))
+}
+
+/// Presses and releases a key combination.
+///
+/// The modifiers are pressed first and released last, in the opposite order.
+pub fn press(combo: &KeyCombo, backend: Backend) -> io::Result<Option<Child>> {
+    match backend.resolve()? {
+        Backend::Wayland => wayland_key(combo),
+        Backend::X11 => x11_key(combo),
+        Backend::Auto => unreachable!(),
+    }
+}
+
+/// Clicks a mouse button, wherever the pointer happens to be.
+pub fn click(button: Button, backend: Backend) -> io::Result<Option<Child>> {
+    match backend.resolve()? {
+        Backend::Wayland => wayland_click(button),
+        Backend::X11 => x11_click(button),
+        Backend::Auto => unreachable!(),
+    }
+}
+
+#[cfg(all(test, feature = "wayland"))]
+mod tests {
+    use super::virtual_input::{keymap, keysym_name};
+
+    #[test]
+    fn keysym_names() {
+        assert_eq!(keysym_name("z"), Some("z".to_owned()));
+        assert_eq!(keysym_name("Page_Down"), Some("Page_Down".to_owned()));
+        assert_eq!(keysym_name("?"), Some("U003F".to_owned()));
+        assert_eq!(keysym_name("\u{e9}"), Some("U00E9".to_owned()));
+        // Nothing else can get into the keymap.
+        assert_eq!(keysym_name("a ] }; };"), None);
+        assert_eq!(keysym_name(""), None);
+    }
+
+    #[test]
+    fn keymaps() {
+        let map = keymap("question");
+        assert!(map.contains("<K1> = 9;"));
+        assert!(map.contains("key <K1> { [ question ] };"));
+    }
+}
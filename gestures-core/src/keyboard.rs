@@ -0,0 +1,93 @@
+use input::event::Event;
+use input::event::keyboard::{KeyboardEvent, KeyboardEventTrait, KeyState};
+use std::collections::HashSet;
+
+// Linux evdev key codes for the modifier keys.
+const KEY_LEFTCTRL: u32 = 29;
+const KEY_LEFTSHIFT: u32 = 42;
+const KEY_RIGHTSHIFT: u32 = 54;
+const KEY_LEFTALT: u32 = 56;
+const KEY_RIGHTCTRL: u32 = 97;
+const KEY_RIGHTALT: u32 = 100;
+const KEY_LEFTMETA: u32 = 125;
+const KEY_RIGHTMETA: u32 = 126;
+
+const SHIFT: u8 = 1;
+const CTRL: u8 = 2;
+const ALT: u8 = 4;
+const SUPER: u8 = 8;
+
+/// A set of keyboard modifiers.
+///
+/// We don't distinguish between the left and right versions of the modifier keys.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Modifiers {
+    bits: u8,
+}
+
+impl Modifiers {
+    /// Parses a set of modifiers separated by `+`, like `"super+shift"`.
+    pub fn parse(s: &str) -> Result<Modifiers, String> {
+        let mut bits = 0;
+        for m in s.split('+') {
+            bits |= match m.trim() {
+                "shift" => SHIFT,
+                "ctrl" | "control" => CTRL,
+                "alt" => ALT,
+                "super" | "meta" | "logo" => SUPER,
+                _ => return Err(format!("unknown modifier {:?} in {:?}", m, s)),
+            };
+        }
+        Ok(Modifiers { bits: bits })
+    }
+
+    /// The names of the modifiers in this set, in the same form that `parse` accepts.
+    pub fn names(&self) -> Vec<&'static str> {
+        [(SHIFT, "shift"), (CTRL, "ctrl"), (ALT, "alt"), (SUPER, "super")].iter()
+            .filter(|&&(bit, _)| self.bits & bit != 0)
+            .map(|&(_, name)| name)
+            .collect()
+    }
+
+    fn from_key(key: u32) -> Option<u8> {
+        match key {
+            KEY_LEFTSHIFT | KEY_RIGHTSHIFT => Some(SHIFT),
+            KEY_LEFTCTRL | KEY_RIGHTCTRL => Some(CTRL),
+            KEY_LEFTALT | KEY_RIGHTALT => Some(ALT),
+            KEY_LEFTMETA | KEY_RIGHTMETA => Some(SUPER),
+            _ => None,
+        }
+    }
+}
+
+/// Keeps track of which modifier keys are held down, by watching libinput's keyboard events.
+#[derive(Debug, Default)]
+pub struct KeyboardState {
+    pressed: HashSet<u32>,
+}
+
+impl KeyboardState {
+    pub fn new() -> KeyboardState {
+        KeyboardState::default()
+    }
+
+    pub fn handle_event(&mut self, ev: &Event) {
+        if let &Event::Keyboard(KeyboardEvent::Key(ref ev)) = ev {
+            let key = ev.key();
+            if Modifiers::from_key(key).is_some() {
+                match ev.key_state() {
+                    KeyState::Pressed => self.pressed.insert(key),
+                    KeyState::Released => self.pressed.remove(&key),
+                };
+            }
+        }
+    }
+
+    /// Returns the modifiers that are currently held down.
+    pub fn modifiers(&self) -> Modifiers {
+        let bits = self.pressed.iter()
+            .filter_map(|&k| Modifiers::from_key(k))
+            .fold(0, |a, b| a | b);
+        Modifiers { bits: bits }
+    }
+}
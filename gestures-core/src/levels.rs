@@ -0,0 +1,167 @@
+//! Turning the screen brightness and the volume up and down.
+//!
+//! A binding changes one of them by a percentage of its full range:
+//!
+//! ```toml
+//! [[bindings]]
+//! gesture = "swipe 2 up"
+//! brightness = "+10%"
+//!
+//! [[bindings]]
+//! gesture = "swipe 2 down"
+//! volume = "-5%"
+//! ```
+//!
+//! The brightness is that of the first backlight in `/sys/class/backlight`. It is set through
+//! logind, which lets the user of the active session do that without any special permissions, or
+//! by writing to the backlight's `brightness` file if logind isn't there. The volume is that of
+//! the default output, and is set with `pactl`, which works with both PulseAudio and PipeWire.
+//!
+//! Usually, each gesture changes the level by the whole step once it is recognized. A swipe with
+//! `fire = "continuously"` works like a slider instead: while the fingers move, the level follows
+//! them, and a swipe that goes `tuning.swipe.slide-mm` millimeters changes it by the whole step.
+//! A shorter swipe changes it by a smaller part of the step, and a longer one doesn't go any
+//! further.
+//!
+//! ```toml
+//! [[bindings]]
+//! gesture = "swipe 2 up"
+//! brightness = "+30%"
+//! fire = "continuously"
+//! ```
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+
+use session;
+
+const BACKLIGHTS: &'static str = "/sys/class/backlight";
+
+/// Something that a binding can turn up or down.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Level {
+    Brightness,
+    Volume,
+}
+
+/// Parses a step like "+10%" or "-5%" into a percentage. The sign is required, so that nobody
+/// expects "50%" to set the level to one half.
+pub fn parse_step(s: &str) -> Result<i32, String> {
+    let n = s.trim();
+    let n = if n.ends_with('%') { &n[..(n.len() - 1)] } else { n };
+    if !n.starts_with('+') && !n.starts_with('-') {
+        return Err(format!("step {:?} must start with + or -", s));
+    }
+    match n[1..].parse::<i32>() {
+        Ok(p) if p > 0 && p <= 100 => Ok(if n.starts_with('-') { -p } else { p }),
+        _ => Err(format!("step {:?} must be between 1% and 100%", s)),
+    }
+}
+
+/// Works out how much further (in percent) to change a level, for a gesture that changes it by
+/// `percent` if it goes all the way, when the gesture has got to `progress` (between 0 and 1) and
+/// has already changed the level by `applied`.
+///
+/// The levels only change by whole percentages, so some updates don't change anything.
+pub fn scaled_step(percent: i32, progress: f64, applied: i32) -> i32 {
+    let progress = progress.max(0.0).min(1.0);
+    (percent as f64 * progress).round() as i32 - applied
+}
+
+/// Changes `level` by `percent` percent of its full range.
+///
+/// If this runs a process, returns a handle to it.
+pub fn adjust(level: Level, percent: i32) -> Result<Option<Child>, String> {
+    match level {
+        Level::Brightness => adjust_brightness(percent).map(|_| None),
+        Level::Volume => {
+            Command::new("pactl")
+                .args(&["set-sink-volume", "@DEFAULT_SINK@", &format!("{:+}%", percent)])
+                .spawn()
+                .map(Some)
+                .map_err(|e| format!("couldn't run pactl: {}", e))
+        },
+    }
+}
+
+fn read_number(path: &Path) -> io::Result<u32> {
+    let mut s = String::new();
+    File::open(path)?.read_to_string(&mut s)?;
+    s.trim().parse().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "not a number"))
+}
+
+// Finds the first backlight, in alphabetical order.
+fn backlight() -> Result<PathBuf, String> {
+    let mut dirs = fs::read_dir(BACKLIGHTS)
+        .map_err(|e| format!("couldn't read {}: {}", BACKLIGHTS, e))?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs.into_iter().next().ok_or("there is no backlight".to_owned())
+}
+
+// Works out the brightness after a step, keeping it within range. Going all the way to zero
+// turns some screens off, so turning the brightness down stops at 1.
+fn step_brightness(current: u32, max: u32, percent: i32) -> u32 {
+    let step = (max as i64 * percent as i64 / 100).abs().max(1);
+    let new = if percent < 0 { current as i64 - step } else { current as i64 + step };
+    new.max(1).min(max as i64) as u32
+}
+
+fn adjust_brightness(percent: i32) -> Result<(), String> {
+    let dir = backlight()?;
+    let name = dir.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+    let current = read_number(&dir.join("brightness"))
+        .map_err(|e| format!("couldn't read the brightness of {}: {}", name, e))?;
+    let max = read_number(&dir.join("max_brightness"))
+        .map_err(|e| format!("couldn't read the brightness of {}: {}", name, e))?;
+    let new = step_brightness(current, max, percent);
+    debug!("changing the brightness of {} from {} to {} (of {})", name, current, new, max);
+    session::set_brightness(&name, new).or_else(|e| {
+        debug!("couldn't set the brightness through logind: {}", e);
+        File::create(dir.join("brightness"))
+            .and_then(|mut f| write!(f, "{}", new))
+            .map_err(|e| format!("couldn't set the brightness of {}: {}", name, e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps() {
+        assert_eq!(parse_step("+10%"), Ok(10));
+        assert_eq!(parse_step("-5"), Ok(-5));
+        assert!(parse_step("50%").is_err());
+        assert!(parse_step("+0%").is_err());
+        assert!(parse_step("-150%").is_err());
+
+        assert_eq!(step_brightness(50, 100, 10), 60);
+        assert_eq!(step_brightness(95, 100, 10), 100);
+        assert_eq!(step_brightness(5, 100, -10), 1);
+        // Even a tiny step changes something.
+        assert_eq!(step_brightness(3, 7, 5), 4);
+    }
+
+    #[test]
+    fn partial_swipes() {
+        assert_eq!(scaled_step(30, 1.0, 0), 30);
+        // A swipe that only goes part of the way changes the level by that part of the step.
+        assert_eq!(scaled_step(30, 0.4, 0), 12);
+        assert_eq!(scaled_step(-10, 0.5, 0), -5);
+        // Going further than a full slide doesn't help.
+        assert_eq!(scaled_step(30, 1.5, 0), 30);
+
+        // While the swipe goes on, the steps add up to the same thing.
+        let mut applied = 0;
+        for &p in &[0.1, 0.25, 0.26, 0.4, 0.3] {
+            applied += scaled_step(30, p, applied);
+        }
+        assert_eq!(applied, 9);
+        assert_eq!(scaled_step(30, 0.31, applied), 0);
+    }
+}
@@ -0,0 +1,178 @@
+//! The parts of the gestures daemon that don't depend on how it gets its input.
+//!
+//! This crate reads the config files, decides which binding a recognized gesture fires, and runs
+//! the binding's action. The `gestures` daemon feeds it gestures from libinput and from the
+//! touchscreens that it grabs, but other frontends (like a graphical config editor, or a
+//! compositor that recognizes gestures itself) can use it too:
+//!
+//! ```no_run
+//! extern crate gestures_core;
+//!
+//! use gestures_core::config::{self, Source};
+//! use gestures_core::{build_manager, profile};
+//!
+//! # fn main() {
+//! let config = config::load_config().unwrap();
+//! let profile = profile::load(&config);
+//! let (man, names) = build_manager(&config, Source::Touch, &profile, config.params);
+//! # }
+//! ```
+//!
+//! Each recognized gesture then goes to [`Config::binding`](config/struct.Config.html), and the
+//! binding that it returns goes to a [`Runner`](runner/struct.Runner.html).
+
+#[macro_use] extern crate log;
+#[macro_use] extern crate serde_derive;
+
+extern crate app_dirs;
+extern crate chan;
+extern crate dbus;
+extern crate input;
+extern crate libc;
+extern crate libgestures;
+extern crate serde;
+extern crate serde_json;
+extern crate toml;
+extern crate toml_edit;
+#[cfg(feature = "x11")]
+extern crate x11;
+
+use config::Source;
+use custom::CustomName;
+use libgestures::geom::Direction;
+use libgestures::gestures::GestureSet;
+use libgestures::gestures::Gesture as TouchGesture;
+use libgestures::manager::Manager;
+use libgestures::params::Params;
+use std::fmt;
+
+/// Where the config files and the saved state live.
+pub const APP_INFO: app_dirs::AppInfo = app_dirs::AppInfo {
+    name: "gestures",
+    author: "Joe Neeman",
+};
+
+pub mod audit;
+pub mod conditions;
+pub mod config;
+pub mod context;
+pub mod custom;
+pub mod desktop;
+pub mod dedup;
+pub mod edit;
+pub mod inject;
+pub mod keyboard;
+pub mod levels;
+pub mod mouse;
+pub mod notify;
+pub mod plugin;
+pub mod profile;
+pub mod runner;
+pub mod sandbox;
+pub mod shadowing;
+pub mod session;
+mod wayland;
+
+/// Anything that can be bound to an action.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Gesture {
+    /// A gesture made with fingers (or a pen) on a touch surface.
+    Touch(TouchGesture),
+    /// Holding down one mouse button while clicking another.
+    Rocker {
+        held: mouse::Button,
+        clicked: mouse::Button,
+    },
+    /// Turning the mouse wheel.
+    Wheel {
+        direction: Direction,
+    },
+    /// A gesture defined in the config file.
+    Custom(CustomName),
+    /// A gesture recognized by a plugin.
+    Plugin(CustomName),
+}
+
+impl From<TouchGesture> for Gesture {
+    fn from(g: TouchGesture) -> Gesture {
+        Gesture::Touch(g)
+    }
+}
+
+/// Writes a gesture in the same way that it is named in the configuration file.
+impl fmt::Display for Gesture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Gesture::Touch(ref g) => g.fmt(f),
+            Gesture::Rocker { held, clicked } => write!(f, "rocker {} {}", held, clicked),
+            Gesture::Wheel { direction } => write!(f, "wheel {}", direction),
+            Gesture::Custom(name) => write!(f, "custom {}", name),
+            Gesture::Plugin(name) => write!(f, "plugin {}", name),
+        }
+    }
+}
+
+impl Gesture {
+    /// Returns true if this gesture is made with a mouse, instead of with fingers.
+    pub fn is_mouse_gesture(&self) -> bool {
+        match *self {
+            Gesture::Touch(_) | Gesture::Custom(_) | Gesture::Plugin(_) => false,
+            Gesture::Rocker { .. } | Gesture::Wheel { .. } => true,
+        }
+    }
+
+    pub fn is_edge_gesture(&self) -> bool {
+        match *self {
+            Gesture::Touch(TouchGesture::EdgeSwipe { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Builds a `Manager` that recognizes all of the gestures from `source` that are bound in
+/// `profile` in `config`, using the thresholds in `params`.
+///
+/// Also returns a description of each of the `Manager`'s recognizers, indexed by id.
+pub fn build_manager(config: &config::Config, source: Source, profile: &str, params: Params)
+-> (Manager<Gesture>, Vec<String>) {
+    let (man, recs) = build_manager_with_fingers(config, source, profile, params);
+    (man, recs.into_iter().map(|(name, _)| name).collect())
+}
+
+/// Like [`build_manager`](fn.build_manager.html), but also returns the number of fingers that make
+/// the gestures of each recognizer.
+pub fn build_manager_with_fingers(config: &config::Config, source: Source, profile: &str,
+                                  params: Params)
+-> (Manager<Gesture>, Vec<(String, u8)>) {
+    let mut man = Manager::new();
+    man.set_contact_limit(config.max_contacts, config.overflow);
+    man.set_coalesce_interval(config.coalesce_ms);
+    man.set_rearm_policy(config.rearm);
+
+    let mut set = GestureSet::new()
+        .swipe_angles(config.swipe_angles)
+        .dwell_swipes(config.accessibility)
+        .params(params);
+    // The config file parser makes sure that this is set if there are any edge swipes.
+    if let Some(ref zones) = config.edges {
+        set = set.edge_zones(zones.clone());
+    }
+    // A pen can only make one-finger gestures, so holding them back would leave it with none.
+    if source == Source::Touch {
+        set = set.one_finger(config.one_finger);
+    }
+    let mut custom = Vec::new();
+    for gesture in config.gestures(source, profile) {
+        match gesture {
+            Gesture::Touch(g) => set.insert_firing(g, config.fire(&g)),
+            Gesture::Custom(name) => custom.push(name),
+            _ => {},
+        }
+    }
+    let mut recs = set.push_to_with_fingers(&mut man);
+    // The config file parser makes sure that every custom gesture that is bound is defined.
+    for c in config.custom.iter().filter(|c| custom.contains(&c.name)) {
+        recs.push((c.push_to(&mut man, params), c.spec.fingers));
+    }
+    (man, recs)
+}
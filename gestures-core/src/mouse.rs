@@ -0,0 +1,133 @@
+use input::event::Event;
+use input::event::pointer::{Axis, AxisSource, ButtonState, PointerAxisEvent, PointerEvent};
+use libgestures::geom::Direction;
+use std::fmt;
+
+use Gesture;
+
+// Linux evdev codes for the mouse buttons.
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+
+/// A mouse button.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Button {
+    Left,
+    Right,
+    Middle,
+}
+
+impl Button {
+    pub fn parse(s: &str) -> Option<Button> {
+        match s {
+            "left" => Some(Button::Left),
+            "right" => Some(Button::Right),
+            "middle" => Some(Button::Middle),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Button::Left => "left",
+            Button::Right => "right",
+            Button::Middle => "middle",
+        }
+    }
+
+    fn from_code(code: u32) -> Option<Button> {
+        match code {
+            BTN_LEFT => Some(Button::Left),
+            BTN_RIGHT => Some(Button::Right),
+            BTN_MIDDLE => Some(Button::Middle),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Button {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Recognizes mouse gestures from libinput's pointer events.
+///
+/// There are two kinds of mouse gestures: rocker gestures, where one button is held down while
+/// another one is clicked, and wheel gestures, which are just scroll wheel movements (and so they
+/// are mostly useful when combined with modifier keys). We only watch the events, so the clicks
+/// and scrolls still reach whatever application is under the pointer.
+#[derive(Debug, Default)]
+pub struct MouseTracker {
+    // The buttons that are held down, in the order that they were pressed.
+    pressed: Vec<Button>,
+}
+
+impl MouseTracker {
+    pub fn new() -> MouseTracker {
+        MouseTracker::default()
+    }
+
+    /// Returns true if any mouse button is held down.
+    pub fn is_pressed(&self) -> bool {
+        !self.pressed.is_empty()
+    }
+
+    /// Returns the gestures that `ev` finishes. A scroll wheel that moves by more than one notch
+    /// at once makes one wheel gesture for each notch.
+    pub fn handle_event(&mut self, ev: &Event) -> Vec<Gesture> {
+        match ev {
+            &Event::Pointer(PointerEvent::Button(ref ev)) => {
+                let button = match Button::from_code(ev.button()) {
+                    Some(b) => b,
+                    None => return vec![],
+                };
+                match ev.button_state() {
+                    ButtonState::Pressed => {
+                        let ret = self.pressed.first().map(|&held| Gesture::Rocker {
+                            held: held,
+                            clicked: button,
+                        });
+                        self.pressed.push(button);
+                        ret.into_iter().collect()
+                    },
+                    ButtonState::Released => {
+                        self.pressed.retain(|&b| b != button);
+                        vec![]
+                    },
+                }
+            },
+            &Event::Pointer(PointerEvent::Axis(ref ev)) => {
+                // Touchpads and trackpoints scroll too, but only the wheel comes in notches.
+                if ev.axis_source() != AxisSource::Wheel {
+                    return vec![];
+                }
+                // libinput's scroll values grow downwards and to the right.
+                let (direction, notches) = match notches(ev, Axis::Vertical) {
+                    Some(n) if n < 0 => (Direction::Up, -n),
+                    Some(n) => (Direction::Down, n),
+                    None => match notches(ev, Axis::Horizontal) {
+                        Some(n) if n < 0 => (Direction::Left, -n),
+                        Some(n) => (Direction::Right, n),
+                        None => return vec![],
+                    },
+                };
+                (0..notches).map(|_| Gesture::Wheel { direction: direction }).collect()
+            },
+            _ => vec![],
+        }
+    }
+}
+
+// How many notches the wheel turned along `axis` (which is negative for up or left), or `None` if
+// it didn't turn that way.
+fn notches(ev: &PointerAxisEvent, axis: Axis) -> Option<i32> {
+    if !ev.has_axis(axis) {
+        return None;
+    }
+    match ev.axis_value_discrete(axis) {
+        Some(n) if n.round() != 0.0 => Some(n.round() as i32),
+        _ => None,
+    }
+}
@@ -0,0 +1,41 @@
+use dbus::{BusType, Connection, Message};
+use dbus::arg::{Dict, Variant};
+use std::thread;
+
+// How long to wait for the notification server to answer, in milliseconds.
+const TIMEOUT_MS: i32 = 500;
+
+/// Shows a desktop notification, using the freedesktop.org notification service on the session
+/// bus.
+///
+/// The notification is sent from a separate thread, so that a slow (or missing) notification
+/// server doesn't hold up the caller. If sending it fails, the error is only logged.
+pub fn notify(summary: &str, body: &str) -> Result<(), String> {
+    let (summary, body) = (summary.to_owned(), body.to_owned());
+    thread::Builder::new()
+        .name("notify".to_owned())
+        .spawn(move || if let Err(e) = send(&summary, &body) {
+            warn!("Failed to show the notification {:?}: {}.", summary, e);
+        })
+        .map(|_| ())
+        .map_err(|e| format!("failed to start the notification thread: {}", e))
+}
+
+fn send(summary: &str, body: &str) -> Result<(), String> {
+    let conn = Connection::get_private(BusType::Session)
+        .map_err(|e| format!("couldn't connect to the session bus: {:?}", e.message()))?;
+    let msg = Message::new_method_call("org.freedesktop.Notifications",
+                                       "/org/freedesktop/Notifications",
+                                       "org.freedesktop.Notifications",
+                                       "Notify")?
+        // The application name, the id of a notification to replace, and an icon.
+        .append3("gestures", 0u32, "")
+        // The summary, the body, and a list of actions (which we don't use).
+        .append3(summary, body, Vec::<&str>::new())
+        // Some hints (which we don't use either), and an expiration timeout that lets the
+        // notification server decide.
+        .append2(Dict::<&str, Variant<i32>, _>::new(Vec::new().into_iter()), -1i32);
+    conn.send_with_reply_and_block(msg, TIMEOUT_MS)
+        .map(|_| ())
+        .map_err(|e| format!("{:?}", e.message()))
+}
@@ -0,0 +1,609 @@
+//! Plugins: separate programs that recognize gestures or run actions.
+//!
+//! A plugin is declared in the config file with a name and a command:
+//!
+//! ```toml
+//! [[plugin]]
+//! name = "lasso"
+//! command = "/usr/lib/gestures-lasso/lasso"
+//! args = ["--sensitivity", "2"]
+//! # The gestures that this plugin recognizes, which are bound as "plugin NAME".
+//! recognizes = ["lasso", "scribble"]
+//!
+//! [[bindings]]
+//! gesture = "plugin lasso"
+//! exec = "flameshot gui"
+//!
+//! [[bindings]]
+//! gesture = "swipe 4 up"
+//! # Instead of running a command, ask the plugin to do something.
+//! plugin = "lasso"
+//! args = ["reset"]
+//! ```
+//!
+//! The daemon starts a plugin when it is first needed (or right away, if it recognizes gestures),
+//! and talks to it with JSON, one message per line, on its standard input and output. The first
+//! message is always
+//!
+//! ```json
+//! {"type": "hello", "version": 1}
+//! ```
+//!
+//! where the version is [`PROTOCOL_VERSION`](constant.PROTOCOL_VERSION.html). A plugin that
+//! recognizes gestures gets every touch event from every touchscreen, like
+//!
+//! ```json
+//! {"type": "touch", "device": "event5", "kind": "motion", "slot": 0, "x": 12, "y": 40, "time": 0}
+//! ```
+//!
+//! where `kind` is "down", "up", "motion", "cancel" or "frame", positions are in millimeters, and
+//! `time` (which is only set for frames) is in milliseconds. The sizes and pressures of the
+//! contacts aren't sent. A plugin that is bound to an action gets
+//!
+//! ```json
+//! {"type": "action", "gesture": "swipe 4 up", "args": ["reset"]}
+//! ```
+//!
+//! when the binding fires. When a plugin recognizes a gesture, it writes
+//!
+//! ```json
+//! {"type": "gesture", "name": "lasso", "x": 30.0, "y": 41.5}
+//! ```
+//!
+//! where `x` and `y` (which are optional) are where the gesture started, for filling in
+//! placeholders. Anything the plugin writes to its standard error goes to the daemon's.
+//!
+//! If a plugin can't keep up, the daemon doesn't wait for it. Instead, the motions of each contact
+//! are merged, so that the plugin only gets the latest position, but contacts coming down and
+//! lifting are never left out. A plugin that stops reading altogether is stopped.
+//!
+//! If a plugin exits, it is started again the next time it is needed, but not right away: the
+//! wait doubles every time it fails in a row, and after
+//! [`MAX_FAILURES`](constant.MAX_FAILURES.html) failures in a row it stays stopped until the
+//! config is reloaded.
+
+use chan;
+use libgestures::event::Event;
+use libgestures::geom::Point;
+use serde_json;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use Gesture;
+use custom::CustomName;
+
+/// The version of the protocol described above. It changes whenever a change to the protocol
+/// could break an existing plugin.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// How many times in a row a plugin may fail before it is disabled.
+pub const MAX_FAILURES: u32 = 5;
+
+// How many messages can be waiting for a plugin before we start merging them.
+const QUEUE_LEN: usize = 256;
+
+// How many messages that couldn't be merged can be waiting for a plugin before we decide that it
+// has stopped reading.
+const BACKLOG_LEN: usize = 1024;
+
+// How long to wait before starting a plugin again after its first failure. The wait doubles with
+// every failure in a row, up to `MAX_RESTART_DELAY_MS`.
+const RESTART_DELAY_MS: u64 = 500;
+const MAX_RESTART_DELAY_MS: u64 = 60_000;
+
+// A plugin that runs for this long before exiting did its job for a while, so its earlier failures
+// are forgotten.
+const STABLE_SECS: u64 = 60;
+
+/// A `[[plugin]]` section of the config file.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct PluginParsed {
+    pub name: String,
+    command: String,
+    args: Option<Vec<String>>,
+    recognizes: Option<Vec<String>>,
+}
+
+impl PluginParsed {
+    pub fn to_plugin(self) -> Result<PluginConfig, String> {
+        let mut recognizes = Vec::new();
+        for g in self.recognizes.unwrap_or_else(Vec::new) {
+            recognizes.push(CustomName::new(&g)
+                .ok_or(format!("plugin {:?} has an invalid gesture name {:?}", self.name, g))?);
+        }
+        Ok(PluginConfig {
+            name: self.name,
+            command: self.command,
+            args: self.args.unwrap_or_else(Vec::new),
+            recognizes: recognizes,
+        })
+    }
+}
+
+/// How to run a plugin.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PluginConfig {
+    pub name: String,
+    pub command: String,
+    pub args: Vec<String>,
+    /// The gestures that the plugin recognizes. If this is empty, the plugin doesn't get any touch
+    /// events.
+    pub recognizes: Vec<CustomName>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum ToPlugin<'a> {
+    Hello {
+        version: u32,
+    },
+    Touch {
+        device: &'a str,
+        kind: &'static str,
+        slot: usize,
+        x: f64,
+        y: f64,
+        time: u32,
+    },
+    Action {
+        gesture: String,
+        args: &'a [String],
+    },
+}
+
+impl<'a> ToPlugin<'a> {
+    // Returns `None` for the events that plugins don't get.
+    fn touch(device: &'a str, ev: &Event) -> Option<ToPlugin<'a>> {
+        let (kind, slot, pos, time) = match *ev {
+            Event::Down { slot, pos } => ("down", slot, pos, 0),
+            Event::Up { slot } => ("up", slot, Point::zero(), 0),
+            Event::Motion { slot, pos } => ("motion", slot, pos, 0),
+            Event::Cancel => ("cancel", 0, Point::zero(), 0),
+            Event::Frame { time } => ("frame", 0, Point::zero(), time),
+            Event::Shape { .. } => return None,
+        };
+        Some(ToPlugin::Touch { device, kind, slot, x: pos.x, y: pos.y, time })
+    }
+
+    fn merge(&self) -> Merge {
+        match *self {
+            ToPlugin::Touch { kind: "motion", slot, .. } => Merge::Motion(slot),
+            ToPlugin::Touch { kind: "frame", .. } => Merge::Frame,
+            _ => Merge::Never,
+        }
+    }
+}
+
+// Whether a message that is waiting for a plugin can be replaced by a newer one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Merge {
+    // By a newer motion of the same contact, unless the contacts came down or lifted in between.
+    Motion(usize),
+    // By a newer frame, if nothing came in between.
+    Frame,
+    // Never, like a contact lifting.
+    Never,
+}
+
+// Adds a message to the ones that are waiting for room in a plugin's queue, replacing an older one
+// if it can.
+fn push_backlog(backlog: &mut VecDeque<(Merge, String)>, merge: Merge, line: String) {
+    match merge {
+        Merge::Motion(slot) => {
+            for i in (0..backlog.len()).rev() {
+                match backlog[i].0 {
+                    Merge::Motion(s) if s == slot => {
+                        backlog.remove(i);
+                        break;
+                    },
+                    Merge::Motion(_) | Merge::Frame => {},
+                    Merge::Never => break,
+                }
+            }
+        },
+        Merge::Frame => {
+            let last_is_frame = backlog.back().map_or(false, |b| b.0 == Merge::Frame);
+            if last_is_frame {
+                backlog.pop_back();
+            }
+        },
+        Merge::Never => {},
+    }
+    backlog.push_back((merge, line));
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum FromPlugin {
+    Gesture {
+        name: String,
+        x: Option<f64>,
+        y: Option<f64>,
+    },
+}
+
+/// A gesture that a plugin recognized.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PluginGesture {
+    pub gesture: Gesture,
+    /// Where the gesture started, if the plugin said.
+    pub pos: Option<Point>,
+}
+
+#[derive(Debug)]
+struct Running {
+    child: Child,
+    started: Instant,
+    // Messages for the thread that writes to the plugin's standard input.
+    send: SyncSender<String>,
+    // Messages that didn't fit in `send` yet.
+    backlog: VecDeque<(Merge, String)>,
+}
+
+impl Running {
+    // Moves as many messages from the backlog to the writing thread as it has room for.
+    fn flush(&mut self, name: &str) {
+        while let Some((merge, line)) = self.backlog.pop_front() {
+            match self.send.try_send(line) {
+                Ok(()) => {},
+                Err(TrySendError::Full(line)) => {
+                    debug!("plugin={:?} backlog={}", name, self.backlog.len() + 1);
+                    self.backlog.push_front((merge, line));
+                    return;
+                },
+                Err(TrySendError::Disconnected(_)) => {
+                    warn!("plugin {:?} stopped reading its input", name);
+                    self.backlog.clear();
+                    return;
+                },
+            }
+        }
+    }
+}
+
+// How a plugin has been failing, so that it isn't started over and over.
+#[derive(Debug)]
+struct Failures {
+    // How many times in a row it failed.
+    count: u32,
+    // When it may be started again.
+    retry_at: Instant,
+}
+
+// Writes the messages from `recv` to a plugin, until it goes away.
+fn write_messages(name: String, mut stdin: ChildStdin, recv: Receiver<String>) {
+    for msg in recv {
+        if let Err(e) = stdin.write_all(msg.as_bytes()).and_then(|_| stdin.flush()) {
+            warn!("failed to write to plugin {:?}: {}", name, e);
+            return;
+        }
+    }
+}
+
+// Reads the messages that a plugin writes, and forwards the gestures to `send`. Only the gestures
+// in `recognizes` are allowed, so that a plugin can't pretend to be some other plugin.
+fn read_messages<R: BufRead>(name: String, stdout: R, recognizes: Vec<CustomName>,
+                             send: chan::Sender<PluginGesture>) {
+    for line in stdout.lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => {
+                warn!("failed to read from plugin {:?}: {}", name, e);
+                break;
+            },
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<FromPlugin>(&line) {
+            Ok(FromPlugin::Gesture { name: ref g, x, y }) => {
+                match CustomName::new(g) {
+                    Some(n) if recognizes.contains(&n) => send.send(PluginGesture {
+                        gesture: Gesture::Plugin(n),
+                        pos: x.and_then(|x| y.map(|y| Point::new(x, y))),
+                    }),
+                    _ => warn!("plugin {:?} recognized {:?}, which it didn't declare", name, g),
+                }
+            },
+            Err(e) => warn!("plugin {:?} sent an invalid message {:?}: {}", name, line, e),
+        }
+    }
+    info!("plugin {:?} exited", name);
+}
+
+/// The plugins that the daemon is running.
+pub struct Plugins {
+    configs: Vec<PluginConfig>,
+    running: HashMap<String, Running>,
+    failures: HashMap<String, Failures>,
+    send: chan::Sender<PluginGesture>,
+    recv: chan::Receiver<PluginGesture>,
+}
+
+impl Plugins {
+    pub fn new(configs: Vec<PluginConfig>) -> Plugins {
+        let (send, recv) = chan::async();
+        let mut ret = Plugins {
+            configs: Vec::new(),
+            running: HashMap::new(),
+            failures: HashMap::new(),
+            send: send,
+            recv: recv,
+        };
+        ret.set_configs(configs);
+        ret
+    }
+
+    /// The gestures that the plugins recognize.
+    pub fn gestures(&self) -> chan::Receiver<PluginGesture> {
+        self.recv.clone()
+    }
+
+    /// Stops all the plugins, and starts the ones in `configs` that recognize gestures.
+    ///
+    /// This also gives the plugins that were disabled for failing another chance.
+    pub fn set_configs(&mut self, configs: Vec<PluginConfig>) {
+        self.stop_all();
+        self.failures.clear();
+        self.configs = configs;
+        let names = self.configs.iter()
+            .filter(|c| !c.recognizes.is_empty())
+            .map(|c| c.name.clone())
+            .collect::<Vec<_>>();
+        for name in names {
+            self.get(&name);
+        }
+    }
+
+    // Returns the plugin called `name`, starting it if it isn't running (and hasn't been failing
+    // too much).
+    fn get(&mut self, name: &str) -> Option<&mut Running> {
+        let alive = match self.running.get_mut(name) {
+            Some(r) => r.child.try_wait().map(|s| s.is_none()).unwrap_or(false),
+            None => false,
+        };
+        if !alive {
+            if let Some(r) = self.running.remove(name) {
+                self.failed(name, r.started.elapsed());
+            }
+            if !self.may_start(name) {
+                return None;
+            }
+            let config = match self.configs.iter().find(|c| c.name == name) {
+                Some(c) => c.clone(),
+                None => {
+                    error!("there is no plugin called {:?}", name);
+                    return None;
+                },
+            };
+            match start(config, self.send.clone()) {
+                Ok(r) => { self.running.insert(name.to_owned(), r); },
+                Err(e) => {
+                    error!("failed to start plugin {:?}: {}", name, e);
+                    self.failed(name, Duration::from_secs(0));
+                    return None;
+                },
+            }
+        }
+        self.running.get_mut(name)
+    }
+
+    // Takes note that the plugin called `name` exited (or didn't start) after running for
+    // `ran_for`, and decides when it may be started again.
+    fn failed(&mut self, name: &str, ran_for: Duration) {
+        let f = self.failures.entry(name.to_owned())
+            .or_insert(Failures { count: 0, retry_at: Instant::now() });
+        if ran_for >= Duration::from_secs(STABLE_SECS) {
+            f.count = 0;
+        }
+        f.count += 1;
+        let delay = (RESTART_DELAY_MS << (f.count - 1).min(16)).min(MAX_RESTART_DELAY_MS);
+        f.retry_at = Instant::now() + Duration::from_millis(delay);
+        if f.count == MAX_FAILURES {
+            error!("Plugin {:?} failed {} times in a row, so it is disabled until the config is \
+                    reloaded.", name, f.count);
+        } else if f.count < MAX_FAILURES {
+            info!("Plugin {:?} will be started again in {} ms.", name, delay);
+        }
+    }
+
+    // Returns true if the plugin called `name` may be started now.
+    fn may_start(&self, name: &str) -> bool {
+        match self.failures.get(name) {
+            Some(f) => f.count < MAX_FAILURES && Instant::now() >= f.retry_at,
+            None => true,
+        }
+    }
+
+    // Sends a message to a plugin, without waiting for it to be read.
+    fn send(&mut self, name: &str, msg: &ToPlugin) {
+        let line = match serde_json::to_string(msg) {
+            Ok(l) => l + "\n",
+            Err(e) => {
+                error!("failed to serialize a message for plugin {:?}: {}", name, e);
+                return;
+            },
+        };
+        let stuck = match self.get(name) {
+            Some(r) => {
+                push_backlog(&mut r.backlog, msg.merge(), line);
+                r.flush(name);
+                r.backlog.len() > BACKLOG_LEN
+            },
+            None => return,
+        };
+        if stuck {
+            warn!("Plugin {:?} isn't reading its input, so it is being stopped.", name);
+            if let Some(r) = self.running.remove(name) {
+                let ran_for = r.started.elapsed();
+                stop(name, r);
+                self.failed(name, ran_for);
+            }
+        }
+    }
+
+    /// Sends a touch event from `device` to all the plugins that recognize gestures.
+    pub fn touch(&mut self, device: &str, ev: &Event) {
+        let names = self.configs.iter()
+            .filter(|c| !c.recognizes.is_empty())
+            .map(|c| c.name.clone())
+            .collect::<Vec<_>>();
+        let msg = match ToPlugin::touch(device, ev) {
+            Some(m) => m,
+            None => return,
+        };
+        for name in names {
+            self.send(&name, &msg);
+        }
+    }
+
+    /// Tells the plugin called `name` that a binding to it fired for `gesture`.
+    pub fn action(&mut self, name: &str, gesture: &Gesture, args: &[String]) {
+        self.send(name, &ToPlugin::Action { gesture: gesture.to_string(), args: args });
+    }
+
+    fn stop_all(&mut self) {
+        for (name, r) in self.running.drain() {
+            stop(&name, r);
+        }
+    }
+}
+
+fn stop(name: &str, mut r: Running) {
+    debug!("stopping plugin {:?}", name);
+    if let Err(e) = r.child.kill() {
+        warn!("failed to stop plugin {:?}: {}", name, e);
+    }
+    let _ = r.child.wait();
+}
+
+impl Drop for Plugins {
+    fn drop(&mut self) {
+        self.stop_all();
+    }
+}
+
+fn start(config: PluginConfig, gestures: chan::Sender<PluginGesture>) -> Result<Running, String> {
+    info!("starting plugin {:?}", config.name);
+    let mut child = Command::new(&config.command)
+        .args(&config.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    let stdin = child.stdin.take().ok_or("no standard input")?;
+    let stdout = child.stdout.take().ok_or("no standard output")?;
+
+    let (send, recv) = sync_channel(QUEUE_LEN);
+    let name = config.name.clone();
+    thread::spawn(move || write_messages(name, stdin, recv));
+    let name = config.name.clone();
+    let recognizes = config.recognizes;
+    thread::spawn(move || read_messages(name, BufReader::new(stdout), recognizes, gestures));
+
+    let hello = serde_json::to_string(&ToPlugin::Hello { version: PROTOCOL_VERSION })
+        .map_err(|e| e.to_string())?;
+    send.try_send(hello + "\n").map_err(|e| e.to_string())?;
+    Ok(Running { child: child, started: Instant::now(), send: send, backlog: VecDeque::new() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{push_backlog, read_messages, Merge, PluginGesture, Plugins, ToPlugin,
+                MAX_FAILURES};
+    use chan;
+    use std::collections::VecDeque;
+    use std::time::{Duration, Instant};
+    use custom::CustomName;
+    use libgestures::event::Event;
+    use libgestures::geom::Point;
+    use serde_json;
+    use Gesture;
+
+    #[test]
+    fn touch_messages() {
+        let ev = Event::Motion { slot: 1, pos: Point::new(12.5, 40.0) };
+        assert_eq!(serde_json::to_string(&ToPlugin::touch("event5", &ev).unwrap()).unwrap(),
+                   concat!(r#"{"type":"touch","device":"event5","kind":"motion","slot":1,"#,
+                           r#""x":12.5,"y":40.0,"time":0}"#));
+        let shape = Event::Shape { slot: 1, size: 9.0, pressure: 0.5 };
+        assert!(ToPlugin::touch("event5", &shape).is_none());
+    }
+
+    #[test]
+    fn only_declared_gestures() {
+        let lasso = CustomName::new("lasso").unwrap();
+        let output = concat!(r#"{"type": "gesture", "name": "lasso", "x": 1.0, "y": 2.0}"#, "\n",
+                             r#"{"type": "gesture", "name": "swipe"}"#, "\n",
+                             "nonsense\n",
+                             r#"{"type": "gesture", "name": "lasso"}"#, "\n");
+        let (send, recv) = chan::async();
+        read_messages("test".to_owned(), output.as_bytes(), vec![lasso], send);
+        assert_eq!(recv.iter().collect::<Vec<_>>(), vec![
+            PluginGesture { gesture: Gesture::Plugin(lasso), pos: Some(Point::new(1.0, 2.0)) },
+            PluginGesture { gesture: Gesture::Plugin(lasso), pos: None },
+        ]);
+    }
+
+    #[test]
+    fn backlog_merges_motions() {
+        let mut backlog = VecDeque::new();
+        let events = vec![
+            Event::Motion { slot: 0, pos: Point::new(1.0, 0.0) },
+            Event::Motion { slot: 1, pos: Point::new(1.0, 0.0) },
+            Event::Frame { time: 10 },
+            Event::Motion { slot: 0, pos: Point::new(2.0, 0.0) },
+            Event::Frame { time: 20 },
+            Event::Up { slot: 1 },
+            Event::Motion { slot: 0, pos: Point::new(3.0, 0.0) },
+            Event::Frame { time: 30 },
+            Event::Motion { slot: 0, pos: Point::new(4.0, 0.0) },
+            Event::Frame { time: 40 },
+        ];
+        for ev in &events {
+            let msg = ToPlugin::touch("event5", ev).unwrap();
+            push_backlog(&mut backlog, msg.merge(), format!("{:?}", ev));
+        }
+        // The first two motions of slot 0 are merged, and so are the last two, but not across
+        // the finger that lifted. Each merged motion takes the place of the newer one.
+        assert_eq!(backlog.iter().map(|b| b.0).collect::<Vec<_>>(), vec![
+            Merge::Motion(1),
+            Merge::Frame,
+            Merge::Motion(0),
+            Merge::Frame,
+            Merge::Never,
+            Merge::Frame,
+            Merge::Motion(0),
+            Merge::Frame,
+        ]);
+        assert_eq!(backlog[2].1, format!("{:?}", events[3]));
+        assert_eq!(backlog[6].1, format!("{:?}", events[8]));
+    }
+
+    #[test]
+    fn failing_plugins_back_off() {
+        let mut plugins = Plugins::new(vec![]);
+        assert!(plugins.may_start("lasso"));
+        plugins.failed("lasso", Duration::from_secs(1));
+        assert!(!plugins.may_start("lasso"));
+        let first = plugins.failures["lasso"].retry_at;
+        plugins.failed("lasso", Duration::from_secs(1));
+        assert!(plugins.failures["lasso"].retry_at > first);
+
+        // A plugin that ran for a while starts over.
+        plugins.failed("lasso", Duration::from_secs(600));
+        assert_eq!(plugins.failures["lasso"].count, 1);
+
+        for _ in 1..MAX_FAILURES {
+            plugins.failed("lasso", Duration::from_secs(1));
+        }
+        plugins.failures.get_mut("lasso").unwrap().retry_at = Instant::now();
+        assert!(!plugins.may_start("lasso"));
+        plugins.set_configs(vec![]);
+        assert!(plugins.may_start("lasso"));
+    }
+}
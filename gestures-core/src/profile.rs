@@ -0,0 +1,44 @@
+use app_dirs::{app_root, AppDataType};
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use APP_INFO;
+use config::Config;
+
+/// The name of the profile that bindings belong to if they don't say otherwise.
+pub const DEFAULT: &'static str = "default";
+
+fn state_file() -> io::Result<PathBuf> {
+    let mut path = app_root(AppDataType::UserData, &APP_INFO)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+    path.push("active-profile");
+    Ok(path)
+}
+
+/// Returns the profile that was active when the daemon last stopped.
+///
+/// Falls back to the default profile if there is no saved profile, or if the saved profile no
+/// longer exists in `config`.
+pub fn load(config: &Config) -> String {
+    let mut name = String::new();
+    let result = state_file()
+        .and_then(|path| File::open(path))
+        .and_then(|mut f| f.read_to_string(&mut name));
+    let name = name.trim();
+    if result.is_err() || name.is_empty() {
+        DEFAULT.to_owned()
+    } else if !config.profiles().contains(name) {
+        warn!("the saved profile {:?} doesn't exist anymore; using the default", name);
+        DEFAULT.to_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Remembers `name` as the active profile, so that it will still be active after a restart.
+pub fn save(name: &str) -> io::Result<()> {
+    let mut file = File::create(state_file()?)?;
+    writeln!(file, "{}", name)
+}
@@ -0,0 +1,307 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Child, ExitStatus};
+use std::time::{Duration, Instant};
+
+use Gesture;
+use config::{Action, Binding, Overlap};
+use levels;
+
+/// How many finished actions we remember.
+const MAX_RESULTS: usize = 20;
+
+/// How many actions can wait for the one before them, for a binding that queues its actions.
+const MAX_QUEUED: usize = 8;
+
+/// An action whose process is still running.
+#[derive(Debug)]
+struct Running {
+    child: Child,
+    action: Action,
+    started: Instant,
+}
+
+/// The outcome of an action that has finished running.
+#[derive(Debug)]
+pub struct ActionResult {
+    pub gesture: Gesture,
+    pub action: Action,
+    /// The exit status of the process, or a description of what went wrong.
+    pub status: Result<ExitStatus, String>,
+    pub duration: Duration,
+}
+
+/// The state of the actions belonging to a single binding.
+#[derive(Debug, Default)]
+struct Slot {
+    running: Vec<Running>,
+    // Feedback actions that are still running. These don't count for the overlap policy.
+    feedback: Vec<Running>,
+    queued: VecDeque<Action>,
+    last_run: Option<Instant>,
+    // How far (in percent) a gesture that fires continuously has changed its level so far.
+    adjusted: i32,
+}
+
+// Starts `action`, adding it to `list` if it started a process.
+fn start(list: &mut Vec<Running>, gesture: &Gesture, action: &Action,
+         results: &mut VecDeque<ActionResult>) {
+    match action.start() {
+        Ok(Some(child)) => list.push(Running {
+            child: child,
+            action: action.clone(),
+            started: Instant::now(),
+        }),
+        Ok(None) => push_result(results, ActionResult {
+            gesture: *gesture,
+            action: action.clone(),
+            status: Ok(ExitStatus::from_raw(0)),
+            duration: Duration::from_secs(0),
+        }),
+        Err(e) => {
+            error!("failed to run action {:?} for gesture {:?}: {}", action, gesture, e);
+            push_result(results, ActionResult {
+                gesture: *gesture,
+                action: action.clone(),
+                status: Err(e.to_string()),
+                duration: Duration::from_secs(0),
+            });
+        },
+    }
+}
+
+// Kills a process, along with everything that it started if its sandbox can tell (see
+// `Sandbox::kill`).
+fn kill(r: &mut Running) -> io::Result<()> {
+    match r.action {
+        Action::Command { ref sandbox, .. } => sandbox.kill(&mut r.child),
+        _ => r.child.kill(),
+    }
+}
+
+// Removes all the finished processes from `list`, and records their results.
+fn reap(list: &mut Vec<Running>, gesture: &Gesture, results: &mut VecDeque<ActionResult>) {
+    let mut i = 0;
+    while i < list.len() {
+        let status = match list[i].child.try_wait() {
+            Ok(Some(status)) => Ok(status),
+            Ok(None) => {
+                let expired = list[i].action.timeout()
+                    .map_or(false, |t| list[i].started.elapsed() >= t);
+                if !expired {
+                    i += 1;
+                    continue;
+                }
+                warn!("action {:?} for gesture {:?} ran for too long; killing it",
+                      list[i].action, gesture);
+                if let Err(e) = kill(&mut list[i]) {
+                    warn!("failed to kill process {}: {}", list[i].child.id(), e);
+                }
+                list[i].child.wait().map_err(|e| e.to_string())
+            },
+            Err(e) => Err(e.to_string()),
+        };
+
+        let r = list.swap_remove(i);
+        match status {
+            Ok(ref s) if !s.success() => {
+                warn!("action {:?} for gesture {:?} failed: {}", r.action, gesture, s);
+            },
+            Err(ref e) => {
+                error!("failed to check on process {}: {}", r.child.id(), e);
+            },
+            _ => {},
+        }
+        push_result(results, ActionResult {
+            gesture: *gesture,
+            action: r.action,
+            status: status,
+            duration: r.started.elapsed(),
+        });
+    }
+}
+
+impl Slot {
+    fn start(&mut self, gesture: &Gesture, action: &Action, results: &mut VecDeque<ActionResult>) {
+        start(&mut self.running, gesture, action, results);
+    }
+
+    fn start_feedback(&mut self, gesture: &Gesture, feedback: &[Action],
+                      results: &mut VecDeque<ActionResult>) {
+        for action in feedback {
+            start(&mut self.feedback, gesture, action, results);
+        }
+    }
+
+    // Changes the level of an `Adjust` action as far as `progress` says that it should have got,
+    // given how far it has been changed already.
+    fn adjust(&mut self, gesture: &Gesture, action: &Action, progress: f64,
+              results: &mut VecDeque<ActionResult>) {
+        if let Action::Adjust { level, percent } = *action {
+            let step = levels::scaled_step(percent, progress, self.adjusted);
+            if step != 0 {
+                self.adjusted += step;
+                self.start(gesture, &Action::Adjust { level: level, percent: step }, results);
+            }
+        }
+    }
+
+    fn kill_all(&mut self) {
+        for mut r in self.running.drain(..) {
+            if let Err(e) = kill(&mut r) {
+                warn!("failed to kill process {}: {}", r.child.id(), e);
+            }
+            let _ = r.child.wait();
+        }
+    }
+
+    fn reap(&mut self, gesture: &Gesture, results: &mut VecDeque<ActionResult>) {
+        reap(&mut self.running, gesture, results);
+        reap(&mut self.feedback, gesture, results);
+
+        if self.running.is_empty() {
+            if let Some(action) = self.queued.pop_front() {
+                debug!("starting queued action for gesture {:?}", gesture);
+                self.last_run = Some(Instant::now());
+                self.start(gesture, &action, results);
+            }
+        }
+    }
+}
+
+fn push_result(results: &mut VecDeque<ActionResult>, result: ActionResult) {
+    if results.len() >= MAX_RESULTS {
+        results.pop_front();
+    }
+    results.push_back(result);
+}
+
+/// Runs the actions for recognized gestures, and keeps track of the processes that they start.
+///
+/// Each binding has its own cooldown and overlap policy, so the processes are kept track of
+/// separately for each binding. A binding is identified by its gesture, and its index among that
+/// gesture's bindings (as returned by
+/// [`Config::binding`](../config/struct.Config.html#method.binding)).
+#[derive(Debug)]
+pub struct Runner {
+    slots: HashMap<(Gesture, usize), Slot>,
+    results: VecDeque<ActionResult>,
+}
+
+impl Runner {
+    pub fn new() -> Runner {
+        Runner {
+            slots: HashMap::new(),
+            results: VecDeque::new(),
+        }
+    }
+
+    /// Runs the action for `binding`, which was triggered by `gesture` and is its binding number
+    /// `index`.
+    ///
+    /// The action might not actually run (or it might run later), depending on the binding's
+    /// cooldown and overlap policy. If it changes a level, the change is scaled by `progress`
+    /// (see the `levels` module), which is 1 for gestures that don't report their progress.
+    pub fn trigger(&mut self, gesture: Gesture, index: usize, binding: &Binding, progress: f64) {
+        let slot = self.slots.entry((gesture, index)).or_insert_with(Slot::default);
+        let now = Instant::now();
+
+        // A gesture that fires continuously has been changing its level all along, so it only
+        // has the rest of the way to go.
+        if slot.adjusted != 0 {
+            slot.adjust(&gesture, &binding.action, progress, &mut self.results);
+            slot.adjusted = 0;
+            slot.start_feedback(&gesture, &binding.feedback, &mut self.results);
+            return;
+        }
+        let action = match binding.action {
+            Action::Adjust { level, percent } => {
+                let step = levels::scaled_step(percent, progress, 0);
+                if step == 0 {
+                    debug!("ignoring gesture {:?} because it didn't go far enough", gesture);
+                    return;
+                }
+                Action::Adjust { level: level, percent: step }
+            },
+            ref a => a.clone(),
+        };
+
+        if let (Some(cooldown), Some(last)) = (binding.cooldown, slot.last_run) {
+            if now.duration_since(last) < cooldown {
+                info!("ignoring gesture {:?} because its binding is cooling down", gesture);
+                return;
+            }
+        }
+
+        // Clean up anything that finished since the last tick, so that we don't think an old
+        // process is still running.
+        slot.reap(&gesture, &mut self.results);
+        if !slot.running.is_empty() {
+            match binding.overlap {
+                Overlap::Allow => {},
+                Overlap::Skip => {
+                    info!("ignoring gesture {:?} because its action is still running", gesture);
+                    return;
+                },
+                Overlap::Queue if slot.queued.len() >= MAX_QUEUED => {
+                    warn!("Dropping the action for gesture {:?}, because {} of its actions are \
+                           already waiting.", gesture, slot.queued.len());
+                    return;
+                },
+                Overlap::Queue => {
+                    debug!("queueing action for gesture {:?}", gesture);
+                    slot.queued.push_back(action);
+                    // The gesture was recognized, so give feedback right away even though the
+                    // action itself has to wait.
+                    slot.start_feedback(&gesture, &binding.feedback, &mut self.results);
+                    return;
+                },
+                Overlap::KillPrevious => slot.kill_all(),
+            }
+        }
+        // The cooldown only counts the times that the action actually started.
+        slot.last_run = Some(now);
+        slot.start(&gesture, &action, &mut self.results);
+        slot.start_feedback(&gesture, &binding.feedback, &mut self.results);
+    }
+
+    /// Changes a level part of the way, for a gesture that fires continuously and has got to
+    /// `progress`, if `binding` changes a level. Otherwise, this does nothing: the action only
+    /// runs once the gesture is finished, and is [triggered](#method.trigger).
+    ///
+    /// These changes don't wait for the binding's cooldown or overlap policy, since they all
+    /// belong to the same gesture.
+    pub fn slide(&mut self, gesture: Gesture, index: usize, binding: &Binding, progress: f64) {
+        let slot = self.slots.entry((gesture, index)).or_insert_with(Slot::default);
+        slot.adjust(&gesture, &binding.action, progress, &mut self.results);
+    }
+
+    /// Cleans up any finished processes, and starts any queued actions that are ready to go.
+    pub fn reap(&mut self) {
+        for (&(ref gesture, _), slot) in &mut self.slots {
+            slot.reap(gesture, &mut self.results);
+        }
+    }
+
+    /// Returns true if no actions are running or waiting to run, so there is nothing to reap.
+    pub fn is_idle(&self) -> bool {
+        self.slots.values()
+            .all(|s| s.running.is_empty() && s.feedback.is_empty() && s.queued.is_empty())
+    }
+
+    /// Describes the most recently finished actions, one per line.
+    pub fn describe_results(&self) -> String {
+        let mut ret = String::new();
+        for r in &self.results {
+            let status = match r.status {
+                Ok(ref s) => s.to_string(),
+                Err(ref e) => format!("error: {}", e),
+            };
+            let _ = writeln!(ret, "{:?}\t{:?}\t{}\t{}ms", r.gesture, r.action, status,
+                             r.duration.as_secs() * 1000 + r.duration.subsec_nanos() as u64 / 1_000_000);
+        }
+        ret
+    }
+}
@@ -0,0 +1,198 @@
+//! Keeping the commands that bindings run from hurting the session.
+//!
+//! A script that hangs, spins, or forks forever shouldn't be able to take the whole desktop down
+//! with it, and a daemon that was started with extra privileges (or secrets in its environment)
+//! shouldn't pass them on. A binding's command can be confined with a `sandbox` section:
+//!
+//! ```toml
+//! [[bindings]]
+//! gesture = "swipe 3 up"
+//! exec = "~/bin/sync-notes"
+//! [bindings.sandbox]
+//! # Start with an empty environment, apart from what a desktop app needs (and `env`).
+//! clear-env = true
+//! # Run at this niceness, from -20 (the most favored) to 19 (the least).
+//! nice = 10
+//! # Run in a transient systemd scope, so that systemd can keep track of (and limit) everything
+//! # that the command starts.
+//! scope = true
+//! # Kill the command, and everything that it started, if it is still running after this many
+//! # milliseconds.
+//! timeout-ms = 10000
+//! ```
+//!
+//! A command with a timeout runs in a process group of its own, so that the whole group can be
+//! killed. Anything that leaves the group (like a program that daemonizes itself) escapes, unless
+//! the command also runs in a scope, which the daemon stops too.
+
+use libc;
+use std::env;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command};
+use std::time::Duration;
+
+// The variables that a command keeps with `clear-env`, because apps can't talk to the desktop
+// without them.
+const KEEP_VARS: &'static [&'static str] = &[
+    "DBUS_SESSION_BUS_ADDRESS", "DISPLAY", "HOME", "LANG", "LOGNAME", "PATH", "USER",
+    "WAYLAND_DISPLAY", "XAUTHORITY", "XDG_RUNTIME_DIR", "XDG_SESSION_TYPE",
+];
+
+/// A `sandbox` section of a binding.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct SandboxParsed {
+    #[serde(rename = "clear-env")]
+    clear_env: Option<bool>,
+    nice: Option<i32>,
+    scope: Option<bool>,
+    #[serde(rename = "timeout-ms")]
+    timeout_ms: Option<u64>,
+}
+
+impl SandboxParsed {
+    pub fn to_sandbox(&self) -> Result<Sandbox, String> {
+        if let Some(n) = self.nice {
+            if n < -20 || n > 19 {
+                return Err(format!("nice must be between -20 and 19, not {}", n));
+            }
+        }
+        Ok(Sandbox {
+            clear_env: self.clear_env.unwrap_or(false),
+            nice: self.nice,
+            scope: self.scope.unwrap_or(false),
+            timeout: self.timeout_ms.map(Duration::from_millis),
+        })
+    }
+}
+
+/// How a command is confined. The default doesn't confine it at all.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Sandbox {
+    /// If set, the command only gets the variables in `KEEP_VARS` from the daemon's environment.
+    pub clear_env: bool,
+    /// If set, the niceness that the command runs at.
+    pub nice: Option<i32>,
+    /// If set, the command runs in a transient scope of the user's systemd instance.
+    pub scope: bool,
+    /// If set, the command (and the rest of its process group) is killed if it runs for longer
+    /// than this.
+    pub timeout: Option<Duration>,
+}
+
+impl Sandbox {
+    /// Builds the command that runs `command` with `args` and the extra environment variables
+    /// `vars`, inside this sandbox.
+    pub fn command(&self, command: &str, args: &[String], vars: &[(String, String)]) -> Command {
+        let mut cmd = if self.scope {
+            let mut cmd = Command::new("systemd-run");
+            cmd.args(&["--user", "--scope", "--quiet", "--"]).arg(command);
+            cmd
+        } else {
+            Command::new(command)
+        };
+        cmd.args(args);
+        if self.clear_env {
+            let kept = KEEP_VARS.iter()
+                .filter_map(|&v| env::var_os(v).map(|value| (v, value)))
+                .collect::<Vec<(&str, OsString)>>();
+            cmd.env_clear().envs(kept);
+        }
+        cmd.envs(vars.iter().cloned());
+        if let Some(n) = self.nice {
+            cmd.before_exec(move || {
+                if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, n) } < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(())
+                }
+            });
+        }
+        if self.timeout.is_some() {
+            cmd.before_exec(|| {
+                if unsafe { libc::setpgid(0, 0) } < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(())
+                }
+            });
+        }
+        cmd
+    }
+
+    /// Kills a command that was started with [`command`](#method.command), and everything that
+    /// it started.
+    ///
+    /// If the sandbox has a timeout, the command leads its own process group, and the whole group
+    /// is killed. If it runs in a scope, everything in the scope is killed too, which takes care
+    /// of anything that left the group. Otherwise, only the command itself is killed.
+    pub fn kill(&self, child: &mut Child) -> io::Result<()> {
+        if self.scope {
+            // systemd-run replaces itself with the command, so the command is in the scope.
+            match scope_of(child.id()) {
+                Ok(unit) => {
+                    let killed = Command::new("systemctl")
+                        .args(&["--user", "kill", "--signal=SIGKILL"])
+                        .arg(&unit)
+                        .status();
+                    if let Err(e) = killed {
+                        warn!("failed to kill the processes in {}: {}", unit, e);
+                    }
+                },
+                Err(e) => warn!("failed to find the scope of process {}: {}", child.id(), e),
+            }
+        }
+        if self.timeout.is_some() {
+            if unsafe { libc::killpg(child.id() as libc::pid_t, libc::SIGKILL) } == 0 {
+                return Ok(());
+            }
+        }
+        child.kill()
+    }
+}
+
+// Returns the name of the systemd unit that the process `pid` belongs to, like "run-u12.scope".
+fn scope_of(pid: u32) -> io::Result<String> {
+    let mut cgroups = String::new();
+    File::open(format!("/proc/{}/cgroup", pid))?.read_to_string(&mut cgroups)?;
+    parse_scope(&cgroups)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "it isn't in a scope"))
+}
+
+// Finds the scope in the contents of `/proc/PID/cgroup`, which has lines like
+// "0::/user.slice/user-1000.slice/user@1000.service/app.slice/run-u12.scope".
+fn parse_scope(cgroups: &str) -> Option<String> {
+    cgroups.lines()
+        .filter_map(|line| line.rsplit('/').next())
+        .find(|unit| unit.ends_with(".scope"))
+        .map(|unit| unit.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use toml;
+
+    #[test]
+    fn parses_config() {
+        let s: SandboxParsed = toml::from_str("clear-env = true\ntimeout-ms = 500").unwrap();
+        assert_eq!(s.to_sandbox(), Ok(Sandbox {
+            clear_env: true,
+            nice: None,
+            scope: false,
+            timeout: Some(Duration::from_millis(500)),
+        }));
+        let s: SandboxParsed = toml::from_str("nice = 40").unwrap();
+        assert!(s.to_sandbox().is_err());
+    }
+
+    #[test]
+    fn finds_the_scope() {
+        let cgroups = "0::/user.slice/user-1000.slice/user@1000.service/app.slice/run-u12.scope\n";
+        assert_eq!(parse_scope(cgroups), Some("run-u12.scope".to_owned()));
+        let cgroups = "0::/user.slice/user-1000.slice/session-2.scope/gestures.service\n";
+        assert_eq!(parse_scope(cgroups), None);
+    }
+}
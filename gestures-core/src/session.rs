@@ -0,0 +1,227 @@
+//! Keeping track of whether the session is locked, and keeping it awake.
+//!
+//! While the screen is locked, whoever picks up the device shouldn't be able to launch things with
+//! a swipe, so gestures don't run their actions. We find out about the lock from logind, which
+//! sets the `LockedHint` property of the session whenever the screen locker starts or stops.
+//!
+//! Touches go to the compositor, which normally counts them as activity. The ones that the daemon
+//! swallows (see the `forward` module) don't, so with `keep-awake` the daemon tells the screen
+//! saver about every gesture:
+//!
+//! ```toml
+//! [session]
+//! # Whether to ignore gestures while the session is locked (the default is true).
+//! pause-when-locked = true
+//! # Whether gestures count as activity, so that the screen doesn't blank (the default is false).
+//! keep-awake = true
+//! ```
+
+use chan;
+use dbus::{BusType, Connection, ConnectionItem, Message, Path};
+use dbus::arg::Variant;
+use std::env;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+// How long to wait for logind (or the screen saver) to answer, in milliseconds.
+const TIMEOUT_MS: i32 = 500;
+// How often the watcher's thread checks whether it should stop, in milliseconds.
+const POLL_MS: i32 = 1000;
+
+const LOGIND: &'static str = "org.freedesktop.login1";
+const SESSION: &'static str = "org.freedesktop.login1.Session";
+const PROPERTIES: &'static str = "org.freedesktop.DBus.Properties";
+
+/// A `[session]` section of the config file.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct SessionParsed {
+    #[serde(rename = "pause-when-locked")]
+    pause_when_locked: Option<bool>,
+    #[serde(rename = "keep-awake")]
+    keep_awake: Option<bool>,
+}
+
+impl SessionParsed {
+    pub fn to_session(&self) -> SessionConfig {
+        let d = SessionConfig::default();
+        SessionConfig {
+            pause_when_locked: self.pause_when_locked.unwrap_or(d.pause_when_locked),
+            keep_awake: self.keep_awake.unwrap_or(d.keep_awake),
+        }
+    }
+}
+
+/// What the daemon does about the session.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SessionConfig {
+    /// If set, gestures don't run their actions while the session is locked.
+    pub pause_when_locked: bool,
+    /// If set, every gesture counts as activity, so that the session doesn't go idle.
+    pub keep_awake: bool,
+}
+
+impl Default for SessionConfig {
+    fn default() -> SessionConfig {
+        SessionConfig {
+            pause_when_locked: true,
+            keep_awake: false,
+        }
+    }
+}
+
+/// Sends whether the session is locked to a channel every time it changes, until it is dropped.
+pub struct LockWatcher {
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for LockWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Starts watching logind for the session getting locked and unlocked, sending the new state to
+/// `send` (starting with the current one).
+pub fn watch_lock(send: chan::Sender<bool>) -> Result<LockWatcher, String> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    // A connection can't move between threads, so it's opened in the watcher's thread, which
+    // tells us if that worked.
+    let (started_send, started) = mpsc::channel();
+    thread::spawn(move || {
+        let (conn, path, mut locked) = match connect() {
+            Ok(x) => {
+                let _ = started_send.send(Ok(()));
+                x
+            },
+            Err(e) => {
+                let _ = started_send.send(Err(e));
+                return;
+            },
+        };
+        send.send(locked);
+        while !thread_stop.load(Ordering::Relaxed) {
+            for item in conn.iter(POLL_MS) {
+                match item {
+                    // We only listen to our session's properties, so something about it changed.
+                    ConnectionItem::Signal(_) => match locked_hint(&conn, &path) {
+                        Ok(l) if l != locked => {
+                            locked = l;
+                            send.send(locked);
+                        },
+                        Ok(_) => {},
+                        Err(e) => warn!("{}", e),
+                    },
+                    ConnectionItem::Nothing => break,
+                    _ => {},
+                }
+            }
+        }
+        debug!("stopped watching the session lock");
+    });
+    match started.recv() {
+        Ok(Ok(())) => Ok(LockWatcher { stop: stop }),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err("the session watcher stopped".to_owned()),
+    }
+}
+
+// Connects to logind, finds our session, and subscribes to changes to its properties. Also
+// returns whether the session is locked now.
+fn connect() -> Result<(Connection, Path<'static>, bool), String> {
+    let conn = Connection::get_private(BusType::System)
+        .map_err(|e| format!("couldn't connect to the system bus: {:?}", e.message()))?;
+    let path = session_path(&conn)?;
+    let rule = format!("type='signal',sender='{}',interface='{}',member='PropertiesChanged',\
+                        path='{}'", LOGIND, PROPERTIES, &*path);
+    conn.add_match(&rule)
+        .map_err(|e| format!("couldn't watch the session: {:?}", e.message()))?;
+    let locked = locked_hint(&conn, &path)?;
+    debug!("watching the session lock at {}; locked: {}", &*path, locked);
+    Ok((conn, path, locked))
+}
+
+fn call(conn: &Connection, msg: Message) -> Result<Message, String> {
+    conn.send_with_reply_and_block(msg, TIMEOUT_MS)
+        .map_err(|e| format!("logind didn't answer: {:?}", e.message()))
+}
+
+// Finds the object path of our session. If the daemon isn't running inside a session (for
+// example, because it was started as a systemd user service), logind's "auto" session is the
+// user's graphical one.
+fn session_path(conn: &Connection) -> Result<Path<'static>, String> {
+    let id = match env::var("XDG_SESSION_ID") {
+        Ok(id) => id,
+        Err(_) => {
+            let msg = Message::new_method_call(LOGIND, "/org/freedesktop/login1/session/auto",
+                                               PROPERTIES, "Get")?
+                .append2(SESSION, "Id");
+            call(conn, msg)?.get1::<Variant<String>>()
+                .map(|v| v.0)
+                .ok_or("couldn't find the session".to_owned())?
+        },
+    };
+    let msg = Message::new_method_call(LOGIND, "/org/freedesktop/login1",
+                                       "org.freedesktop.login1.Manager", "GetSession")?
+        .append1(id.as_str());
+    call(conn, msg)?.get1::<Path>()
+        .map(|p| p.into_static())
+        .ok_or(format!("couldn't find the session {:?}", id))
+}
+
+fn locked_hint(conn: &Connection, path: &Path<'static>) -> Result<bool, String> {
+    let msg = Message::new_method_call(LOGIND, path.clone(), PROPERTIES, "Get")?
+        .append2(SESSION, "LockedHint");
+    call(conn, msg)?.get1::<Variant<bool>>()
+        .map(|v| v.0)
+        .ok_or("logind didn't say whether the session is locked".to_owned())
+}
+
+/// Sets the brightness of a backlight (named as in `/sys/class/backlight`) through logind.
+pub fn set_brightness(name: &str, brightness: u32) -> Result<(), String> {
+    let conn = Connection::get_private(BusType::System)
+        .map_err(|e| format!("couldn't connect to the system bus: {:?}", e.message()))?;
+    let path = session_path(&conn)?;
+    let msg = Message::new_method_call(LOGIND, path, SESSION, "SetBrightness")?
+        .append3("backlight", name, brightness);
+    call(&conn, msg).map(|_| ())
+}
+
+/// Tells the screen saver that the user did something, so that the session doesn't go idle.
+///
+/// This happens in the background, and failures are only logged: there might not be a screen
+/// saver at all.
+pub fn simulate_activity() {
+    thread::spawn(|| {
+        let result = Connection::get_private(BusType::Session)
+            .map_err(|e| format!("couldn't connect to the session bus: {:?}", e.message()))
+            .and_then(|conn| {
+                let msg = Message::new_method_call("org.freedesktop.ScreenSaver",
+                                                   "/org/freedesktop/ScreenSaver",
+                                                   "org.freedesktop.ScreenSaver",
+                                                   "SimulateUserActivity")?;
+                conn.send_with_reply_and_block(msg, TIMEOUT_MS)
+                    .map(|_| ())
+                    .map_err(|e| format!("the screen saver didn't answer: {:?}", e.message()))
+            });
+        if let Err(e) = result {
+            debug!("couldn't keep the session awake: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use toml;
+
+    #[test]
+    fn parses_config() {
+        let s: SessionParsed = toml::from_str("keep-awake = true").unwrap();
+        assert_eq!(s.to_session(), SessionConfig { pause_when_locked: true, keep_awake: true });
+        let s: SessionParsed = toml::from_str("pause-when-locked = false").unwrap();
+        assert_eq!(s.to_session(), SessionConfig { pause_when_locked: false, keep_awake: false });
+    }
+}
@@ -0,0 +1,203 @@
+//! Noticing when a gesture keeps getting in the way of a more specific one.
+//!
+//! If one finger of a four-finger swipe comes down a little late, the swipe can be recognized as
+//! a three-finger one instead. People tend to try again straight away, so when a gesture is often
+//! followed, within a couple of seconds, by the same gesture with more fingers, the daemon logs a
+//! hint about which setting to change. It only does that once for each pair of gestures (until
+//! the config files are reloaded).
+
+use libgestures::gestures::Gesture as TouchGesture;
+use libgestures::manager::Recognized;
+use std::collections::{HashMap, HashSet};
+
+use Gesture;
+
+// How soon (in milliseconds) after a gesture ends another one has to start to count as a retry.
+const RETRY_MS: u32 = 2000;
+
+// How many retries it takes before we say anything.
+const MIN_RETRIES: u32 = 3;
+
+// At least one in this many of the less specific gestures has to be followed by a retry.
+const MAX_RATIO: u32 = 4;
+
+/// A gesture that was recently recognized.
+#[derive(Clone, Debug)]
+struct Last {
+    gesture: TouchGesture,
+    device: Option<String>,
+    end_time: u32,
+    // True if it cut short some recognizers with higher priorities.
+    claimed: bool,
+}
+
+/// How often one gesture was retried as another.
+#[derive(Clone, Debug, Default)]
+struct Tally {
+    retries: u32,
+    // How many of those retries came after the first gesture claimed the touch.
+    claimed: u32,
+}
+
+/// Keeps statistics about which gestures get retried with more fingers.
+#[derive(Debug, Default)]
+pub struct Shadowing {
+    last: Option<Last>,
+    // How many times each gesture was recognized.
+    seen: HashMap<TouchGesture, u32>,
+    // Indexed by the gesture that was recognized, and then the one that was meant.
+    tallies: HashMap<(TouchGesture, TouchGesture), Tally>,
+    hinted: HashSet<(TouchGesture, TouchGesture)>,
+}
+
+// Returns `g`, but made with `n` fingers, if that makes sense.
+fn with_fingers(g: TouchGesture, n: u8) -> Option<TouchGesture> {
+    match g {
+        TouchGesture::Swipe { direction, .. } => {
+            Some(TouchGesture::Swipe { num_fingers: n, direction })
+        },
+        TouchGesture::Spread { .. } => Some(TouchGesture::Spread { num_fingers: n }),
+        TouchGesture::Grab { .. } => Some(TouchGesture::Grab { num_fingers: n }),
+        TouchGesture::Shake { .. } => Some(TouchGesture::Shake { num_fingers: n }),
+        TouchGesture::Path { path, .. } => Some(TouchGesture::Path { num_fingers: n, path }),
+        TouchGesture::Tap { .. } => Some(TouchGesture::Tap { num_fingers: n }),
+        TouchGesture::Hold { .. } => Some(TouchGesture::Hold { num_fingers: n }),
+        TouchGesture::Pinch { direction, .. } => {
+            Some(TouchGesture::Pinch { num_fingers: n, direction })
+        },
+        TouchGesture::Rotate { direction, .. } => {
+            Some(TouchGesture::Rotate { num_fingers: n, direction })
+        },
+        TouchGesture::EdgeSwipe { .. } => None,
+    }
+}
+
+// Returns true if `b` is the same gesture as `a`, but with more fingers.
+fn is_more_specific(a: TouchGesture, b: TouchGesture) -> bool {
+    b.num_fingers() > a.num_fingers() && with_fingers(a, b.num_fingers()) == Some(b)
+}
+
+impl Shadowing {
+    pub fn new() -> Shadowing {
+        Shadowing::default()
+    }
+
+    /// Takes note of a gesture that was recognized on a touch surface or with a pen.
+    ///
+    /// If this makes it look like the previous gesture is often recognized when the user meant
+    /// this one, returns a hint about what to do about it.
+    pub fn observe(&mut self, r: &Recognized<Gesture>) -> Option<String> {
+        let g = match r.value {
+            Gesture::Touch(g) => g,
+            _ => return None,
+        };
+        *self.seen.entry(g).or_insert(0) += 1;
+        let last = self.last.take();
+        self.last = Some(Last {
+            gesture: g,
+            device: r.device.clone(),
+            end_time: r.end_time,
+            claimed: !r.shadowed.is_empty(),
+        });
+
+        let last = match last {
+            Some(l) => l,
+            None => return None,
+        };
+        // If this one started first, the difference wraps around, and is much too big.
+        let soon = r.start_time.wrapping_sub(last.end_time) <= RETRY_MS;
+        if !soon || last.device != r.device || !is_more_specific(last.gesture, g) {
+            return None;
+        }
+        let key = (last.gesture, g);
+        let tally = {
+            let t = self.tallies.entry(key).or_insert_with(Tally::default);
+            t.retries += 1;
+            if last.claimed {
+                t.claimed += 1;
+            }
+            t.clone()
+        };
+        debug!("gesture={:?} retried_as={:?} retries={}", last.gesture.to_string(),
+               g.to_string(), tally.retries);
+
+        let seen = self.seen.get(&last.gesture).cloned().unwrap_or(0);
+        if tally.retries < MIN_RETRIES || tally.retries * MAX_RATIO < seen
+            || self.hinted.contains(&key) {
+            return None;
+        }
+        self.hinted.insert(key);
+        let a = Gesture::Touch(last.gesture);
+        let b = Gesture::Touch(g);
+        let advice = if tally.claimed * 2 >= tally.retries {
+            format!("{} is recognized before the fingers lift, and then nothing else can be. \
+                     Setting fire = \"release\" in its binding (or turning off early-swipes) \
+                     gives the last finger time to come down, so that {} can win", a, b)
+        } else {
+            format!("The fingers probably start moving before the last one comes down. \
+                     Raising tuning.filter.still-mm lets them move further while they land, \
+                     so that {} gets a chance", b)
+        };
+        Some(format!("{} was followed straight away by {} {} times out of {}, so it might be \
+                      getting in the way. {}.", a, b, tally.retries, seen, advice))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libgestures::geom::{Direction, Point};
+
+    fn swipe(num_fingers: u8, start_time: u32, claimed: bool) -> Recognized<Gesture> {
+        Recognized {
+            value: Gesture::Touch(TouchGesture::Swipe { num_fingers, direction: Direction::Up }),
+            finished: true,
+            progress: 1.0,
+            device: Some("event5".to_owned()),
+            fingers: num_fingers,
+            start_time: start_time,
+            start_pos: Point::new(0.0, 0.0),
+            end_time: start_time + 300,
+            shadowed: if claimed { vec![1] } else { vec![] },
+        }
+    }
+
+    #[test]
+    fn retries_with_more_fingers() {
+        let mut s = Shadowing::new();
+        let mut time = 0;
+        let mut hints = Vec::new();
+        for _ in 0..3 {
+            hints.extend(s.observe(&swipe(3, time, true)));
+            hints.extend(s.observe(&swipe(4, time + 1000, false)));
+            // Fewer fingers, or a long pause, isn't a retry.
+            hints.extend(s.observe(&swipe(3, time + 1500, false)));
+            hints.extend(s.observe(&swipe(4, time + 10_000, false)));
+            time += 20_000;
+        }
+        assert_eq!(hints.len(), 1);
+        assert!(hints[0].starts_with("swipe 3 up was followed straight away by swipe 4 up"));
+        assert!(hints[0].contains("3 times out of 5"));
+        assert!(hints[0].contains("fire = \"release\""));
+
+        // It only says so once.
+        for _ in 0..5 {
+            assert!(s.observe(&swipe(3, time, false)).is_none());
+            assert!(s.observe(&swipe(4, time + 1000, false)).is_none());
+            time += 20_000;
+        }
+    }
+
+    #[test]
+    fn occasional_retries_are_fine() {
+        let mut s = Shadowing::new();
+        let mut time = 0;
+        for i in 0..40 {
+            assert!(s.observe(&swipe(3, time, false)).is_none());
+            if i % 8 == 0 {
+                assert!(s.observe(&swipe(4, time + 1000, false)).is_none());
+            }
+            time += 20_000;
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Just enough of the Wayland wire protocol to bind a few globals and send them requests.
+//!
+//! This is used for talking to river (in the `desktop` module) and to the compositor's virtual
+//! keyboard and pointer (in the `inject` module). Those only need to send a handful of requests
+//! and wait for the compositor to handle them, which isn't worth a dependency on libwayland.
+
+use libc::{self, c_int, c_void};
+use std::env;
+use std::io::{Read, Write};
+use std::mem;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+// The wl_display always has the id 1, and we allocate the others from there.
+pub const DISPLAY: u32 = 1;
+
+// The opcodes of the core requests and events that we use.
+const DISPLAY_SYNC: u16 = 0;
+const DISPLAY_GET_REGISTRY: u16 = 1;
+const DISPLAY_ERROR: u16 = 0;
+const REGISTRY_BIND: u16 = 0;
+const REGISTRY_GLOBAL: u16 = 0;
+
+// The Wayland protocol (and i3's) use the native byte order.
+pub fn to_bytes(n: u32) -> [u8; 4] {
+    unsafe { mem::transmute(n) }
+}
+
+pub fn from_bytes(b: &[u8]) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&b[..4]);
+    unsafe { mem::transmute(bytes) }
+}
+
+/// An argument of a request. Objects and new ids are sent as `Uint`s, and file descriptors go
+/// through [`Conn::send_fd`](struct.Conn.html#method.send_fd).
+pub enum Arg<'a> {
+    Uint(u32),
+    Str(&'a str),
+}
+
+/// A global that the compositor advertised.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Global {
+    pub name: u32,
+    pub interface: String,
+    pub version: u32,
+}
+
+// The buffer for a control message that carries one file descriptor. The descriptor comes right
+// after the header, which is already aligned, but the whole thing has some padding at the end.
+#[repr(C)]
+struct FdMessage {
+    header: libc::cmsghdr,
+    fd: c_int,
+}
+
+/// A connection to the compositor.
+pub struct Conn {
+    sock: UnixStream,
+    last_id: u32,
+    registry: u32,
+}
+
+impl Conn {
+    /// Connects to the compositor in `WAYLAND_DISPLAY`.
+    pub fn open() -> Result<Conn, String> {
+        let display = env::var_os("WAYLAND_DISPLAY")
+            .ok_or("WAYLAND_DISPLAY isn't set".to_owned())?;
+        let mut path = PathBuf::from(display);
+        if path.is_relative() {
+            let runtime = env::var_os("XDG_RUNTIME_DIR")
+                .ok_or("XDG_RUNTIME_DIR isn't set".to_owned())?;
+            path = PathBuf::from(runtime).join(path);
+        }
+        let sock = UnixStream::connect(&path)
+            .map_err(|e| format!("couldn't connect to {}: {}", path.display(), e))?;
+        Ok(Conn { sock: sock, last_id: DISPLAY, registry: 0 })
+    }
+
+    pub fn new_id(&mut self) -> u32 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    /// Sends a request to `object`.
+    pub fn send(&mut self, object: u32, opcode: u16, args: &[Arg]) -> Result<(), String> {
+        let msg = message(object, opcode, args);
+        self.sock.write_all(&msg).map_err(|e| format!("couldn't talk to the compositor: {}", e))
+    }
+
+    /// Sends a request to `object` that passes the file descriptor `fd`, as well as `args`.
+    ///
+    /// On the wire, the descriptor doesn't take up any room in the arguments, so it doesn't
+    /// matter where it goes in the request's signature.
+    pub fn send_fd(&mut self, object: u32, opcode: u16, args: &[Arg], fd: RawFd)
+    -> Result<(), String> {
+        let msg = message(object, opcode, args);
+        let mut iov = libc::iovec {
+            iov_base: msg.as_ptr() as *mut c_void,
+            iov_len: msg.len(),
+        };
+        let mut control: FdMessage = unsafe { mem::zeroed() };
+        let len = mem::size_of::<libc::cmsghdr>() + mem::size_of::<c_int>();
+        control.header.cmsg_len = len as _;
+        control.header.cmsg_level = libc::SOL_SOCKET;
+        control.header.cmsg_type = libc::SCM_RIGHTS;
+        control.fd = fd;
+        let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+        hdr.msg_iov = &mut iov;
+        hdr.msg_iovlen = 1;
+        hdr.msg_control = &mut control as *mut FdMessage as *mut c_void;
+        hdr.msg_controllen = mem::size_of::<FdMessage>() as _;
+        let sent = unsafe { libc::sendmsg(self.sock.as_raw_fd(), &hdr, 0) };
+        if sent < 0 || sent as usize != msg.len() {
+            return Err(format!("couldn't talk to the compositor: {}",
+                               ::std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    /// Returns the object, the opcode and the arguments of the next event.
+    pub fn recv(&mut self) -> Result<(u32, u16, Vec<u8>), String> {
+        let mut header = [0; 8];
+        self.sock.read_exact(&mut header)
+            .map_err(|e| format!("couldn't hear from the compositor: {}", e))?;
+        let word = from_bytes(&header[4..]);
+        let mut body = vec![0; ((word >> 16) as usize).saturating_sub(header.len())];
+        self.sock.read_exact(&mut body)
+            .map_err(|e| format!("couldn't hear from the compositor: {}", e))?;
+        let (object, opcode) = (from_bytes(&header), (word & 0xffff) as u16);
+        if object == DISPLAY && opcode == DISPLAY_ERROR {
+            let mut args = Args(&body);
+            let _ = args.uint();
+            let _ = args.uint();
+            let msg = args.string().unwrap_or_else(String::new);
+            return Err(format!("the compositor reported an error: {}", msg));
+        }
+        Ok((object, opcode, body))
+    }
+
+    /// Waits until the compositor has handled all the requests so far, ignoring any events that
+    /// come in the meantime.
+    pub fn roundtrip(&mut self) -> Result<(), String> {
+        let sync = self.new_id();
+        self.send(DISPLAY, DISPLAY_SYNC, &[Arg::Uint(sync)])?;
+        loop {
+            if self.recv()?.0 == sync {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Asks for the globals that the compositor has.
+    pub fn globals(&mut self) -> Result<Vec<Global>, String> {
+        self.registry = self.new_id();
+        let registry = self.registry;
+        self.send(DISPLAY, DISPLAY_GET_REGISTRY, &[Arg::Uint(registry)])?;
+        // The compositor answers the sync after it has told us about all the globals.
+        let sync = self.new_id();
+        self.send(DISPLAY, DISPLAY_SYNC, &[Arg::Uint(sync)])?;
+        let mut ret = Vec::new();
+        loop {
+            let (object, opcode, body) = self.recv()?;
+            if object == sync {
+                return Ok(ret);
+            } else if object == registry && opcode == REGISTRY_GLOBAL {
+                let mut args = Args(&body);
+                if let (Some(name), Some(interface), Some(version)) =
+                    (args.uint(), args.string(), args.uint()) {
+                    ret.push(Global { name: name, interface: interface, version: version });
+                }
+            }
+        }
+    }
+
+    /// Binds a global that [`globals`](#method.globals) found, and returns the new object.
+    pub fn bind(&mut self, global: &Global, version: u32) -> Result<u32, String> {
+        let id = self.new_id();
+        let registry = self.registry;
+        self.send(registry, REGISTRY_BIND, &[Arg::Uint(global.name), Arg::Str(&global.interface),
+                                             Arg::Uint(version), Arg::Uint(id)])?;
+        Ok(id)
+    }
+}
+
+/// Finds the global with the interface `interface`. If there are several (like seats), this is
+/// the first one.
+pub fn find<'a>(globals: &'a [Global], interface: &str) -> Option<&'a Global> {
+    globals.iter().find(|g| g.interface == interface)
+}
+
+// Lays out a request.
+fn message(object: u32, opcode: u16, args: &[Arg]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for a in args {
+        match *a {
+            Arg::Uint(n) => body.extend_from_slice(&to_bytes(n)),
+            Arg::Str(s) => {
+                // Strings include their terminating zero, and are padded to 32 bits.
+                body.extend_from_slice(&to_bytes(s.len() as u32 + 1));
+                body.extend_from_slice(s.as_bytes());
+                body.push(0);
+                while body.len() % 4 != 0 {
+                    body.push(0);
+                }
+            },
+        }
+    }
+    let mut msg = to_bytes(object).to_vec();
+    msg.extend_from_slice(&to_bytes(((body.len() as u32 + 8) << 16) | opcode as u32));
+    msg.extend_from_slice(&body);
+    msg
+}
+
+/// Reads the arguments of an event.
+pub struct Args<'a>(pub &'a [u8]);
+
+impl<'a> Args<'a> {
+    pub fn uint(&mut self) -> Option<u32> {
+        if self.0.len() < 4 {
+            return None;
+        }
+        let ret = from_bytes(self.0);
+        self.0 = &self.0[4..];
+        Some(ret)
+    }
+
+    pub fn string(&mut self) -> Option<String> {
+        let len = self.uint()? as usize;
+        let padded = (len + 3) / 4 * 4;
+        if len == 0 || self.0.len() < padded {
+            return None;
+        }
+        let ret = String::from_utf8_lossy(&self.0[..(len - 1)]).into_owned();
+        self.0 = &self.0[padded..];
+        Some(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn args() {
+        let mut body = to_bytes(7).to_vec();
+        body.extend_from_slice(&to_bytes(8));
+        body.extend_from_slice(b"wl_seat\0");
+        body.extend_from_slice(&to_bytes(3));
+        let mut args = Args(&body);
+        assert_eq!(args.uint(), Some(7));
+        assert_eq!(args.string(), Some("wl_seat".to_owned()));
+        assert_eq!(args.uint(), Some(3));
+        assert_eq!(args.uint(), None);
+    }
+
+    #[test]
+    fn messages() {
+        let msg = message(3, 1, &[Arg::Uint(9), Arg::Str("abc")]);
+        assert_eq!(msg.len(), 20);
+        assert_eq!(from_bytes(&msg), 3);
+        assert_eq!(from_bytes(&msg[4..]), (20 << 16) | 1);
+        assert_eq!(from_bytes(&msg[8..]), 9);
+        assert_eq!(from_bytes(&msg[12..]), 4);
+        assert_eq!(&msg[16..], b"abc\0");
+    }
+}
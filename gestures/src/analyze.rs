@@ -0,0 +1,282 @@
+//! Replaying a whole directory of traces at once, for checking that changed thresholds don't break
+//! the gestures that used to work.
+//!
+//! Traces can say what they should be recognized as, with comment lines like
+//! `# expect: swipe 3 up` (or `# expect: nothing`), in the same way as the traces in
+//! `libgestures/tests/synthetic_traces`. The ones that don't are still replayed, but nothing is
+//! checked.
+
+use gestures_core::build_manager;
+use gestures_core::config::{Config, Source};
+use libgestures::event::Event;
+use libgestures::manager::Transition;
+use libgestures::trace;
+use rayon::prelude::*;
+use serde_json;
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Instant;
+
+/// How to write the report.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Option<Format> {
+        match s {
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a trace was recognized as it should have been.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// The trace was recognized as the gestures that it expects.
+    Ok,
+    /// The trace was recognized as something else.
+    Mismatch,
+    /// The trace doesn't say what it should be recognized as.
+    Unchecked,
+    /// The trace couldn't be read.
+    Error,
+}
+
+impl Status {
+    fn name(&self) -> &'static str {
+        match *self {
+            Status::Ok => "ok",
+            Status::Mismatch => "mismatch",
+            Status::Unchecked => "unchecked",
+            Status::Error => "error",
+        }
+    }
+}
+
+/// What happened when one trace was replayed.
+#[derive(Clone, Debug, Serialize)]
+pub struct TraceReport {
+    pub trace: String,
+    pub status: Status,
+    /// The gestures that the trace expects, if it says.
+    pub expected: Option<Vec<String>>,
+    pub recognized: Vec<String>,
+    pub frames: usize,
+    /// How long (in milliseconds) the trace lasts, from the first frame to the last.
+    pub trace_ms: u32,
+    /// For each recognized gesture, how long (in milliseconds) after the first finger came down it
+    /// was recognized.
+    pub latency_ms: Vec<u32>,
+    /// How long it took to replay the trace, in microseconds.
+    pub replay_us: u64,
+    /// The recognizers that failed, or were cancelled, and when (counting from the first frame).
+    pub failures: Vec<String>,
+    /// Why the trace couldn't be read.
+    pub error: Option<String>,
+}
+
+/// Replays every trace in `dir` through the recognizers for `profile` in `config`, and prints a
+/// report about them in `format`.
+///
+/// The traces are replayed in parallel, but the report lists them in order of their names.
+/// Returns an error if any trace wasn't recognized as it should have been.
+pub fn run(dir: &str, format: Format, config: &Config, profile: &str) -> Result<(), String> {
+    let paths = trace_paths(Path::new(dir))?;
+    let reports = paths.par_iter()
+        .map(|p| analyze(p, config, profile))
+        .collect::<Vec<_>>();
+
+    match format {
+        Format::Csv => print!("{}", to_csv(&reports)),
+        Format::Json => {
+            let json = serde_json::to_string_pretty(&reports)
+                .map_err(|e| format!("couldn't write the report: {}", e))?;
+            println!("{}", json);
+        },
+    }
+
+    let bad = reports.iter()
+        .filter(|r| r.status == Status::Mismatch || r.status == Status::Error)
+        .count();
+    if bad > 0 {
+        Err(format!("{} of {} traces weren't recognized as expected", bad, reports.len()))
+    } else {
+        Ok(())
+    }
+}
+
+fn trace_paths(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("couldn't read {}: {}", dir.display(), e))?;
+    let mut paths = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().map_or(false, |e| e == "trace"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    if paths.is_empty() {
+        return Err(format!("there are no traces in {}", dir.display()));
+    }
+    Ok(paths)
+}
+
+// The gestures that the trace at `path` expects, or `None` if it doesn't say.
+fn expectations(path: &Path) -> Result<Option<Vec<String>>, String> {
+    let file = File::open(path).map_err(|e| format!("couldn't open {}: {}", path.display(), e))?;
+    let mut ret = None;
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+        if line.starts_with("# expect:") {
+            let expected = ret.get_or_insert_with(Vec::new);
+            let e = line["# expect:".len()..].trim();
+            if e != "nothing" {
+                expected.push(e.to_owned());
+            }
+        }
+    }
+    Ok(ret)
+}
+
+fn analyze(path: &Path, config: &Config, profile: &str) -> TraceReport {
+    let mut report = TraceReport {
+        trace: path.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned()),
+        status: Status::Error,
+        expected: None,
+        recognized: Vec::new(),
+        frames: 0,
+        trace_ms: 0,
+        latency_ms: Vec::new(),
+        replay_us: 0,
+        failures: Vec::new(),
+        error: None,
+    };
+    let events = expectations(path).and_then(|expected| {
+        report.expected = expected;
+        let file = File::open(path)
+            .map_err(|e| format!("couldn't open {}: {}", path.display(), e))?;
+        trace::read(BufReader::new(file))
+    });
+    let events = match events {
+        Ok(events) => events,
+        Err(e) => {
+            report.error = Some(e);
+            return report;
+        },
+    };
+
+    let (mut man, names) = build_manager(config, Source::Touch, profile, config.params);
+    let first_time = events.iter()
+        .filter_map(|ev| match *ev {
+            Event::Frame { time } => Some(time),
+            _ => None,
+        })
+        .next()
+        .unwrap_or(0);
+    let failures = Rc::new(RefCell::new(Vec::new()));
+    let observer_failures = failures.clone();
+    man.set_observer(move |frame, id, t| {
+        let what = match t {
+            Transition::Failed => "failed",
+            Transition::Cancelled => "was cancelled",
+            Transition::Panicked => "panicked",
+            Transition::Started | Transition::Succeeded => return,
+        };
+        observer_failures.borrow_mut().push(format!("{} {} at {} ms", names[id], what,
+                                                    frame.time.wrapping_sub(first_time)));
+    });
+
+    let start = Instant::now();
+    for ev in &events {
+        if let Event::Frame { time } = *ev {
+            report.frames += 1;
+            report.trace_ms = time.wrapping_sub(first_time);
+        }
+        let recognized = man.update_event_recognized(ev);
+        if let Some(r) = recognized.into_iter().find(|r| r.finished) {
+            report.latency_ms.push(r.end_time.wrapping_sub(r.start_time));
+            report.recognized.push(r.value.to_string());
+        }
+    }
+    let elapsed = start.elapsed();
+    report.replay_us = elapsed.as_secs() * 1_000_000 + (elapsed.subsec_nanos() / 1000) as u64;
+
+    report.failures = failures.borrow().clone();
+    report.status = match report.expected {
+        Some(ref e) if *e == report.recognized => Status::Ok,
+        Some(_) => Status::Mismatch,
+        None => Status::Unchecked,
+    };
+    report
+}
+
+// Quotes a CSV field, if it needs it.
+fn csv_field(s: &str) -> String {
+    if s.contains(|c| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+// Writes the reports as CSV, with lists separated by semicolons.
+fn to_csv(reports: &[TraceReport]) -> String {
+    let mut ret = String::from("trace,status,expected,recognized,frames,trace_ms,latency_ms,\
+                                replay_us,failures\n");
+    for r in reports {
+        let latency = r.latency_ms.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        let failures = match r.error {
+            Some(ref e) => e.clone(),
+            None => r.failures.join("; "),
+        };
+        let fields = [
+            r.trace.clone(),
+            r.status.name().to_owned(),
+            r.expected.as_ref().map_or(String::new(), |e| e.join("; ")),
+            r.recognized.join("; "),
+            r.frames.to_string(),
+            r.trace_ms.to_string(),
+            latency.join("; "),
+            r.replay_us.to_string(),
+            failures,
+        ];
+        let fields = fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>();
+        ret.push_str(&fields.join(","));
+        ret.push('\n');
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv() {
+        let report = TraceReport {
+            trace: "swipe.trace".to_owned(),
+            status: Status::Mismatch,
+            expected: Some(vec!["swipe 3 up".to_owned()]),
+            recognized: Vec::new(),
+            frames: 12,
+            trace_ms: 110,
+            latency_ms: Vec::new(),
+            replay_us: 40,
+            failures: vec!["3-finger gestures failed at 50 ms".to_owned()],
+            error: None,
+        };
+        let csv = to_csv(&[report]);
+        assert_eq!(csv.lines().nth(1),
+                   Some("swipe.trace,mismatch,swipe 3 up,,12,110,,40,\
+                         3-finger gestures failed at 50 ms"));
+        assert_eq!(csv_field("say \"hi\", then go"), "\"say \"\"hi\"\", then go\"");
+    }
+}
@@ -0,0 +1,917 @@
+use input::DeviceCapability;
+use chan;
+use gestures_core::{build_manager_with_fingers, profile, session, Gesture};
+use gestures_core::audit::AuditLog;
+use gestures_core::conditions::Environment;
+use gestures_core::config::{self, Action, Config, Internal, Placeholders, Source};
+use gestures_core::context::{self, FocusWatcher};
+use gestures_core::dedup::Dedup;
+use gestures_core::keyboard::KeyboardState;
+use gestures_core::mouse::MouseTracker;
+use gestures_core::plugin::{PluginGesture, Plugins};
+use gestures_core::runner::Runner;
+use gestures_core::session::LockWatcher;
+use gestures_core::shadowing::Shadowing;
+use input::event::{DeviceEvent, Event, EventTrait};
+use libgestures::classify::{ShapeClassifier, ThumbDetector};
+use libgestures::event::Event as TouchEvent;
+use libgestures::geom::surface_fraction;
+use libgestures::manager::{Manager, Recognized};
+use libgestures::params::Params;
+use serde_json;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use forward::{self, Grab, GrabbedFrame, Verdict};
+use libinput;
+use outputs::{self, Output};
+use pen::PenTracker;
+
+// Builds the recognizers for `source`, logging what happens to each of them.
+//
+// Also returns the ids of the recognizers for one-finger gestures.
+fn build_logged_manager(config: &Config, source: Source, profile: &str, params: Params)
+-> (Manager<Gesture>, Vec<usize>) {
+    let (mut man, recs) = build_manager_with_fingers(config, source, profile, params);
+    let (names, fingers): (Vec<_>, Vec<_>) = recs.into_iter().unzip();
+    let one_finger = forward::one_finger_ids(&fingers);
+    man.set_observer(move |frame, id, t| {
+        debug!("recognizer=#{} name={:?} source={:?} transition={:?} fingers={}",
+               id, names[id], source, t, frame.cur.num_down);
+    });
+    (man, one_finger)
+}
+
+// Leaves out the progress updates of gestures that aren't finished, which don't fire anything.
+// (The touch surfaces and the pen pass them to `Daemon::slide` instead.)
+fn finished(r: Option<Recognized<Gesture>>) -> Option<Recognized<Gesture>> {
+    match r {
+        Some(ref r) if !r.finished => None,
+        r => r,
+    }
+}
+
+fn log_recognized(r: &Recognized<Gesture>) {
+    debug!("gesture={:?} device={} fingers={} duration_ms={}",
+           r.value.to_string(), r.device.as_ref().map_or("unknown", |d| d.as_str()), r.fingers,
+           r.end_time.wrapping_sub(r.start_time));
+}
+
+// Opens the audit log, if the config file asks for one.
+fn open_audit(config: &Config) -> Option<AuditLog> {
+    config.audit.as_ref().and_then(|a| {
+        AuditLog::open(a).map_err(|e| warn!("not keeping an audit log: {}", e)).ok()
+    })
+}
+
+/// Everything that the daemon keeps track of while it runs.
+pub struct Daemon {
+    config: Config,
+    profile: String,
+    // The touch recognizers for each device, indexed by sysname. Each device gets its own, so
+    // that it can have its own thresholds. They are built when the device first sends a touch.
+    touch: HashMap<String, Manager<Gesture>>,
+    // The ids of the one-finger recognizers in each of `touch`.
+    one_finger: HashMap<String, Vec<usize>>,
+    // The touchscreens that we took away from the other programs, indexed by sysname.
+    grabs: HashMap<String, Grab>,
+    grabbed: (chan::Sender<GrabbedFrame>, chan::Receiver<GrabbedFrame>),
+    // Pen input gets its own recognizers, so that the pen and the fingers can't be mistaken for
+    // a single multi-finger gesture.
+    pen_man: Manager<Gesture>,
+    pen: PenTracker,
+    mouse: MouseTracker,
+    env: Environment,
+    keyboard: KeyboardState,
+    runner: Runner,
+    started: Instant,
+    // The devices that we can recognize gestures from, indexed by their sysnames.
+    devices: BTreeMap<String, DeviceStatus>,
+    // How many times each gesture has been recognized since the daemon started.
+    counts: HashMap<Gesture, u64>,
+    // The gestures that were recently recognized, for noticing when two devices saw the same one.
+    dedup: Dedup,
+    // Which gestures keep being followed by the same gesture with more fingers.
+    shadowing: Shadowing,
+    // Where we log every gesture and what we did about it, if anywhere.
+    audit: Option<AuditLog>,
+    // The last time that we got a touch or pen event.
+    last_touch: Instant,
+    // The monitors, for working out where gestures are on the screen.
+    outputs: Vec<Output>,
+    plugins: Plugins,
+    // False if the gestures were turned off by `Internal::ToggleGestures`.
+    enabled: bool,
+    // True while an app that the gestures pause for is focused.
+    paused: bool,
+    focus: (chan::Sender<String>, chan::Receiver<String>),
+    focus_watcher: Option<FocusWatcher>,
+    // True while the session is locked (as far as logind knows).
+    locked: bool,
+    lock: (chan::Sender<bool>, chan::Receiver<bool>),
+    lock_watcher: Option<LockWatcher>,
+    // The last time that we told the screen saver about a gesture.
+    last_activity: Option<Instant>,
+    // The config to start using, and the profile to switch to, once the current touch is over.
+    next_config: Option<Config>,
+    next_profile: Option<String>,
+    quit: bool,
+}
+
+// Finds the monitors. Most people don't need them, so failing to find them isn't worth a warning.
+fn query_outputs() -> Vec<Output> {
+    outputs::query().unwrap_or_else(|e| {
+        debug!("couldn't find the outputs: {}", e);
+        Vec::new()
+    })
+}
+
+// How long after the last touch we wait before going idle.
+const IDLE_DELAY_SECS: u64 = 5;
+
+// How often we tell the screen saver about gestures, at most. Screen savers time out after
+// minutes, so there's no need to send a message for every swipe.
+const ACTIVITY_INTERVAL_SECS: u64 = 20;
+
+/// An input device, as described by the `status` command.
+#[derive(Clone, Debug, Serialize)]
+struct DeviceStatus {
+    name: String,
+    sysname: String,
+    /// The USB vendor id.
+    #[serde(skip)]
+    vendor: u32,
+    /// The sources that this device provides: "touch", "pen" and/or "mouse".
+    sources: Vec<&'static str>,
+    /// The width and height of the touch surface in millimeters, if libinput knows it.
+    size_mm: Option<(f64, f64)>,
+    /// The output that the compositor says this device belongs to.
+    output: Option<String>,
+    /// How many touches the device can keep track of at once, if it's a touch device and we could
+    /// find out.
+    touch_slots: Option<u32>,
+}
+
+/// The reply to the `status` command.
+#[derive(Debug, Serialize)]
+struct Status {
+    uptime_secs: u64,
+    devices: Vec<DeviceStatus>,
+    profile: String,
+    num_recognizers: usize,
+    /// How many times each gesture has been recognized, indexed by the gesture's description.
+    gesture_counts: BTreeMap<String, u64>,
+    /// True if the gestures are paused for the focused app.
+    paused: bool,
+    /// True if the session is locked.
+    locked: bool,
+}
+
+impl Daemon {
+    pub fn new(config: Config) -> Daemon {
+        let profile = profile::load(&config);
+        info!("starting with profile {:?}", profile);
+        let pen_man = build_logged_manager(&config, Source::Pen, &profile, config.params).0;
+        let plugins = Plugins::new(config.plugins.clone());
+        let audit = open_audit(&config);
+        let mut ret = Daemon {
+            config: config,
+            profile: profile,
+            touch: HashMap::new(),
+            one_finger: HashMap::new(),
+            grabs: HashMap::new(),
+            grabbed: chan::async(),
+            pen_man: pen_man,
+            pen: PenTracker::new(),
+            mouse: MouseTracker::new(),
+            env: Environment::new(),
+            keyboard: KeyboardState::new(),
+            runner: Runner::new(),
+            started: Instant::now(),
+            devices: BTreeMap::new(),
+            counts: HashMap::new(),
+            dedup: Dedup::new(),
+            shadowing: Shadowing::new(),
+            audit: audit,
+            last_touch: Instant::now(),
+            outputs: query_outputs(),
+            plugins: plugins,
+            enabled: true,
+            paused: false,
+            focus: chan::async(),
+            focus_watcher: None,
+            locked: false,
+            lock: chan::async(),
+            lock_watcher: None,
+            last_activity: None,
+            next_config: None,
+            next_profile: None,
+            quit: false,
+        };
+        ret.watch_focus();
+        ret.watch_lock();
+        ret
+    }
+
+    // Starts watching whether the session is locked, if the gestures should stop while it is.
+    fn watch_lock(&mut self) {
+        self.lock_watcher = None;
+        self.locked = false;
+        if self.config.session.pause_when_locked {
+            match session::watch_lock(self.lock.0.clone()) {
+                Ok(w) => self.lock_watcher = Some(w),
+                Err(e) => warn!("not pausing while the session is locked: {}", e),
+            }
+        }
+    }
+
+    /// Returns whether the session is locked every time that changes, which should be passed to
+    /// [`handle_lock`](#method.handle_lock).
+    pub fn lock_changes(&self) -> chan::Receiver<bool> {
+        self.lock.1.clone()
+    }
+
+    /// Stops or resumes running the gestures' actions, now that the session is (un)locked.
+    pub fn handle_lock(&mut self, locked: bool) {
+        // A watcher that was replaced might have sent something on its way out.
+        if self.lock_watcher.is_none() || locked == self.locked {
+            return;
+        }
+        info!("the session was {}", if locked { "locked" } else { "unlocked" });
+        self.locked = locked;
+    }
+
+    // Starts watching the focus, if there are apps to pause for.
+    fn watch_focus(&mut self) {
+        self.focus_watcher = None;
+        if let Some(ref p) = self.config.pause {
+            match context::watch(p.focus, self.focus.0.clone()) {
+                Ok(w) => self.focus_watcher = Some(w),
+                Err(e) => warn!("not pausing for any apps, because of the focus: {}", e),
+            }
+        }
+    }
+
+    /// Returns the apps that get the focus, which should be passed to
+    /// [`handle_focus`](#method.handle_focus).
+    pub fn focus_changes(&self) -> chan::Receiver<String> {
+        self.focus.1.clone()
+    }
+
+    /// Pauses or resumes the touch and pen gestures, now that `app` is focused.
+    pub fn handle_focus(&mut self, app: &str) {
+        let paused = self.config.pause.as_ref().map_or(false, |p| p.pauses_for(app));
+        if paused != self.paused {
+            info!("{} the gestures for {:?}", if paused { "pausing" } else { "resuming" }, app);
+            self.set_paused(paused);
+        }
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.update_touch_enabled();
+        self.pen_man.set_enabled(!paused);
+    }
+
+    // Returns true if the touch recognizers for the device `sysname` should be running.
+    fn touch_enabled(&self, sysname: &str) -> bool {
+        let clicking = self.mouse.is_pressed() && self.devices.get(sysname)
+            .map_or(false, |d| self.config.pauses_while_clicking(&d.name, d.vendor));
+        !self.paused && !clicking
+    }
+
+    // Turns each device's touch recognizers on or off, after something that `touch_enabled`
+    // depends on changed.
+    fn update_touch_enabled(&mut self) {
+        let enabled = self.touch.keys()
+            .map(|s| (s.clone(), self.touch_enabled(s)))
+            .collect::<Vec<_>>();
+        for (sysname, e) in enabled {
+            let man = self.touch.get_mut(&sysname).unwrap();
+            if man.is_enabled() != e {
+                debug!("{} the touch gestures on {}", if e { "resuming" } else { "pausing" },
+                       sysname);
+                man.set_enabled(e);
+            }
+        }
+    }
+
+    /// Makes `name` the active profile, and remembers it for the next time the daemon starts.
+    ///
+    /// Switching replaces all the recognizers, which would lose track of a touch that is going
+    /// on. So while any fingers are down (or a gesture is still waiting to be reported), the
+    /// switch waits until they are done.
+    pub fn switch_profile(&mut self, name: &str) -> Result<(), String> {
+        // If a reload is waiting, the profile only needs to be in the new config.
+        if !self.next_config.as_ref().unwrap_or(&self.config).profiles().contains(name) {
+            return Err(format!("there is no profile called {:?}", name));
+        }
+        self.next_profile = Some(name.to_owned());
+        if self.touch_is_idle() {
+            self.apply_next_profile();
+        } else {
+            info!("switching to profile {:?} once the current touch is over", name);
+        }
+        Ok(())
+    }
+
+    // Returns true if none of the touch surfaces or pens are in the middle of a touch.
+    fn touch_is_idle(&self) -> bool {
+        self.touch.values().all(|m| m.is_idle()) && self.pen_man.is_idle()
+    }
+
+    // Starts using the config that `reload_config` loaded, and switches to the profile that
+    // `switch_profile` was waiting to switch to, if the touch that they were waiting for is over.
+    fn switch_when_idle(&mut self) {
+        if (self.next_config.is_some() || self.next_profile.is_some()) && self.touch_is_idle() {
+            self.apply_next_config();
+            self.apply_next_profile();
+        }
+    }
+
+    fn apply_next_profile(&mut self) {
+        let name = match self.next_profile.take() {
+            Some(n) => n,
+            None => return,
+        };
+        // The config might have been reloaded while we were waiting.
+        if !self.config.profiles().contains(&name) {
+            warn!("Not switching to profile {:?}, because it doesn't exist anymore.", name);
+            return;
+        }
+        info!("switching to profile {:?}", name);
+        self.shadowing = Shadowing::new();
+        self.touch.clear();
+        self.one_finger.clear();
+        let params = self.config.params;
+        self.pen_man = build_logged_manager(&self.config, Source::Pen, &name, params).0;
+        self.pen_man.set_enabled(!self.paused);
+        if let Err(e) = profile::save(&name) {
+            warn!("failed to save the active profile: {}", e);
+        }
+        self.profile = name;
+    }
+
+    /// Reads the config files again, and starts using them if they are valid.
+    ///
+    /// The active profile stays the same, unless it no longer exists. Like
+    /// [`switch_profile`](#method.switch_profile), this replaces all the recognizers, so while a
+    /// touch is going on the new config waits until it is over.
+    pub fn reload_config(&mut self) -> Result<(), String> {
+        let config = config::load_config()?;
+        info!("reloaded the config files");
+        self.next_config = Some(config);
+        if self.touch_is_idle() {
+            self.apply_next_config();
+        } else {
+            info!("using the new config files once the current touch is over");
+        }
+        Ok(())
+    }
+
+    fn apply_next_config(&mut self) {
+        let config = match self.next_config.take() {
+            Some(c) => c,
+            None => return,
+        };
+        if !config.profiles().contains(&self.profile) {
+            warn!("the profile {:?} doesn't exist anymore; using the default", self.profile);
+            self.profile = profile::DEFAULT.to_owned();
+        }
+        self.shadowing = Shadowing::new();
+        self.touch.clear();
+        self.one_finger.clear();
+        self.pen_man = build_logged_manager(&config, Source::Pen, &self.profile, config.params).0;
+        if config.plugins != self.config.plugins {
+            self.plugins.set_configs(config.plugins.clone());
+        }
+        if config.audit != self.config.audit {
+            self.audit = open_audit(&config);
+        }
+        let regrab = config.grab_touchscreens != self.config.grab_touchscreens;
+        let refocus = config.pause != self.config.pause;
+        let relock = config.session.pause_when_locked != self.config.session.pause_when_locked;
+        self.config = config;
+        if relock {
+            self.watch_lock();
+        }
+        if refocus {
+            // We don't know what's focused until the focus changes again.
+            self.set_paused(false);
+            self.watch_focus();
+        } else {
+            self.pen_man.set_enabled(!self.paused);
+        }
+        if regrab {
+            self.grabs.clear();
+            let touchscreens = self.devices.values()
+                .filter(|d| d.sources.contains(&"touch"))
+                .cloned()
+                .collect::<Vec<_>>();
+            for d in touchscreens {
+                self.grab(&d);
+            }
+        }
+        for d in self.devices.values() {
+            self.check_fingers(d);
+        }
+        self.outputs = query_outputs();
+    }
+
+    /// Returns the gestures that the plugins recognize, which should be passed to
+    /// [`handle_plugin_gesture`](#method.handle_plugin_gesture).
+    pub fn plugin_gestures(&self) -> chan::Receiver<PluginGesture> {
+        self.plugins.gestures()
+    }
+
+    /// Returns the frames from the touchscreens that the daemon grabbed, which should be passed
+    /// to [`handle_grabbed`](#method.handle_grabbed).
+    pub fn grabbed_frames(&self) -> chan::Receiver<GrabbedFrame> {
+        self.grabbed.1.clone()
+    }
+
+    // Takes a touchscreen away from the other programs, if the config file says to.
+    fn grab(&mut self, d: &DeviceStatus) {
+        if !self.config.grab_touchscreens {
+            return;
+        }
+        match Grab::new(&d.sysname, &d.name, d.vendor, d.size_mm, self.grabbed.0.clone()) {
+            Ok(g) => {
+                info!("grabbed the touchscreen {} ({})", d.name, d.sysname);
+                self.grabs.insert(d.sysname.clone(), g);
+            },
+            Err(e) => warn!("couldn't grab the touchscreen {}: {}", d.name, e),
+        }
+    }
+
+    // Warns about the bound gestures that need more fingers than the device can keep track of.
+    fn check_fingers(&self, d: &DeviceStatus) {
+        let slots = match d.touch_slots {
+            Some(n) => n,
+            None => return,
+        };
+        let mut too_many = self.config.bindings.keys()
+            .filter(|g| self.config.num_fingers(g).map_or(false, |n| n as u32 > slots))
+            .map(|g| g.to_string())
+            .collect::<Vec<_>>();
+        if !too_many.is_empty() {
+            too_many.sort();
+            warn!("{} can only keep track of {} touches at once, so these gestures can't be made \
+                   on it: {}", d.name, slots, too_many.join(", "));
+        }
+    }
+
+    // Builds the touch recognizers for a device, unless they're already there.
+    fn ensure_touch_manager(&mut self, sysname: &str, name: &str, vendor: u32) {
+        if self.touch.contains_key(sysname) {
+            return;
+        }
+        let params = self.config.params_for(Some((name, vendor)));
+        debug!("building the touch recognizers for {}", name);
+        let (mut man, one_finger) = build_logged_manager(&self.config, Source::Touch,
+                                                         &self.profile, params);
+        man.set_enabled(self.touch_enabled(sysname));
+        man.add_classifier(ShapeClassifier::new(params.shape));
+        // Telling a resting thumb apart needs to know where the bottom edge is.
+        match self.devices.get(sysname).and_then(|d| d.size_mm) {
+            Some((_, h)) if params.thumb.edge_mm > 0.0 => {
+                man.add_classifier(ThumbDetector::new(h, params.thumb));
+            },
+            _ => {},
+        }
+        self.touch.insert(sysname.to_owned(), man);
+        self.one_finger.insert(sysname.to_owned(), one_finger);
+    }
+
+    /// Handles a frame from a touchscreen that the daemon grabbed, and decides whether the
+    /// other programs should see it.
+    pub fn handle_grabbed(&mut self, f: GrabbedFrame) {
+        self.last_touch = Instant::now();
+        let (name, vendor) = match self.grabs.get(&f.sysname) {
+            Some(g) => (g.name().to_owned(), g.vendor()),
+            // The frame was already on its way when the grab ended.
+            None => return,
+        };
+        self.ensure_touch_manager(&f.sysname, &name, vendor);
+
+        let mut recognized = None;
+        let verdict = {
+            let man = self.touch.get_mut(&f.sysname).unwrap();
+            for ev in &f.events {
+                self.plugins.touch(&f.sysname, ev);
+                if let Some(mut r) = finished(man.update_event_recognized(ev)) {
+                    r.device = Some(f.sysname.clone());
+                    recognized = Some(r);
+                }
+            }
+            if recognized.is_some() {
+                Verdict::Gesture
+            } else {
+                forward::verdict(man, &self.one_finger[&f.sysname], f.num_down)
+            }
+        };
+        if let Some(g) = self.grabs.get_mut(&f.sysname) {
+            g.handle(f, verdict);
+        }
+        if let Some(r) = recognized {
+            log_recognized(&r);
+            if !self.is_duplicate(&r) {
+                let p = self.placeholders(&r);
+                self.fire(r.value, Source::Touch, Some(&r), p);
+            }
+        }
+        self.switch_when_idle();
+    }
+
+    /// Handles a gesture that a plugin recognized.
+    pub fn handle_plugin_gesture(&mut self, g: PluginGesture) {
+        let p = Placeholders { pos: g.pos, ..Placeholders::default() };
+        self.fire(g.gesture, Source::Touch, None, p);
+    }
+
+    /// Returns true if a gesture told the daemon to stop.
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    fn run_internal(&mut self, action: &Internal) {
+        let result = match *action {
+            Internal::ReloadConfig => self.reload_config(),
+            Internal::ToggleGestures => {
+                self.enabled = !self.enabled;
+                info!("gestures are {}", if self.enabled { "on" } else { "off" });
+                Ok(())
+            },
+            Internal::SwitchProfile(ref name) => self.switch_profile(name),
+            Internal::Quit => {
+                info!("quitting, because a gesture said so");
+                self.quit = true;
+                Ok(())
+            },
+        };
+        if let Err(e) = result {
+            error!("{}", e);
+        }
+    }
+
+    // Keeps track of the devices that we can recognize gestures from.
+    fn handle_device_event(&mut self, event: &DeviceEvent) {
+        match event {
+            &DeviceEvent::Added(ref ev) => {
+                let dev = ev.device();
+                // We already saw the touches that our own copies of touchscreens pass on.
+                if forward::is_mirror(dev.name()) {
+                    return;
+                }
+                let sources = [(DeviceCapability::Touch, "touch"),
+                               (DeviceCapability::TabletTool, "pen"),
+                               (DeviceCapability::Pointer, "mouse")]
+                    .iter()
+                    .filter(|&&(cap, _)| dev.has_capability(cap))
+                    .map(|&(_, name)| name)
+                    .collect::<Vec<_>>();
+                if !sources.is_empty() {
+                    debug!("using device {} ({})", dev.name(), dev.sysname());
+                    let touch_slots = if sources.contains(&"touch") {
+                        forward::touch_slots(dev.sysname())
+                            .map_err(|e| debug!("couldn't count {}'s touches: {}", dev.name(), e))
+                            .ok()
+                    } else {
+                        None
+                    };
+                    let status = DeviceStatus {
+                        name: dev.name().to_owned(),
+                        sysname: dev.sysname().to_owned(),
+                        vendor: dev.id_vendor(),
+                        sources: sources,
+                        size_mm: dev.size(),
+                        output: dev.output_name().map(|o| o.to_owned()),
+                        touch_slots: touch_slots,
+                    };
+                    if status.sources.contains(&"touch") {
+                        self.grab(&status);
+                    }
+                    self.check_fingers(&status);
+                    self.devices.insert(dev.sysname().to_owned(), status);
+                }
+            },
+            &DeviceEvent::Removed(ref ev) => {
+                self.touch.remove(ev.device().sysname());
+                self.one_finger.remove(ev.device().sysname());
+                self.grabs.remove(ev.device().sysname());
+                if self.devices.remove(ev.device().sysname()).is_some() {
+                    debug!("device {} went away", ev.device().sysname());
+                }
+            },
+        }
+    }
+
+    // Works out the values of the placeholders for a touch or pen gesture.
+    fn placeholders(&self, r: &Recognized<Gesture>) -> Placeholders {
+        let dev = r.device.as_ref().and_then(|d| self.devices.get(d));
+        let size = dev.and_then(|d| d.size_mm);
+        let output = self.config.output.as_ref()
+            .or_else(|| dev.and_then(|d| d.output.as_ref()))
+            .map(|o| o.as_str());
+        let mapping = size.and_then(|(w, h)| {
+            outputs::find(&self.outputs, output).map(|o| o.mapping(w, h))
+        });
+        Placeholders {
+            pos: Some(r.start_pos),
+            fraction: size.map(|(w, h)| surface_fraction(r.start_pos, w, h)),
+            screen: mapping.map(|m| m.to_screen(r.start_pos)),
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        if let &Event::Device(ref ev) = event {
+            self.handle_device_event(ev);
+        }
+        self.env.handle_event(event);
+        self.keyboard.handle_event(event);
+
+        let mut gestures = Vec::new();
+        let was_clicking = self.mouse.is_pressed();
+        gestures.extend(self.mouse.handle_event(event).into_iter()
+                        .map(|g| (g, Source::Mouse, Placeholders::default(), None)));
+        // From here until the button goes up, the devices that pause while clicking ignore all
+        // their touches.
+        if self.mouse.is_pressed() != was_clicking {
+            self.update_touch_enabled();
+        }
+        match event {
+            &Event::Touch(_) | &Event::Tablet(_) => self.last_touch = Instant::now(),
+            _ => {},
+        }
+        match event {
+            &Event::Touch(ref ev) if !forward::is_mirror(ev.device().name()) => {
+                let dev = ev.device();
+                let sysname = dev.sysname().to_owned();
+                self.ensure_touch_manager(&sysname, dev.name(), dev.id_vendor());
+                self.plugins.touch(&sysname, &TouchEvent::from(ev));
+                let recognized = self.touch.get_mut(&sysname)
+                    .and_then(|man| man.update_recognized(ev));
+                if let Some(ref r) = recognized {
+                    if !r.finished {
+                        self.slide(r, Source::Touch);
+                    }
+                }
+                if let Some(r) = finished(recognized) {
+                    log_recognized(&r);
+                    if !self.is_duplicate(&r) {
+                        let p = self.placeholders(&r);
+                        gestures.push((r.value, Source::Touch, p, Some(r)));
+                    }
+                }
+            },
+            &Event::Tablet(ref ev) => {
+                let sysname = ev.device().sysname().to_owned();
+                for ev in self.pen.handle_event(ev) {
+                    let recognized = self.pen_man.update_event_recognized(&ev);
+                    if let Some(ref r) = recognized {
+                        if !r.finished {
+                            self.slide(r, Source::Pen);
+                        }
+                    }
+                    if let Some(mut r) = finished(recognized) {
+                        r.device = Some(sysname.clone());
+                        log_recognized(&r);
+                        if !self.is_duplicate(&r) {
+                            let p = self.placeholders(&r);
+                            gestures.push((r.value, Source::Pen, p, Some(r)));
+                        }
+                    }
+                }
+            },
+            _ => {},
+        }
+
+        for (g, source, p, r) in gestures {
+            self.fire(g, source, r.as_ref(), p);
+        }
+        self.switch_when_idle();
+    }
+
+    // Returns true if `r` is a copy of a gesture that a different device recognized, which
+    // already fired (see the `dedup` module). Gestures that don't know their device are never
+    // copies.
+    fn is_duplicate(&mut self, r: &Recognized<Gesture>) -> bool {
+        let (window_ms, device) = match (self.config.dedup_ms, r.device.as_ref()) {
+            (Some(ms), Some(d)) => (ms, d),
+            _ => return false,
+        };
+        let dup = self.dedup.is_duplicate(&r.value, device, r.end_time, window_ms);
+        if dup {
+            debug!("ignoring gesture {} from {}, because another device recognized it too",
+                   r.value, device);
+            self.audit(|| {
+                format!("gesture={:?} device={} time_ms={} ignored=\"another device recognized \
+                         it too\"", r.value.to_string(), device, r.end_time)
+            });
+        }
+        dup
+    }
+
+    // Adds a line to the audit log, if there is one.
+    fn audit<F: FnOnce() -> String>(&mut self, line: F) {
+        if let Some(ref mut a) = self.audit {
+            a.record(&line());
+        }
+    }
+
+    // Runs the binding (if there is one) for a gesture that was recognized. If the gesture came
+    // from a touch surface or a pen, `r` says which one and when.
+    fn fire(&mut self, g: Gesture, source: Source, r: Option<&Recognized<Gesture>>,
+            p: Placeholders) {
+        info!("gesture={:?} source={:?}", g.to_string(), source);
+        *self.counts.entry(g).or_insert(0) += 1;
+        if let Some(hint) = r.and_then(|r| self.shadowing.observe(r)) {
+            info!("{}", hint);
+        }
+        self.audit(|| {
+            let mut line = format!("gesture={:?} source={:?}", g.to_string(), source);
+            if let Some(r) = r {
+                line.push_str(&format!(" device={} fingers={} time_ms={}",
+                                       r.device.as_ref().map_or("unknown", |d| d.as_str()),
+                                       r.fingers, r.end_time));
+            }
+            line
+        });
+        let binding = self.config.binding(&g, source, &self.profile, &self.env,
+                                          self.keyboard.modifiers())
+            .map(|(i, b)| (i, b.with_placeholders(&p)));
+        let (index, binding) = match binding {
+            Some(b) => b,
+            None => {
+                self.audit(|| format!("gesture={:?} ignored=\"it isn't bound\"", g.to_string()));
+                return;
+            },
+        };
+        let internal = match binding.action {
+            Action::Internal(ref i) => Some(i.clone()),
+            _ => None,
+        };
+        // While the gestures are off, the only thing that works is turning them back on.
+        if !self.enabled && internal != Some(Internal::ToggleGestures) {
+            debug!("ignoring gesture {}, because gestures are off", g);
+            self.audit(|| format!("gesture={:?} ignored=\"gestures are off\"", g.to_string()));
+            return;
+        }
+        // Recognizing gestures on the lock screen is harmless, but running their actions isn't.
+        if self.locked && self.config.session.pause_when_locked {
+            debug!("ignoring gesture {}, because the session is locked", g);
+            self.audit(|| {
+                format!("gesture={:?} ignored=\"the session is locked\"", g.to_string())
+            });
+            return;
+        }
+        if self.config.session.keep_awake {
+            let due = self.last_activity
+                .map_or(true, |t| t.elapsed() >= Duration::from_secs(ACTIVITY_INTERVAL_SECS));
+            if due {
+                self.last_activity = Some(Instant::now());
+                session::simulate_activity();
+            }
+        }
+        self.audit(|| {
+            format!("gesture={:?} profile={:?} action={:?} feedback={:?}", g.to_string(),
+                    binding.profile, binding.action, binding.feedback)
+        });
+        if let Some(ref i) = internal {
+            self.run_internal(i);
+        }
+        if let Action::Plugin { ref plugin, ref args } = binding.action {
+            self.plugins.action(plugin, &g, args);
+        }
+        self.runner.trigger(g, index, &binding, r.map_or(1.0, |r| r.progress));
+    }
+
+    // Passes the progress of a gesture that fires continuously to its binding, if it changes a
+    // level (see `levels` in gestures-core). The gesture fires as usual once it's finished.
+    fn slide(&mut self, r: &Recognized<Gesture>, source: Source) {
+        if !self.enabled || (self.locked && self.config.session.pause_when_locked) {
+            return;
+        }
+        let binding = self.config.binding(&r.value, source, &self.profile, &self.env,
+                                          self.keyboard.modifiers());
+        if let Some((i, b)) = binding {
+            debug!("gesture={:?} progress={:.2}", r.value.to_string(), r.progress);
+            self.runner.slide(r.value, i, b, r.progress);
+        }
+    }
+
+    /// Returns true if the daemon has nothing to do until the next input event.
+    ///
+    /// While the daemon is idle, there is no need to call [`tick`](#method.tick).
+    pub fn is_idle(&self) -> bool {
+        self.last_touch.elapsed() >= Duration::from_secs(IDLE_DELAY_SECS)
+            && self.touch.values().all(|m| m.is_idle()) && self.pen_man.is_idle()
+            && self.runner.is_idle()
+    }
+
+    /// Returns true if fingers are down, and some of the recognizers might be waiting for them to
+    /// hold still for long enough.
+    ///
+    /// While this is true, [`tick`](#method.tick) should be called often, because the touch
+    /// surface doesn't send anything while the fingers are still.
+    pub fn is_dwelling(&self) -> bool {
+        (self.config.needs_ticks() && self.touch.values().any(|m| !m.is_idle()))
+            || self.grabs.values().any(|g| g.is_holding())
+    }
+
+    /// Does the periodic housekeeping, and lets the recognizers know how much time has passed.
+    pub fn tick(&mut self) {
+        self.runner.reap();
+        let now = libinput::now_ms();
+        let mut gestures = Vec::new();
+        if self.config.needs_ticks() {
+            for (sysname, man) in &mut self.touch {
+                if let Some(mut r) = finished(man.tick(now)) {
+                    log_recognized(&r);
+                    // A touch that was held back turned out to be a gesture after all.
+                    if let Some(g) = self.grabs.get_mut(sysname) {
+                        g.swallow();
+                    }
+                    r.device = r.device.or_else(|| Some(sysname.clone()));
+                    gestures.push(r);
+                }
+            }
+        }
+        for g in self.grabs.values_mut() {
+            g.tick(now);
+        }
+        for r in gestures {
+            if !self.is_duplicate(&r) {
+                let p = self.placeholders(&r);
+                self.fire(r.value, Source::Touch, Some(&r), p);
+            }
+        }
+        self.switch_when_idle();
+    }
+
+    fn status(&self) -> Status {
+        Status {
+            uptime_secs: self.started.elapsed().as_secs(),
+            devices: self.devices.values().cloned().collect(),
+            profile: self.profile.clone(),
+            num_recognizers: self.touch.values().map(|m| m.num_recognizers()).sum::<usize>()
+                + self.pen_man.num_recognizers(),
+            gesture_counts: self.counts.iter().map(|(g, &n)| (g.to_string(), n)).collect(),
+            paused: self.paused,
+            locked: self.locked,
+        }
+    }
+
+    /// Handles a command received on the control socket.
+    pub fn handle_command(&mut self, command: &str) -> String {
+        let words = command.split_whitespace().collect::<Vec<_>>();
+        match (words.first().cloned(), words.len()) {
+            (Some("results"), 1) => self.runner.describe_results(),
+            (Some("status"), 1) => {
+                let status = self.status();
+                let mut ret = format!("uptime: {}s\nprofile: {}\nrecognizers: {}\n",
+                                      status.uptime_secs, status.profile, status.num_recognizers);
+                for d in &status.devices {
+                    ret.push_str(&format!("device: {} ({}): {}\n",
+                                          d.name, d.sysname, d.sources.join(", ")));
+                }
+                if status.paused {
+                    ret.push_str("paused for the focused app\n");
+                }
+                if status.locked {
+                    ret.push_str("paused while the session is locked\n");
+                }
+                for (g, n) in &status.gesture_counts {
+                    ret.push_str(&format!("recognized {}: {}\n", g, n));
+                }
+                ret
+            },
+            (Some("status"), 2) if words[1] == "--json" => {
+                match serde_json::to_string(&self.status()) {
+                    Ok(json) => json + "\n",
+                    Err(e) => format!("failed to serialize status: {}\n", e),
+                }
+            },
+            (Some("reload-config"), 1) => match self.reload_config() {
+                Ok(()) if self.next_config.is_some() => {
+                    "reloading the config files once the current touch is over\n".to_owned()
+                },
+                Ok(()) => "reloaded the config files\n".to_owned(),
+                Err(e) => format!("{}\n", e),
+            },
+            (Some("profile"), 1) => format!("{}\n", self.profile),
+            (Some("profiles"), 1) => {
+                self.config.profiles().iter().map(|p| format!("{}\n", p)).collect()
+            },
+            (Some("profile"), 2) => match self.switch_profile(words[1]) {
+                Ok(()) if self.profile != words[1] => {
+                    format!("switching to profile {:?} once the current touch is over\n",
+                            words[1])
+                },
+                Ok(()) => format!("switched to profile {:?}\n", words[1]),
+                Err(e) => format!("{}\n", e),
+            },
+            _ => format!("unknown command {:?}\n", command),
+        }
+    }
+}
@@ -0,0 +1,734 @@
+//! Taking touchscreens away from the other programs, so that one-finger gestures can be captured.
+//!
+//! Usually, the daemon only watches the touches that libinput reports, and every touch also goes
+//! to the compositor and the apps. That's fine for gestures with several fingers, but a
+//! one-finger swipe would also scroll or drag whatever is under it. That is why one-finger
+//! gestures usually have to start with a hold, or at an edge.
+//!
+//! If the `[one-finger]` section of the config file sets `grab = true`, the daemon instead grabs
+//! each touchscreen (with the `EVIOCGRAB` ioctl), so that nothing else (not even libinput) sees
+//! its events. It makes a copy of the touchscreen with uinput, and reads the touches itself.
+//! While a touch might still become a one-finger gesture, its events are held back. If it becomes
+//! a gesture, they are thrown away; otherwise, they are replayed through the copy (which
+//! libinput picks up like any other touchscreen), and the rest of the touch goes straight
+//! through. As soon as a second finger comes down, the touch is replayed, so gestures with more
+//! fingers work just like they do without the grab.
+//!
+//! The catch is that ordinary touches reach the apps late, because they are held back until the
+//! recognizers give up on them. A finger that comes down and doesn't move is held back for at
+//! most [`MAX_HOLD_MS`](constant.MAX_HOLD_MS.html). The daemon also needs to be able to read the
+//! touchscreens' device nodes and write to `/dev/uinput`, which usually means being in the
+//! `input` group (or having the helper from the `privsep` module open them).
+
+use chan;
+use gestures_core::{config, Gesture};
+use libc;
+use libc::{c_char, c_int, c_ulong, c_void};
+use libgestures::event::Event;
+use libgestures::geom::Point;
+use libgestures::manager::Manager;
+use std::ffi::CString;
+use std::io;
+use std::mem;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use privsep;
+
+/// The longest (in milliseconds) that a touch is held back while the recognizers make up their
+/// minds.
+pub const MAX_HOLD_MS: u32 = 1500;
+
+/// The end of the names of the copies of the grabbed touchscreens. The daemon ignores the
+/// devices with names like this, since it has already seen their touches.
+pub const MIRROR_SUFFIX: &'static str = " (forwarded by gestures)";
+
+// From linux/input-event-codes.h.
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_ABS: u16 = 0x03;
+const SYN_REPORT: u16 = 0;
+const SYN_DROPPED: u16 = 3;
+const BTN_TOOL_FINGER: u16 = 0x145;
+const BTN_TOUCH: u16 = 0x14a;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_MT_SLOT: u16 = 0x2f;
+const ABS_MT_TOUCH_MAJOR: u16 = 0x30;
+const ABS_MT_POSITION_X: u16 = 0x35;
+const ABS_MT_POSITION_Y: u16 = 0x36;
+const ABS_MT_TRACKING_ID: u16 = 0x39;
+const ABS_MT_PRESSURE: u16 = 0x3a;
+const INPUT_PROP_DIRECT: c_int = 0x01;
+const BUS_VIRTUAL: u16 = 0x06;
+
+// From linux/input.h and linux/uinput.h.
+const EVIOCGRAB: c_ulong = 0x4004_4590;
+const EVIOCSCLOCKID: c_ulong = 0x4004_45a0;
+// EVIOCGBIT(EV_ABS, 8)
+const EVIOCGBIT_ABS: c_ulong = 0x8008_4523;
+// EVIOCGABS(0); the axis goes in the low bits.
+const EVIOCGABS: c_ulong = 0x8018_4540;
+const UI_SET_EVBIT: c_ulong = 0x4004_5564;
+const UI_SET_KEYBIT: c_ulong = 0x4004_5565;
+const UI_SET_ABSBIT: c_ulong = 0x4004_5567;
+const UI_SET_PROPBIT: c_ulong = 0x4004_556e;
+const UI_DEV_SETUP: c_ulong = 0x405c_5503;
+const UI_ABS_SETUP: c_ulong = 0x401c_5504;
+const UI_DEV_CREATE: c_ulong = 0x5501;
+const UI_DEV_DESTROY: c_ulong = 0x5502;
+const UINPUT_MAX_NAME_SIZE: usize = 80;
+
+// The axes that we copy to the mirror, if the touchscreen has them.
+const AXES: [u16; 8] = [ABS_X, ABS_Y, ABS_MT_SLOT, ABS_MT_TOUCH_MAJOR, ABS_MT_POSITION_X,
+                        ABS_MT_POSITION_Y, ABS_MT_TRACKING_ID, ABS_MT_PRESSURE];
+// The most touches that we keep track of. Any others are ignored.
+const MAX_SLOTS: usize = config::MAX_FINGERS as usize;
+// How often (in milliseconds) the reader thread checks whether it should stop.
+const STOP_POLL_MS: c_int = 200;
+
+#[repr(C)]
+struct UinputSetup {
+    bustype: u16,
+    vendor: u16,
+    product: u16,
+    version: u16,
+    name: [c_char; UINPUT_MAX_NAME_SIZE],
+    ff_effects_max: u32,
+}
+
+#[repr(C)]
+struct UinputAbsSetup {
+    code: u16,
+    absinfo: libc::input_absinfo,
+}
+
+/// A frame of touches from a grabbed touchscreen.
+pub struct GrabbedFrame {
+    /// The system name of the touchscreen.
+    pub sysname: String,
+    /// The touches, converted for the recognizers. The last one is always a frame or a cancel.
+    pub events: Vec<Event>,
+    /// How many fingers are down at the end of the frame.
+    pub num_down: usize,
+    /// The time of the frame, in milliseconds.
+    pub time: u32,
+    // The events as the touchscreen sent them, to pass on if it turns out not to be a gesture.
+    raw: Vec<libc::input_event>,
+}
+
+/// What the recognizers think of the touch that is going on.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Verdict {
+    /// It might still become a one-finger gesture.
+    Undecided,
+    /// It can't be a one-finger gesture anymore.
+    NotAGesture,
+    /// It was recognized as a gesture.
+    Gesture,
+}
+
+/// Finds the recognizers that can recognize one-finger gestures, given the number of fingers for
+/// each recognizer that `build_manager_with_fingers` returned.
+pub fn one_finger_ids(fingers: &[u8]) -> Vec<usize> {
+    fingers.iter()
+        .enumerate()
+        .filter(|&(_, &n)| n == 1)
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// Decides whether the touch going on in `man` can still be a one-finger gesture, given that
+/// nothing was recognized in the last frame. `one_finger` are the ids from
+/// [`one_finger_ids`](fn.one_finger_ids.html).
+pub fn verdict(man: &Manager<Gesture>, one_finger: &[usize], num_down: usize) -> Verdict {
+    if num_down != 1 || !one_finger.iter().any(|&id| man.is_active(id)) {
+        Verdict::NotAGesture
+    } else {
+        Verdict::Undecided
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum State {
+    // Keeping the events until we know what they are.
+    Holding,
+    // Passing the events on to the mirror.
+    Passing,
+    // Throwing the events away.
+    Swallowing,
+}
+
+// Decides which events to pass on, one touch at a time.
+struct Holder {
+    state: State,
+    held: Vec<libc::input_event>,
+    // The time of the first frame in `held`.
+    since: u32,
+}
+
+impl Holder {
+    fn new() -> Holder {
+        Holder {
+            state: State::Holding,
+            held: Vec::new(),
+            since: 0,
+        }
+    }
+
+    // Returns the events that should be passed on.
+    fn handle(&mut self, raw: Vec<libc::input_event>, num_down: usize, time: u32, v: Verdict)
+    -> Vec<libc::input_event> {
+        let mut ret = Vec::new();
+        match self.state {
+            State::Holding => {
+                if self.held.is_empty() {
+                    self.since = time;
+                }
+                self.held.extend(raw);
+                match v {
+                    Verdict::Undecided if time.wrapping_sub(self.since) < MAX_HOLD_MS => {},
+                    Verdict::Gesture => self.swallow(),
+                    _ => ret = self.release(),
+                }
+            },
+            State::Passing => ret = raw,
+            State::Swallowing => {},
+        }
+        if num_down == 0 {
+            self.state = State::Holding;
+            self.held.clear();
+        }
+        ret
+    }
+
+    fn swallow(&mut self) {
+        if self.state == State::Holding {
+            self.held.clear();
+            self.state = State::Swallowing;
+        }
+    }
+
+    fn release(&mut self) -> Vec<libc::input_event> {
+        self.state = State::Passing;
+        mem::replace(&mut self.held, Vec::new())
+    }
+
+    fn is_holding(&self) -> bool {
+        self.state == State::Holding && !self.held.is_empty()
+    }
+}
+
+// The size of a touchscreen's units, for converting them to millimeters.
+#[derive(Clone, Copy, Debug)]
+struct Scale {
+    min_x: i32,
+    min_y: i32,
+    // Units per millimeter.
+    x: f64,
+    y: f64,
+    // Units per millimeter of the contacts' size, if the touchscreen reports it.
+    major: Option<f64>,
+    // The range of the contacts' pressure, if the touchscreen reports it.
+    pressure: Option<(i32, i32)>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Slot {
+    active: bool,
+    x: i32,
+    y: i32,
+    major: i32,
+    pressure: i32,
+    went_down: bool,
+    went_up: bool,
+    moved: bool,
+    reshaped: bool,
+}
+
+// Turns multitouch (protocol B) events into `Event`s.
+struct Parser {
+    scale: Scale,
+    slots: [Slot; MAX_SLOTS],
+    cur: usize,
+    raw: Vec<libc::input_event>,
+    // After the kernel drops some events, we ignore everything until the next report.
+    dropped: bool,
+}
+
+impl Parser {
+    fn new(scale: Scale) -> Parser {
+        Parser {
+            scale: scale,
+            slots: [Slot::default(); MAX_SLOTS],
+            cur: 0,
+            raw: Vec::new(),
+            dropped: false,
+        }
+    }
+
+    fn pos(&self, s: &Slot) -> Point {
+        Point::new((s.x - self.scale.min_x) as f64 / self.scale.x,
+                   (s.y - self.scale.min_y) as f64 / self.scale.y)
+    }
+
+    // The size and pressure of a contact, if the touchscreen knows either of them.
+    fn shape(&self, slot: usize, s: &Slot) -> Option<Event> {
+        if self.scale.major.is_none() && self.scale.pressure.is_none() {
+            return None;
+        }
+        Some(Event::Shape {
+            slot: slot,
+            size: self.scale.major.map_or(0.0, |per_mm| s.major as f64 / per_mm),
+            pressure: self.scale.pressure.map_or(0.0, |(min, max)| {
+                (s.pressure - min) as f64 / (max - min) as f64
+            }),
+        })
+    }
+
+    // Returns the events, the number of fingers down, the time, and the raw events, at the end
+    // of each frame.
+    fn push(&mut self, ev: &libc::input_event)
+    -> Option<(Vec<Event>, usize, u32, Vec<libc::input_event>)> {
+        match (ev.type_, ev.code) {
+            (EV_SYN, SYN_DROPPED) => {
+                self.dropped = true;
+                return None;
+            },
+            (EV_SYN, SYN_REPORT) => {
+                if self.dropped {
+                    // We don't know where the fingers are anymore, so we start over.
+                    self.dropped = false;
+                    self.slots = [Slot::default(); MAX_SLOTS];
+                    self.raw.clear();
+                    return Some((vec![Event::Cancel], 0, time_ms(ev), Vec::new()));
+                }
+                self.raw.push(*ev);
+                return Some(self.finish_frame(time_ms(ev)));
+            },
+            _ if self.dropped => return None,
+            (EV_ABS, ABS_MT_SLOT) => self.cur = ev.value as usize,
+            (EV_ABS, code) if self.cur < MAX_SLOTS => {
+                let s = &mut self.slots[self.cur];
+                match code {
+                    ABS_MT_TRACKING_ID if ev.value < 0 => s.went_up = s.active,
+                    ABS_MT_TRACKING_ID => s.went_down = true,
+                    ABS_MT_POSITION_X => {
+                        s.x = ev.value;
+                        s.moved = true;
+                    },
+                    ABS_MT_POSITION_Y => {
+                        s.y = ev.value;
+                        s.moved = true;
+                    },
+                    ABS_MT_TOUCH_MAJOR => {
+                        s.major = ev.value;
+                        s.reshaped = true;
+                    },
+                    ABS_MT_PRESSURE => {
+                        s.pressure = ev.value;
+                        s.reshaped = true;
+                    },
+                    _ => {},
+                }
+            },
+            _ => {},
+        }
+        self.raw.push(*ev);
+        None
+    }
+
+    fn finish_frame(&mut self, time: u32) -> (Vec<Event>, usize, u32, Vec<libc::input_event>) {
+        let mut events = Vec::new();
+        for slot in 0..MAX_SLOTS {
+            let s = self.slots[slot];
+            if s.went_up {
+                events.push(Event::Up { slot });
+            } else if s.went_down {
+                events.push(Event::Down { slot, pos: self.pos(&s) });
+            } else if s.active && s.moved {
+                events.push(Event::Motion { slot, pos: self.pos(&s) });
+            }
+            if !s.went_up && (s.went_down || (s.active && s.reshaped)) {
+                events.extend(self.shape(slot, &s));
+            }
+            let s = &mut self.slots[slot];
+            s.active = (s.active || s.went_down) && !s.went_up;
+            s.went_down = false;
+            s.went_up = false;
+            s.moved = false;
+            s.reshaped = false;
+        }
+        events.push(Event::Frame { time });
+        let num_down = self.slots.iter().filter(|s| s.active).count();
+        (events, num_down, time, mem::replace(&mut self.raw, Vec::new()))
+    }
+}
+
+fn time_ms(ev: &libc::input_event) -> u32 {
+    (ev.time.tv_sec as u64 * 1000 + ev.time.tv_usec as u64 / 1000) as u32
+}
+
+fn check(ret: c_int) -> io::Result<c_int> {
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+// Returns the axes from `AXES` that the touchscreen has, with their ranges.
+fn query_axes(fd: c_int) -> io::Result<Vec<(u16, libc::input_absinfo)>> {
+    let mut bits = 0u64;
+    let mut ret = Vec::new();
+    unsafe {
+        check(libc::ioctl(fd, EVIOCGBIT_ABS, &mut bits as *mut u64))?;
+        for &axis in AXES.iter().filter(|&&a| bits & (1 << a) != 0) {
+            let mut info: libc::input_absinfo = mem::zeroed();
+            check(libc::ioctl(fd, EVIOCGABS + axis as c_ulong,
+                              &mut info as *mut libc::input_absinfo))?;
+            ret.push((axis, info));
+        }
+    }
+    Ok(ret)
+}
+
+/// Returns how many touches the touch device with system name `sysname` can keep track of at
+/// once.
+pub fn touch_slots(sysname: &str) -> io::Result<u32> {
+    let path = CString::new(format!("/dev/input/{}", sysname)).unwrap();
+    let fd = privsep::open(&path, libc::O_RDONLY | libc::O_NONBLOCK)?;
+    let axes = query_axes(fd);
+    unsafe { libc::close(fd); }
+    // A device without slots can only report one touch.
+    Ok(axes?.iter()
+        .find(|&&(axis, _)| axis == ABS_MT_SLOT)
+        .map_or(1, |&(_, info)| (info.maximum - info.minimum + 1) as u32))
+}
+
+// Works out how to convert the touchscreen's units to millimeters. Some touchscreens don't say
+// how big their units are, and then we go by the size that libinput found.
+fn scale(axes: &[(u16, libc::input_absinfo)], size_mm: Option<(f64, f64)>) -> io::Result<Scale> {
+    let get = |a| axes.iter().find(|&&(axis, _)| axis == a).map(|&(_, info)| info);
+    let (x, y) = match (get(ABS_MT_POSITION_X), get(ABS_MT_POSITION_Y)) {
+        (Some(x), Some(y)) => (x, y),
+        _ => return Err(io::Error::new(io::ErrorKind::Other, "not a multitouch touchscreen")),
+    };
+    let per_mm = |info: libc::input_absinfo, mm: Option<f64>| {
+        if info.resolution > 0 {
+            info.resolution as f64
+        } else {
+            mm.map_or(1.0, |mm| (info.maximum - info.minimum) as f64 / mm)
+        }
+    };
+    let x_per_mm = per_mm(x, size_mm.map(|s| s.0));
+    Ok(Scale {
+        min_x: x.minimum,
+        min_y: y.minimum,
+        x: x_per_mm,
+        y: per_mm(y, size_mm.map(|s| s.1)),
+        // Without a resolution of its own, the size is in the same units as the position.
+        major: get(ABS_MT_TOUCH_MAJOR)
+            .map(|m| if m.resolution > 0 { m.resolution as f64 } else { x_per_mm }),
+        pressure: get(ABS_MT_PRESSURE)
+            .and_then(|p| if p.maximum > p.minimum { Some((p.minimum, p.maximum)) } else { None }),
+    })
+}
+
+// A copy of a touchscreen, made with uinput.
+struct Mirror {
+    fd: c_int,
+}
+
+impl Mirror {
+    fn new(name: &str, axes: &[(u16, libc::input_absinfo)]) -> io::Result<Mirror> {
+        let path = CString::new("/dev/uinput").unwrap();
+        let fd = privsep::open(&path, libc::O_WRONLY | libc::O_NONBLOCK)?;
+        // From here on, dropping the mirror closes the file.
+        let ret = Mirror { fd: fd };
+
+        let mut setup = UinputSetup {
+            bustype: BUS_VIRTUAL,
+            vendor: 0,
+            product: 0,
+            version: 1,
+            name: [0; UINPUT_MAX_NAME_SIZE],
+            ff_effects_max: 0,
+        };
+        let full_name = format!("{}{}", name, MIRROR_SUFFIX);
+        // Long names lose the end of the original name, but keep the suffix.
+        let keep = full_name.len().min(UINPUT_MAX_NAME_SIZE - 1) - MIRROR_SUFFIX.len();
+        let bytes = name.bytes().take(keep).chain(MIRROR_SUFFIX.bytes());
+        for (dst, b) in setup.name.iter_mut().zip(bytes) {
+            *dst = b as c_char;
+        }
+
+        unsafe {
+            for &ev in &[EV_SYN, EV_KEY, EV_ABS] {
+                check(libc::ioctl(fd, UI_SET_EVBIT, ev as c_int))?;
+            }
+            for &key in &[BTN_TOUCH, BTN_TOOL_FINGER] {
+                check(libc::ioctl(fd, UI_SET_KEYBIT, key as c_int))?;
+            }
+            check(libc::ioctl(fd, UI_SET_PROPBIT, INPUT_PROP_DIRECT))?;
+            for &(axis, info) in axes {
+                check(libc::ioctl(fd, UI_SET_ABSBIT, axis as c_int))?;
+                let abs = UinputAbsSetup { code: axis, absinfo: info };
+                check(libc::ioctl(fd, UI_ABS_SETUP, &abs as *const UinputAbsSetup))?;
+            }
+            check(libc::ioctl(fd, UI_DEV_SETUP, &setup as *const UinputSetup))?;
+            check(libc::ioctl(fd, UI_DEV_CREATE))?;
+        }
+        Ok(ret)
+    }
+
+    fn write(&self, events: &[libc::input_event]) {
+        if events.is_empty() {
+            return;
+        }
+        let len = events.len() * mem::size_of::<libc::input_event>();
+        let ret = unsafe { libc::write(self.fd, events.as_ptr() as *const c_void, len) };
+        if ret < 0 {
+            warn!("failed to forward touches: {}", io::Error::last_os_error());
+        }
+    }
+}
+
+impl Drop for Mirror {
+    fn drop(&mut self) {
+        unsafe {
+            libc::ioctl(self.fd, UI_DEV_DESTROY);
+            libc::close(self.fd);
+        }
+    }
+}
+
+/// A touchscreen that the daemon has taken away from the other programs.
+///
+/// The grab ends when this is dropped.
+pub struct Grab {
+    name: String,
+    vendor: u32,
+    mirror: Mirror,
+    holder: Holder,
+    // Tells the reader thread to stop.
+    stop: Arc<AtomicBool>,
+}
+
+impl Grab {
+    /// Grabs the touchscreen with system name `sysname`, and starts sending its frames to
+    /// `frames`.
+    ///
+    /// `name` and `vendor` are the ones that libinput reports, and `size_mm` is the size of the
+    /// touchscreen (if libinput knows it).
+    pub fn new(sysname: &str, name: &str, vendor: u32, size_mm: Option<(f64, f64)>,
+               frames: chan::Sender<GrabbedFrame>) -> io::Result<Grab> {
+        let path = CString::new(format!("/dev/input/{}", sysname)).unwrap();
+        let fd = privsep::open(&path, libc::O_RDONLY | libc::O_NONBLOCK)?;
+        let setup = || -> io::Result<(Scale, Mirror)> {
+            let axes = query_axes(fd)?;
+            let scale = scale(&axes, size_mm)?;
+            let mirror = Mirror::new(name, &axes)?;
+            unsafe {
+                // The same clock as libinput's events, and as `libinput::now_ms`.
+                let clock: c_int = libc::CLOCK_MONOTONIC;
+                check(libc::ioctl(fd, EVIOCSCLOCKID, &clock as *const c_int))?;
+                check(libc::ioctl(fd, EVIOCGRAB, 1 as c_int))?;
+            }
+            Ok((scale, mirror))
+        };
+        let (scale, mirror) = match setup() {
+            Ok(x) => x,
+            Err(e) => {
+                unsafe { libc::close(fd); }
+                return Err(e);
+            },
+        };
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let sysname = sysname.to_owned();
+        thread::spawn(move || {
+            if let Err(e) = read_frames(fd, &sysname, Parser::new(scale), &frames, &thread_stop) {
+                error!("stopped reading from {}: {}", sysname, e);
+            }
+            unsafe {
+                libc::ioctl(fd, EVIOCGRAB, 0 as c_int);
+                libc::close(fd);
+            }
+        });
+        Ok(Grab {
+            name: name.to_owned(),
+            vendor: vendor,
+            mirror: mirror,
+            holder: Holder::new(),
+            stop: stop,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn vendor(&self) -> u32 {
+        self.vendor
+    }
+
+    /// Passes on the frame, or holds it back, or throws it away, according to `verdict`.
+    pub fn handle(&mut self, frame: GrabbedFrame, verdict: Verdict) {
+        let out = self.holder.handle(frame.raw, frame.num_down, frame.time, verdict);
+        self.mirror.write(&out);
+    }
+
+    /// Throws away the touch that is being held back, because it was recognized as a gesture
+    /// while the fingers were still.
+    pub fn swallow(&mut self) {
+        self.holder.swallow();
+    }
+
+    /// Passes on the touch that is being held back, if it has been held for too long.
+    pub fn tick(&mut self, now: u32) {
+        if self.holder.is_holding() && now.wrapping_sub(self.holder.since) >= MAX_HOLD_MS {
+            let out = self.holder.release();
+            self.mirror.write(&out);
+        }
+    }
+
+    /// Returns true if a touch is being held back.
+    pub fn is_holding(&self) -> bool {
+        self.holder.is_holding()
+    }
+}
+
+impl Drop for Grab {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// Reads from the touchscreen until `stop` is set.
+fn read_frames(fd: c_int, sysname: &str, mut parser: Parser,
+               frames: &chan::Sender<GrabbedFrame>, stop: &AtomicBool) -> io::Result<()> {
+    let mut buf: [libc::input_event; 64] = unsafe { mem::zeroed() };
+    let mut pollfd = libc::pollfd { fd: fd, events: libc::POLLIN, revents: 0 };
+    while !stop.load(Ordering::Relaxed) {
+        if check(unsafe { libc::poll(&mut pollfd, 1, STOP_POLL_MS) })? == 0 {
+            continue;
+        }
+        let size = mem::size_of::<libc::input_event>();
+        let ret = unsafe {
+            libc::read(fd, buf.as_mut_ptr() as *mut c_void, buf.len() * size)
+        };
+        if ret < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+        for ev in &buf[..(ret as usize / size)] {
+            if let Some((events, num_down, time, raw)) = parser.push(ev) {
+                frames.send(GrabbedFrame {
+                    sysname: sysname.to_owned(),
+                    events: events,
+                    num_down: num_down,
+                    time: time,
+                    raw: raw,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if `name` is the name of one of the copies that we made of a touchscreen.
+pub fn is_mirror(name: &str) -> bool {
+    name.ends_with(MIRROR_SUFFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ev(type_: u16, code: u16, value: i32, ms: i64) -> libc::input_event {
+        libc::input_event {
+            time: libc::timeval { tv_sec: ms / 1000, tv_usec: (ms % 1000) * 1000 },
+            type_: type_,
+            code: code,
+            value: value,
+        }
+    }
+
+    #[test]
+    fn parses_touches() {
+        let scale = Scale { min_x: 0, min_y: 0, x: 10.0, y: 10.0, major: None, pressure: None };
+        let mut p = Parser::new(scale);
+        assert!(p.push(&ev(EV_ABS, ABS_MT_SLOT, 1, 0)).is_none());
+        p.push(&ev(EV_ABS, ABS_MT_TRACKING_ID, 7, 0));
+        p.push(&ev(EV_ABS, ABS_MT_POSITION_X, 100, 0));
+        p.push(&ev(EV_ABS, ABS_MT_POSITION_Y, 250, 0));
+        let (events, num_down, time, raw) = p.push(&ev(EV_SYN, SYN_REPORT, 0, 1500)).unwrap();
+        assert_eq!(events, vec![Event::Down { slot: 1, pos: Point::new(10.0, 25.0) },
+                                Event::Frame { time: 1500 }]);
+        assert_eq!((num_down, time, raw.len()), (1, 1500, 5));
+
+        p.push(&ev(EV_ABS, ABS_MT_POSITION_X, 110, 0));
+        let (events, ..) = p.push(&ev(EV_SYN, SYN_REPORT, 0, 1510)).unwrap();
+        assert_eq!(events[0], Event::Motion { slot: 1, pos: Point::new(11.0, 25.0) });
+
+        p.push(&ev(EV_ABS, ABS_MT_TRACKING_ID, -1, 0));
+        let (events, num_down, ..) = p.push(&ev(EV_SYN, SYN_REPORT, 0, 1520)).unwrap();
+        assert_eq!(events[0], Event::Up { slot: 1 });
+        assert_eq!(num_down, 0);
+    }
+
+    #[test]
+    fn parses_shapes() {
+        let scale = Scale {
+            min_x: 0,
+            min_y: 0,
+            x: 10.0,
+            y: 10.0,
+            major: Some(10.0),
+            pressure: Some((0, 255)),
+        };
+        let mut p = Parser::new(scale);
+        p.push(&ev(EV_ABS, ABS_MT_TRACKING_ID, 7, 0));
+        p.push(&ev(EV_ABS, ABS_MT_POSITION_X, 100, 0));
+        p.push(&ev(EV_ABS, ABS_MT_TOUCH_MAJOR, 90, 0));
+        let (events, ..) = p.push(&ev(EV_SYN, SYN_REPORT, 0, 1500)).unwrap();
+        assert_eq!(events[1], Event::Shape { slot: 0, size: 9.0, pressure: 0.0 });
+
+        // Moving doesn't change the shape.
+        p.push(&ev(EV_ABS, ABS_MT_POSITION_X, 110, 0));
+        assert_eq!(p.push(&ev(EV_SYN, SYN_REPORT, 0, 1510)).unwrap().0.len(), 2);
+        p.push(&ev(EV_ABS, ABS_MT_PRESSURE, 51, 0));
+        let (events, ..) = p.push(&ev(EV_SYN, SYN_REPORT, 0, 1520)).unwrap();
+        assert_eq!(events[0], Event::Shape { slot: 0, size: 9.0, pressure: 0.2 });
+    }
+
+    #[test]
+    fn holds_back_touches() {
+        let frame = |ms| vec![ev(EV_SYN, SYN_REPORT, 0, ms)];
+        let mut h = Holder::new();
+        assert!(h.handle(frame(0), 1, 0, Verdict::Undecided).is_empty());
+        assert!(h.handle(frame(10), 1, 10, Verdict::Undecided).is_empty());
+        assert!(h.is_holding());
+        // Once it isn't a gesture, everything comes out, and then the rest goes straight through.
+        assert_eq!(h.handle(frame(20), 2, 20, Verdict::NotAGesture).len(), 3);
+        assert_eq!(h.handle(frame(30), 0, 30, Verdict::NotAGesture).len(), 1);
+
+        // A gesture is thrown away, until all the fingers are up.
+        assert!(h.handle(frame(40), 1, 40, Verdict::Undecided).is_empty());
+        assert!(h.handle(frame(50), 1, 50, Verdict::Gesture).is_empty());
+        assert!(h.handle(frame(60), 1, 60, Verdict::NotAGesture).is_empty());
+        assert!(h.handle(frame(70), 0, 70, Verdict::NotAGesture).is_empty());
+        assert!(!h.is_holding());
+
+        // A touch that takes too long to make up its mind goes through anyway.
+        assert!(h.handle(frame(100), 1, 100, Verdict::Undecided).is_empty());
+        let late = 100 + MAX_HOLD_MS;
+        assert_eq!(h.handle(frame(late as i64), 1, late, Verdict::Undecided).len(), 2);
+    }
+}
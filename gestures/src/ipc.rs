@@ -0,0 +1,100 @@
+use chan;
+use libc;
+use std::env;
+use std::fs;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+// How long a client has to send its command before we give up on it.
+const READ_TIMEOUT_SECS: u64 = 5;
+
+/// A command that was received on the control socket.
+///
+/// The reply should be sent on `reply`; the connection is closed once the reply is sent (or once
+/// `reply` is dropped).
+pub struct Request {
+    pub command: String,
+    pub reply: chan::Sender<String>,
+}
+
+/// Returns the path of the control socket.
+pub fn socket_path() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => {
+            let mut path = PathBuf::from(dir);
+            path.push("gestures.sock");
+            path
+        },
+        None => PathBuf::from(format!("/tmp/gestures-{}.sock", unsafe { libc::getuid() })),
+    }
+}
+
+fn handle(stream: UnixStream, send: &chan::Sender<Request>) {
+    // A client that connects and then says nothing shouldn't hang around forever.
+    if let Err(e) = stream.set_read_timeout(Some(Duration::from_secs(READ_TIMEOUT_SECS))) {
+        warn!("failed to set a timeout on the control socket: {}", e);
+        return;
+    }
+    let mut line = String::new();
+    if let Err(e) = BufReader::new(&stream).read_line(&mut line) {
+        warn!("failed to read from control socket: {}", e);
+        return;
+    }
+
+    let (reply_send, reply_recv) = chan::sync(1);
+    send.send(Request {
+        command: line.trim().to_owned(),
+        reply: reply_send,
+    });
+    if let Some(reply) = reply_recv.recv() {
+        if let Err(e) = (&stream).write_all(reply.as_bytes()) {
+            warn!("failed to write to control socket: {}", e);
+        }
+    }
+}
+
+/// Starts listening on the control socket.
+///
+/// Each line that a client sends is forwarded as a `Request` on the returned channel. Fails if
+/// another daemon is already listening on the socket.
+pub fn listen() -> io::Result<chan::Receiver<Request>> {
+    let path = socket_path();
+    if UnixStream::connect(&path).is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AddrInUse,
+                                  format!("another daemon is listening on {}", path.display())));
+    }
+    // If the daemon didn't shut down cleanly last time, there could be a stale socket lying
+    // around.
+    let _ = fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+
+    let (send, recv) = chan::sync(0);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                // Each client gets its own thread, so that a slow one doesn't hold up the others.
+                Ok(stream) => {
+                    let send = send.clone();
+                    thread::spawn(move || handle(stream, &send));
+                },
+                Err(e) => warn!("failed to accept connection on control socket: {}", e),
+            }
+        }
+    });
+    Ok(recv)
+}
+
+/// Sends a command to a running daemon, and returns its reply.
+pub fn send_command(command: &str) -> io::Result<String> {
+    let mut stream = UnixStream::connect(socket_path())?;
+    stream.write_all(command.as_bytes())?;
+    stream.write_all(b"\n")?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    Ok(reply)
+}
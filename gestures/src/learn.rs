@@ -0,0 +1,378 @@
+//! Teaching the daemon a new gesture by example.
+//!
+//! `gestures learn NAME` asks the user to make a gesture a few times, works out what kind of
+//! gesture it is (a swipe, a spread, a grab, or a path) and how far and how fast it goes, and
+//! writes a [custom gesture](../custom/index.html) called `NAME` to a new file in the user's
+//! `bindings.toml.d` directory. The thresholds are a bit looser than the examples, so that the
+//! gesture doesn't have to be made exactly the same way every time.
+
+use app_dirs::{app_root, AppDataType};
+use gestures_core::APP_INFO;
+use gestures_core::custom::CustomName;
+use input::event::Event as InputEvent;
+use libgestures::event::Event;
+use libgestures::frame::FrameTracker;
+use libgestures::geom::{Angle, Direction, Point, UAngle};
+use libgestures::gestures::Path;
+use libgestures::gestures::spec::{Motion, Spec};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use toml;
+
+use libinput::{self, Input};
+
+/// How many times the user makes the gesture, unless they say otherwise.
+pub const DEFAULT_ATTEMPTS: usize = 3;
+
+// The spread of the fingers must change by this factor for a spread or a grab.
+const PINCH_SCALE: f64 = 1.25;
+// How far (in millimeters) the fingers must move for us to learn anything.
+const MIN_TRAVEL_MM: f64 = 5.0;
+// A movement counts as straight if it ends up at least this fraction of the distance it
+// travelled from where it started.
+const STRAIGHTNESS: f64 = 0.8;
+// Paths are split into pieces this long (in millimeters), and each piece is rounded to a
+// direction.
+const PATH_STEP_MM: f64 = 4.0;
+// How far (in degrees) a piece of a path may be from a direction, and still be rounded to it.
+const PATH_ANGLE_DEG: f64 = 30.0;
+// The learned thresholds are this much looser than the loosest example.
+const LENGTH_SLACK: f64 = 0.75;
+const DURATION_SLACK: f64 = 1.5;
+
+/// What one attempt at the gesture looked like.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Attempt {
+    fingers: u8,
+    motion: Motion,
+    // For straight movements, the direction (if it was close to one).
+    direction: Option<Direction>,
+    // How far (in millimeters) the fingers moved: for spreads and grabs, this is how far each
+    // finger moved towards or away from the others.
+    length: f64,
+    duration_ms: u32,
+}
+
+// Rounds the direction of `v` to one of the four directions, if it's within `threshold_deg`.
+fn direction_of(v: Point, threshold_deg: f64) -> Option<Direction> {
+    let angle = Angle::from_radians((-v.y).atan2(v.x));
+    Direction::from_angle(angle, UAngle::from_degrees(threshold_deg))
+}
+
+// Picks out the points that are at least `PATH_STEP_MM` apart, which smooths out any shaking.
+fn resample(points: &[Point]) -> Vec<Point> {
+    let mut ret = vec![points[0]];
+    for &p in points {
+        if (p - ret[ret.len() - 1]).length() >= PATH_STEP_MM {
+            ret.push(p);
+        }
+    }
+    ret
+}
+
+// Works out the directions of the segments of a path through `points`, which should already be
+// resampled.
+fn path_directions(points: &[Point]) -> Vec<Direction> {
+    // Each direction, and how many pieces in a row went that way.
+    let mut runs: Vec<(Direction, usize)> = Vec::new();
+    for w in points.windows(2) {
+        if let Some(d) = direction_of(w[1] - w[0], PATH_ANGLE_DEG) {
+            if runs.last().map_or(false, |&(last, _)| last == d) {
+                runs.last_mut().unwrap().1 += 1;
+            } else {
+                runs.push((d, 1));
+            }
+        }
+    }
+    // A single piece going some other way is probably just the corner being rounded off.
+    let mut ret = runs.into_iter().filter(|&(_, n)| n >= 2).map(|(d, _)| d).collect::<Vec<_>>();
+    ret.dedup();
+    ret
+}
+
+/// Works out what kind of gesture the fingers made in `events`.
+///
+/// Only the part of the gesture while all the fingers were down counts.
+fn summarize(events: &[Event]) -> Result<Attempt, String> {
+    let mut tracker = FrameTracker::new();
+    let mut fingers = 0;
+    let mut lifted = false;
+    // The time, the middle of the fingers, and their spread in each frame.
+    let mut track: Vec<(u32, Point, f64)> = Vec::new();
+    for ev in events {
+        let time = match *ev {
+            Event::Frame { time } => time,
+            _ => {
+                tracker.push_event(ev);
+                continue;
+            },
+        };
+        let frame = tracker.end_frame(time);
+        let n = frame.cur.num_down;
+        if n > fingers && !lifted {
+            // Another finger came down, so the gesture starts again.
+            fingers = n;
+            track.clear();
+        }
+        if n < fingers {
+            lifted = true;
+        } else if !lifted && n > 0 {
+            track.push((time, frame.cur.mean_pos(), frame.cur.spread()));
+        }
+    }
+
+    let (first, last) = match (track.first(), track.last()) {
+        (Some(&f), Some(&l)) if track.len() >= 2 => (f, l),
+        _ => return Err("the fingers didn't stay down".to_owned()),
+    };
+    let points = resample(&track.iter().map(|t| t.1).collect::<Vec<_>>());
+    let travel = points.windows(2).map(|w| (w[1] - w[0]).length()).sum::<f64>();
+    let disp = last.1 - first.1;
+    let scale = if fingers >= 2 && first.2 > 0.0 { last.2 / first.2 } else { 1.0 };
+
+    let mut ret = Attempt {
+        fingers: fingers,
+        motion: Motion::Straight,
+        direction: None,
+        length: disp.length(),
+        duration_ms: last.0.wrapping_sub(first.0),
+    };
+    if scale >= PINCH_SCALE {
+        ret.motion = Motion::Spread;
+        ret.length = last.2 - first.2;
+    } else if scale <= 1.0 / PINCH_SCALE {
+        ret.motion = Motion::Grab;
+        ret.length = first.2 - last.2;
+    } else if travel < MIN_TRAVEL_MM {
+        return Err("the fingers hardly moved".to_owned());
+    } else if disp.length() >= STRAIGHTNESS * travel {
+        ret.direction = direction_of(disp, 25.0);
+    } else {
+        let directions = path_directions(&points);
+        ret.motion = Motion::Path(Path::new(&directions).ok_or_else(|| {
+            "the fingers didn't move in a straight line, or along a clear path".to_owned()
+        })?);
+    }
+    Ok(ret)
+}
+
+/// Works out a spec that recognizes all of `attempts`.
+fn learn(attempts: &[Attempt]) -> Result<Spec, String> {
+    let first = attempts.first().ok_or("there are no examples")?;
+    if attempts.iter().any(|a| a.fingers != first.fingers) {
+        return Err("the examples didn't all use the same number of fingers".to_owned());
+    }
+    if attempts.iter().any(|a| a.motion != first.motion) {
+        let kinds = attempts.iter().map(|a| a.motion.to_string()).collect::<Vec<_>>();
+        return Err(format!("the examples didn't look alike: {}", kinds.join(", ")));
+    }
+
+    let mut spec = Spec::new(first.fingers, first.motion);
+    if first.motion == Motion::Straight {
+        // If the examples went different ways, the gesture can go any way.
+        if attempts.iter().all(|a| a.direction == first.direction) {
+            spec.direction = first.direction;
+        }
+    }
+    match first.motion {
+        // The length of a path comes from its segments.
+        Motion::Path(_) => {},
+        _ => {
+            let shortest = attempts.iter().map(|a| a.length).fold(::std::f64::INFINITY, f64::min);
+            spec.min_len_mm = Some((shortest * LENGTH_SLACK).floor().max(1.0));
+        },
+    }
+    let slowest = attempts.iter().map(|a| a.duration_ms).max().unwrap_or(0);
+    // Round up to a multiple of 50 milliseconds.
+    let max_ms = (slowest as f64 * DURATION_SLACK / 50.0).ceil() as u32 * 50;
+    spec.max_duration_ms = Some(max_ms.max(50));
+    Ok(spec)
+}
+
+/// Writes a spec as a `[[gesture]]` section of a config file.
+fn to_toml(name: &str, spec: &Spec, attempts: usize) -> String {
+    let mut ret = format!("# Learned by `gestures learn` from {} examples.\n", attempts);
+    ret += &format!("[[gesture]]\nname = {}\nfingers = {}\n",
+                    toml::Value::String(name.to_owned()), spec.fingers);
+    ret += &format!("move = \"{}\"\n", spec.motion);
+    if let Some(d) = spec.direction {
+        ret += &format!("direction = \"{}\"\n", d);
+    }
+    if let Some(mm) = spec.min_len_mm {
+        ret += &format!("min-len-mm = {:.1}\n", mm);
+    }
+    if let Some(ms) = spec.max_duration_ms {
+        ret += &format!("max-duration-ms = {}\n", ms);
+    }
+    ret += &format!("end = \"{}\"\n", spec.end.name());
+    ret += &format!("\n# To use it, add a binding like this one:\n# [[bindings]]\n\
+                     # gesture = \"custom {}\"\n# exec = \"...\"\n", name);
+    ret
+}
+
+// Records the touch events from a finger coming down until all the fingers are up again.
+fn record_attempt(input: &mut Input) -> Result<Vec<Event>, String> {
+    let mut events = Vec::new();
+    let mut down = 0i32;
+    loop {
+        if input.poll.recv().is_none() {
+            return Err("lost the connection to libinput".to_owned());
+        }
+        input.libinput.dispatch().map_err(|e| format!("libinput failed: {}", e))?;
+        while let Some(ev) = input.libinput.next() {
+            let ev = match ev {
+                InputEvent::Touch(ref ev) => Event::from(ev),
+                _ => continue,
+            };
+            match ev {
+                Event::Down { .. } => down += 1,
+                Event::Up { .. } => down -= 1,
+                Event::Cancel => return Err("the touch was cancelled".to_owned()),
+                _ => {},
+            }
+            events.push(ev);
+            if let Event::Frame { .. } = ev {
+                if down <= 0 && events.len() > 1 {
+                    return Ok(events);
+                }
+            }
+        }
+    }
+}
+
+// Returns true if `name` can be a learned gesture's name. It goes into a file name (and a comment
+// in the file), so it is limited to letters, digits, '-' and '_'.
+fn valid_name(name: &str) -> bool {
+    CustomName::new(name).is_some() && name.chars().all(|c| match c {
+        'a'...'z' | 'A'...'Z' | '0'...'9' | '-' | '_' => true,
+        _ => false,
+    })
+}
+
+// Where the learned gesture called `name` goes.
+fn output_path(name: &str) -> Result<PathBuf, String> {
+    let dir = app_root(AppDataType::UserConfig, &APP_INFO)
+        .map_err(|e| format!("couldn't open the user config directory: {}", e))?
+        .join("bindings.toml.d");
+    fs::create_dir_all(&dir).map_err(|e| format!("couldn't create {}: {}", dir.display(), e))?;
+    Ok(dir.join(format!("learned-{}.toml", name)))
+}
+
+/// Learns a gesture called `name` from `attempts` examples, and writes it to the config.
+pub fn run(name: &str, attempts: usize) -> Result<(), String> {
+    if !valid_name(name) {
+        return Err(format!("{:?} is not a valid gesture name: it can only have letters, digits, \
+                            '-' and '_'", name));
+    }
+    let path = output_path(name)?;
+    if path.exists() {
+        return Err(format!("{} already exists; remove it to learn {:?} again",
+                           path.display(), name));
+    }
+
+    let mut input = libinput::input().map_err(|_| "couldn't initialize libinput")?;
+    // Consume the initial events.
+    input.libinput.dispatch().map_err(|e| format!("libinput failed: {}", e))?;
+    while let Some(_) = input.libinput.next() {
+    }
+
+    let mut examples = Vec::new();
+    while examples.len() < attempts {
+        println!("example {} of {}: make the gesture, and then lift your fingers",
+                 examples.len() + 1, attempts);
+        match record_attempt(&mut input).and_then(|events| summarize(&events)) {
+            Ok(a) => {
+                println!("  {} fingers, {}, {:.0} mm in {} ms",
+                         a.fingers, a.motion, a.length, a.duration_ms);
+                examples.push(a);
+            },
+            Err(e) => println!("  {}; please try again", e),
+        }
+    }
+
+    let spec = learn(&examples)?;
+    fs::File::create(&path)
+        .and_then(|mut f| f.write_all(to_toml(name, &spec, attempts).as_bytes()))
+        .map_err(|e| format!("couldn't write {}: {}", path.display(), e))?;
+    println!("learned \"custom {}\" ({}), and wrote it to {}", name, spec, path.display());
+    println!("bind it with `gestures bind \"custom {}\" -- <command>`", name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{learn, summarize, to_toml, valid_name};
+    use gestures_core::custom::CustomParsed;
+    use libgestures::event::Event;
+    use libgestures::geom::{Direction, Point};
+    use libgestures::gestures::Path;
+    use libgestures::gestures::spec::Motion;
+    use libgestures::testing::Synth;
+    use toml;
+
+    #[test]
+    fn learns_a_swipe() {
+        let examples = (0..3)
+            .map(|seed| {
+                let events = Synth::new(3).noise(0.5).seed(seed)
+                    .swipe(Direction::Up.to_angle(), 30.0 + seed as f64 * 5.0);
+                summarize(&events).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let spec = learn(&examples).unwrap();
+        assert_eq!(spec.fingers, 3);
+        assert_eq!(spec.motion, Motion::Straight);
+        assert_eq!(spec.direction, Some(Direction::Up));
+        assert!(spec.min_len_mm.unwrap() <= 30.0 * 0.75);
+
+        // What we write should read back as the same gesture.
+        let parsed: toml::Value = toml::from_str(&to_toml("up3", &spec, 3)).unwrap();
+        let g: CustomParsed = parsed["gesture"][0].clone().try_into().unwrap();
+        assert_eq!(g.to_spec(), Ok(spec));
+    }
+
+    #[test]
+    fn names() {
+        assert!(valid_name("flick_up-2"));
+        assert!(!valid_name(""));
+        assert!(!valid_name("../up"));
+        assert!(!valid_name("up\"3"));
+        assert!(!valid_name("ünïcode"));
+    }
+
+    #[test]
+    fn learns_a_spread() {
+        let events = Synth::new(2).spacing(40.0).pinch(2.0, 4.0);
+        let spec = learn(&[summarize(&events).unwrap()]).unwrap();
+        assert_eq!(spec.motion, Motion::Spread);
+    }
+
+    #[test]
+    fn learns_a_path() {
+        // Down 30 millimeters, and then right 30 millimeters.
+        let mut pos = Point::new(100.0, 100.0);
+        let mut time = 0;
+        let mut events = vec![Event::Down { slot: 0, pos: pos }, Event::Frame { time: time }];
+        let down = (0..30).map(|_| Point::new(0.0, 1.0));
+        let steps = down.chain((0..30).map(|_| Point::new(1.0, 0.0)));
+        for step in steps {
+            pos = pos + step;
+            time += 10;
+            events.push(Event::Motion { slot: 0, pos: pos });
+            events.push(Event::Frame { time: time });
+        }
+        events.push(Event::Up { slot: 0 });
+        events.push(Event::Frame { time: time + 10 });
+        let spec = learn(&[summarize(&events).unwrap()]).unwrap();
+        let l = Path::new(&[Direction::Down, Direction::Right]).unwrap();
+        assert_eq!(spec.motion, Motion::Path(l));
+    }
+
+    #[test]
+    fn different_examples() {
+        let up = Synth::new(3).swipe(Direction::Up.to_angle(), 30.0);
+        let grab = Synth::new(3).spacing(30.0).pinch(0.3, 4.0);
+        assert!(learn(&[summarize(&up).unwrap(), summarize(&grab).unwrap()]).is_err());
+    }
+}
@@ -4,10 +4,17 @@ use input::Libinput;
 use libc;
 use libc::{c_char, c_int, c_void};
 use libudev_sys;
+use std::ffi::CStr;
 
+use privsep;
+
+// libinput wants a negative errno if the device couldn't be opened.
 unsafe extern "C"
 fn open_restricted(path: *const c_char, flags: c_int, _: *mut c_void) -> c_int {
-    libc::open(path, flags)
+    match privsep::open(CStr::from_ptr(path), flags) {
+        Ok(fd) => fd,
+        Err(e) => -e.raw_os_error().unwrap_or(libc::EIO),
+    }
 }
 
 unsafe extern "C"
@@ -44,6 +51,15 @@ fn init_libinput() -> Result<Libinput, ()> {
     }
 }
 
+/// Returns the current time in milliseconds, on the same clock as the times of libinput's events.
+pub fn now_ms() -> u32 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts);
+    }
+    (ts.tv_sec as u64 * 1000 + ts.tv_nsec as u64 / 1_000_000) as u32
+}
+
 pub fn input() -> Result<Input, ()> {
     let libinput = init_libinput()?;
     let mut pollfd = libc::pollfd {
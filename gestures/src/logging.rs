@@ -0,0 +1,81 @@
+use env_logger::LogBuilder;
+use std::env;
+
+/// Where log messages go.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Target {
+    /// Plain messages on stderr.
+    Stderr,
+    /// Messages on stderr, prefixed with their syslog priorities.
+    ///
+    /// When stderr is connected to the journal (for example, when the daemon runs as a systemd
+    /// service), journald picks up the priorities, so that `journalctl -p` works. The journal
+    /// also forwards the messages to syslog, if there is one.
+    Journald,
+}
+
+pub fn parse_target(s: &str) -> Option<Target> {
+    match s {
+        "stderr" => Some(Target::Stderr),
+        "journald" | "syslog" => Some(Target::Journald),
+        _ => None,
+    }
+}
+
+impl Target {
+    /// Chooses the journal if stderr is connected to it, and plain stderr otherwise.
+    pub fn detect() -> Target {
+        // systemd sets this when it connects stderr to the journal.
+        if env::var_os("JOURNAL_STREAM").is_some() {
+            Target::Journald
+        } else {
+            Target::Stderr
+        }
+    }
+}
+
+// The syslog priority corresponding to a log level.
+fn priority(level: &str) -> u8 {
+    match level {
+        "ERROR" => 3,
+        "WARN" => 4,
+        "INFO" => 6,
+        _ => 7,
+    }
+}
+
+/// Sets up logging.
+///
+/// `filter` says which messages to show, in the same format as the `RUST_LOG` environment
+/// variable: for example, "debug", or "info,libgestures=debug" to see why recognizers fail. If it
+/// is missing, `RUST_LOG` is used instead, and if that's missing too then only warnings and
+/// errors are shown.
+///
+/// Each message is tagged with the module that it came from, so that noise from other modules
+/// can be filtered out.
+pub fn init(filter: Option<&str>, target: Target) -> Result<(), String> {
+    let mut builder = LogBuilder::new();
+    match target {
+        Target::Stderr => builder.format(|record| {
+            format!("{} {}: {}", record.level(), record.target(), record.args())
+        }),
+        Target::Journald => builder.format(|record| {
+            let level = record.level().to_string();
+            format!("<{}>{}: {}", priority(&level), record.target(), record.args())
+        }),
+    };
+    match filter.map(|f| f.to_owned()).or_else(|| env::var("RUST_LOG").ok()) {
+        Some(f) => builder.parse(&f),
+        None => builder.parse("warn"),
+    };
+    builder.init().map_err(|e| format!("{:?}", e))
+}
+
+/// Sets up logging for the `explain` command, whose explanations come from the debug logs of the
+/// recognizers.
+pub fn init_explain(filter: Option<&str>) -> Result<(), String> {
+    let mut builder = LogBuilder::new();
+    builder.format(|record| format!("    {}", record.args()));
+    builder.parse(filter.unwrap_or("libgestures=debug"));
+    builder.init().map_err(|e| format!("{:?}", e))
+}
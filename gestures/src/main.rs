@@ -5,82 +5,298 @@
 extern crate app_dirs;
 extern crate chan_signal;
 extern crate env_logger;
+extern crate gestures_core;
 extern crate input;
 extern crate libc;
 extern crate libgestures;
 extern crate libudev_sys;
+extern crate rayon;
 extern crate serde;
+extern crate serde_json;
 extern crate toml;
 
 use chan_signal::Signal;
-use input::event::Event;
-use libgestures::Recognizer;
-use libgestures::geom::Direction;
-use libgestures::gestures::compound::direction_swipe;
-use libgestures::manager::Manager;
-use std::collections::HashSet;
+use gestures_core::{build_manager, config, profile};
+use gestures_core::config::Source;
+use gestures_core::edit::ConfigFile;
+use std::env;
+use std::process;
 
-const APP_INFO: app_dirs::AppInfo = app_dirs::AppInfo {
-    name: "gestures",
-    author: "Joe Neeman",
-};
-
-mod config;
+mod analyze;
+mod daemon;
+mod forward;
+mod ipc;
+mod learn;
 mod libinput;
+mod logging;
+mod outputs;
+mod pen;
+mod privsep;
+mod simulate;
+mod trace;
+
+// How often (in milliseconds) the daemon does its housekeeping, when it isn't idle.
+const TICK_MS: u32 = 500;
+// How often (in milliseconds) the daemon tells the recognizers the time, while there are fingers
+// holding still that it might be waiting for.
+const DWELL_TICK_MS: u32 = 50;
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-pub enum Gesture {
-    Swipe {
-        num_fingers: u8,
-        direction: Direction,
-    },
+fn exit_with_error(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    process::exit(1);
 }
 
-fn main() {
-    if let Err(e) = env_logger::init() {
-        println!("failed to initialize logging: {:?}", e);
+// Removes `--name VALUE` from `args`, returning `VALUE`.
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let i = match args.iter().position(|a| a == name) {
+        Some(i) => i,
+        None => return None,
+    };
+    if i + 1 >= args.len() {
+        exit_with_error(&format!("{} needs a value", name));
     }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Some(value)
+}
 
-    let config = config::open_config();
-    let signal = chan_signal::notify(&[Signal::INT, Signal::TERM]);
-    let mut input = libinput::input().unwrap();
-    let mut man = Manager::new();
+// Changes the user's config file with `edit`, which returns what it did. Then tells the daemon (if
+// it is running) to reload the config.
+fn edit_config<F>(edit: F)
+where F: FnOnce(&mut ConfigFile) -> Result<String, String>
+{
+    let mut file = ConfigFile::open_user().unwrap_or_else(|e| exit_with_error(&e));
+    let done = edit(&mut file).unwrap_or_else(|e| exit_with_error(&e));
+    file.save().unwrap_or_else(|e| exit_with_error(&e));
+    println!("{} in {}", done, file.path().display());
+    match ipc::send_command("reload-config") {
+        Ok(reply) => print!("{}", reply),
+        Err(_) => println!("the daemon isn't running; it will use the new config when it starts"),
+    }
+}
 
-    let mut fingers = HashSet::new();
-    for gesture in config.bindings.keys() {
-        match gesture {
-            &Gesture::Swipe { num_fingers, .. } => fingers.insert(num_fingers),
-        };
+fn main() {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    let log_level = take_option(&mut args, "--log-level");
+    let log_target = match take_option(&mut args, "--log-target") {
+        Some(t) => logging::parse_target(&t)
+            .unwrap_or_else(|| exit_with_error(&format!("unknown log target {:?}", t))),
+        None => logging::Target::detect(),
+    };
+    // The user that the daemon runs as, if it was started as root (see the `privsep` module).
+    let user = take_option(&mut args, "--user");
+    let command = args.get(0).map(|s| s.as_str());
+
+    let logger = if command == Some("explain") {
+        logging::init_explain(log_level.as_ref().map(|s| s.as_str()))
+    } else {
+        logging::init(log_level.as_ref().map(|s| s.as_str()), log_target)
+    };
+    if let Err(e) = logger {
+        eprintln!("failed to initialize logging: {}", e);
     }
-    for &num_fingers in &fingers {
-        man.push(direction_swipe(num_fingers).map_outcome(move |direction| Gesture::Swipe { num_fingers, direction }));
+
+    match command {
+        None => {},
+        Some("record") => {
+            let path = args.get(1).unwrap_or_else(|| exit_with_error("usage: gestures record <trace-file>"));
+            if let Err(e) = trace::record(path) {
+                exit_with_error(&format!("failed to record trace: {}", e));
+            }
+            return;
+        },
+        Some("explain") => {
+            let usage = "usage: gestures explain <trace-file> [--step]";
+            let mut rest = args[1..].to_vec();
+            let step = rest.iter().position(|a| a == "--step");
+            let step = match step {
+                Some(i) => {
+                    rest.remove(i);
+                    true
+                },
+                None => false,
+            };
+            if rest.len() != 1 {
+                exit_with_error(usage);
+            }
+            let config = config::open_config();
+            let profile = profile::load(&config);
+            if let Err(e) = trace::explain(&rest[0], &config, &profile, step) {
+                exit_with_error(&format!("failed to explain trace: {}", e));
+            }
+            return;
+        },
+        Some("analyze") => {
+            let usage = "usage: gestures analyze <trace-dir> [--format csv|json]";
+            let mut rest = args[1..].to_vec();
+            let format = match take_option(&mut rest, "--format") {
+                Some(f) => analyze::Format::parse(&f).unwrap_or_else(|| exit_with_error(usage)),
+                None => analyze::Format::Csv,
+            };
+            if rest.len() != 1 {
+                exit_with_error(usage);
+            }
+            let config = config::open_config();
+            let profile = profile::load(&config);
+            if let Err(e) = analyze::run(&rest[0], format, &config, &profile) {
+                exit_with_error(&e);
+            }
+            return;
+        },
+        Some("learn") => {
+            let usage = "usage: gestures learn <name> [examples]";
+            let name = args.get(1).unwrap_or_else(|| exit_with_error(usage));
+            let attempts = match args.get(2).map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) if n > 0 => n,
+                Some(_) => exit_with_error(usage),
+                None => learn::DEFAULT_ATTEMPTS,
+            };
+            if let Err(e) = learn::run(name, attempts) {
+                exit_with_error(&format!("failed to learn {:?}: {}", name, e));
+            }
+            return;
+        },
+        Some("simulate") => {
+            let config = config::open_config();
+            let profile = profile::load(&config);
+            if let Err(e) = simulate::run(&config, &profile) {
+                exit_with_error(&format!("simulator failed: {}", e));
+            }
+            return;
+        },
+        Some("bind") => {
+            let usage = "usage: gestures bind <gesture> [--profile <name>] -- <command> [args]";
+            let mut rest = args[1..].to_vec();
+            let sep = rest.iter().position(|a| a == "--")
+                .unwrap_or_else(|| exit_with_error(usage));
+            let command = rest.split_off(sep + 1);
+            rest.pop();
+            let profile = take_option(&mut rest, "--profile");
+            if rest.len() != 1 || command.is_empty() {
+                exit_with_error(usage);
+            }
+            edit_config(|f| {
+                let replaced = f.bind(&rest[0], &command, profile.as_ref().map(|s| s.as_str()))?;
+                Ok(format!("bound {:?}, replacing {} other bindings", rest[0], replaced))
+            });
+            return;
+        },
+        Some("unbind") => {
+            let usage = "usage: gestures unbind <gesture> [--profile <name>]";
+            let mut rest = args[1..].to_vec();
+            let profile = take_option(&mut rest, "--profile");
+            if rest.len() != 1 {
+                exit_with_error(usage);
+            }
+            edit_config(|f| {
+                match f.unbind(&rest[0], profile.as_ref().map(|s| s.as_str()))? {
+                    0 => Err(format!("there are no bindings for {:?} in {}",
+                                     rest[0], f.path().display())),
+                    n => Ok(format!("removed {} bindings for {:?}", n, rest[0])),
+                }
+            });
+            return;
+        },
+        Some("dump-pipelines") => {
+            let config = config::open_config();
+            let profile = profile::load(&config);
+            let (man, names) = build_manager(&config, Source::Touch, &profile, config.params);
+            for (id, name) in names.iter().enumerate() {
+                println!("// #{}: {}", id, name);
+            }
+            print!("{}", man.to_dot());
+            return;
+        },
+        Some(_) => {
+            // Anything else is a command for the running daemon.
+            match ipc::send_command(&args.join(" ")) {
+                Ok(reply) => print!("{}", reply),
+                Err(e) => exit_with_error(&format!("failed to talk to the gestures daemon: {}", e)),
+            }
+            return;
+        },
     }
 
-    // Consume the initial events.
+    // This forks, so it has to come before anything starts a thread.
+    if let Some(ref user) = user {
+        if let Err(e) = privsep::drop_privileges(user) {
+            exit_with_error(&e);
+        }
+    }
+    let signal = chan_signal::notify(&[Signal::INT, Signal::TERM]);
+    let mut input = libinput::input().unwrap();
+    let mut daemon = daemon::Daemon::new(config::open_config());
+
+    // The initial events tell us which devices there are.
     input.libinput.dispatch().unwrap();
-    while let Some(_) = input.libinput.next() {
+    while let Some(event) = input.libinput.next() {
+        daemon.handle_event(&event);
     }
 
+    let ipc = ipc::listen().expect("couldn't open control socket");
+    let plugins = daemon.plugin_gestures();
+    let grabbed = daemon.grabbed_frames();
+    let focus = daemon.focus_changes();
+    let lock = daemon.lock_changes();
     let poll = input.poll;
+    // A timer that never goes off, for when we're idle. We need to hold on to the sender, or else
+    // the receiver would fire immediately.
+    let (_never_send, never) = chan::sync::<()>(0);
+    // The timer, and how long it was set for.
+    let mut tick: Option<(chan::Receiver<()>, u32)> = None;
     loop {
+        // The timer only runs while there's something to do, so that an idle daemon doesn't wake
+        // up at all until the next input event. If the daemon needs to hear from it sooner than
+        // the timer that is already running, it starts over.
+        if !daemon.is_idle() {
+            let ms = if daemon.is_dwelling() { DWELL_TICK_MS } else { TICK_MS };
+            if tick.as_ref().map_or(true, |&(_, t)| t > ms) {
+                tick = Some((chan::after_ms(ms), ms));
+            }
+        }
+        let next_tick = tick.as_ref().map_or_else(|| never.clone(), |t| t.0.clone());
         chan_select! {
             poll.recv() => {
                 input.libinput.dispatch().unwrap();
                 while let Some(event) = input.libinput.next() {
-                    if let Event::Touch(ev) = event {
-                        if let Some(g) = man.update(&ev) {
-                            println!("got gesture {:?}", g);
-                            if let Some(action) = config.bindings.get(&g) {
-                                action.run();
-                            }
-                        }
-                    }
+                    daemon.handle_event(&event);
+                }
+            },
+            next_tick.recv() => {
+                tick = None;
+                daemon.tick();
+            },
+            focus.recv() -> app => {
+                if let Some(app) = app {
+                    daemon.handle_focus(&app);
+                }
+            },
+            lock.recv() -> locked => {
+                if let Some(locked) = locked {
+                    daemon.handle_lock(locked);
+                }
+            },
+            grabbed.recv() -> f => {
+                if let Some(f) = f {
+                    daemon.handle_grabbed(f);
+                }
+            },
+            plugins.recv() -> g => {
+                if let Some(g) = g {
+                    daemon.handle_plugin_gesture(g);
+                }
+            },
+            ipc.recv() -> req => {
+                if let Some(req) = req {
+                    req.reply.send(daemon.handle_command(&req.command));
                 }
             },
             signal.recv() -> _ => {
                 break;
             },
         }
+        if daemon.should_quit() {
+            break;
+        }
     }
 }
-
@@ -0,0 +1,159 @@
+//! Finding out which part of the screen a touchscreen covers.
+//!
+//! Each touchscreen is mapped to one output (that is, one monitor). The compositor tells libinput
+//! which one through the `WL_OUTPUT` udev property, but it can also be set in the config file.
+//! The geometry of the outputs comes from `xrandr`, which also works under XWayland. There's no
+//! fallback for Wayland sessions without XWayland: the outputs are unknown there, so the screen
+//! placeholders in commands are left empty.
+
+use libgestures::geom::{OutputMapping, OutputRotation, ScreenPoint};
+use std::process::Command;
+
+/// A monitor, and where it is on the screen. All the sizes are in pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Output {
+    pub name: String,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub primary: bool,
+    pub rotation: OutputRotation,
+}
+
+impl Output {
+    /// Lines this output up with a touch surface that is `width` by `height` millimeters.
+    pub fn mapping(&self, width: f64, height: f64) -> OutputMapping {
+        OutputMapping {
+            surface_width: width,
+            surface_height: height,
+            origin: ScreenPoint::new(self.x, self.y),
+            width: self.width,
+            height: self.height,
+            rotation: self.rotation,
+        }
+    }
+}
+
+// Parses a geometry like "1920x1080+0+0".
+fn parse_geometry(s: &str) -> Option<(f64, f64, f64, f64)> {
+    let mut parts = s.split(|c| c == 'x' || c == '+');
+    let mut next = || parts.next().and_then(|p| p.parse::<f64>().ok());
+    let (w, h, x, y) = (next()?, next()?, next()?, next()?);
+    Some((x, y, w, h))
+}
+
+// Parses the word after the geometry, which is only there if the output is rotated.
+fn parse_rotation(s: Option<&&str>) -> OutputRotation {
+    match s {
+        Some(&"left") => OutputRotation::Left,
+        Some(&"inverted") => OutputRotation::Inverted,
+        Some(&"right") => OutputRotation::Right,
+        _ => OutputRotation::Normal,
+    }
+}
+
+// Parses the output of `xrandr --query`, which has a line like
+// "DP-1 connected primary 1080x1920+0+0 left (normal left inverted right) 527mm x 296mm"
+// for each output that is turned on. The geometry is the rotated one.
+fn parse_xrandr(s: &str) -> Vec<Output> {
+    let mut ret = Vec::new();
+    for line in s.lines() {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        if words.len() < 3 || words[1] != "connected" {
+            continue;
+        }
+        let geometry = words.iter().enumerate().skip(2)
+            .filter_map(|(i, w)| parse_geometry(w).map(|g| (i, g)))
+            .next();
+        if let Some((i, (x, y, w, h))) = geometry {
+            ret.push(Output {
+                name: words[0].to_owned(),
+                x: x,
+                y: y,
+                width: w,
+                height: h,
+                primary: words[2] == "primary",
+                rotation: parse_rotation(words.get(i + 1)),
+            });
+        }
+    }
+    ret
+}
+
+/// Asks `xrandr` for all the outputs that are turned on.
+pub fn query() -> Result<Vec<Output>, String> {
+    let out = Command::new("xrandr")
+        .arg("--query")
+        .output()
+        .map_err(|e| format!("failed to run xrandr: {}", e))?;
+    if !out.status.success() {
+        return Err(format!("xrandr failed: {}", String::from_utf8_lossy(&out.stderr).trim()));
+    }
+    Ok(parse_xrandr(&String::from_utf8_lossy(&out.stdout)))
+}
+
+/// Finds the output called `name`. If there isn't one (or if `name` is missing), falls back to
+/// the primary output, and then to the first one.
+pub fn find<'a>(outputs: &'a [Output], name: Option<&str>) -> Option<&'a Output> {
+    name.and_then(|n| outputs.iter().find(|o| o.name == n))
+        .or_else(|| outputs.iter().find(|o| o.primary))
+        .or_else(|| outputs.first())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libgestures::geom::Point;
+
+    const XRANDR: &'static str = "\
+Screen 0: minimum 320 x 200, current 3000 x 1920, maximum 16384 x 16384
+eDP-1 connected primary 1920x1080+0+0 (normal left inverted right x axis y axis) 309mm x 174mm
+   1920x1080     60.02*+  59.93    48.00
+   1680x1050     59.95    59.88
+HDMI-1 disconnected (normal left inverted right x axis y axis)
+DP-1 connected 1080x1920+1920+0 left (normal left inverted right x axis y axis) 527mm x 296mm
+   1920x1080     60.00*+  50.00    59.94
+   1280x720      60.00    50.00    59.94
+DP-2 connected (normal left inverted right x axis y axis)
+   1920x1080     60.00 +
+";
+
+    #[test]
+    fn parses_xrandr() {
+        let outputs = parse_xrandr(XRANDR);
+        assert_eq!(outputs, vec![
+            Output {
+                name: "eDP-1".to_owned(),
+                x: 0.0,
+                y: 0.0,
+                width: 1920.0,
+                height: 1080.0,
+                primary: true,
+                rotation: OutputRotation::Normal,
+            },
+            Output {
+                name: "DP-1".to_owned(),
+                x: 1920.0,
+                y: 0.0,
+                width: 1080.0,
+                height: 1920.0,
+                primary: false,
+                rotation: OutputRotation::Left,
+            },
+        ]);
+        assert_eq!(find(&outputs, Some("HDMI-1")).unwrap().name, "eDP-1");
+        assert_eq!(find(&outputs, Some("DP-1")).unwrap().name, "DP-1");
+    }
+
+    #[test]
+    fn rotated_outputs() {
+        let outputs = parse_xrandr(XRANDR);
+        // A 520mm by 290mm touchscreen on the rotated monitor. Its top left corner is now at the
+        // top right of the monitor.
+        let mapping = find(&outputs, Some("DP-1")).unwrap().mapping(520.0, 290.0);
+        assert_eq!(mapping.to_screen(Point::new(0.0, 0.0)), ScreenPoint::new(3000.0, 0.0));
+        assert_eq!(mapping.to_screen(Point::new(520.0, 290.0)), ScreenPoint::new(1920.0, 1920.0));
+        assert_eq!(mapping.to_screen(Point::new(260.0, 0.0)), ScreenPoint::new(3000.0, 960.0));
+    }
+}
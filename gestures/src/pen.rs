@@ -0,0 +1,103 @@
+use input::event::tablet_tool::{TabletToolEvent, TabletToolEventTrait, TipState};
+use libgestures::event::Event;
+use libgestures::geom::Point;
+
+/// Turns the events of a pen (or any other libinput tablet tool) into touch events.
+///
+/// While the pen tip is touching the tablet, it looks like a single finger in slot 0. Hovering,
+/// tilt, pressure and the pen's buttons are ignored.
+#[derive(Debug, Default)]
+pub struct PenTracker {
+    tip_down: bool,
+}
+
+// The parts of a tablet tool event that we care about.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Tool {
+    TipDown,
+    TipUp,
+    Moved,
+    Proximity,
+    Other,
+}
+
+impl PenTracker {
+    pub fn new() -> PenTracker {
+        PenTracker::default()
+    }
+
+    /// Converts a tablet tool event into zero or more touch events.
+    ///
+    /// Every batch of events that this returns ends with a `Frame`.
+    pub fn handle_event(&mut self, ev: &TabletToolEvent) -> Vec<Event> {
+        let tool = match ev {
+            &TabletToolEvent::Tip(ref tip) => match tip.tip_state() {
+                TipState::Down => Tool::TipDown,
+                TipState::Up => Tool::TipUp,
+            },
+            &TabletToolEvent::Axis(_) => Tool::Moved,
+            &TabletToolEvent::Proximity(_) => Tool::Proximity,
+            _ => Tool::Other,
+        };
+        self.handle(tool, Point::new(ev.x(), ev.y()), ev.time())
+    }
+
+    fn handle(&mut self, tool: Tool, pos: Point, time: u32) -> Vec<Event> {
+        let ev = match tool {
+            Tool::TipDown => {
+                self.tip_down = true;
+                Event::Down { slot: 0, pos: pos }
+            },
+            Tool::TipUp => {
+                if !self.tip_down {
+                    return vec![];
+                }
+                self.tip_down = false;
+                Event::Up { slot: 0 }
+            },
+            Tool::Moved if self.tip_down => Event::Motion { slot: 0, pos: pos },
+            // If the pen leaves proximity without lifting the tip (which shouldn't really happen),
+            // the tip is as good as lifted.
+            Tool::Proximity if self.tip_down => {
+                self.tip_down = false;
+                Event::Up { slot: 0 }
+            },
+            _ => return vec![],
+        };
+        vec![ev, Event::Frame { time: time }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libgestures::frame::Frame;
+
+    #[test]
+    fn leaving_proximity_lifts_the_tip() {
+        let mut pen = PenTracker::new();
+        let mut frame = Frame::new();
+        let pos = Point::new(10.0, 10.0);
+        let events = vec![
+            (Tool::Proximity, 0),
+            (Tool::TipDown, 10),
+            (Tool::Moved, 20),
+            (Tool::Proximity, 30),
+            // The tip can't lift twice.
+            (Tool::TipUp, 40),
+            (Tool::Moved, 50),
+        ];
+        let mut out = Vec::new();
+        for (tool, time) in events {
+            for ev in pen.handle(tool, pos, time) {
+                out.push(ev);
+                frame.update_event(&ev);
+                if let Event::Frame { .. } = ev {
+                    frame.advance();
+                }
+            }
+        }
+        assert_eq!(&out[4..], &[Event::Up { slot: 0 }, Event::Frame { time: 30 }]);
+        assert_eq!(frame.cur.num_down, 0);
+    }
+}
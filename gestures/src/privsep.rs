@@ -0,0 +1,233 @@
+//! Running the daemon as an ordinary user, with a small privileged helper to open devices.
+//!
+//! Reading the input devices (and writing to `/dev/uinput`, for grabbed touchscreens) needs
+//! privileges that most users don't have. Running the whole daemon as root gives them to
+//! everything else too, including the actions that the config file runs. Instead, the daemon
+//! can be started as root with `--user NAME`:
+//!
+//! ```text
+//! sudo gestures --user alice
+//! ```
+//!
+//! Before it does anything else, it forks off a helper, which keeps root's privileges and does
+//! nothing but open device nodes. The daemon itself then switches to the user, and asks the
+//! helper whenever it needs to open a device, over a socket that only the two of them share. The
+//! helper only opens `/dev/input/event*` and `/dev/uinput`, and hands back the open file (so the
+//! events themselves go straight to the daemon). It exits once the daemon does.
+
+use libc::{self, c_int, c_void};
+use std::env;
+use std::ffi::{CStr, CString};
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicIsize, Ordering, ATOMIC_ISIZE_INIT};
+
+// The daemon's end of the socket to the helper, plus one (so that zero means there is no
+// helper). Only the main thread opens devices, so requests never overlap.
+static HELPER: AtomicIsize = ATOMIC_ISIZE_INIT;
+
+// The longest path that the helper will open.
+const MAX_PATH: usize = 64;
+
+fn check(ret: c_int) -> io::Result<c_int> {
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+// Returns true if the helper may open `path`.
+fn allowed(path: &str) -> bool {
+    const EVENT: &'static str = "/dev/input/event";
+    path == "/dev/uinput"
+        || (path.starts_with(EVENT) && path.len() > EVENT.len()
+            && path[EVENT.len()..].bytes().all(|b| b >= b'0' && b <= b'9'))
+}
+
+// The buffer for a control message that carries one file descriptor. The descriptor comes right
+// after the header, which is already aligned, but the whole thing has some padding at the end.
+#[repr(C)]
+struct FdMessage {
+    header: libc::cmsghdr,
+    fd: c_int,
+}
+
+/// Opens a device node, through the helper if there is one.
+pub fn open(path: &CStr, flags: c_int) -> io::Result<c_int> {
+    match HELPER.load(Ordering::SeqCst) {
+        0 => check(unsafe { libc::open(path.as_ptr(), flags) }),
+        sock => request(sock as c_int - 1, path, flags),
+    }
+}
+
+// Asks the helper to open `path`. The request is the flags followed by the path, and the reply
+// is an errno (zero if it worked), together with the file.
+fn request(sock: c_int, path: &CStr, flags: c_int) -> io::Result<c_int> {
+    let mut msg = unsafe { mem::transmute::<c_int, [u8; 4]>(flags) }.to_vec();
+    msg.extend_from_slice(path.to_bytes());
+    check(unsafe { libc::send(sock, msg.as_ptr() as *const c_void, msg.len(), 0) as c_int })?;
+
+    let mut errno: c_int = 0;
+    let mut iov = libc::iovec {
+        iov_base: &mut errno as *mut c_int as *mut c_void,
+        iov_len: mem::size_of::<c_int>(),
+    };
+    let mut control: FdMessage = unsafe { mem::zeroed() };
+    let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+    hdr.msg_iov = &mut iov;
+    hdr.msg_iovlen = 1;
+    hdr.msg_control = &mut control as *mut FdMessage as *mut c_void;
+    hdr.msg_controllen = mem::size_of::<FdMessage>() as _;
+    match check(unsafe { libc::recvmsg(sock, &mut hdr, 0) as c_int })? {
+        0 => Err(io::Error::new(io::ErrorKind::BrokenPipe, "the device helper went away")),
+        _ if errno != 0 => Err(io::Error::from_raw_os_error(errno)),
+        _ if control.header.cmsg_level == libc::SOL_SOCKET
+            && control.header.cmsg_type == libc::SCM_RIGHTS => Ok(control.fd),
+        _ => Err(io::Error::new(io::ErrorKind::Other, "the device helper sent no file")),
+    }
+}
+
+// Sends the outcome of opening a device back to the daemon.
+fn reply(sock: c_int, result: io::Result<c_int>) {
+    let mut errno = match result {
+        Ok(_) => 0,
+        Err(ref e) => e.raw_os_error().unwrap_or(libc::EIO),
+    };
+    let mut iov = libc::iovec {
+        iov_base: &mut errno as *mut c_int as *mut c_void,
+        iov_len: mem::size_of::<c_int>(),
+    };
+    let mut control: FdMessage = unsafe { mem::zeroed() };
+    let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+    hdr.msg_iov = &mut iov;
+    hdr.msg_iovlen = 1;
+    if let Ok(fd) = result {
+        control.header.cmsg_len = (mem::size_of::<libc::cmsghdr>() + mem::size_of::<c_int>()) as _;
+        control.header.cmsg_level = libc::SOL_SOCKET;
+        control.header.cmsg_type = libc::SCM_RIGHTS;
+        control.fd = fd;
+        hdr.msg_control = &mut control as *mut FdMessage as *mut c_void;
+        hdr.msg_controllen = mem::size_of::<FdMessage>() as _;
+    }
+    unsafe {
+        libc::sendmsg(sock, &hdr, 0);
+        if let Ok(fd) = result {
+            // The daemon has its own copy now.
+            libc::close(fd);
+        }
+    }
+}
+
+// What the helper does, until the daemon goes away.
+fn serve(sock: c_int) -> ! {
+    let mut buf = [0u8; 4 + MAX_PATH];
+    loop {
+        let len = unsafe { libc::recv(sock, buf.as_mut_ptr() as *mut c_void, buf.len(), 0) };
+        if len <= 0 {
+            process::exit(0);
+        }
+        let len = len as usize;
+        if len < 4 {
+            reply(sock, Err(io::Error::from_raw_os_error(libc::EINVAL)));
+            continue;
+        }
+        let mut flags = [0; 4];
+        flags.copy_from_slice(&buf[..4]);
+        let flags = unsafe { mem::transmute::<[u8; 4], c_int>(flags) };
+        // Nothing but the access mode and a couple of harmless flags: in particular, no O_CREAT.
+        let flags = flags & (libc::O_ACCMODE | libc::O_NONBLOCK | libc::O_CLOEXEC);
+        let path = String::from_utf8_lossy(&buf[4..len]).into_owned();
+        let result = if allowed(&path) {
+            // The path is all ASCII, so it has no zero bytes.
+            let c_path = CString::new(path.clone()).unwrap();
+            check(unsafe { libc::open(c_path.as_ptr(), flags) })
+        } else {
+            Err(io::Error::from_raw_os_error(libc::EACCES))
+        };
+        if let Err(ref e) = result {
+            warn!("the device helper refused to open {:?}: {}", path, e);
+        }
+        reply(sock, result);
+    }
+}
+
+/// Starts the privileged helper, and then makes this process run as `user`.
+///
+/// This has to happen before any threads are started.
+pub fn drop_privileges(user: &str) -> Result<(), String> {
+    if unsafe { libc::geteuid() } != 0 {
+        return Err("--user only works when gestures is started as root".to_owned());
+    }
+    let c_user = CString::new(user).map_err(|_| format!("bad user name {:?}", user))?;
+    let pw = unsafe { libc::getpwnam(c_user.as_ptr()) };
+    if pw.is_null() {
+        return Err(format!("there is no user {:?}", user));
+    }
+    let (uid, gid, home) = unsafe {
+        ((*pw).pw_uid, (*pw).pw_gid, CStr::from_ptr((*pw).pw_dir).to_string_lossy().into_owned())
+    };
+    if uid == 0 {
+        return Err("--user needs an ordinary user, not root".to_owned());
+    }
+
+    let mut socks = [0; 2];
+    check(unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC, 0,
+                                    socks.as_mut_ptr()) })
+        .map_err(|e| format!("couldn't make a socket for the device helper: {}", e))?;
+    match check(unsafe { libc::fork() }) {
+        Err(e) => return Err(format!("couldn't start the device helper: {}", e)),
+        Ok(0) => {
+            unsafe {
+                libc::close(socks[0]);
+                // Don't outlive the daemon, even if it dies without closing the socket.
+                libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM);
+            }
+            serve(socks[1]);
+        },
+        Ok(pid) => {
+            debug!("started the device helper (pid {})", pid);
+            unsafe { libc::close(socks[1]); }
+        },
+    }
+
+    let ok = unsafe {
+        libc::initgroups(c_user.as_ptr(), gid) == 0 && libc::setgid(gid) == 0
+            && libc::setuid(uid) == 0
+    };
+    if !ok {
+        return Err(format!("couldn't switch to user {:?}: {}", user, io::Error::last_os_error()));
+    }
+    HELPER.store(socks[0] as isize + 1, Ordering::SeqCst);
+
+    // The environment still belongs to root, so the config files and the control socket would
+    // end up in the wrong places.
+    env::set_var("HOME", &home);
+    env::set_var("USER", user);
+    env::set_var("LOGNAME", user);
+    let runtime = format!("/run/user/{}", uid);
+    if Path::new(&runtime).is_dir() {
+        env::set_var("XDG_RUNTIME_DIR", &runtime);
+    } else {
+        env::remove_var("XDG_RUNTIME_DIR");
+    }
+    info!("running as {:?}, with a helper to open the devices", user);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allowed_paths() {
+        assert!(allowed("/dev/input/event12"));
+        assert!(allowed("/dev/uinput"));
+        assert!(!allowed("/dev/input/event"));
+        assert!(!allowed("/dev/input/event1/../../sda"));
+        assert!(!allowed("/dev/input/mice"));
+        assert!(!allowed("/etc/shadow"));
+    }
+}
@@ -0,0 +1,283 @@
+//! A terminal program for trying out the recognizers without a touch device.
+//!
+//! Dragging with the mouse in the terminal moves some virtual fingers, which are fed to the same
+//! recognizers that the daemon would build. The screen shows what each recognizer is doing, and
+//! the last gesture that was recognized. This only needs a terminal that reports mouse movement
+//! (xterm's "SGR" mouse mode), which most of them do.
+//!
+//! The fingers start out in a row, but while they are down the keyboard can spread them apart,
+//! squeeze them together or turn them, for trying out pinches and rotations. While they are down
+//! and holding still, the recognizers hear about the time passing, just like in the daemon, so
+//! that holds and timeouts work.
+
+use gestures_core::build_manager;
+use gestures_core::config::{Config, Source};
+use libc::{self, c_void};
+use libgestures::event::Event as TouchEvent;
+use libgestures::geom::Point;
+use libgestures::manager::Transition;
+use std::cell::RefCell;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::rc::Rc;
+use std::time::Instant;
+
+// How big (in millimeters) a character cell is. Cells are about twice as tall as they are wide.
+const CELL_WIDTH_MM: f64 = 2.0;
+const CELL_HEIGHT_MM: f64 = 4.0;
+// How far apart (in millimeters) the virtual fingers start out.
+const FINGER_SPACING_MM: f64 = 15.0;
+// How much one key press spreads or squeezes the fingers, and how far it turns them.
+const SPREAD_FACTOR: f64 = 1.1;
+const TURN_DEG: f64 = 10.0;
+// How often the recognizers hear about the time passing while fingers are down.
+const TICK_MS: i32 = 20;
+
+// The mouse buttons and modifiers, as xterm reports them.
+const BUTTON_MASK: u32 = 3;
+const SHIFT: u32 = 4;
+const CTRL: u32 = 16;
+const MOTION: u32 = 32;
+
+// Puts the terminal into raw mode and turns on mouse reporting, and undoes it all when dropped.
+struct RawTerminal {
+    saved: libc::termios,
+}
+
+impl RawTerminal {
+    fn new() -> io::Result<RawTerminal> {
+        let mut saved: libc::termios = unsafe { mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut saved) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut raw = saved;
+        unsafe { libc::cfmakeraw(&mut raw) };
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Switch to the alternate screen, hide the cursor, and report all mouse movement.
+        print!("\x1b[?1049h\x1b[?25l\x1b[?1003h\x1b[?1006h");
+        io::stdout().flush()?;
+        Ok(RawTerminal { saved: saved })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        print!("\x1b[?1006l\x1b[?1003l\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.saved) };
+    }
+}
+
+// Reads straight from the terminal, without the buffering of `io::Stdin`. Otherwise, input that
+// was already buffered would be invisible to `wait_for_input`.
+struct RawStdin;
+
+impl Read for RawStdin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let ptr = buf.as_mut_ptr() as *mut c_void;
+        let n = unsafe { libc::read(libc::STDIN_FILENO, ptr, buf.len()) };
+        if n < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(n as usize)
+        }
+    }
+}
+
+// Waits for up to `timeout_ms` (or forever, if it's negative) for some input, and returns false if
+// there wasn't any.
+fn wait_for_input(timeout_ms: i32) -> io::Result<bool> {
+    let mut fd = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+    match unsafe { libc::poll(&mut fd, 1, timeout_ms) } {
+        n if n < 0 => {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::Interrupted { Ok(false) } else { Err(e) }
+        },
+        n => Ok(n > 0),
+    }
+}
+
+// Something that happened in the terminal.
+enum Input {
+    Key(u8),
+    // The button code, the column and row (starting from 1), and whether it was a release.
+    Mouse(u32, u32, u32, bool),
+}
+
+// Reads the next input from the terminal, or returns `None` at the end of the input.
+fn read_input(stdin: &mut RawStdin) -> io::Result<Option<Input>> {
+    let mut byte = [0u8];
+    let mut next = || -> io::Result<Option<u8>> {
+        match stdin.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    };
+    match next()? {
+        Some(0x1b) => {},
+        Some(b) => return Ok(Some(Input::Key(b))),
+        None => return Ok(None),
+    }
+    if next()? != Some(b'[') || next()? != Some(b'<') {
+        // Some other escape sequence, which we don't care about.
+        return Ok(Some(Input::Key(0x1b)));
+    }
+
+    // The rest looks like "0;12;34M".
+    let mut fields = [0u32; 3];
+    let mut i = 0;
+    loop {
+        match next()? {
+            Some(c @ b'0'...b'9') if i < 3 => fields[i] = fields[i] * 10 + (c - b'0') as u32,
+            Some(b';') => i += 1,
+            Some(b'M') => return Ok(Some(Input::Mouse(fields[0], fields[1], fields[2], false))),
+            Some(b'm') => return Ok(Some(Input::Mouse(fields[0], fields[1], fields[2], true))),
+            Some(_) => return Ok(Some(Input::Key(0x1b))),
+            None => return Ok(None),
+        }
+    }
+}
+
+// Where `n` fingers in a row are, compared to the middle of the row.
+fn row_offsets(n: u8) -> Vec<Point> {
+    (0..n).map(|i| Point::new((i as f64 - (n - 1) as f64 / 2.0) * FINGER_SPACING_MM, 0.0))
+        .collect()
+}
+
+// Turns `p` counter-clockwise (as it looks on the screen, where y goes down) around the origin.
+fn turn(p: Point, degrees: f64) -> Point {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    Point::new(p.x * cos + p.y * sin, p.y * cos - p.x * sin)
+}
+
+fn time_ms(started: Instant) -> u32 {
+    let elapsed = started.elapsed();
+    elapsed.as_secs() as u32 * 1000 + elapsed.subsec_nanos() / 1_000_000
+}
+
+fn describe(t: Option<Transition>) -> &'static str {
+    match t {
+        None => "idle",
+        Some(Transition::Started) => "running",
+        Some(Transition::Failed) => "failed",
+        Some(Transition::Succeeded) => "succeeded",
+        Some(Transition::Cancelled) => "cancelled",
+        Some(Transition::Panicked) => "panicked",
+    }
+}
+
+/// Runs the simulator until the user presses `q`.
+pub fn run(config: &Config, profile: &str) -> Result<(), String> {
+    let (mut man, names) = build_manager(config, Source::Touch, profile, config.params);
+    let states = Rc::new(RefCell::new(vec![None; names.len()]));
+    let observer_states = states.clone();
+    man.set_observer(move |_, id, t| observer_states.borrow_mut()[id] = Some(t));
+
+    let _term = RawTerminal::new().map_err(|e| format!("couldn't set up the terminal: {}", e))?;
+    let mut stdin = RawStdin;
+    let started = Instant::now();
+    let mut base_fingers = 3;
+    // The middle of the fingers that are down, and where each of them is compared to it.
+    let mut center = Point::zero();
+    let mut offsets: Vec<Point> = Vec::new();
+    let mut last_gesture = String::from("none");
+
+    loop {
+        // While fingers are down, the recognizers need to know about the time passing, even if
+        // nothing moves.
+        let timeout = if offsets.is_empty() { -1 } else { TICK_MS };
+        if !wait_for_input(timeout).map_err(|e| e.to_string())? {
+            if let Some(r) = man.tick(time_ms(started)) {
+                if r.finished {
+                    last_gesture = r.value.to_string();
+                }
+            }
+        } else {
+            let mut events = Vec::new();
+            let mut moved = false;
+            match read_input(&mut stdin).map_err(|e| e.to_string())? {
+                None | Some(Input::Key(b'q')) | Some(Input::Key(3)) => return Ok(()),
+                Some(Input::Key(c @ b'1'...b'5')) => base_fingers = c - b'0',
+                Some(Input::Key(b'+')) | Some(Input::Key(b'=')) => {
+                    offsets = offsets.iter().map(|&p| p * SPREAD_FACTOR).collect();
+                    moved = true;
+                },
+                Some(Input::Key(b'-')) => {
+                    offsets = offsets.iter().map(|&p| p / SPREAD_FACTOR).collect();
+                    moved = true;
+                },
+                Some(Input::Key(b',')) => {
+                    offsets = offsets.iter().map(|&p| turn(p, TURN_DEG)).collect();
+                    moved = true;
+                },
+                Some(Input::Key(b'.')) => {
+                    offsets = offsets.iter().map(|&p| turn(p, -TURN_DEG)).collect();
+                    moved = true;
+                },
+                Some(Input::Key(_)) => {},
+                Some(Input::Mouse(code, col, row, release)) => {
+                    let pos = Point::new(col as f64 * CELL_WIDTH_MM, row as f64 * CELL_HEIGHT_MM);
+                    let left = code & BUTTON_MASK == 0;
+                    if release {
+                        for slot in 0..offsets.len() {
+                            events.push(TouchEvent::Up { slot: slot });
+                        }
+                        offsets.clear();
+                    } else if left && code & MOTION == 0 && offsets.is_empty() {
+                        let mut n = base_fingers;
+                        if code & SHIFT != 0 {
+                            n += 1;
+                        }
+                        if code & CTRL != 0 {
+                            n += 2;
+                        }
+                        center = pos;
+                        offsets = row_offsets(n);
+                        for (slot, &p) in offsets.iter().enumerate() {
+                            events.push(TouchEvent::Down { slot: slot, pos: center + p });
+                        }
+                    } else if left && code & MOTION != 0 && !offsets.is_empty() {
+                        center = pos;
+                        moved = true;
+                    }
+                },
+            }
+            if moved {
+                for (slot, &p) in offsets.iter().enumerate() {
+                    events.push(TouchEvent::Motion { slot: slot, pos: center + p });
+                }
+            }
+
+            if !events.is_empty() {
+                events.push(TouchEvent::Frame { time: time_ms(started) });
+                for ev in &events {
+                    if let Some(g) = man.update_event(ev) {
+                        last_gesture = g.to_string();
+                    }
+                }
+            }
+        }
+
+        // Redraw everything.
+        let mut screen = String::from("\x1b[2J\x1b[H");
+        screen.push_str("Drag with the mouse to move the fingers. 1-5 sets the number of fingers; \
+                         shift adds one and ctrl adds two. While dragging, + and - spread and \
+                         squeeze the fingers, and , and . turn them. q quits.\r\n\r\n");
+        screen.push_str(&format!("fingers: {} ({} down)\r\n", base_fingers, offsets.len()));
+        screen.push_str(&format!("last gesture: {}\r\n\r\nrecognizers:\r\n", last_gesture));
+        for (id, name) in names.iter().enumerate() {
+            let state = describe(states.borrow()[id]);
+            screen.push_str(&format!("  #{} {}: {}\r\n", id, name, state));
+        }
+        for (i, &p) in offsets.iter().enumerate() {
+            let p = center + p;
+            let col = (p.x / CELL_WIDTH_MM).round().max(1.0);
+            let row = (p.y / CELL_HEIGHT_MM).round().max(1.0);
+            screen.push_str(&format!("\x1b[{};{}H{}", row, col, i + 1));
+        }
+        print!("{}", screen);
+        io::stdout().flush().map_err(|e| e.to_string())?;
+    }
+}
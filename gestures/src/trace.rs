@@ -0,0 +1,281 @@
+use chan_signal;
+use chan_signal::Signal;
+use gestures_core::{build_manager, Gesture};
+use gestures_core::config::{Config, Source};
+use input::event::Event;
+use libgestures::event;
+use libgestures::manager::{Manager, ManagerState, Transition};
+use libgestures::trace;
+use log::{self, LevelFilter};
+use std::cell::Cell;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::rc::Rc;
+
+use libinput;
+
+/// Records all the touch events to a trace file, until we're interrupted.
+pub fn record(path: &str) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let signal = chan_signal::notify(&[Signal::INT, Signal::TERM]);
+    let mut input = libinput::input()
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "couldn't initialize libinput"))?;
+
+    // Consume the initial events.
+    input.libinput.dispatch()?;
+    while let Some(_) = input.libinput.next() {
+    }
+
+    println!("recording touch events to {}; press Ctrl-C to stop", path);
+    let poll = input.poll;
+    // The events of the frame that hasn't ended yet. Whole frames are written at once, so that
+    // each one can be followed by its checksum.
+    let mut frame = Vec::new();
+    loop {
+        chan_select! {
+            poll.recv() => {
+                input.libinput.dispatch()?;
+                while let Some(ev) = input.libinput.next() {
+                    if let Event::Touch(ev) = ev {
+                        let ev = event::Event::from(&ev);
+                        frame.push(ev);
+                        if let event::Event::Frame { .. } = ev {
+                            trace::write_frame(&mut out, &frame)?;
+                            frame.clear();
+                        }
+                    }
+                }
+            },
+            signal.recv() -> _ => {
+                break;
+            },
+        }
+    }
+    out.flush()
+}
+
+/// What to do next, while stepping through a trace.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Step {
+    /// Replay the next frame.
+    Next,
+    /// Go back this many frames.
+    Back(usize),
+    /// Go to the frame with this number (counting from 1), backwards or forwards.
+    Goto(usize),
+    /// Replay the rest of the trace without stopping.
+    Continue,
+    Quit,
+}
+
+const STEP_HELP: &'static str = "commands: (n)ext (the default), (b)ack [FRAMES], (g)oto FRAME, \
+                                 (c)ontinue, (q)uit";
+
+fn parse_step(line: &str) -> Option<Step> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    let num = |w: Option<&&str>, default: Option<usize>| match w {
+        Some(w) => w.parse().ok(),
+        None => default,
+    };
+    match words.get(0).cloned() {
+        None | Some("n") | Some("next") if words.len() <= 1 => Some(Step::Next),
+        Some("b") | Some("back") if words.len() <= 2 => num(words.get(1), Some(1)).map(Step::Back),
+        Some("g") | Some("goto") if words.len() == 2 => num(words.get(1), None).map(Step::Goto),
+        Some("c") | Some("continue") if words.len() == 1 => Some(Step::Continue),
+        Some("q") | Some("quit") if words.len() == 1 => Some(Step::Quit),
+        _ => None,
+    }
+}
+
+// Asks what to do after frame `frame_num`. The end of the input means quitting.
+fn prompt(frame_num: usize, num_frames: usize) -> Result<Step, String> {
+    let stdin = io::stdin();
+    loop {
+        print!("[frame {}/{}] ", frame_num, num_frames);
+        io::stdout().flush().map_err(|e| e.to_string())?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            println!("");
+            return Ok(Step::Quit);
+        }
+        match parse_step(&line) {
+            Some(step) => return Ok(step),
+            None => println!("{}", STEP_HELP),
+        }
+    }
+}
+
+// How often (in frames) the explainer saves the state of the recognizers, so that it doesn't have
+// to replay the whole trace to go back.
+const CHECKPOINT_FRAMES: usize = 100;
+
+/// Replays a trace through the recognizers, and describes what happens.
+struct Explainer<'a> {
+    config: &'a Config,
+    profile: &'a str,
+    man: Manager<Gesture>,
+    // While this is set, nothing is described. This is for catching up after rewinding.
+    quiet: Rc<Cell<bool>>,
+    // How many frames have been replayed.
+    pos: usize,
+    recognized: usize,
+    // The state of the recognizers after some of the frames, with `pos` and `recognized` then.
+    checkpoints: Vec<(usize, usize, ManagerState)>,
+}
+
+impl<'a> Explainer<'a> {
+    fn new(config: &'a Config, profile: &'a str) -> Explainer<'a> {
+        let quiet = Rc::new(Cell::new(false));
+        let (man, names) = Explainer::build(config, profile, &quiet);
+        println!("recognizers:");
+        for (id, name) in names.iter().enumerate() {
+            println!("  #{}: {}", id, name);
+        }
+        Explainer {
+            config: config,
+            profile: profile,
+            man: man,
+            quiet: quiet,
+            pos: 0,
+            recognized: 0,
+            checkpoints: Vec::new(),
+        }
+    }
+
+    fn build(config: &Config, profile: &str, quiet: &Rc<Cell<bool>>)
+    -> (Manager<Gesture>, Vec<String>) {
+        let (mut man, names) = build_manager(config, Source::Touch, profile, config.params);
+        let observer_names = names.clone();
+        let quiet = quiet.clone();
+        man.set_observer(move |frame, id, t| {
+            if quiet.get() {
+                return;
+            }
+            let what = match t {
+                Transition::Started => "started",
+                Transition::Failed => "failed",
+                Transition::Succeeded => "succeeded",
+                Transition::Cancelled => "cancelled by a higher-priority gesture",
+                Transition::Panicked => "panicked, and was disabled",
+            };
+            println!("  #{} ({}) {}, with {} fingers down", id, observer_names[id], what,
+                     frame.cur.num_down);
+        });
+        (man, names)
+    }
+
+    // Replays the next frame.
+    fn replay(&mut self, frame: &[event::Event]) {
+        self.pos += 1;
+        let quiet = self.quiet.get();
+        for ev in frame {
+            if let event::Event::Frame { time } = *ev {
+                if !quiet {
+                    println!("frame {} at {} ms:", self.pos, time);
+                }
+            }
+            if let Some(g) = self.man.update_event(ev) {
+                self.recognized += 1;
+                if !quiet {
+                    println!("  => recognized {}", g);
+                    if !self.config.bindings.contains_key(&g) {
+                        println!("  => but nothing is bound to it");
+                    }
+                }
+            }
+        }
+        let saved = self.checkpoints.last().map_or(0, |c| c.0);
+        if self.pos % CHECKPOINT_FRAMES == 0 && self.pos > saved {
+            // In the middle of a very long touch, there might not be a state to save.
+            if let Some(state) = self.man.snapshot() {
+                self.checkpoints.push((self.pos, self.recognized, state));
+            }
+        }
+    }
+
+    // Replays (without describing them) all the frames before `target`. The recognizers only go
+    // forwards, so going back means starting new ones, from the last checkpoint before `target`
+    // (or from the beginning).
+    fn seek(&mut self, frames: &[&[event::Event]], target: usize) {
+        // The recognizers' debug logs would describe the frames too.
+        let level = log::max_level();
+        log::set_max_level(LevelFilter::Off);
+        self.quiet.set(true);
+
+        let checkpoint = self.checkpoints.iter().rev().find(|c| c.0 < target).cloned();
+        let from = checkpoint.as_ref().map_or(0, |c| c.0);
+        if target <= self.pos || from > self.pos {
+            self.man = Explainer::build(self.config, self.profile, &self.quiet).0;
+            self.pos = 0;
+            self.recognized = 0;
+            if let Some((pos, recognized, state)) = checkpoint {
+                // This only fails if the recognizers were built differently, which they aren't.
+                // Even then, starting from the beginning still works.
+                if self.man.restore(&state).is_ok() {
+                    self.pos = pos;
+                    self.recognized = recognized;
+                } else {
+                    self.man = Explainer::build(self.config, self.profile, &self.quiet).0;
+                }
+            }
+        }
+        while self.pos + 1 < target {
+            let frame = frames[self.pos];
+            self.replay(frame);
+        }
+
+        self.quiet.set(false);
+        log::set_max_level(level);
+    }
+}
+
+/// Replays a trace file through the recognizers for `profile` in `config`, describing what happens
+/// to each one.
+///
+/// If `step` is set, this stops after each frame and asks what to do next, which can be to go
+/// back (or forwards) to another frame.
+pub fn explain(path: &str, config: &Config, profile: &str, step: bool) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| format!("couldn't open {}: {}", path, e))?;
+    let events = trace::read(BufReader::new(file))?;
+    let frames = trace::frames(&events);
+    let mut ex = Explainer::new(config, profile);
+    let mut stepping = step;
+    if stepping {
+        println!("{}", STEP_HELP);
+    }
+
+    while ex.pos < frames.len() {
+        let frame = frames[ex.pos];
+        ex.replay(frame);
+        if !stepping {
+            continue;
+        }
+        match prompt(ex.pos, frames.len())? {
+            Step::Next => {},
+            Step::Back(n) => ex.seek(&frames, ex.pos.saturating_sub(n).max(1)),
+            Step::Goto(n) => ex.seek(&frames, n.max(1).min(frames.len())),
+            Step::Continue => stepping = false,
+            Step::Quit => return Ok(()),
+        }
+    }
+
+    println!("{} frames, {} gestures recognized", frames.len(), ex.recognized);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps() {
+        assert_eq!(parse_step("\n"), Some(Step::Next));
+        assert_eq!(parse_step("b"), Some(Step::Back(1)));
+        assert_eq!(parse_step("back 5"), Some(Step::Back(5)));
+        assert_eq!(parse_step("g 12"), Some(Step::Goto(12)));
+        assert_eq!(parse_step("goto"), None);
+        assert_eq!(parse_step("c"), Some(Step::Continue));
+        assert_eq!(parse_step("sideways"), None);
+    }
+}
@@ -0,0 +1,188 @@
+/*
+ * The C interface to libgestures.
+ *
+ * Build libgestures with `cargo build --features ffi`, and link against the resulting
+ * `liblibgestures.so`. For WebAssembly, build with
+ * `cargo build --target wasm32-unknown-unknown --no-default-features --features ffi`, and see
+ * `web/gestures.js` for the JavaScript side.
+ *
+ * The typical use is:
+ *
+ *   GesturesSet *set = gestures_set_new();
+ *   GesturesGesture swipe = { .kind = GESTURES_SWIPE, .num_fingers = 3,
+ *                             .direction = GESTURES_UP };
+ *   gestures_set_add(set, &swipe);
+ *   GesturesManager *man = gestures_manager_new(set);
+ *   gestures_set_free(set);
+ *   gestures_manager_set_callbacks(man, on_gesture, NULL, my_data);
+ *
+ *   // For each touch event:
+ *   gestures_manager_update(man, &ev);
+ *
+ *   gestures_manager_free(man);
+ *
+ * All positions are in millimeters, and all times are in milliseconds. Every function accepts
+ * NULL pointers, and does nothing (or returns an error) when it gets one. None of the objects
+ * may be used from more than one thread at a time.
+ */
+
+#ifndef LIBGESTURES_H
+#define LIBGESTURES_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The kinds of touch event. */
+#define GESTURES_EVENT_DOWN 0
+#define GESTURES_EVENT_UP 1
+#define GESTURES_EVENT_MOTION 2
+#define GESTURES_EVENT_CANCEL 3
+#define GESTURES_EVENT_FRAME 4
+#define GESTURES_EVENT_SHAPE 5
+
+/* The kinds of gesture. */
+#define GESTURES_SWIPE 0
+#define GESTURES_SPREAD 1
+#define GESTURES_GRAB 2
+#define GESTURES_SHAKE 3
+#define GESTURES_PATH 4
+#define GESTURES_EDGE_SWIPE 5
+#define GESTURES_TAP 6
+#define GESTURES_HOLD 7
+#define GESTURES_PINCH 8
+#define GESTURES_ROTATE 9
+
+/* Directions, for swipes and paths. */
+#define GESTURES_UP 0
+#define GESTURES_DOWN 1
+#define GESTURES_LEFT 2
+#define GESTURES_RIGHT 3
+
+/* Directions, for pinches. */
+#define GESTURES_PINCH_IN 0
+#define GESTURES_PINCH_OUT 1
+
+/* Directions, for rotations. */
+#define GESTURES_CLOCKWISE 0
+#define GESTURES_COUNTERCLOCKWISE 1
+
+/* Edges of the touch surface, for edge swipes. */
+#define GESTURES_EDGE_TOP 0
+#define GESTURES_EDGE_BOTTOM 1
+#define GESTURES_EDGE_LEFT 2
+#define GESTURES_EDGE_RIGHT 3
+
+/* The longest path gesture. */
+#define GESTURES_MAX_PATH_LEN 4
+
+/*
+ * A touch event, like the ones that libinput produces.
+ *
+ * A finger comes down in `slot` at (`x`, `y`) (DOWN), moves to (`x`, `y`) (MOTION), or goes up
+ * (UP). The touch sequence can be cancelled (CANCEL). A FRAME event, with a timestamp in `time`,
+ * ends each group of events that happened at the same time. Fields that an event doesn't use are
+ * ignored.
+ *
+ * Devices that know how big their contacts are can also say that the finger in `slot` is `x`
+ * millimeters across its long side and presses with pressure `y`, from 0 to 1 (SHAPE).
+ */
+typedef struct {
+    uint32_t kind;
+    uint32_t slot;
+    double x;
+    double y;
+    uint32_t time;
+} GesturesEvent;
+
+/*
+ * A gesture.
+ *
+ * Every gesture except an edge swipe uses `num_fingers`. Swipes, pinches and rotations use
+ * `direction` (with their own constants), edge swipes use `edge`, and paths use the first
+ * `path_len` entries of `path`, which are directions. A path must have at least two segments,
+ * and no two segments in a row may go the same way.
+ */
+typedef struct {
+    uint32_t kind;
+    uint8_t num_fingers;
+    uint32_t direction;
+    uint32_t edge;
+    uint32_t path[GESTURES_MAX_PATH_LEN];
+    uint32_t path_len;
+} GesturesGesture;
+
+/* A set of gestures to recognize. */
+typedef struct GesturesSet GesturesSet;
+
+/* Recognizes gestures in a stream of touch events. */
+typedef struct GesturesManager GesturesManager;
+
+/* Called with a gesture that was recognized. The gesture is only valid during the call. */
+typedef void (*GesturesGestureCallback)(void *user_data, const GesturesGesture *gesture);
+
+/*
+ * Called while a progressive swipe is happening, with its number of fingers, its direction, and
+ * how far it has gone (from 0 to 1). `done` is 1 on the last call for each swipe, and 0 before.
+ */
+typedef void (*GesturesProgressCallback)(void *user_data, uint8_t num_fingers,
+                                         uint32_t direction, double progress, int done);
+
+/* Allocate and free events and gestures on the heap. C programs can just put them on the stack,
+ * but this is useful for callers that can't, like JavaScript using the WebAssembly build. */
+GesturesEvent *gestures_event_new(void);
+void gestures_event_free(GesturesEvent *event);
+GesturesGesture *gestures_gesture_new(void);
+void gestures_gesture_free(GesturesGesture *gesture);
+
+GesturesSet *gestures_set_new(void);
+void gestures_set_free(GesturesSet *set);
+
+/* Adds a gesture to the set. Returns 0, or -1 if the gesture isn't valid. */
+int gestures_set_add(GesturesSet *set, const GesturesGesture *gesture);
+
+/* Sets the size of the touch surface in millimeters. Edge swipes are only recognized after this
+ * is set. */
+void gestures_set_surface_size(GesturesSet *set, double width, double height);
+
+/* Creates a manager that recognizes the gestures in `set`. Changing `set` afterwards doesn't
+ * affect the manager, and it may be freed. */
+GesturesManager *gestures_manager_new(const GesturesSet *set);
+void gestures_manager_free(GesturesManager *man);
+
+/* Adds a swipe with `num_fingers` fingers that reports its progress, reaching 1 after the fingers
+ * have moved `full_travel_mm` millimeters. Returns 0, or -1 if `man` is NULL. */
+int gestures_manager_add_progressive_swipe(GesturesManager *man, uint8_t num_fingers,
+                                           double full_travel_mm);
+
+/* Sets the functions to call with recognized gestures and progress reports. Either of them may
+ * be NULL. `user_data` is passed to both of them. */
+void gestures_manager_set_callbacks(GesturesManager *man, GesturesGestureCallback on_gesture,
+                                    GesturesProgressCallback on_progress, void *user_data);
+
+/* Feeds an event to the manager, calling one of the callbacks if something was recognized.
+ * Returns 1 if a gesture was recognized or made progress, 0 if not, or -1 if the event isn't
+ * valid. */
+int gestures_manager_update(GesturesManager *man, const GesturesEvent *event);
+
+/* Lets time pass without any touch events, which holds need in order to be recognized while the
+ * fingers stay still. `time` is in milliseconds, on the same clock as the frames. Returns 1 if a
+ * gesture was recognized or made progress, and 0 if not. */
+int gestures_manager_tick(GesturesManager *man, uint32_t time);
+
+/* Copies the last gesture that was recognized into `out`, and forgets it. This is for callers that
+ * would rather poll than get callbacks. Returns 1 if there was such a gesture, and 0 if not. */
+int gestures_manager_take_gesture(GesturesManager *man, GesturesGesture *out);
+
+/* Returns 1 if the manager has nothing to do until a finger comes down, and 0 otherwise. A caller
+ * that only feeds the manager frames while there are fingers down should keep feeding it frames
+ * until this returns 1. */
+int gestures_manager_is_idle(const GesturesManager *man);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
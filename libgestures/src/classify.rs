@@ -0,0 +1,358 @@
+//! Deciding what kind of thing each contact is.
+//!
+//! Not everything that touches the surface is a finger making a gesture: a thumb resting on a
+//! clickpad, for example, would turn a three-finger swipe into a four-finger one that barely
+//! moves. Every contact in a [`Snapshot`](../frame/struct.Snapshot.html) has a
+//! [`ContactKind`](enum.ContactKind.html), which starts out as `Unknown` when the contact comes
+//! down. At the end of every frame, and before the recognizers see it, the
+//! [`Classifier`](trait.Classifier.html)s that were added to the `Manager` (with
+//! [`add_classifier`](../manager/struct.Manager.html#method.add_classifier)) update the labels,
+//! using whatever they know about the contacts' size, pressure, position and motion.
+//!
+//! The labels are used in two ways:
+//!
+//! - The `Frame` leaves out some kinds of contact altogether (thumbs and palms, unless
+//!   [`Manager::set_left_out`](../manager/struct.Manager.html#method.set_left_out) says
+//!   otherwise), so they don't count towards `num_down` and they don't move the mean position,
+//!   until they lift. Whatever was going on when one of them is left out fails, and the
+//!   recognizers start again without it.
+//! - A recognizer can opt in to only counting some kinds of contact, with
+//!   [`Recognizer::counting`](../recognizer/trait.Recognizer.html#method.counting).
+
+use frame::Frame;
+use geom::Point;
+use params::{ShapeParams, ThumbParams};
+use std::fmt::{self, Debug};
+use std::str::FromStr;
+
+/// What kind of thing a contact is.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ContactKind {
+    /// Nothing has decided yet, which is what happens when no classifier knows enough about
+    /// the contact.
+    Unknown,
+    Finger,
+    Thumb,
+    Palm,
+    /// The tip of a stylus that the device reports as a touch.
+    Pen,
+}
+
+const ALL_KINDS: [ContactKind; 5] = [
+    ContactKind::Unknown,
+    ContactKind::Finger,
+    ContactKind::Thumb,
+    ContactKind::Palm,
+    ContactKind::Pen,
+];
+
+impl ContactKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ContactKind::Unknown => "unknown",
+            ContactKind::Finger => "finger",
+            ContactKind::Thumb => "thumb",
+            ContactKind::Palm => "palm",
+            ContactKind::Pen => "pen",
+        }
+    }
+}
+
+impl fmt::Display for ContactKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ContactKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ContactKind, String> {
+        ALL_KINDS.iter()
+            .find(|k| k.name() == s)
+            .cloned()
+            .ok_or_else(|| format!("unknown kind of contact {:?}", s))
+    }
+}
+
+/// A set of [`ContactKind`](enum.ContactKind.html)s.
+///
+/// # Examples
+/// ```
+/// use libgestures::classify::{ContactKind, Kinds};
+///
+/// let kinds: Kinds = "finger unknown".parse().unwrap();
+/// assert!(kinds.contains(ContactKind::Finger));
+/// assert!(!kinds.contains(ContactKind::Thumb));
+/// assert_eq!(kinds, Kinds::of(&[ContactKind::Unknown, ContactKind::Finger]));
+/// assert_eq!(kinds.to_string(), "unknown finger");
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Kinds(u8);
+
+impl Kinds {
+    pub fn none() -> Kinds {
+        Kinds(0)
+    }
+
+    pub fn all() -> Kinds {
+        Kinds::of(&ALL_KINDS)
+    }
+
+    pub fn of(kinds: &[ContactKind]) -> Kinds {
+        Kinds(kinds.iter().fold(0, |acc, &k| acc | (1 << k as u8)))
+    }
+
+    pub fn contains(&self, k: ContactKind) -> bool {
+        self.0 & (1 << k as u8) != 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=ContactKind> {
+        let set = *self;
+        ALL_KINDS.iter().cloned().filter(move |&k| set.contains(k))
+    }
+}
+
+/// Writes the kinds separated by spaces, in the form that `from_str` reads.
+impl fmt::Display for Kinds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = self.iter().map(|k| k.name()).collect::<Vec<_>>();
+        f.write_str(&names.join(" "))
+    }
+}
+
+impl FromStr for Kinds {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Kinds, String> {
+        let kinds = s.split_whitespace()
+            .map(str::parse::<ContactKind>)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Kinds::of(&kinds))
+    }
+}
+
+/// Decides what kind of thing some of the contacts are.
+pub trait Classifier: Debug {
+    /// Updates the labels of the contacts at the end of a frame.
+    ///
+    /// `kinds` is indexed by slot, like `frame.cur.down`. It holds the labels from the last
+    /// frame, as updated by the classifiers that ran before this one; a contact that just came
+    /// down starts out as `Unknown`. A classifier changes the labels of the contacts that it has
+    /// an opinion about, and leaves the others alone.
+    ///
+    /// This is called at the end of every frame, so that the classifier can keep track of the
+    /// contacts over time. `frame.cur` only has the contacts that are still counted; the ones
+    /// that were left out before are already gone.
+    fn classify(&mut self, frame: &Frame, kinds: &mut [ContactKind]);
+}
+
+/// Labels contacts by their size and pressure.
+///
+/// A contact that is big enough or presses hard enough is a `Palm`, one that is small enough is
+/// a `Pen`, and anything else is a `Finger`. This needs a device that sends
+/// [`Shape`](../event/enum.Event.html#variant.Shape) events; it leaves the contacts of other
+/// devices alone.
+#[derive(Clone, Debug)]
+pub struct ShapeClassifier {
+    params: ShapeParams,
+}
+
+impl ShapeClassifier {
+    pub fn new(params: ShapeParams) -> ShapeClassifier {
+        ShapeClassifier {
+            params: params,
+        }
+    }
+}
+
+impl Classifier for ShapeClassifier {
+    fn classify(&mut self, frame: &Frame, kinds: &mut [ContactKind]) {
+        let p = &self.params;
+        for (slot, _) in frame.cur.fingers() {
+            let size = frame.cur.size[slot];
+            let pressure = frame.cur.pressure[slot];
+            if size == 0.0 && pressure == 0.0 {
+                // The device didn't say.
+                continue;
+            }
+            let big = p.palm_size_mm > 0.0 && size >= p.palm_size_mm;
+            let hard = p.palm_pressure > 0.0 && pressure >= p.palm_pressure;
+            kinds[slot] = if big || hard {
+                ContactKind::Palm
+            } else if size > 0.0 && size <= p.pen_size_mm {
+                ContactKind::Pen
+            } else {
+                ContactKind::Finger
+            };
+        }
+    }
+}
+
+// What a `ThumbDetector` remembers about a contact.
+#[derive(Clone, Copy, Debug)]
+struct Contact {
+    down_time: u32,
+    start: Point,
+    // True if the contact came down near the bottom edge, and hasn't strayed since.
+    resting: bool,
+}
+
+/// Labels a thumb that rests near the bottom edge of the surface while other fingers make a
+/// gesture.
+///
+/// A contact counts as a resting thumb if it came down within `edge_mm` of the bottom edge, it
+/// hasn't moved more than `max_move_mm` since, and it had been down for at least `min_rest_ms`
+/// when another finger came down. A thumb on its own is never labelled, so that it can still
+/// make one-finger gestures. Neither are contacts that some other classifier already decided
+/// are palms or pens.
+///
+/// # Examples
+/// ```
+/// use libgestures::Recognizer;
+/// use libgestures::classify::ThumbDetector;
+/// use libgestures::event::Event;
+/// use libgestures::geom::Point;
+/// use libgestures::gestures::primitive::NFingers;
+/// use libgestures::manager::Manager;
+/// use libgestures::params::ThumbParams;
+///
+/// let mut man = Manager::new();
+/// man.push(NFingers::new(2).map_outcome(|_| "two fingers"));
+/// let params = ThumbParams { edge_mm: 10.0, ..Default::default() };
+/// man.add_classifier(ThumbDetector::new(60.0, params));
+///
+/// // A thumb rests near the bottom of a surface that is 60mm tall...
+/// man.update_event(&Event::Down { slot: 0, pos: Point::new(50.0, 55.0) });
+/// assert_eq!(man.update_event(&Event::Frame { time: 0 }), None);
+/// // ...and then two fingers come down, which makes two fingers and not three.
+/// man.update_event(&Event::Down { slot: 1, pos: Point::new(40.0, 20.0) });
+/// man.update_event(&Event::Down { slot: 2, pos: Point::new(60.0, 20.0) });
+/// assert_eq!(man.update_event(&Event::Frame { time: 500 }), Some("two fingers"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ThumbDetector {
+    params: ThumbParams,
+    height_mm: f64,
+    // Indexed by slot.
+    contacts: Vec<Option<Contact>>,
+}
+
+impl ThumbDetector {
+    /// Creates a `ThumbDetector` for a surface that is `height_mm` tall.
+    pub fn new(height_mm: f64, params: ThumbParams) -> ThumbDetector {
+        ThumbDetector {
+            params: params,
+            height_mm: height_mm,
+            contacts: Vec::new(),
+        }
+    }
+}
+
+impl Classifier for ThumbDetector {
+    fn classify(&mut self, frame: &Frame, kinds: &mut [ContactKind]) {
+        let cur = &frame.cur;
+        if self.contacts.len() < cur.down.len() {
+            self.contacts.resize(cur.down.len(), None);
+        }
+        let mut new_fingers = false;
+        for (slot, c) in self.contacts.iter_mut().enumerate() {
+            if !cur.is_down(slot) {
+                *c = None;
+                continue;
+            }
+            let pos = cur.pos[slot];
+            match *c {
+                Some(ref mut c) => {
+                    if (pos - c.start).length() > self.params.max_move_mm {
+                        c.resting = false;
+                    }
+                },
+                None => {
+                    new_fingers = true;
+                    *c = Some(Contact {
+                        down_time: frame.time,
+                        start: pos,
+                        resting: pos.y >= self.height_mm - self.params.edge_mm,
+                    });
+                },
+            }
+        }
+        if !new_fingers || cur.num_down < 2 {
+            return;
+        }
+
+        let min_rest = self.params.min_rest_ms;
+        for (slot, c) in self.contacts.iter().enumerate() {
+            let c = match *c {
+                Some(c) => c,
+                None => continue,
+            };
+            let other = kinds[slot] == ContactKind::Palm || kinds[slot] == ContactKind::Pen;
+            if c.resting && !other && frame.time.wrapping_sub(c.down_time) >= min_rest {
+                kinds[slot] = ContactKind::Thumb;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use event::Event;
+    use frame::FrameTracker;
+
+    fn params() -> ThumbParams {
+        ThumbParams { edge_mm: 10.0, max_move_mm: 2.0, min_rest_ms: 300 }
+    }
+
+    fn classify(c: &mut Classifier, frame: &Frame) -> Vec<ContactKind> {
+        let mut kinds = frame.cur.kind.clone();
+        c.classify(frame, &mut kinds);
+        kinds
+    }
+
+    // Puts a thumb down at `thumb`, and then two fingers down `ms` later, returning the labels.
+    fn thumb_then_fingers(thumb: Point, ms: u32, wiggle: f64) -> Vec<ContactKind> {
+        let mut d = ThumbDetector::new(60.0, params());
+        let mut t = FrameTracker::new();
+        t.push_event(&Event::Down { slot: 0, pos: thumb });
+        assert_eq!(classify(&mut d, t.end_frame(0)), vec![ContactKind::Unknown]);
+        t.push_event(&Event::Motion { slot: 0, pos: thumb + Point::new(wiggle, 0.0) });
+        classify(&mut d, t.end_frame(ms / 2));
+        t.push_event(&Event::Down { slot: 1, pos: Point::new(40.0, 20.0) });
+        t.push_event(&Event::Down { slot: 2, pos: Point::new(60.0, 20.0) });
+        classify(&mut d, t.end_frame(ms))
+    }
+
+    #[test]
+    fn resting_thumb() {
+        let thumb = |kinds: Vec<ContactKind>| kinds[0] == ContactKind::Thumb;
+        assert!(thumb(thumb_then_fingers(Point::new(50.0, 55.0), 500, 0.5)));
+        // Too high up.
+        assert!(!thumb(thumb_then_fingers(Point::new(50.0, 40.0), 500, 0.5)));
+        // Not there for long enough.
+        assert!(!thumb(thumb_then_fingers(Point::new(50.0, 55.0), 100, 0.5)));
+        // Moving.
+        assert!(!thumb(thumb_then_fingers(Point::new(50.0, 55.0), 500, 5.0)));
+    }
+
+    #[test]
+    fn shapes() {
+        let mut c = ShapeClassifier::new(ShapeParams::default());
+        let mut t = FrameTracker::new();
+        for (slot, &size) in [0.0, 8.0, 30.0, 1.5].iter().enumerate() {
+            let pos = Point::new(10.0 * slot as f64, 0.0);
+            t.push_event(&Event::Down { slot: slot, pos: pos });
+            if size > 0.0 {
+                t.push_event(&Event::Shape { slot: slot, size: size, pressure: 0.2 });
+            }
+        }
+        assert_eq!(classify(&mut c, t.end_frame(0)), vec![
+            ContactKind::Unknown,
+            ContactKind::Finger,
+            ContactKind::Palm,
+            ContactKind::Pen,
+        ]);
+    }
+}
@@ -0,0 +1,321 @@
+//! Recognizing gestures from several groups of fingers at once.
+//!
+//! A [`Manager`](../manager/struct.Manager.html) treats all the fingers on a surface as taking
+//! part in a single gesture. On a large touchscreen, that isn't always true: one hand might be
+//! scrolling while the other one taps. A [`ClusteredManager`](struct.ClusteredManager.html)
+//! divides the fingers into clusters of nearby fingers, and runs a separate `Manager` for each
+//! cluster.
+
+use euclid::vec2;
+use std::collections::HashMap;
+
+use event::Event;
+use geom::Point;
+use manager::{Manager, Recognized};
+
+struct Cluster<T> {
+    id: usize,
+    man: Manager<T>,
+    // The slots of the fingers in this cluster.
+    slots: Vec<usize>,
+    // The events for this cluster since the last frame.
+    events: Vec<Event>,
+    // Set if the fingers in this cluster changed because of a merge or a split, which means that
+    // its `Manager` needs to start over.
+    restart: bool,
+}
+
+/// Runs a separate [`Manager`](../manager/struct.Manager.html) for each group of nearby fingers.
+///
+/// When a finger comes down within `radius` millimeters of the middle of an existing cluster, it
+/// joins that cluster; otherwise, it starts a new one. If two clusters move within `radius` of
+/// each other they are merged, and if a finger moves more than twice `radius` away from the rest
+/// of its cluster, it is split off into a cluster of its own. In either case, the gestures that
+/// the affected clusters were in the middle of are abandoned, and they start over with a new
+/// `Manager`, as though their fingers had just come down.
+///
+/// Each cluster has an id, which stays the same for as long as the cluster exists. A cluster
+/// exists until its last finger goes up and its `Manager` becomes
+/// [idle](../manager/struct.Manager.html#method.is_idle).
+///
+/// # Examples
+/// ```
+/// use libgestures::cluster::ClusteredManager;
+/// use libgestures::event::Event;
+/// use libgestures::geom::Point;
+/// use libgestures::manager::Manager;
+///
+/// let mut man = ClusteredManager::<()>::new(|| Manager::new());
+/// man.update_event(&Event::Down { slot: 0, pos: Point::new(10.0, 10.0) });
+/// man.update_event(&Event::Down { slot: 1, pos: Point::new(200.0, 10.0) });
+/// man.update_event(&Event::Frame { time: 0 });
+/// assert_eq!(man.num_clusters(), 2);
+/// ```
+pub struct ClusteredManager<T> {
+    factory: Box<FnMut() -> Manager<T>>,
+    clusters: Vec<Cluster<T>>,
+    // The id of the cluster that each finger belongs to, by slot.
+    owners: HashMap<usize, usize>,
+    // The position of each finger that is down, by slot.
+    positions: HashMap<usize, Point>,
+    radius: f64,
+    next_id: usize,
+}
+
+impl<T> ClusteredManager<T> {
+    /// Creates a new `ClusteredManager`, which calls `factory` to create the `Manager` for each
+    /// cluster.
+    ///
+    /// The default `radius` is 60 millimeters, which is about the size of a hand.
+    pub fn new<F: FnMut() -> Manager<T> + 'static>(factory: F) -> ClusteredManager<T> {
+        ClusteredManager {
+            factory: Box::new(factory),
+            clusters: Vec::new(),
+            owners: HashMap::new(),
+            positions: HashMap::new(),
+            radius: 60.0,
+            next_id: 0,
+        }
+    }
+
+    /// Sets how close together (in millimeters) fingers must be to belong to the same cluster.
+    pub fn set_radius(&mut self, mm: f64) {
+        self.radius = mm;
+    }
+
+    /// Returns the number of clusters, including the ones whose fingers have all gone up but whose
+    /// `Manager`s aren't idle yet.
+    pub fn num_clusters(&self) -> usize {
+        self.clusters.len()
+    }
+
+    /// Returns the ids of the clusters, and the slots of the fingers in each of them.
+    pub fn clusters(&self) -> Vec<(usize, Vec<usize>)> {
+        self.clusters.iter().map(|c| (c.id, c.slots.clone())).collect()
+    }
+
+    /// Updates the clusters with a new event.
+    ///
+    /// Returns the outcomes (and progress reports) of all the clusters whose `Manager`s returned
+    /// something, together with the ids of the clusters. A progress report's `finished` is false.
+    pub fn update_event(&mut self, ev: &Event) -> Vec<(usize, Recognized<T>)> {
+        match *ev {
+            Event::Down { slot, pos } => {
+                self.positions.insert(slot, pos);
+                let i = match self.nearest(pos) {
+                    Some(i) => i,
+                    None => self.new_cluster(),
+                };
+                self.owners.insert(slot, self.clusters[i].id);
+                self.clusters[i].slots.push(slot);
+                self.clusters[i].events.push(*ev);
+            },
+            Event::Motion { slot, pos } => {
+                if self.positions.contains_key(&slot) {
+                    self.positions.insert(slot, pos);
+                }
+                if let Some(i) = self.owner(slot) {
+                    self.clusters[i].events.push(*ev);
+                }
+            },
+            Event::Shape { slot, .. } => {
+                if let Some(i) = self.owner(slot) {
+                    self.clusters[i].events.push(*ev);
+                }
+            },
+            Event::Up { slot } => {
+                if let Some(i) = self.owner(slot) {
+                    self.clusters[i].events.push(*ev);
+                    self.clusters[i].slots.retain(|&s| s != slot);
+                }
+                self.owners.remove(&slot);
+                self.positions.remove(&slot);
+            },
+            Event::Cancel => {
+                for c in &mut self.clusters {
+                    c.events.push(*ev);
+                }
+            },
+            Event::Frame { time } => return self.end_frame(time),
+        }
+        Vec::new()
+    }
+
+    // The index of the cluster that the finger in `slot` belongs to.
+    fn owner(&self, slot: usize) -> Option<usize> {
+        match self.owners.get(&slot) {
+            Some(&id) => self.clusters.iter().position(|c| c.id == id),
+            None => None,
+        }
+    }
+
+    fn centroid(&self, c: &Cluster<T>) -> Point {
+        let sum = c.slots.iter().fold(vec2(0.0, 0.0), |a, s| a + self.positions[s]);
+        sum / (c.slots.len() as f64)
+    }
+
+    // The index of the cluster closest to `pos`, if there is one within `radius`.
+    fn nearest(&self, pos: Point) -> Option<usize> {
+        let mut best = None;
+        for (i, c) in self.clusters.iter().enumerate() {
+            if c.slots.is_empty() {
+                continue;
+            }
+            let dist = (self.centroid(c) - pos).length();
+            if dist < self.radius && best.map_or(true, |(_, d)| dist < d) {
+                best = Some((i, dist));
+            }
+        }
+        best.map(|(i, _)| i)
+    }
+
+    // Adds a new cluster without any fingers, and returns its index.
+    fn new_cluster(&mut self) -> usize {
+        let man = (*self.factory)();
+        self.clusters.push(Cluster {
+            id: self.next_id,
+            man: man,
+            slots: Vec::new(),
+            events: Vec::new(),
+            restart: false,
+        });
+        self.next_id += 1;
+        self.clusters.len() - 1
+    }
+
+    fn end_frame(&mut self, time: u32) -> Vec<(usize, Recognized<T>)> {
+        let mut ret = Vec::new();
+        for c in &mut self.clusters {
+            for ev in c.events.drain(..) {
+                c.man.update_event(&ev);
+            }
+            if let Some(g) = c.man.update_event_recognized(&Event::Frame { time: time }) {
+                ret.push((c.id, g));
+            }
+        }
+
+        self.merge();
+        self.split();
+        self.restart(time, &mut ret);
+        self.clusters.retain(|c| !c.slots.is_empty() || !c.man.is_idle());
+        ret
+    }
+
+    // Merges any clusters that came too close together.
+    fn merge(&mut self) {
+        while let Some((i, j)) = self.close_pair() {
+            debug!("merging cluster {} into cluster {}", self.clusters[j].id, self.clusters[i].id);
+            let c = self.clusters.remove(j);
+            for &slot in &c.slots {
+                self.owners.insert(slot, self.clusters[i].id);
+            }
+            self.clusters[i].slots.extend(c.slots);
+            self.clusters[i].restart = true;
+        }
+    }
+
+    // Finds two clusters, `i < j`, that are closer together than `radius`.
+    fn close_pair(&self) -> Option<(usize, usize)> {
+        let n = self.clusters.len();
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let (a, b) = (&self.clusters[i], &self.clusters[j]);
+                if a.slots.is_empty() || b.slots.is_empty() {
+                    continue;
+                }
+                if (self.centroid(a) - self.centroid(b)).length() < self.radius {
+                    return Some((i, j));
+                }
+            }
+        }
+        None
+    }
+
+    // Splits off any fingers that strayed too far from the rest of their clusters.
+    fn split(&mut self) {
+        let n = self.clusters.len();
+        for i in 0..n {
+            loop {
+                let stray = self.stray_finger(&self.clusters[i]);
+                let slot = match stray {
+                    Some(s) => s,
+                    None => break,
+                };
+                debug!("splitting finger {} off cluster {}", slot, self.clusters[i].id);
+                self.clusters[i].slots.retain(|&s| s != slot);
+                self.clusters[i].restart = true;
+                let k = self.new_cluster();
+                self.clusters[k].slots.push(slot);
+                self.clusters[k].restart = true;
+                self.owners.insert(slot, self.clusters[k].id);
+            }
+        }
+    }
+
+    // Finds a finger that is more than twice `radius` away from the middle of the other fingers
+    // in its cluster.
+    fn stray_finger(&self, c: &Cluster<T>) -> Option<usize> {
+        if c.slots.len() < 2 {
+            return None;
+        }
+        let sum = c.slots.iter().fold(vec2(0.0, 0.0), |a, s| a + self.positions[s]);
+        let others = (c.slots.len() - 1) as f64;
+        c.slots.iter()
+            .cloned()
+            .find(|s| {
+                let pos = self.positions[s];
+                let mean = (sum - pos) / others;
+                (pos - mean).length() > 2.0 * self.radius
+            })
+    }
+
+    // Gives new `Manager`s to the clusters that changed, and tells them about their fingers.
+    fn restart(&mut self, time: u32, ret: &mut Vec<(usize, Recognized<T>)>) {
+        for i in 0..self.clusters.len() {
+            if !self.clusters[i].restart {
+                continue;
+            }
+            let mut man = (*self.factory)();
+            for &slot in &self.clusters[i].slots {
+                man.update_event(&Event::Down { slot: slot, pos: self.positions[&slot] });
+            }
+            if let Some(g) = man.update_event_recognized(&Event::Frame { time: time }) {
+                ret.push((self.clusters[i].id, g));
+            }
+            self.clusters[i].man = man;
+            self.clusters[i].restart = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use event::Event;
+    use geom::Point;
+    use manager::Manager;
+    use super::ClusteredManager;
+
+    #[test]
+    fn clusters_merge_and_split() {
+        let mut man = ClusteredManager::<()>::new(|| Manager::new());
+        man.update_event(&Event::Down { slot: 0, pos: Point::new(0.0, 0.0) });
+        man.update_event(&Event::Down { slot: 1, pos: Point::new(20.0, 0.0) });
+        man.update_event(&Event::Down { slot: 2, pos: Point::new(200.0, 0.0) });
+        man.update_event(&Event::Frame { time: 0 });
+        assert_eq!(man.clusters(), vec![(0, vec![0, 1]), (1, vec![2])]);
+
+        man.update_event(&Event::Motion { slot: 2, pos: Point::new(40.0, 0.0) });
+        man.update_event(&Event::Frame { time: 10 });
+        assert_eq!(man.clusters(), vec![(0, vec![0, 1, 2])]);
+
+        man.update_event(&Event::Motion { slot: 0, pos: Point::new(-200.0, 0.0) });
+        man.update_event(&Event::Frame { time: 20 });
+        assert_eq!(man.clusters(), vec![(0, vec![1, 2]), (2, vec![0])]);
+
+        for slot in 0..3 {
+            man.update_event(&Event::Up { slot: slot });
+        }
+        man.update_event(&Event::Frame { time: 30 });
+        assert_eq!(man.num_clusters(), 0);
+    }
+}
@@ -0,0 +1,156 @@
+//! Deciding whether a touch started at the edge of the screen.
+//!
+//! An edge swipe (like the ones that GNOME and KDE use to open panels) is different from an
+//! ordinary swipe that happens to start near the edge: the finger has to come down on the very
+//! outermost part of the surface, as though it had been dragged in from outside. An
+//! [`EdgeZones`](struct.EdgeZones.html) describes which parts of the surface count.
+
+use geom::{Direction, Point};
+use std::fmt;
+use std::str::FromStr;
+
+/// One of the four edges of a touch surface.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Edge {
+    /// Returns the direction that points from this edge towards the middle of the surface.
+    pub fn inward(&self) -> Direction {
+        match *self {
+            Edge::Top => Direction::Down,
+            Edge::Bottom => Direction::Up,
+            Edge::Left => Direction::Right,
+            Edge::Right => Direction::Left,
+        }
+    }
+
+    /// The name of this edge, as it is written in configuration files.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Edge::Top => "top",
+            Edge::Bottom => "bottom",
+            Edge::Left => "left",
+            Edge::Right => "right",
+        }
+    }
+
+    fn index(&self) -> usize {
+        match *self {
+            Edge::Top => 0,
+            Edge::Bottom => 1,
+            Edge::Left => 2,
+            Edge::Right => 3,
+        }
+    }
+}
+
+impl fmt::Display for Edge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Reads an edge from its name.
+impl FromStr for Edge {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Edge, String> {
+        match s {
+            "top" => Ok(Edge::Top),
+            "bottom" => Ok(Edge::Bottom),
+            "left" => Ok(Edge::Left),
+            "right" => Ok(Edge::Right),
+            _ => Err(format!("unknown edge {:?}", s)),
+        }
+    }
+}
+
+/// Describes the parts of a touch surface where edge swipes can start.
+///
+/// Along each edge there is an activation zone: a strip whose width (in millimeters) can be set
+/// for each edge separately. A width of zero turns off that edge. Parts of an edge can also be
+/// excluded, for example because the compositor reserves them for its own gestures.
+///
+/// # Examples
+/// ```
+/// use libgestures::edge::{Edge, EdgeZones};
+/// use libgestures::geom::Point;
+///
+/// let zones = EdgeZones::new(200.0, 120.0)
+///     .zone(Edge::Bottom, 0.0)
+///     .exclude(Edge::Top, 0.0, 20.0);
+/// assert_eq!(zones.edge_at(Point::new(0.5, 60.0)), Some(Edge::Left));
+/// assert_eq!(zones.edge_at(Point::new(3.0, 60.0)), None);
+/// assert_eq!(zones.edge_at(Point::new(100.0, 119.5)), None);
+/// assert_eq!(zones.edge_at(Point::new(10.0, 0.5)), None);
+/// assert_eq!(zones.edge_at(Point::new(30.0, 0.5)), Some(Edge::Top));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct EdgeZones {
+    width: f64,
+    height: f64,
+    // Indexed by `Edge::index`.
+    zones: [f64; 4],
+    exclusions: Vec<(Edge, f64, f64)>,
+}
+
+impl EdgeZones {
+    /// Creates a new `EdgeZones` for a surface that is `width_mm` wide and `height_mm` tall.
+    ///
+    /// By default, every edge has an activation zone that is one millimeter wide.
+    pub fn new(width_mm: f64, height_mm: f64) -> EdgeZones {
+        EdgeZones {
+            width: width_mm,
+            height: height_mm,
+            zones: [1.0; 4],
+            exclusions: Vec::new(),
+        }
+    }
+
+    /// Sets the width of the activation zone along `edge`, in millimeters.
+    pub fn zone(mut self, edge: Edge, mm: f64) -> EdgeZones {
+        self.zones[edge.index()] = mm;
+        self
+    }
+
+    /// Excludes part of `edge` from starting edge swipes.
+    ///
+    /// The excluded part goes from `from_mm` to `to_mm`, measured along the edge from the top
+    /// (for the left and right edges) or from the left (for the top and bottom edges).
+    pub fn exclude(mut self, edge: Edge, from_mm: f64, to_mm: f64) -> EdgeZones {
+        self.exclusions.push((edge, from_mm, to_mm));
+        self
+    }
+
+    /// Returns the edge whose activation zone contains `pos`, if there is one.
+    ///
+    /// If `pos` is in a corner where two zones overlap, the nearer edge wins.
+    pub fn edge_at(&self, pos: Point) -> Option<Edge> {
+        let candidates = [
+            (Edge::Top, pos.y, pos.x),
+            (Edge::Bottom, self.height - pos.y, pos.x),
+            (Edge::Left, pos.x, pos.y),
+            (Edge::Right, self.width - pos.x, pos.y),
+        ];
+
+        let mut best: Option<(Edge, f64)> = None;
+        for &(edge, dist, along) in &candidates {
+            if dist >= self.zones[edge.index()] {
+                continue;
+            }
+            if self.exclusions.iter().any(|&(e, from, to)| e == edge && from <= along && along <= to) {
+                continue;
+            }
+            if best.map_or(true, |(_, d)| dist < d) {
+                best = Some((edge, dist));
+            }
+        }
+        best.map(|(edge, _)| edge)
+    }
+}
@@ -0,0 +1,138 @@
+use euclid::vec2;
+#[cfg(feature = "input")]
+use input::event::touch::{TouchEvent, TouchEventPosition, TouchEventSlot, TouchEventTrait};
+use geom::Point;
+#[cfg(feature = "serialize")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// A touch event.
+///
+/// This carries the same information as libinput's `TouchEvent`, but it can be created by hand,
+/// which makes it possible to record and replay streams of events, or to feed in events that didn't
+/// come from libinput at all.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// A finger came down in `slot`, at position `pos` (in millimeters).
+    Down { slot: usize, pos: Point },
+    /// The finger in `slot` went up.
+    Up { slot: usize },
+    /// The finger in `slot` moved to position `pos` (in millimeters).
+    Motion { slot: usize, pos: Point },
+    /// The contact in `slot` is `size` millimeters across its long side, and presses with
+    /// `pressure` (from 0 to 1).
+    ///
+    /// Only some devices know this. Those that do send it in the same frame as the `Down` or
+    /// `Motion` event for the contact, after it.
+    Shape { slot: usize, size: f64, pressure: f64 },
+    /// The touch sequence was cancelled.
+    Cancel,
+    /// The end of a frame. `time` is a timestamp, in milliseconds.
+    Frame { time: u32 },
+}
+
+#[cfg(feature = "input")]
+impl<'a> From<&'a TouchEvent> for Event {
+    fn from(ev: &'a TouchEvent) -> Event {
+        match ev {
+            &TouchEvent::Down(ref ev) => Event::Down {
+                slot: ev.slot().unwrap_or(0) as usize,
+                pos: vec2(ev.x(), ev.y()),
+            },
+            &TouchEvent::Up(ref ev) => Event::Up {
+                slot: ev.slot().unwrap_or(0) as usize,
+            },
+            &TouchEvent::Motion(ref ev) => Event::Motion {
+                slot: ev.slot().unwrap_or(0) as usize,
+                pos: vec2(ev.x(), ev.y()),
+            },
+            &TouchEvent::Cancel(_) => Event::Cancel,
+            &TouchEvent::Frame(ref ev) => Event::Frame {
+                time: ev.time(),
+            },
+        }
+    }
+}
+
+/// Writes an event in the format used by [trace files](../trace/index.html).
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Event::Down { slot, pos } => write!(f, "down {} {} {}", slot, pos.x, pos.y),
+            Event::Up { slot } => write!(f, "up {}", slot),
+            Event::Motion { slot, pos } => write!(f, "motion {} {} {}", slot, pos.x, pos.y),
+            Event::Shape { slot, size, pressure } => {
+                write!(f, "shape {} {} {}", slot, size, pressure)
+            },
+            Event::Cancel => write!(f, "cancel"),
+            Event::Frame { time } => write!(f, "frame {}", time),
+        }
+    }
+}
+
+/// Reads an event in the format used by [trace files](../trace/index.html).
+///
+/// # Examples
+/// ```
+/// use libgestures::event::Event;
+///
+/// let ev: Event = "up 2".parse().unwrap();
+/// assert_eq!(ev, Event::Up { slot: 2 });
+/// assert_eq!(ev.to_string().parse::<Event>(), Ok(ev));
+/// assert!("sideways 2".parse::<Event>().is_err());
+///
+/// let ev: Event = "shape 1 9.5 0.25".parse().unwrap();
+/// assert_eq!(ev, Event::Shape { slot: 1, size: 9.5, pressure: 0.25 });
+/// ```
+impl FromStr for Event {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Event, String> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        let num = |i: usize| -> Result<f64, String> {
+            words.get(i)
+                .ok_or(format!("missing field in event {:?}", s))?
+                .parse::<f64>()
+                .map_err(|e| format!("bad number in event {:?}: {}", s, e))
+        };
+        let slot = |i: usize| -> Result<usize, String> {
+            words.get(i)
+                .ok_or(format!("missing slot in event {:?}", s))?
+                .parse::<usize>()
+                .map_err(|e| format!("bad slot in event {:?}: {}", s, e))
+        };
+
+        let (ev, len) = match words.get(0) {
+            Some(&"down") => (Event::Down { slot: slot(1)?, pos: vec2(num(2)?, num(3)?) }, 4),
+            Some(&"up") => (Event::Up { slot: slot(1)? }, 2),
+            Some(&"motion") => (Event::Motion { slot: slot(1)?, pos: vec2(num(2)?, num(3)?) }, 4),
+            Some(&"shape") => {
+                (Event::Shape { slot: slot(1)?, size: num(2)?, pressure: num(3)? }, 4)
+            },
+            Some(&"cancel") => (Event::Cancel, 1),
+            Some(&"frame") => (Event::Frame { time: num(1)? as u32 }, 2),
+            _ => return Err(format!("unknown event {:?}", s)),
+        };
+        if words.len() != len {
+            return Err(format!("wrong number of fields in event {:?}", s));
+        }
+        Ok(ev)
+    }
+}
+
+/// With the `serialize` feature, an event is serialized as its line in a
+/// [trace file](../trace/index.html).
+#[cfg(feature = "serialize")]
+impl Serialize for Event {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serialize")]
+impl<'de> Deserialize<'de> for Event {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Event, D::Error> {
+        String::deserialize(d)?.parse().map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,572 @@
+//! A C interface to the recognizers, for embedding them in programs that aren't written in Rust.
+//!
+//! This module is only built with the `ffi` feature. The functions here are declared in
+//! `include/libgestures.h`, which has the documentation for C programmers; the comments here are
+//! mostly about how things map onto the Rust API.
+//!
+//! A C program describes the gestures it wants with a `GesturesSet` (which wraps a
+//! [`GestureSet`](../gestures/struct.GestureSet.html)), builds a `GesturesManager` (which wraps a
+//! [`Manager`](../manager/struct.Manager.html)) from it, and then feeds it `GesturesEvent`s (which
+//! are the same as [`Event`](../event/enum.Event.html)s). Recognized gestures and progress
+//! reports come back through callbacks.
+//!
+//! Every function accepts null pointers, and does nothing (or returns an error) when it gets one.
+
+use std::mem;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+
+use edge::{Edge, EdgeZones};
+use event::Event;
+use frame::Frame;
+use geom::{Direction, Point};
+use gestures::{Gesture, GestureSet, Path, PinchDirection, RotateDirection, MAX_PATH_LEN};
+use gestures::compound::progressive_swipe;
+use graph::Graph;
+use manager::Manager;
+use progress::Normalizer;
+use {Recognizer, RecResult};
+
+pub const GESTURES_EVENT_DOWN: u32 = 0;
+pub const GESTURES_EVENT_UP: u32 = 1;
+pub const GESTURES_EVENT_MOTION: u32 = 2;
+pub const GESTURES_EVENT_CANCEL: u32 = 3;
+pub const GESTURES_EVENT_FRAME: u32 = 4;
+/// A `Shape` event, whose size is in `x` and whose pressure is in `y`.
+pub const GESTURES_EVENT_SHAPE: u32 = 5;
+
+pub const GESTURES_SWIPE: u32 = 0;
+pub const GESTURES_SPREAD: u32 = 1;
+pub const GESTURES_GRAB: u32 = 2;
+pub const GESTURES_SHAKE: u32 = 3;
+pub const GESTURES_PATH: u32 = 4;
+pub const GESTURES_EDGE_SWIPE: u32 = 5;
+pub const GESTURES_TAP: u32 = 6;
+pub const GESTURES_HOLD: u32 = 7;
+pub const GESTURES_PINCH: u32 = 8;
+pub const GESTURES_ROTATE: u32 = 9;
+
+pub const GESTURES_UP: u32 = 0;
+pub const GESTURES_DOWN: u32 = 1;
+pub const GESTURES_LEFT: u32 = 2;
+pub const GESTURES_RIGHT: u32 = 3;
+
+pub const GESTURES_PINCH_IN: u32 = 0;
+pub const GESTURES_PINCH_OUT: u32 = 1;
+
+pub const GESTURES_CLOCKWISE: u32 = 0;
+pub const GESTURES_COUNTERCLOCKWISE: u32 = 1;
+
+pub const GESTURES_EDGE_TOP: u32 = 0;
+pub const GESTURES_EDGE_BOTTOM: u32 = 1;
+pub const GESTURES_EDGE_LEFT: u32 = 2;
+pub const GESTURES_EDGE_RIGHT: u32 = 3;
+
+/// A touch event. `kind` is one of the `GESTURES_EVENT_*` constants, and the other fields are only
+/// used by the kinds of event that have them in [`Event`](../event/enum.Event.html).
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct GesturesEvent {
+    pub kind: u32,
+    pub slot: u32,
+    pub x: f64,
+    pub y: f64,
+    pub time: u32,
+}
+
+/// A gesture. `kind` is one of the `GESTURES_SWIPE`, `GESTURES_SPREAD`, etc. constants, and the
+/// other fields are only used by the kinds of gesture that have them in
+/// [`Gesture`](../gestures/enum.Gesture.html).
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct GesturesGesture {
+    pub kind: u32,
+    pub num_fingers: u8,
+    pub direction: u32,
+    pub edge: u32,
+    pub path: [u32; MAX_PATH_LEN],
+    pub path_len: u32,
+}
+
+pub type GesturesGestureCallback = Option<extern "C" fn(*mut c_void, *const GesturesGesture)>;
+pub type GesturesProgressCallback = Option<extern "C" fn(*mut c_void, u8, u32, f64, c_int)>;
+
+fn direction_from_c(d: u32) -> Option<Direction> {
+    match d {
+        GESTURES_UP => Some(Direction::Up),
+        GESTURES_DOWN => Some(Direction::Down),
+        GESTURES_LEFT => Some(Direction::Left),
+        GESTURES_RIGHT => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+fn direction_to_c(d: Direction) -> u32 {
+    match d {
+        Direction::Up => GESTURES_UP,
+        Direction::Down => GESTURES_DOWN,
+        Direction::Left => GESTURES_LEFT,
+        Direction::Right => GESTURES_RIGHT,
+    }
+}
+
+fn edge_from_c(e: u32) -> Option<Edge> {
+    match e {
+        GESTURES_EDGE_TOP => Some(Edge::Top),
+        GESTURES_EDGE_BOTTOM => Some(Edge::Bottom),
+        GESTURES_EDGE_LEFT => Some(Edge::Left),
+        GESTURES_EDGE_RIGHT => Some(Edge::Right),
+        _ => None,
+    }
+}
+
+fn edge_to_c(e: Edge) -> u32 {
+    match e {
+        Edge::Top => GESTURES_EDGE_TOP,
+        Edge::Bottom => GESTURES_EDGE_BOTTOM,
+        Edge::Left => GESTURES_EDGE_LEFT,
+        Edge::Right => GESTURES_EDGE_RIGHT,
+    }
+}
+
+impl GesturesGesture {
+    fn from_gesture(g: Gesture) -> GesturesGesture {
+        let mut ret = GesturesGesture {
+            kind: 0,
+            num_fingers: g.num_fingers(),
+            direction: 0,
+            edge: 0,
+            path: [0; MAX_PATH_LEN],
+            path_len: 0,
+        };
+        match g {
+            Gesture::Swipe { direction, .. } => {
+                ret.kind = GESTURES_SWIPE;
+                ret.direction = direction_to_c(direction);
+            },
+            Gesture::Spread { .. } => ret.kind = GESTURES_SPREAD,
+            Gesture::Grab { .. } => ret.kind = GESTURES_GRAB,
+            Gesture::Shake { .. } => ret.kind = GESTURES_SHAKE,
+            Gesture::Path { path, .. } => {
+                ret.kind = GESTURES_PATH;
+                for (i, &d) in path.directions().iter().enumerate() {
+                    ret.path[i] = direction_to_c(d);
+                }
+                ret.path_len = path.directions().len() as u32;
+            },
+            Gesture::EdgeSwipe { edge } => {
+                ret.kind = GESTURES_EDGE_SWIPE;
+                ret.edge = edge_to_c(edge);
+            },
+            Gesture::Tap { .. } => ret.kind = GESTURES_TAP,
+            Gesture::Hold { .. } => ret.kind = GESTURES_HOLD,
+            Gesture::Pinch { direction, .. } => {
+                ret.kind = GESTURES_PINCH;
+                ret.direction = match direction {
+                    PinchDirection::In => GESTURES_PINCH_IN,
+                    PinchDirection::Out => GESTURES_PINCH_OUT,
+                };
+            },
+            Gesture::Rotate { direction, .. } => {
+                ret.kind = GESTURES_ROTATE;
+                ret.direction = match direction {
+                    RotateDirection::Clockwise => GESTURES_CLOCKWISE,
+                    RotateDirection::Counterclockwise => GESTURES_COUNTERCLOCKWISE,
+                };
+            },
+        }
+        ret
+    }
+
+    fn to_gesture(&self) -> Option<Gesture> {
+        let num_fingers = self.num_fingers;
+        match self.kind {
+            GESTURES_SWIPE => direction_from_c(self.direction)
+                .map(|direction| Gesture::Swipe { num_fingers, direction }),
+            GESTURES_SPREAD => Some(Gesture::Spread { num_fingers }),
+            GESTURES_GRAB => Some(Gesture::Grab { num_fingers }),
+            GESTURES_SHAKE => Some(Gesture::Shake { num_fingers }),
+            GESTURES_PATH => {
+                let len = (self.path_len as usize).min(MAX_PATH_LEN);
+                let dirs = self.path[..len].iter()
+                    .map(|&d| direction_from_c(d))
+                    .collect::<Option<Vec<_>>>()?;
+                Path::new(&dirs).map(|path| Gesture::Path { num_fingers, path })
+            },
+            GESTURES_EDGE_SWIPE => edge_from_c(self.edge).map(|edge| Gesture::EdgeSwipe { edge }),
+            GESTURES_TAP => Some(Gesture::Tap { num_fingers }),
+            GESTURES_HOLD => Some(Gesture::Hold { num_fingers }),
+            GESTURES_PINCH => {
+                let direction = match self.direction {
+                    GESTURES_PINCH_IN => PinchDirection::In,
+                    GESTURES_PINCH_OUT => PinchDirection::Out,
+                    _ => return None,
+                };
+                Some(Gesture::Pinch { num_fingers, direction })
+            },
+            GESTURES_ROTATE => {
+                let direction = match self.direction {
+                    GESTURES_CLOCKWISE => RotateDirection::Clockwise,
+                    GESTURES_COUNTERCLOCKWISE => RotateDirection::Counterclockwise,
+                    _ => return None,
+                };
+                Some(Gesture::Rotate { num_fingers, direction })
+            },
+            _ => None,
+        }
+    }
+}
+
+impl GesturesEvent {
+    fn to_event(&self) -> Option<Event> {
+        let slot = self.slot as usize;
+        let pos = Point::new(self.x, self.y);
+        match self.kind {
+            GESTURES_EVENT_DOWN => Some(Event::Down { slot: slot, pos: pos }),
+            GESTURES_EVENT_UP => Some(Event::Up { slot: slot }),
+            GESTURES_EVENT_MOTION => Some(Event::Motion { slot: slot, pos: pos }),
+            GESTURES_EVENT_CANCEL => Some(Event::Cancel),
+            GESTURES_EVENT_FRAME => Some(Event::Frame { time: self.time }),
+            GESTURES_EVENT_SHAPE => {
+                Some(Event::Shape { slot: slot, size: self.x, pressure: self.y })
+            },
+            _ => None,
+        }
+    }
+}
+
+// What the recognizers in a `GesturesManager` produce.
+enum Outcome {
+    Gesture(Gesture),
+    // The number of fingers, the direction, the progress, and whether the swipe is finished.
+    Progress(u8, Direction, f64, bool),
+}
+
+impl From<Gesture> for Outcome {
+    fn from(g: Gesture) -> Outcome {
+        Outcome::Gesture(g)
+    }
+}
+
+// Tells apart the progress reports of a progressive swipe from its final outcome, which C can't
+// otherwise see because both come out of `Manager::update_event`.
+struct ProgressReport<R> {
+    rec: R,
+    num_fingers: u8,
+}
+
+impl<R: Recognizer<In=(), Out=(Direction, f64)>> Recognizer for ProgressReport<R> {
+    type In = ();
+    type Out = Outcome;
+
+    fn init(&mut self, input: (), frame: &Frame) {
+        self.rec.init(input, frame);
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<Outcome> {
+        let n = self.num_fingers;
+        match self.rec.update(frame) {
+            RecResult::Continuing => RecResult::Continuing,
+            RecResult::Failed => RecResult::Failed,
+            RecResult::Progressing((d, p)) => {
+                RecResult::Progressing(Outcome::Progress(n, d, p, false))
+            },
+            RecResult::Succeeded((d, p)) => RecResult::Succeeded(Outcome::Progress(n, d, p, true)),
+        }
+    }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        self.rec.wants_frames_while_idle()
+    }
+
+    fn progress(&self) -> f64 {
+        self.rec.progress()
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        self.rec.to_graph(g)
+    }
+}
+
+/// A set of gestures to recognize. This is opaque to C.
+pub struct GesturesSet(GestureSet);
+
+/// Recognizes gestures, and reports them through callbacks. This is opaque to C.
+pub struct GesturesManager {
+    man: Manager<Outcome>,
+    on_gesture: GesturesGestureCallback,
+    on_progress: GesturesProgressCallback,
+    user_data: *mut c_void,
+    // The last gesture that was recognized, until `gestures_manager_take_gesture` takes it.
+    last: Option<Gesture>,
+}
+
+/// Allocates an event, for callers that can't put one on the stack (like JavaScript, when this is
+/// built for WebAssembly).
+#[no_mangle]
+pub extern "C" fn gestures_event_new() -> *mut GesturesEvent {
+    Box::into_raw(Box::new(GesturesEvent { kind: GESTURES_EVENT_FRAME, slot: 0, x: 0.0, y: 0.0,
+                                           time: 0 }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gestures_event_free(ev: *mut GesturesEvent) {
+    if !ev.is_null() {
+        drop(Box::from_raw(ev));
+    }
+}
+
+/// Allocates a gesture, like `gestures_event_new`.
+#[no_mangle]
+pub extern "C" fn gestures_gesture_new() -> *mut GesturesGesture {
+    Box::into_raw(Box::new(GesturesGesture::from_gesture(Gesture::Spread { num_fingers: 0 })))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gestures_gesture_free(g: *mut GesturesGesture) {
+    if !g.is_null() {
+        drop(Box::from_raw(g));
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn gestures_set_new() -> *mut GesturesSet {
+    Box::into_raw(Box::new(GesturesSet(GestureSet::new())))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gestures_set_free(set: *mut GesturesSet) {
+    if !set.is_null() {
+        drop(Box::from_raw(set));
+    }
+}
+
+/// Returns 0 if the gesture was added, or -1 if it isn't a valid gesture.
+#[no_mangle]
+pub unsafe extern "C" fn gestures_set_add(set: *mut GesturesSet, g: *const GesturesGesture)
+-> c_int {
+    if set.is_null() || g.is_null() {
+        return -1;
+    }
+    match (*g).to_gesture() {
+        Some(g) => {
+            (*set).0.insert(g);
+            0
+        },
+        None => -1,
+    }
+}
+
+/// Sets the size of the touch surface (in millimeters), which edge swipes need.
+#[no_mangle]
+pub unsafe extern "C" fn gestures_set_surface_size(set: *mut GesturesSet, width: f64,
+                                                   height: f64) {
+    if !set.is_null() {
+        let s = mem::replace(&mut (*set).0, GestureSet::new());
+        (*set).0 = s.edge_zones(EdgeZones::new(width, height));
+    }
+}
+
+/// The set can be freed afterwards, or used for more managers.
+#[no_mangle]
+pub unsafe extern "C" fn gestures_manager_new(set: *const GesturesSet) -> *mut GesturesManager {
+    let mut man = Manager::new();
+    if !set.is_null() {
+        (*set).0.push_to(&mut man);
+    }
+    Box::into_raw(Box::new(GesturesManager {
+        man: man,
+        on_gesture: None,
+        on_progress: None,
+        user_data: ptr::null_mut(),
+        last: None,
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn gestures_manager_free(man: *mut GesturesManager) {
+    if !man.is_null() {
+        drop(Box::from_raw(man));
+    }
+}
+
+/// Adds a swipe that reports its progress as it goes, reaching 1 after `full_travel_mm`
+/// millimeters. Returns 0, or -1 if `man` is null.
+#[no_mangle]
+pub unsafe extern "C" fn gestures_manager_add_progressive_swipe(man: *mut GesturesManager,
+                                                                num_fingers: u8,
+                                                                full_travel_mm: f64) -> c_int {
+    if man.is_null() {
+        return -1;
+    }
+    let rec = ProgressReport {
+        rec: progressive_swipe(num_fingers, Normalizer::new(full_travel_mm)),
+        num_fingers: num_fingers,
+    };
+    (*man).man.push_with_priority(rec, num_fingers as i32);
+    0
+}
+
+/// Either callback may be null. `user_data` is passed to both of them.
+#[no_mangle]
+pub unsafe extern "C" fn gestures_manager_set_callbacks(man: *mut GesturesManager,
+                                                        on_gesture: GesturesGestureCallback,
+                                                        on_progress: GesturesProgressCallback,
+                                                        user_data: *mut c_void) {
+    if !man.is_null() {
+        (*man).on_gesture = on_gesture;
+        (*man).on_progress = on_progress;
+        (*man).user_data = user_data;
+    }
+}
+
+/// Returns 1 if a gesture was recognized or made progress, 0 if not, or -1 if the event isn't
+/// valid.
+#[no_mangle]
+pub unsafe extern "C" fn gestures_manager_update(man: *mut GesturesManager,
+                                                 ev: *const GesturesEvent) -> c_int {
+    if man.is_null() || ev.is_null() {
+        return -1;
+    }
+    let ev = match (*ev).to_event() {
+        Some(ev) => ev,
+        None => return -1,
+    };
+    let man = &mut *man;
+    // The progress updates say for themselves whether the swipe is done.
+    let out = man.man.update_event_recognized(&ev).map(|r| r.value);
+    man.report(out)
+}
+
+/// Lets time pass without any touch events, which holds need in order to be recognized while the
+/// fingers stay still. `time` is in milliseconds, on the same clock as the frames. Returns 1 if a
+/// gesture was recognized or made progress, and 0 if not.
+#[no_mangle]
+pub unsafe extern "C" fn gestures_manager_tick(man: *mut GesturesManager, time: u32) -> c_int {
+    if man.is_null() {
+        return 0;
+    }
+    let man = &mut *man;
+    let out = man.man.tick(time).map(|r| r.value);
+    man.report(out)
+}
+
+impl GesturesManager {
+    // Passes an outcome on to the callbacks.
+    unsafe fn report(&mut self, out: Option<Outcome>) -> c_int {
+        match out {
+            Some(Outcome::Gesture(g)) => {
+                self.last = Some(g);
+                if let Some(f) = self.on_gesture {
+                    f(self.user_data, &GesturesGesture::from_gesture(g));
+                }
+                1
+            },
+            Some(Outcome::Progress(n, d, p, done)) => {
+                if let Some(f) = self.on_progress {
+                    f(self.user_data, n, direction_to_c(d), p, done as c_int);
+                }
+                1
+            },
+            None => 0,
+        }
+    }
+}
+
+/// Copies the last recognized gesture into `out` and forgets it, for callers that would rather poll
+/// than get callbacks. Returns 1 if there was one, and 0 if not.
+#[no_mangle]
+pub unsafe extern "C" fn gestures_manager_take_gesture(man: *mut GesturesManager,
+                                                       out: *mut GesturesGesture) -> c_int {
+    if man.is_null() || out.is_null() {
+        return 0;
+    }
+    match (*man).last.take() {
+        Some(g) => {
+            *out = GesturesGesture::from_gesture(g);
+            1
+        },
+        None => 0,
+    }
+}
+
+/// Returns 1 if the manager has nothing to do until a finger comes down, and 0 otherwise.
+#[no_mangle]
+pub unsafe extern "C" fn gestures_manager_is_idle(man: *const GesturesManager) -> c_int {
+    if man.is_null() {
+        return 1;
+    }
+    (*man).man.is_idle() as c_int
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::raw::c_void;
+    use super::*;
+
+    // Records the gestures in the `Vec<GesturesGesture>` that `data` points to.
+    extern "C" fn record(data: *mut c_void, g: *const GesturesGesture) {
+        let seen = unsafe { &mut *(data as *mut Vec<GesturesGesture>) };
+        seen.push(unsafe { *g });
+    }
+
+    fn event(kind: u32, slot: u32, x: f64, y: f64, time: u32) -> GesturesEvent {
+        GesturesEvent { kind: kind, slot: slot, x: x, y: y, time: time }
+    }
+
+    #[test]
+    fn recognizes_a_swipe() {
+        let mut seen: Vec<GesturesGesture> = Vec::new();
+        unsafe {
+            let set = gestures_set_new();
+            let mut swipe = GesturesGesture {
+                kind: GESTURES_SWIPE,
+                num_fingers: 2,
+                direction: GESTURES_RIGHT,
+                edge: 0,
+                path: [0; 4],
+                path_len: 0,
+            };
+            assert_eq!(gestures_set_add(set, &swipe), 0);
+            swipe.kind = 42;
+            assert_eq!(gestures_set_add(set, &swipe), -1);
+            let man = gestures_manager_new(set);
+            gestures_set_free(set);
+            gestures_manager_set_callbacks(man, Some(record), None,
+                                           &mut seen as *mut Vec<GesturesGesture> as *mut c_void);
+
+            let mut time = 0;
+            for &slot in &[0, 1] {
+                gestures_manager_update(man, &event(GESTURES_EVENT_DOWN, slot,
+                                                    50.0, 50.0 + 20.0 * slot as f64, 0));
+            }
+            gestures_manager_update(man, &event(GESTURES_EVENT_FRAME, 0, 0.0, 0.0, time));
+            for step in 1..11 {
+                time += 10;
+                for &slot in &[0, 1] {
+                    gestures_manager_update(man, &event(GESTURES_EVENT_MOTION, slot,
+                                                        50.0 + 3.0 * step as f64,
+                                                        50.0 + 20.0 * slot as f64, 0));
+                }
+                gestures_manager_update(man, &event(GESTURES_EVENT_FRAME, 0, 0.0, 0.0, time));
+            }
+            for &slot in &[0, 1] {
+                time += 10;
+                gestures_manager_update(man, &event(GESTURES_EVENT_UP, slot, 0.0, 0.0, 0));
+                gestures_manager_update(man, &event(GESTURES_EVENT_FRAME, 0, 0.0, 0.0, time));
+            }
+            assert_eq!(gestures_manager_is_idle(man), 1);
+            let out = gestures_gesture_new();
+            assert_eq!(gestures_manager_take_gesture(man, out), 1);
+            assert_eq!((*out).direction, GESTURES_RIGHT);
+            assert_eq!(gestures_manager_take_gesture(man, out), 0);
+            gestures_gesture_free(out);
+            gestures_manager_free(man);
+        }
+
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].kind, GESTURES_SWIPE);
+        assert_eq!(seen[0].num_fingers, 2);
+        assert_eq!(seen[0].direction, GESTURES_RIGHT);
+    }
+}
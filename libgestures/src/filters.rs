@@ -1,5 +1,8 @@
 use {Filter, FilterResult};
 use frame::{Frame, Snapshot};
+use geom::{adaptation, fit_similarity, Angle, Point, UAngle};
+use graph::Graph;
+use params::FilterParams;
 
 /// A filter that fails if a finger moves too much.
 ///
@@ -12,8 +15,13 @@ pub struct NoMovement {
 
 impl NoMovement {
     pub fn new() -> NoMovement {
+        NoMovement::with_params(&FilterParams::default())
+    }
+
+    /// Creates a filter that fails if a finger moves more than `params.still_mm`.
+    pub fn with_params(params: &FilterParams) -> NoMovement {
         NoMovement {
-            threshold: 1.0,
+            threshold: params.still_mm,
             init_pos: Snapshot::new(),
         }
     }
@@ -21,7 +29,7 @@ impl NoMovement {
 
 impl Filter for NoMovement {
     fn init(&mut self, frame: &Frame) {
-        self.init_pos = frame.cur;
+        self.init_pos = frame.cur.clone();
     }
 
     fn update(&mut self, frame: &Frame) -> FilterResult {
@@ -33,42 +41,72 @@ impl Filter for NoMovement {
             FilterResult::Passed
         }
     }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        g.node(&format!("NoMovement (threshold {} mm)", self.threshold))
+    }
 }
 
+// How far (in millimeters) the hand must move before `NoRelativeMovement` adapts to its new
+// shape. Adapting on every frame would count the jitter in each frame as movement, so faster
+// devices would adapt more.
+const ADAPT_STEP_MM: f64 = 1.0;
+
 /// A filter that fails if a finger moves too much relative to the others.
 ///
 /// That is, this filter allows the hand as a whole to move, but it should retain the same
 /// basic "shape." Fingers are allowed to go up or down.
+///
+/// The expected shape slowly follows the fingers as the hand moves, depending on how far it has
+/// moved but not on how many frames that took.
 #[derive(Clone, Debug)]
 pub struct NoRelativeMovement {
     threshold: f64,
     adaptivity: f64,
     init_rel_pos: Snapshot,
+    // The mean position of the fingers the last time that we adapted.
+    last_mean: Point,
 }
 
 impl NoRelativeMovement {
     pub fn new() -> NoRelativeMovement {
+        NoRelativeMovement::with_params(&FilterParams::default())
+    }
+
+    /// Creates a filter that uses `params.shape_mm` as its threshold and
+    /// `params.shape_adaptivity` as its adaptivity.
+    pub fn with_params(params: &FilterParams) -> NoRelativeMovement {
         NoRelativeMovement {
-            threshold: 5.0,
-            adaptivity: 0.02,
+            threshold: params.shape_mm,
+            adaptivity: params.shape_adaptivity,
             init_rel_pos: Snapshot::new(),
+            last_mean: Point::zero(),
+        }
+    }
+
+    /// Sets how far (in millimeters) the fingers may move relative to one another before this
+    /// filter fails.
+    pub fn threshold(self, mm: f64) -> NoRelativeMovement {
+        NoRelativeMovement {
+            threshold: mm,
+            ..self
         }
     }
 }
 
 impl Filter for NoRelativeMovement {
     fn init(&mut self, frame: &Frame) {
-        self.init_rel_pos = frame.cur;
-        self.init_rel_pos -= frame.cur.mean_pos();
+        self.init_rel_pos = frame.cur.clone();
+        self.init_rel_pos -= frame.delta.mean_pos;
+        self.last_mean = frame.delta.mean_pos;
     }
 
     fn update(&mut self, frame: &Frame) -> FilterResult {
         // If fingers went up or down, adjust the expected relative positions to account for the
         // fact that the mean position was shifted by the new fingers.
         if frame.touch_down || frame.touch_up {
-            let mean_diff = frame.cur.mean_pos() - frame.last.mean_pos();
-            let mean_diff_corrected = frame.cur.mean_pos_filtered(&frame.last)
-                - frame.last.mean_pos_filtered(&frame.cur);
+            let mean_diff = frame.delta.mean_pos - frame.delta.last_mean_pos;
+            let mean_diff_corrected = frame.delta.motion.translation;
             // How much of the movement of the mean was due to the fingers going up or down, as
             // opposed to fingers moving?
             let offset = mean_diff - mean_diff_corrected;
@@ -76,23 +114,346 @@ impl Filter for NoRelativeMovement {
             self.init_rel_pos -= offset;
         }
 
-        let mut rel_pos = frame.cur;
-        rel_pos -= frame.cur.mean_pos();
+        let mut rel_pos = frame.cur.clone();
+        rel_pos -= frame.delta.mean_pos;
 
         if frame.touch_down || frame.touch_up {
             self.init_rel_pos.merge(&rel_pos);
+            self.last_mean = frame.delta.mean_pos;
         }
 
         if rel_pos.mean_dist(&self.init_rel_pos) > self.threshold {
 			debug!("NoRelativeMovement failed");
             FilterResult::Failed
         } else {
-            let dist = (frame.cur.mean_pos() - frame.last.mean_pos()).length();
-            let lambda = (dist * self.adaptivity).min(1.0);
-            self.init_rel_pos.interpolate_to(&rel_pos, lambda);
+            let dist = (frame.delta.mean_pos - self.last_mean).length();
+            if dist >= ADAPT_STEP_MM {
+                self.init_rel_pos.interpolate_to(&rel_pos, adaptation(self.adaptivity, dist));
+                self.last_mean = frame.delta.mean_pos;
+            }
 
             FilterResult::Passed
         }
     }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        g.node(&format!("NoRelativeMovement (threshold {} mm, adaptivity {})",
+                        self.threshold, self.adaptivity))
+    }
+}
+
+/// A filter that fails if the fingers move apart or together too much.
+///
+/// This is a cheaper and more specific check than
+/// [`NoRelativeMovement`](struct.NoRelativeMovement.html), for making sure that a pinch doesn't
+/// get mistaken for a swipe. It compares
+/// [`Snapshot::spread`](../frame/struct.Snapshot.html#method.spread) to its value when the filter
+/// started, which is reset whenever fingers go up or come down.
+#[derive(Clone, Debug)]
+pub struct MaintainSpread {
+    max_scale: f64,
+    init_spread: f64,
+}
+
+impl MaintainSpread {
+    /// Creates a filter that fails if the spread of the fingers changes by a factor of more than
+    /// `1.2`.
+    pub fn new() -> MaintainSpread {
+        MaintainSpread::with_params(&FilterParams::default())
+    }
+
+    /// Creates a filter that fails if the spread of the fingers changes by a factor of more than
+    /// `params.max_spread_scale`.
+    pub fn with_params(params: &FilterParams) -> MaintainSpread {
+        MaintainSpread {
+            max_scale: 1.0,
+            init_spread: 0.0,
+        }.max_scale(params.max_spread_scale)
+    }
+
+    /// Creates the filter that swipes use, which is only there if `params.maintain_spread` is
+    /// set.
+    pub fn if_enabled(params: &FilterParams) -> Option<MaintainSpread> {
+        if params.maintain_spread {
+            Some(MaintainSpread::with_params(params))
+        } else {
+            None
+        }
+    }
+
+    /// Sets how much the spread of the fingers may change. For example, with a `factor` of `1.5`
+    /// the filter fails if the spread shrinks to less than two thirds of its initial value, or
+    /// grows by more than a half.
+    pub fn max_scale(self, factor: f64) -> MaintainSpread {
+        assert!(factor > 1.0);
+        MaintainSpread {
+            max_scale: factor,
+            ..self
+        }
+    }
 }
 
+impl Filter for MaintainSpread {
+    fn init(&mut self, frame: &Frame) {
+        self.init_spread = frame.delta.spread;
+    }
+
+    fn update(&mut self, frame: &Frame) -> FilterResult {
+        if frame.touch_down || frame.touch_up {
+            self.init_spread = frame.delta.spread;
+            return FilterResult::Passed;
+        }
+        // A single finger has no spread, so there's nothing to check.
+        if self.init_spread <= 0.0 {
+            return FilterResult::Passed;
+        }
+
+        let scale = frame.delta.spread / self.init_spread;
+        if scale > self.max_scale || scale * self.max_scale < 1.0 {
+            debug!("MaintainSpread failed: scale {}", scale);
+            FilterResult::Failed
+        } else {
+            FilterResult::Passed
+        }
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        g.node(&format!("MaintainSpread (max scale {})", self.max_scale))
+    }
+}
+
+/// A filter that fails if the fingers rotate too much.
+///
+/// This is meant for pinches, which shouldn't be mistaken for rotations. The rotation is measured
+/// (using [`fit_similarity`](../geom/fn.fit_similarity.html)) from the fingers' positions when the
+/// filter started, or when fingers last went up or came down; the rotations before and after each
+/// of those are added up, so fingers are allowed to go up or come down. Measuring from frame to
+/// frame instead would add up the jitter in every frame, which would make the filter stricter on
+/// devices with a higher frame rate.
+///
+/// The rotation doesn't wrap around, so turning most of the way around still counts as a large
+/// rotation.
+#[derive(Clone, Debug)]
+pub struct MaintainAngle {
+    max_rotation: UAngle,
+    // The rotation up to the last time that we started measuring again, in degrees. Unlike an
+    // `Angle`, this doesn't wrap around.
+    rotation: f64,
+    // The positions of the fingers at that time.
+    base: Snapshot,
+}
+
+impl MaintainAngle {
+    /// Creates a filter that fails if the fingers rotate more than 30 degrees in either direction.
+    pub fn new() -> MaintainAngle {
+        MaintainAngle::with_params(&FilterParams::default())
+    }
+
+    /// Creates a filter that fails if the fingers rotate more than `params.max_rotation_deg`
+    /// degrees in either direction.
+    pub fn with_params(params: &FilterParams) -> MaintainAngle {
+        MaintainAngle {
+            max_rotation: UAngle::from_degrees(params.max_rotation_deg),
+            rotation: 0.0,
+            base: Snapshot::new(),
+        }
+    }
+
+    /// Sets how far the fingers may rotate, in either direction.
+    pub fn max_rotation(self, angle: UAngle) -> MaintainAngle {
+        MaintainAngle {
+            max_rotation: angle,
+            ..self
+        }
+    }
+}
+
+impl Filter for MaintainAngle {
+    fn init(&mut self, frame: &Frame) {
+        self.rotation = 0.0;
+        self.base = frame.cur.clone();
+    }
+
+    fn update(&mut self, frame: &Frame) -> FilterResult {
+        if frame.touch_down || frame.touch_up {
+            self.rotation += degrees(fit_similarity(&self.base, &frame.last).rotation)
+                + degrees(fit_similarity(&frame.last, &frame.cur).rotation);
+            self.base = frame.cur.clone();
+        }
+        let mut turned = degrees(fit_similarity(&self.base, &frame.cur).rotation);
+        // Much further than a quarter turn from `base`, we couldn't tell which way the fingers
+        // went, so we start measuring again from here.
+        if turned.abs() > 90.0 {
+            self.rotation += turned;
+            self.base = frame.cur.clone();
+            turned = 0.0;
+        }
+        let rotation = self.rotation + turned;
+        if rotation.abs() > self.max_rotation.to_degrees() {
+            debug!("MaintainAngle failed: rotated {} degrees", rotation);
+            FilterResult::Failed
+        } else {
+            FilterResult::Passed
+        }
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        g.node(&format!("MaintainAngle (max rotation {})", self.max_rotation))
+    }
+}
+
+// Converts a rotation to degrees, in the interval `(-180, 180]`.
+fn degrees(angle: Angle) -> f64 {
+    let deg = angle.to_degrees();
+    if deg > 180.0 { deg - 360.0 } else { deg }
+}
+
+/// A filter that fails if the gesture takes too long.
+///
+/// The time is measured from the frame in which the filter started.
+#[derive(Clone, Debug)]
+pub struct MaxDuration {
+    max_ms: u32,
+    start: u32,
+}
+
+impl MaxDuration {
+    /// Creates a filter that fails once more than `ms` milliseconds have passed.
+    pub fn new(ms: u32) -> MaxDuration {
+        MaxDuration {
+            max_ms: ms,
+            start: 0,
+        }
+    }
+
+    /// Creates a filter that allows `ms` milliseconds, stretched by `params.duration_scale`.
+    pub fn with_params(ms: u32, params: &FilterParams) -> MaxDuration {
+        MaxDuration::new((ms as f64 * params.duration_scale).round() as u32)
+    }
+}
+
+impl Filter for MaxDuration {
+    fn init(&mut self, frame: &Frame) {
+        self.start = frame.time;
+    }
+
+    fn update(&mut self, frame: &Frame) -> FilterResult {
+        if frame.time.wrapping_sub(self.start) > self.max_ms {
+            debug!("MaxDuration failed");
+            FilterResult::Failed
+        } else {
+            FilterResult::Passed
+        }
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        g.node(&format!("MaxDuration ({} ms)", self.max_ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use event::Event;
+    use frame::{Frame, FrameTracker};
+    use geom::{Point, UAngle};
+    use {Filter, FilterResult};
+    use super::{MaintainAngle, MaintainSpread};
+
+    // Ends a frame with some events in it.
+    fn frame(tracker: &mut FrameTracker, time: u32, evs: &[Event]) -> Frame {
+        for ev in evs {
+            tracker.push_event(ev);
+        }
+        tracker.end_frame(time).clone()
+    }
+
+    // Puts two fingers down, `dist` mm apart, and starts `filter` on that frame.
+    fn spread_down<F: Filter>(filter: &mut F, tracker: &mut FrameTracker, dist: f64) {
+        let down = frame(tracker, 0, &[
+            Event::Down { slot: 0, pos: Point::new(50.0 - dist / 2.0, 50.0) },
+            Event::Down { slot: 1, pos: Point::new(50.0 + dist / 2.0, 50.0) },
+        ]);
+        filter.init(&down);
+    }
+
+    // Moves the two fingers so that they are `dist` mm apart.
+    fn spread_to(tracker: &mut FrameTracker, time: u32, dist: f64) -> Frame {
+        frame(tracker, time, &[
+            Event::Motion { slot: 0, pos: Point::new(50.0 - dist / 2.0, 50.0) },
+            Event::Motion { slot: 1, pos: Point::new(50.0 + dist / 2.0, 50.0) },
+        ])
+    }
+
+    #[test]
+    fn maintain_spread() {
+        let mut filter = MaintainSpread::new();
+        let mut tracker = FrameTracker::new();
+        spread_down(&mut filter, &mut tracker, 40.0);
+        assert_eq!(filter.update(&spread_to(&mut tracker, 10, 44.0)), FilterResult::Passed);
+        assert_eq!(filter.update(&spread_to(&mut tracker, 20, 36.0)), FilterResult::Passed);
+        assert_eq!(filter.update(&spread_to(&mut tracker, 30, 50.0)), FilterResult::Failed);
+
+        let mut filter = MaintainSpread::new();
+        let mut tracker = FrameTracker::new();
+        spread_down(&mut filter, &mut tracker, 40.0);
+        assert_eq!(filter.update(&spread_to(&mut tracker, 10, 30.0)), FilterResult::Failed);
+    }
+
+    #[test]
+    fn maintain_spread_resets() {
+        let mut filter = MaintainSpread::new();
+        let mut tracker = FrameTracker::new();
+        spread_down(&mut filter, &mut tracker, 40.0);
+        // A third finger coming down changes the spread a lot, but that doesn't count.
+        let down = frame(&mut tracker, 10, &[
+            Event::Down { slot: 2, pos: Point::new(50.0, 110.0) },
+        ]);
+        assert_eq!(filter.update(&down), FilterResult::Passed);
+        assert_eq!(filter.update(&frame(&mut tracker, 20, &[])), FilterResult::Passed);
+
+        let up = frame(&mut tracker, 30, &[Event::Up { slot: 2 }]);
+        assert_eq!(filter.update(&up), FilterResult::Passed);
+        assert_eq!(filter.update(&spread_to(&mut tracker, 40, 44.0)), FilterResult::Passed);
+        assert_eq!(filter.update(&spread_to(&mut tracker, 50, 50.0)), FilterResult::Failed);
+    }
+
+    // Two fingers on opposite sides of a circle, turned `deg` degrees.
+    fn turned(slot: usize, deg: f64) -> Point {
+        let a = (deg + slot as f64 * 180.0).to_radians();
+        Point::new(50.0 + 20.0 * a.cos(), 50.0 + 20.0 * a.sin())
+    }
+
+    // Starts `filter` with two fingers down, and then turns them 5 degrees in the first frame and
+    // 10 in each one after that. Returns how far they had turned when the filter failed.
+    fn turn_until_failed(mut filter: MaintainAngle) -> Option<f64> {
+        let mut tracker = FrameTracker::new();
+        let down = frame(&mut tracker, 0, &[
+            Event::Down { slot: 0, pos: turned(0, 0.0) },
+            Event::Down { slot: 1, pos: turned(1, 0.0) },
+        ]);
+        filter.init(&down);
+        for i in 1..36 {
+            let deg = 10.0 * i as f64 - 5.0;
+            let f = frame(&mut tracker, 10 * i, &[
+                Event::Motion { slot: 0, pos: turned(0, deg) },
+                Event::Motion { slot: 1, pos: turned(1, deg) },
+            ]);
+            if filter.update(&f) == FilterResult::Failed {
+                return Some(deg);
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn maintain_angle() {
+        assert_eq!(turn_until_failed(MaintainAngle::new()), Some(35.0));
+    }
+
+    #[test]
+    fn maintain_angle_past_half_a_turn() {
+        // The rotation doesn't wrap around, so 195 degrees isn't mistaken for -165.
+        let filter = MaintainAngle::new().max_rotation(UAngle::from_degrees(190.0));
+        assert_eq!(turn_until_failed(filter), Some(195.0));
+    }
+}
@@ -1,6 +1,10 @@
 use euclid::vec2;
-use input::event::touch::{TouchEvent, TouchEventPosition, TouchEventSlot};
-use geom::Point;
+#[cfg(feature = "input")]
+use input::event::touch::TouchEvent;
+use classify::{Classifier, ContactKind, Kinds};
+use event::Event;
+use geom::{fit_similarity, Point, Similarity};
+use std::cmp::Ordering;
 use std::ops::{AddAssign, SubAssign};
 
 /// Summarizes the changes that took place in a `libinput` frame.
@@ -12,6 +16,12 @@ use std::ops::{AddAssign, SubAssign};
 /// Since it would be tedious for all of the individual gesture recognizers to interpret frame
 /// events themselves, this struct exists to summarize all of the changes that happened during
 /// the most recent frame.
+///
+/// A `Frame` is only consistent if it is updated in the right order: all the events of a frame,
+/// then the frame event itself, and then [`advance`](#method.advance) before the first event of
+/// the next frame. Also, `cur.num_down` must agree with `cur.down`. If you are feeding events to
+/// recognizers yourself, use a [`FrameTracker`](struct.FrameTracker.html), which takes care of
+/// this and only gives out read-only access to the `Frame`.
 #[derive(Clone, Debug)]
 pub struct Frame {
     /// Did a `TouchDown` event happen during the last frame?
@@ -22,6 +32,45 @@ pub struct Frame {
     pub cur: Snapshot,
     /// What were the last positions of all the fingers?
     pub last: Snapshot,
+    /// When did the last frame end? This is in milliseconds, measured from some arbitrary
+    /// starting point.
+    pub time: u32,
+    /// If a finger was dropped during the last frame because there were too many of them, this is
+    /// the policy that says what to do about it.
+    pub overflow: Option<Overflow>,
+    /// Did the events during the last frame disagree with what we thought the fingers were doing?
+    ///
+    /// This happens, for example, if a finger that is already down comes down again. Some
+    /// touchscreen firmwares send events out of order, and when they do, any gesture in progress
+    /// is probably confused.
+    pub inconsistent: bool,
+    /// Was a contact left out during the last frame, because of what a
+    /// [`Classifier`](../classify/trait.Classifier.html) decided it was?
+    ///
+    /// A contact that is left out disappears from both `cur` and `last`, so any gesture in
+    /// progress probably has the wrong idea about how many fingers there are.
+    pub excluded: bool,
+    /// How the fingers moved from `last` to `cur`. This is worked out at the end of each frame.
+    pub delta: FrameDelta,
+    limit: Option<(u8, Overflow)>,
+    // The slots of the fingers that we are ignoring because of `limit`.
+    ignored: Vec<usize>,
+    // Set when we are treating the current touch as a palm, and cleared once every finger is up.
+    palm: bool,
+    // The kinds of contact that `classify` leaves out.
+    left_out: Kinds,
+}
+
+/// What to do when more fingers come down than a `Frame` is allowed to track.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Overflow {
+    /// Ignore the extra fingers, and carry on with the others.
+    Ignore,
+    /// Ignore the extra fingers, and make all the gestures in progress fail.
+    Fail,
+    /// Treat the whole touch as a palm: all the gestures in progress fail, and nothing new starts
+    /// until every finger (including the ignored ones) has gone up.
+    Palm,
 }
 
 impl Frame {
@@ -32,71 +81,372 @@ impl Frame {
             touch_up: false,
             cur: Snapshot::new(),
             last: Snapshot::new(),
+            time: 0,
+            overflow: None,
+            inconsistent: false,
+            excluded: false,
+            delta: FrameDelta::new(),
+            limit: None,
+            ignored: Vec::new(),
+            palm: false,
+            left_out: Kinds::of(&[ContactKind::Thumb, ContactKind::Palm]),
         }
     }
 
+    /// Limits the number of fingers that this `Frame` tracks at once.
+    ///
+    /// By default there is no limit. If `max` fingers are already down and another one comes down,
+    /// the new finger is ignored until it goes up again, and `policy` says what else to do.
+    pub fn set_contact_limit(&mut self, max: Option<u8>, policy: Overflow) {
+        self.limit = max.map(|m| (m, policy));
+    }
+
+    /// Sets the kinds of contact that [`classify`](#method.classify) leaves out.
+    ///
+    /// By default, thumbs and palms are left out.
+    pub fn set_left_out(&mut self, kinds: Kinds) {
+        self.left_out = kinds;
+    }
+
     /// Updates a `Frame` to account for a new `TouchEvent` that just happened.
+    #[cfg(feature = "input")]
     pub fn update(&mut self, ev: &TouchEvent) {
-        match ev {
-            &TouchEvent::Down(ref ev) => {
-                let slot = ev.slot().unwrap_or(0) as usize;
+        self.update_event(&Event::from(ev));
+    }
 
-                if slot >= MAX_SLOTS {
-                    println!("not enough slots for {:?}", ev);
+    /// Updates a `Frame` to account for a new `Event` that just happened.
+    pub fn update_event(&mut self, ev: &Event) {
+        match *ev {
+            Event::Down { slot, pos } => {
+                if self.palm {
+                    self.ignored.push(slot);
                     return;
                 }
-                if self.cur.down[slot] {
-                    println!("down event, but the finger was already down?");
+                if let Some((max, policy)) = self.limit {
+                    if self.cur.num_down >= max {
+                        debug!("ignoring finger in slot {}, because {} fingers are already down", slot, max);
+                        self.ignored.push(slot);
+                        self.overflow = Some(policy);
+                        self.palm = policy == Overflow::Palm;
+                        return;
+                    }
+                }
+                if self.cur.is_down(slot) {
+                    warn!("down event in slot {}, but the finger was already down", slot);
+                    self.inconsistent = true;
                     return;
                 }
 
                 self.touch_down = true;
-                self.cur.down[slot] = true;
-                self.cur.pos[slot] = vec2(ev.x(), ev.y());
-                self.cur.num_down += 1;
+                self.cur.set_down(slot, pos);
             },
-            &TouchEvent::Up(ref ev) => {
-                let slot = ev.slot().unwrap_or(0) as usize;
-                if !self.cur.down[slot] {
-                    println!("up event, but the finger was already up?");
-                    return;
+            Event::Up { slot } => {
+                if let Some(i) = self.ignored.iter().position(|&s| s == slot) {
+                    self.ignored.swap_remove(i);
+                } else if !self.cur.is_down(slot) {
+                    warn!("up event in slot {}, but the finger was already up", slot);
+                    self.inconsistent = true;
+                } else {
+                    self.touch_up = true;
+                    self.cur.set_up(slot);
+                }
+                if self.cur.num_down == 0 && self.ignored.is_empty() {
+                    self.palm = false;
                 }
-
-                self.touch_up = true;
-                self.cur.down[slot] = false;
-                self.cur.num_down -= 1;
             },
-            &TouchEvent::Motion(ref ev) => {
-                let slot = ev.slot().unwrap_or(0) as usize;
-                self.cur.pos[slot] = vec2(ev.x(), ev.y());
+            Event::Motion { slot, pos } => {
+                if self.cur.is_down(slot) {
+                    self.cur.pos[slot] = pos;
+                }
             },
-            &TouchEvent::Cancel(_) => {
-                println!("what should I do with a cancel event?");
+            Event::Shape { slot, size, pressure } => {
+                if self.cur.is_down(slot) {
+                    self.cur.size[slot] = size;
+                    self.cur.pressure[slot] = pressure;
+                }
+            },
+            Event::Cancel => {
+                warn!("ignoring a cancel event, because we don't know what to do with it");
             },
-            &TouchEvent::Frame(_) => {
+            Event::Frame { time } => {
+                self.time = time;
+                self.check_consistency();
+                self.delta = FrameDelta::between(&self.last, &self.cur);
             },
         }
     }
 
+    // Makes sure that `num_down` agrees with the fingers that are actually down.
+    fn check_consistency(&mut self) {
+        let count = self.cur.down.iter().filter(|&&d| d).count();
+        if count != self.cur.num_down as usize {
+            warn!("{} fingers are down, but we counted {}", count, self.cur.num_down);
+            self.cur.num_down = count as u8;
+            self.inconsistent = true;
+        }
+    }
+
+    /// Lets `c` update the kinds of the contacts, and then leaves out the ones whose kinds were
+    /// given to [`set_left_out`](#method.set_left_out), until they go up.
+    ///
+    /// This should be called after the frame event. The contacts that are left out are taken out
+    /// of `cur` and `last`, and `delta` is worked out again without them.
+    pub fn classify(&mut self, c: &mut Classifier) {
+        self.cur.grow(0);
+        let mut kinds = self.cur.kind.clone();
+        c.classify(self, &mut kinds);
+        self.cur.kind = kinds;
+
+        let left_out = self.cur.fingers()
+            .map(|(slot, _)| slot)
+            .filter(|&slot| self.left_out.contains(self.cur.kind[slot]))
+            .collect::<Vec<_>>();
+        for slot in left_out {
+            debug!("leaving out the {} in slot {}", self.cur.kind[slot], slot);
+            self.cur.set_up(slot);
+            self.last.set_up(slot);
+            self.ignored.push(slot);
+            self.excluded = true;
+        }
+        if self.excluded {
+            self.delta = FrameDelta::between(&self.last, &self.cur);
+        }
+    }
+
+    /// Returns this frame as it looks to someone who only counts the contacts whose kinds are in
+    /// `kinds`.
+    ///
+    /// A contact whose kind changed during the frame looks like it came down, or went up.
+    pub fn only(&self, kinds: Kinds) -> Frame {
+        let mut ret = self.clone();
+        ret.cur = self.cur.only(kinds);
+        ret.last = self.last.only(kinds);
+        ret.touch_down = ret.cur.fingers().any(|(slot, _)| !ret.last.is_down(slot));
+        ret.touch_up = ret.last.fingers().any(|(slot, _)| !ret.cur.is_down(slot));
+        ret.delta = FrameDelta::between(&ret.last, &ret.cur);
+        ret
+    }
+
+    /// Returns the mean movement, during this frame, of the `k` fingers that moved furthest.
+    ///
+    /// Only the fingers that were down all through the frame count. If there are `k` or fewer of
+    /// them, this is the mean movement of all of them. Following just the fingers that move
+    /// furthest means that a finger that sticks to the surface doesn't slow down the others.
+    pub fn leading_motion(&self, k: usize) -> Point {
+        let mut moves = self.cur.common(&self.last)
+            .map(|i| self.cur.pos[i] - self.last.pos[i])
+            .collect::<Vec<_>>();
+        moves.sort_by(|a, b| b.length().partial_cmp(&a.length()).unwrap_or(Ordering::Equal));
+        moves.truncate(k);
+        if moves.is_empty() {
+            vec2(0.0, 0.0)
+        } else {
+            moves.iter().fold(vec2(0.0, 0.0), |a, &b| a + b) / (moves.len() as f64)
+        }
+    }
+
+    /// Returns true if no contacts are down, including the ones that are being ignored.
+    pub fn is_empty(&self) -> bool {
+        self.cur.num_down == 0 && self.ignored.is_empty()
+    }
+
     /// Resets the `Frame` to get ready for the next frame.
     pub fn advance(&mut self) {
-        self.last = self.cur;
+        self.last = self.cur.clone();
         self.touch_up = false;
         self.touch_down = false;
+        self.overflow = None;
+        self.inconsistent = false;
+        self.excluded = false;
     }
 }
 
-pub const MAX_SLOTS: usize = 10;
+/// A summary of how the fingers moved during a frame.
+///
+/// Most recognizers look at the fingers' mean position, or at how spread out they are, on every
+/// frame. A `Frame` works these out once, when the frame ends, so that every recognizer can share
+/// them.
+///
+/// # Examples
+/// ```
+/// use libgestures::event::Event;
+/// use libgestures::frame::FrameTracker;
+/// use libgestures::geom::Point;
+///
+/// let mut tracker = FrameTracker::new();
+/// tracker.push_event(&Event::Down { slot: 0, pos: Point::new(0.0, 0.0) });
+/// tracker.push_event(&Event::Down { slot: 1, pos: Point::new(10.0, 0.0) });
+/// tracker.end_frame(10);
+///
+/// tracker.push_event(&Event::Motion { slot: 1, pos: Point::new(20.0, 0.0) });
+/// let delta = &tracker.end_frame(20).delta;
+/// assert_eq!(delta.mean_pos, Point::new(10.0, 0.0));
+/// assert_eq!(delta.fingers[1], Point::new(10.0, 0.0));
+/// assert_eq!(delta.spread, 2.0 * delta.last_spread);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FrameDelta {
+    /// The mean position of the fingers at the end of the frame (see
+    /// [`Snapshot::mean_pos`](struct.Snapshot.html#method.mean_pos)).
+    pub mean_pos: Point,
+    /// The mean position of the fingers at the start of the frame.
+    pub last_mean_pos: Point,
+    /// How spread out the fingers are at the end of the frame (see
+    /// [`Snapshot::spread`](struct.Snapshot.html#method.spread)).
+    pub spread: f64,
+    /// How spread out the fingers were at the start of the frame.
+    pub last_spread: f64,
+    /// The mean distance that the fingers moved (see
+    /// [`Snapshot::mean_dist`](struct.Snapshot.html#method.mean_dist)).
+    pub mean_dist: f64,
+    /// How far each finger moved, indexed by slot. This is zero for the fingers that weren't down
+    /// at both the start and the end of the frame.
+    pub fingers: Vec<Point>,
+    /// The movement, rotation and change in spread that best explain how the fingers that were
+    /// down all through the frame moved (see [`fit_similarity`](../geom/fn.fit_similarity.html)).
+    /// Unlike the difference between `mean_pos` and `last_mean_pos`, the movement here ignores
+    /// fingers coming down or going up.
+    pub motion: Similarity,
+}
+
+impl FrameDelta {
+    /// A `FrameDelta` in which nothing moved.
+    pub fn new() -> FrameDelta {
+        FrameDelta {
+            mean_pos: vec2(0.0, 0.0),
+            last_mean_pos: vec2(0.0, 0.0),
+            spread: 0.0,
+            last_spread: 0.0,
+            mean_dist: 0.0,
+            fingers: Vec::new(),
+            motion: Similarity::identity(),
+        }
+    }
+
+    /// Works out how the fingers moved from `last` to `cur`.
+    pub fn between(last: &Snapshot, cur: &Snapshot) -> FrameDelta {
+        let fingers = (0..cur.down.len())
+            .map(|i| if last.is_down(i) && cur.down[i] {
+                cur.pos[i] - last.pos[i]
+            } else {
+                vec2(0.0, 0.0)
+            })
+            .collect();
+        FrameDelta {
+            mean_pos: cur.mean_pos(),
+            last_mean_pos: last.mean_pos(),
+            spread: cur.spread(),
+            last_spread: last.spread(),
+            mean_dist: cur.mean_dist(last),
+            fingers: fingers,
+            motion: fit_similarity(last, cur),
+        }
+    }
+}
+
+/// Keeps a [`Frame`](struct.Frame.html) up to date as events arrive.
+///
+/// # Examples
+/// ```
+/// use libgestures::event::Event;
+/// use libgestures::frame::FrameTracker;
+/// use libgestures::geom::Point;
+///
+/// let mut tracker = FrameTracker::new();
+/// tracker.push_event(&Event::Down { slot: 0, pos: Point::new(1.0, 2.0) });
+/// assert!(tracker.end_frame(10).touch_down);
+///
+/// tracker.push_event(&Event::Motion { slot: 0, pos: Point::new(3.0, 2.0) });
+/// let frame = tracker.end_frame(20);
+/// assert!(!frame.touch_down);
+/// assert_eq!(frame.last.pos[0], Point::new(1.0, 2.0));
+/// assert_eq!(frame.cur.pos[0], Point::new(3.0, 2.0));
+/// ```
+#[derive(Clone, Debug)]
+pub struct FrameTracker {
+    frame: Frame,
+    // Set once a frame has ended, so that it gets advanced before the next event.
+    ended: bool,
+}
+
+impl FrameTracker {
+    /// Creates a new `FrameTracker`, with no fingers down.
+    pub fn new() -> FrameTracker {
+        FrameTracker {
+            frame: Frame::new(),
+            ended: false,
+        }
+    }
+
+    /// Limits the number of fingers that are tracked at once.
+    ///
+    /// See [`Frame::set_contact_limit`](struct.Frame.html#method.set_contact_limit).
+    pub fn set_contact_limit(&mut self, max: Option<u8>, policy: Overflow) {
+        self.frame.set_contact_limit(max, policy);
+    }
+
+    fn start_frame(&mut self) {
+        if self.ended {
+            self.frame.advance();
+            self.ended = false;
+        }
+    }
+
+    /// Adds an event to the current frame.
+    ///
+    /// A frame event ends the frame, just like [`end_frame`](#method.end_frame).
+    pub fn push_event(&mut self, ev: &Event) {
+        match *ev {
+            Event::Frame { time } => { self.end_frame(time); },
+            _ => {
+                self.start_frame();
+                self.frame.update_event(ev);
+            },
+        }
+    }
+
+    /// Adds a libinput event to the current frame.
+    #[cfg(feature = "input")]
+    pub fn push(&mut self, ev: &TouchEvent) {
+        self.push_event(&Event::from(ev));
+    }
+
+    /// Ends the current frame, which happened at `time` (in milliseconds), and returns it.
+    pub fn end_frame(&mut self, time: u32) -> &Frame {
+        // A frame without any events.
+        self.start_frame();
+        self.frame.update_event(&Event::Frame { time: time });
+        self.ended = true;
+        &self.frame
+    }
+
+    /// Returns the most recent frame.
+    ///
+    /// If some events have been pushed since the last frame ended, they are already included.
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+}
 
 /// A `Snapshot` storesa snapshot of the state of the fingers.
-#[derive(Clone, Copy, Debug)]
+///
+/// The fingers are indexed by their libinput slots. There is no fixed limit on the number of
+/// slots: the vectors grow as needed.
+#[derive(Clone, Debug)]
 pub struct Snapshot {
     /// How many fingers are down?
     pub num_down: u8,
     /// Which of the indices in `pos` represent fingers that are down?
-    pub down: [bool; MAX_SLOTS],
+    pub down: Vec<bool>,
     /// What are the positions of the fingers that are down?
-    pub pos: [Point; MAX_SLOTS],
+    pub pos: Vec<Point>,
+    /// What kind of thing is each contact? See the [`classify`](../classify/index.html) module.
+    pub kind: Vec<ContactKind>,
+    /// How big (in millimeters, across the long side) is each contact? This is zero if the device
+    /// doesn't say.
+    pub size: Vec<f64>,
+    /// How hard (from 0 to 1) is each contact pressing? This is zero if the device doesn't say.
+    pub pressure: Vec<f64>,
 }
 
 impl Snapshot {
@@ -104,14 +454,39 @@ impl Snapshot {
     pub fn new() -> Snapshot {
         Snapshot {
             num_down: 0,
-            down: [false; MAX_SLOTS],
-            pos: [vec2(0.0, 0.0); MAX_SLOTS],
+            down: Vec::new(),
+            pos: Vec::new(),
+            kind: Vec::new(),
+            size: Vec::new(),
+            pressure: Vec::new(),
         }
     }
 
+    fn grow(&mut self, len: usize) {
+        // The fields are public, so they might not all have the same length.
+        let len = len.max(self.down.len());
+        self.down.resize(len, false);
+        self.pos.resize(len, vec2(0.0, 0.0));
+        self.kind.resize(len, ContactKind::Unknown);
+        self.size.resize(len, 0.0);
+        self.pressure.resize(len, 0.0);
+    }
+
+    /// Is finger `i` down?
+    pub fn is_down(&self, i: usize) -> bool {
+        self.down.get(i).cloned().unwrap_or(false)
+    }
+
+    // The indices of the fingers that are down in both `self` and `other`.
+    fn common<'a>(&'a self, other: &'a Snapshot) -> impl Iterator<Item=usize> + 'a {
+        (0..self.down.len().min(other.down.len()))
+            .filter(move |&i| self.down[i] && other.down[i])
+    }
+
     /// Interpolates this snapshot towards another snapshot.
     pub fn interpolate_to(&mut self, other: &Snapshot, lambda: f64) {
-        for i in 0..MAX_SLOTS {
+        self.grow(other.down.len());
+        for i in 0..other.down.len() {
             if other.down[i] {
                 self.pos[i] = self.pos[i] * (1.0 - lambda) + other.pos[i] * lambda;
             }
@@ -132,18 +507,30 @@ impl Snapshot {
         }
     }
 
+    /// Returns the mean distance between the fingers that are down and their average position.
+    ///
+    /// This measures how spread out the fingers are. If there are no down fingers, returns zero.
+    pub fn spread(&self) -> f64 {
+        let mean = self.mean_pos();
+        let sum: f64 = self.fingers()
+            .map(|(_, p)| (p - mean).length())
+            .sum();
+        if self.num_down == 0 {
+            0.0
+        } else {
+            sum / (self.num_down as f64)
+        }
+    }
+
     /// Returns the arithmetic mean of the positions of all the fingers that are down in both this
     /// snapshot and `other`.
     ///
     /// If there are no such fingers, returns zero.
     pub fn mean_pos_filtered(&self, other: &Snapshot) -> Point {
-        let sum: Point = (0..MAX_SLOTS)
-            .filter(|i| self.down[*i] && other.down[*i])
+        let sum: Point = self.common(other)
             .map(|i| self.pos[i])
             .fold(vec2(0.0, 0.0), |a, b| {a + b});
-		let count = (0..MAX_SLOTS)
-            .filter(|i| self.down[*i] && other.down[*i])
-			.count();
+        let count = self.common(other).count();
         if count == 0 {
             vec2(0.0, 0.0)
         } else {
@@ -155,8 +542,7 @@ impl Snapshot {
     ///
     /// If there are no fingers that are down in both snapshots, returns zero.
     pub fn mean_dist(&self, other: &Snapshot) -> f64 {
-        let sum: f64 = (0..MAX_SLOTS)
-            .filter(|i| self.down[*i] && other.down[*i])
+        let sum: f64 = self.common(other)
             .map(|i| (self.pos[i] - other.pos[i]).length())
             .sum();
         if self.num_down == 0 {
@@ -168,23 +554,33 @@ impl Snapshot {
 
     /// Marks finger `i` as down, in position `pos`. If the finger is already down, its position is
     /// updated to `pos`.
-    ///
-    /// # Panics
-    /// Panics if `i` is too large (i.e., greater than or equal to `MAX_SLOTS`).
     pub fn set_down(&mut self, i: usize, pos: Point) {
+        self.grow(i + 1);
         if !self.down[i] {
             self.num_down += 1;
             self.down[i] = true;
+            self.kind[i] = ContactKind::Unknown;
+            self.size[i] = 0.0;
+            self.pressure[i] = 0.0;
         }
         self.pos[i] = pos;
     }
 
+    /// Returns a copy of this snapshot without the contacts whose kinds aren't in `kinds`.
+    pub fn only(&self, kinds: Kinds) -> Snapshot {
+        let mut ret = self.clone();
+        ret.grow(0);
+        for i in 0..ret.down.len() {
+            if ret.down[i] && !kinds.contains(ret.kind[i]) {
+                ret.set_up(i);
+            }
+        }
+        ret
+    }
+
     /// Marks finger `i` as up.
-    ///
-    /// # Panics
-    /// Panics if `i` is too large (i.e., greater than or equal to `MAX_SLOTS`).
     pub fn set_up(&mut self, i: usize) {
-        if self.down[i] {
+        if self.is_down(i) {
             self.num_down -= 1;
             self.down[i] = false;
         }
@@ -192,7 +588,7 @@ impl Snapshot {
 
     /// Returns an iterator over all of the indices and positions of the fingers that are down.
     pub fn fingers<'a>(&'a self) -> impl Iterator<Item=(usize, Point)> + 'a {
-        (0..MAX_SLOTS)
+        (0..self.down.len())
             .filter(move |i| self.down[*i])
             .map(move |i| (i, self.pos[i]))
     }
@@ -200,10 +596,10 @@ impl Snapshot {
     /// Copies over to `self` all the positions of fingers that are down in `other` but not in
     /// `self. Deletes from `self` all the fingers that are up in `other`.
     pub fn merge(&mut self, other: &Snapshot) {
-        for i in 0..MAX_SLOTS {
-            if !self.down[i] && other.down[i] {
+        for i in 0..self.down.len().max(other.down.len()) {
+            if !self.is_down(i) && other.is_down(i) {
                 self.set_down(i, other.pos[i]);
-            } else if self.down[i] && !other.down[i] {
+            } else if self.is_down(i) && !other.is_down(i) {
                 self.set_up(i);
             }
         }
@@ -212,16 +608,16 @@ impl Snapshot {
 
 impl AddAssign<Point> for Snapshot {
     fn add_assign(&mut self, rhs: Point) {
-        for i in 0..MAX_SLOTS {
-            self.pos[i] += rhs;
+        for p in &mut self.pos {
+            *p += rhs;
         }
     }
 }
 
 impl SubAssign<Point> for Snapshot {
     fn sub_assign(&mut self, rhs: Point) {
-        for i in 0..MAX_SLOTS {
-            self.pos[i] -= rhs;
+        for p in &mut self.pos {
+            *p -= rhs;
         }
     }
 }
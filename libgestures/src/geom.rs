@@ -1,11 +1,19 @@
 use euclid;
+use euclid::vec2;
+use frame::Snapshot;
 use std::f64;
 use std::f64::consts::PI;
+use std::fmt;
 use std::ops::{Add, Neg, Sub};
+use std::str::FromStr;
 
 pub struct Mm;
 pub type Point = euclid::TypedVector2D<f64, Mm>;
 
+pub struct Px;
+/// A position on the screen, in pixels.
+pub type ScreenPoint = euclid::TypedVector2D<f64, Px>;
+
 /// Represents an angle.
 ///
 /// This type doesn't differentiate between multiples of full rotations; that is,
@@ -140,6 +148,27 @@ impl Neg for Angle {
     }
 }
 
+// Writes a number of degrees, with one decimal place unless the formatter asks for some other
+// precision.
+fn write_degrees(f: &mut fmt::Formatter, degrees: f64) -> fmt::Result {
+    write!(f, "{:.*}°", f.precision().unwrap_or(1), degrees)
+}
+
+/// Writes the angle in degrees.
+///
+/// # Examples
+/// ```
+/// use libgestures::geom::Angle;
+///
+/// assert_eq!(Angle::from_degrees(-90.0).to_string(), "270.0°");
+/// assert_eq!(format!("{:.0}", Angle::from_degrees(12.34)), "12°");
+/// ```
+impl fmt::Display for Angle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_degrees(f, self.to_degrees())
+    }
+}
+
 /// An unsized angle.
 ///
 /// This is useful for measuring the size of an angle without regard to its direction.
@@ -182,6 +211,13 @@ impl UAngle {
     }
 }
 
+/// Writes the angle in degrees, in the same way as for [`Angle`](struct.Angle.html).
+impl fmt::Display for UAngle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_degrees(f, self.to_degrees())
+    }
+}
+
 impl Add<UAngle> for UAngle {
     type Output = UAngle;
 
@@ -193,6 +229,7 @@ impl Add<UAngle> for UAngle {
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Direction {
     Up,
     Down,
@@ -255,6 +292,23 @@ impl Direction {
         }
     }
 
+    /// Converts an angle to a direction by rounding it, with a different threshold for each
+    /// direction.
+    ///
+    /// This is like [`from_angle`](#method.from_angle), except that `angle` is rounded to a
+    /// direction if it is within that direction's threshold.
+    pub fn from_angle_thresholds(angle: Angle, thresholds: &DirectionThresholds)
+    -> Option<Direction> {
+        for &d in &[Direction::Right, Direction::Up, Direction::Left, Direction::Down] {
+            let t = thresholds.get(d).to_radians();
+            assert!(t <= PI / 4.0);
+            if (angle - d.to_angle()).abs().to_radians() <= t {
+                return Some(d);
+            }
+        }
+        None
+    }
+
     /// Converts a `Direction` to an angle.
     ///
     /// # Examples
@@ -276,12 +330,400 @@ impl Direction {
             Down => Angle::from_degrees(270.0),
         }
     }
+
+    /// Returns the direction pointing the other way.
+    pub fn opposite(&self) -> Direction {
+        match *self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    /// Returns the direction that is a quarter turn clockwise from this one.
+    ///
+    /// # Examples
+    /// ```
+    /// use libgestures::geom::Direction;
+    ///
+    /// assert_eq!(Direction::Up.rotate_cw(), Direction::Right);
+    /// assert_eq!(Direction::Up.rotate_cw().rotate_ccw(), Direction::Up);
+    /// ```
+    pub fn rotate_cw(&self) -> Direction {
+        match *self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    /// Returns the direction that is a quarter turn counter-clockwise from this one.
+    pub fn rotate_ccw(&self) -> Direction {
+        self.opposite().rotate_cw()
+    }
+
+    /// Returns the axis that this direction points along.
+    pub fn axis(&self) -> Axis {
+        match *self {
+            Direction::Up | Direction::Down => Axis::Vertical,
+            Direction::Left | Direction::Right => Axis::Horizontal,
+        }
+    }
+
+    /// Returns a vector of length one pointing in this direction.
+    ///
+    /// This is in the coordinates of the touch surface, where the `y` coordinate increases
+    /// downwards. That is, `Direction::Up` points towards negative `y`, even though its
+    /// [angle](#method.to_angle) is 90 degrees.
+    ///
+    /// # Examples
+    /// ```
+    /// use libgestures::geom::{Direction, Point};
+    ///
+    /// assert_eq!(Direction::Up.to_unit_vector(), Point::new(0.0, -1.0));
+    /// assert_eq!(Direction::Right.to_unit_vector(), Point::new(1.0, 0.0));
+    /// ```
+    pub fn to_unit_vector(&self) -> Point {
+        match *self {
+            Direction::Up => Point::new(0.0, -1.0),
+            Direction::Down => Point::new(0.0, 1.0),
+            Direction::Left => Point::new(-1.0, 0.0),
+            Direction::Right => Point::new(1.0, 0.0),
+        }
+    }
+
+    /// The name of this direction, as it is written in configuration files.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Reads a direction from its name.
+///
+/// # Examples
+/// ```
+/// use libgestures::geom::Direction;
+///
+/// assert_eq!("up".parse::<Direction>(), Ok(Direction::Up));
+/// assert_eq!(Direction::Left.to_string().parse::<Direction>(), Ok(Direction::Left));
+/// assert!("sideways".parse::<Direction>().is_err());
+/// ```
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Direction, String> {
+        match s {
+            "up" => Ok(Direction::Up),
+            "down" => Ok(Direction::Down),
+            "left" => Ok(Direction::Left),
+            "right" => Ok(Direction::Right),
+            _ => Err(format!("unknown direction {:?}", s)),
+        }
+    }
+}
+
+/// How far an angle may be from each of the four cardinal directions, and still be rounded to
+/// that direction.
+///
+/// By default, all of the thresholds are 25 degrees.
+///
+/// # Examples
+/// ```
+/// use libgestures::geom::{Angle, Direction, DirectionThresholds, UAngle};
+///
+/// let thresholds = DirectionThresholds::uniform(UAngle::from_degrees(20.0))
+///     .threshold(Direction::Up, UAngle::from_degrees(40.0));
+/// let round = |deg| Direction::from_angle_thresholds(Angle::from_degrees(deg), &thresholds);
+///
+/// assert_eq!(round(125.0), Some(Direction::Up));
+/// assert_eq!(round(55.0), Some(Direction::Up));
+/// assert_eq!(round(30.0), None);
+/// assert_eq!(round(15.0), Some(Direction::Right));
+/// assert_eq!(round(255.0), Some(Direction::Down));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirectionThresholds {
+    up: UAngle,
+    down: UAngle,
+    left: UAngle,
+    right: UAngle,
+}
+
+impl DirectionThresholds {
+    /// Uses the same threshold for every direction.
+    pub fn uniform(t: UAngle) -> DirectionThresholds {
+        DirectionThresholds {
+            up: t,
+            down: t,
+            left: t,
+            right: t,
+        }
+    }
+
+    /// Sets the threshold for the direction `d`.
+    ///
+    /// # Panics
+    /// if `t` is larger than 45 degrees.
+    pub fn threshold(mut self, d: Direction, t: UAngle) -> DirectionThresholds {
+        assert!(t.to_radians() <= PI / 4.0);
+        match d {
+            Direction::Up => self.up = t,
+            Direction::Down => self.down = t,
+            Direction::Left => self.left = t,
+            Direction::Right => self.right = t,
+        }
+        self
+    }
+
+    /// Returns the threshold for the direction `d`.
+    pub fn get(&self, d: Direction) -> UAngle {
+        match d {
+            Direction::Up => self.up,
+            Direction::Down => self.down,
+            Direction::Left => self.left,
+            Direction::Right => self.right,
+        }
+    }
+}
+
+impl Default for DirectionThresholds {
+    fn default() -> DirectionThresholds {
+        DirectionThresholds::uniform(UAngle::from_degrees(25.0))
+    }
+}
+
+/// The horizontal or the vertical axis.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+impl Axis {
+    /// Scales the part of `v` that is perpendicular to this axis by `weight`.
+    ///
+    /// # Examples
+    /// ```
+    /// use libgestures::geom::{Axis, Point};
+    ///
+    /// let v = Point::new(3.0, 4.0);
+    /// assert_eq!(Axis::Horizontal.bias(v, 0.0), Point::new(3.0, 0.0));
+    /// assert_eq!(Axis::Vertical.bias(v, 0.5), Point::new(1.5, 4.0));
+    /// ```
+    pub fn bias(&self, v: Point, weight: f64) -> Point {
+        match *self {
+            Axis::Horizontal => Point::new(v.x, v.y * weight),
+            Axis::Vertical => Point::new(v.x * weight, v.y),
+        }
+    }
+}
+
+/// A movement made of scaling and rotating about some point, followed by a translation.
+///
+/// This describes how a group of fingers moved, when they all moved in the same way; see
+/// [`fit_similarity`](fn.fit_similarity.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Similarity {
+    /// How far (in millimeters) the middle of the fingers moved.
+    pub translation: Point,
+    /// How much the fingers rotated, counter-clockwise.
+    pub rotation: Angle,
+    /// How much the fingers moved apart (if this is more than one) or together (if it is less).
+    pub scale: f64,
+}
+
+impl Similarity {
+    /// The similarity that doesn't move anything.
+    pub fn identity() -> Similarity {
+        Similarity {
+            translation: vec2(0.0, 0.0),
+            rotation: Angle::from_radians(0.0),
+            scale: 1.0,
+        }
+    }
+
+    /// Combines two movements, by applying `other` after `self`.
+    pub fn then(&self, other: &Similarity) -> Similarity {
+        Similarity {
+            translation: self.translation + other.translation,
+            rotation: self.rotation + other.rotation,
+            scale: self.scale * other.scale,
+        }
+    }
+}
+
+/// Finds the similarity that best explains how the fingers that are down in both `prev` and `cur`
+/// moved from one to the other.
+///
+/// The fit is the least-squares one: the scaling and rotation are about the mean position of the
+/// fingers in `prev`, and the translation is the movement of the mean position. If there is only
+/// one finger, there is no way to tell whether it rotated or scaled, so only the translation is
+/// fitted. If no fingers are down in both snapshots, the result is the identity.
+pub fn fit_similarity(prev: &Snapshot, cur: &Snapshot) -> Similarity {
+    let common = (0..prev.down.len().min(cur.down.len()))
+        .filter(|&i| prev.down[i] && cur.down[i])
+        .collect::<Vec<_>>();
+    if common.is_empty() {
+        return Similarity::identity();
+    }
+
+    let n = common.len() as f64;
+    let prev_mean = common.iter().fold(vec2(0.0, 0.0), |a, &i| a + prev.pos[i]) / n;
+    let cur_mean = common.iter().fold(vec2(0.0, 0.0), |a, &i| a + cur.pos[i]) / n;
+    let (mut dot, mut cross, mut norm) = (0.0, 0.0, 0.0);
+    for &i in &common {
+        let p = prev.pos[i] - prev_mean;
+        let q = cur.pos[i] - cur_mean;
+        dot += p.dot(q);
+        cross += p.x * q.y - p.y * q.x;
+        norm += p.dot(p);
+    }
+
+    let mut ret = Similarity::identity();
+    ret.translation = cur_mean - prev_mean;
+    if norm > 0.0 {
+        ret.scale = (dot * dot + cross * cross).sqrt() / norm;
+        // The y axis points down, so a positive cross product is a clockwise rotation.
+        ret.rotation = Angle::from_radians(-cross.atan2(dot));
+    }
+    ret
+}
+
+/// How far something that follows the fingers should move towards where they are now, after they
+/// have moved `dist` millimeters.
+///
+/// `rate` is how quickly (per millimeter) it follows them. The result is between zero and one, and
+/// it doesn't matter how the movement is divided into frames: following the fingers for one
+/// distance and then another ends up in the same place as following them for both at once. That
+/// keeps the recognizers that use this behaving the same whatever the frame rate.
+///
+/// # Examples
+/// ```
+/// use libgestures::geom::adaptation;
+///
+/// let once = adaptation(0.1, 4.0);
+/// let twice = 1.0 - (1.0 - adaptation(0.1, 2.0)) * (1.0 - adaptation(0.1, 2.0));
+/// assert!((once - twice).abs() < 1e-12);
+/// ```
+pub fn adaptation(rate: f64, dist: f64) -> f64 {
+    1.0 - (-rate * dist).exp()
+}
+
+/// Converts a position on a touch surface that is `width` by `height` millimeters into fractions
+/// of the surface's width and height.
+///
+/// The top left corner of the surface is `(0.0, 0.0)`, and the bottom right corner is
+/// `(1.0, 1.0)`. Positions outside the surface are clamped to its edges.
+///
+/// # Examples
+/// ```
+/// use libgestures::geom::{surface_fraction, Point};
+///
+/// assert_eq!(surface_fraction(Point::new(50.0, 30.0), 200.0, 120.0), (0.25, 0.25));
+/// assert_eq!(surface_fraction(Point::new(-5.0, 130.0), 200.0, 120.0), (0.0, 1.0));
+/// ```
+pub fn surface_fraction(p: Point, width: f64, height: f64) -> (f64, f64) {
+    let clamp = |x: f64| x.max(0.0).min(1.0);
+    (clamp(p.x / width), clamp(p.y / height))
+}
+
+/// Which way an output is turned, in the same terms as `xrandr --rotate`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OutputRotation {
+    Normal,
+    /// Turned a quarter turn counter-clockwise.
+    Left,
+    Inverted,
+    /// Turned a quarter turn clockwise.
+    Right,
+}
+
+impl Default for OutputRotation {
+    fn default() -> OutputRotation {
+        OutputRotation::Normal
+    }
+}
+
+/// How a touch surface lines up with the part of the screen that it covers.
+///
+/// A touchscreen covers the whole of one output, which is usually only part of the screen when
+/// there are several monitors. If the output is rotated, the touch surface (which doesn't know
+/// about the rotation) is turned along with it.
+///
+/// # Examples
+/// ```
+/// use libgestures::geom::{OutputMapping, OutputRotation, Point, ScreenPoint};
+///
+/// // A 300mm by 200mm touchscreen, on a monitor to the right of a 1920-pixel-wide one.
+/// let mut mapping = OutputMapping {
+///     surface_width: 300.0,
+///     surface_height: 200.0,
+///     origin: ScreenPoint::new(1920.0, 0.0),
+///     width: 1500.0,
+///     height: 1000.0,
+///     rotation: OutputRotation::Normal,
+/// };
+/// assert_eq!(mapping.to_screen(Point::new(150.0, 20.0)), ScreenPoint::new(2670.0, 100.0));
+///
+/// // The same monitor turned on its side, so the top edge of the touchscreen is on the right.
+/// mapping.width = 1000.0;
+/// mapping.height = 1500.0;
+/// mapping.rotation = OutputRotation::Left;
+/// assert_eq!(mapping.to_screen(Point::new(150.0, 20.0)), ScreenPoint::new(2820.0, 750.0));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutputMapping {
+    /// The size of the touch surface, in millimeters.
+    pub surface_width: f64,
+    pub surface_height: f64,
+    /// The top left corner of the output, in pixels.
+    pub origin: ScreenPoint,
+    /// The size of the output, in pixels (after rotating it).
+    pub width: f64,
+    pub height: f64,
+    pub rotation: OutputRotation,
+}
+
+impl OutputMapping {
+    /// Converts a position on the touch surface into fractions of the surface's width and
+    /// height. See [`surface_fraction`](fn.surface_fraction.html).
+    pub fn to_fraction(&self, p: Point) -> (f64, f64) {
+        surface_fraction(p, self.surface_width, self.surface_height)
+    }
+
+    /// Converts a position on the touch surface into the position on the screen that is
+    /// underneath it.
+    pub fn to_screen(&self, p: Point) -> ScreenPoint {
+        let (u, v) = self.to_fraction(p);
+        // These are the same as the calibration matrices that xinput uses for rotated screens.
+        let (x, y) = match self.rotation {
+            OutputRotation::Normal => (u, v),
+            OutputRotation::Left => (1.0 - v, u),
+            OutputRotation::Inverted => (1.0 - u, 1.0 - v),
+            OutputRotation::Right => (v, 1.0 - u),
+        };
+        self.origin + ScreenPoint::new(x * self.width, y * self.height)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::f64::consts::PI;
-    use super::Angle;
+    use frame::Snapshot;
+    use super::{fit_similarity, Angle, Direction, Point};
 
     #[test]
     fn angle_conversions() {
@@ -292,4 +734,41 @@ mod tests {
         assert_eq!(Angle::from_degrees(360.0), Angle::from_radians(2.0 * PI));
         assert_eq!(Angle::from_degrees(360.0), Angle::from_radians(0.0));
     }
+
+    #[test]
+    fn direction_helpers_agree_with_angles() {
+        let close = |a: Angle, b: Angle| (a - b).abs().to_degrees() < 1e-9;
+        let quarter = Angle::from_degrees(90.0);
+        for &d in &[Direction::Up, Direction::Down, Direction::Left, Direction::Right] {
+            assert!(close(d.opposite().to_angle(), d.to_angle() + quarter + quarter));
+            assert!(close(d.rotate_ccw().to_angle(), d.to_angle() + quarter));
+            assert!(close(d.rotate_cw().to_angle(), d.to_angle() - quarter));
+            assert_eq!(d.opposite().opposite(), d);
+
+            let v = d.to_unit_vector();
+            assert!(close(Angle::from_radians((-v.y).atan2(v.x)), d.to_angle()));
+        }
+    }
+
+    #[test]
+    fn similarity_of_rotated_fingers() {
+        let mut prev = Snapshot::new();
+        let mut cur = Snapshot::new();
+        // Two fingers, 20mm apart horizontally, turn a quarter counter-clockwise (which, since
+        // the y axis points down, means that the right finger ends up on top). They also move
+        // apart to 40mm, and their middle moves 5mm to the right.
+        prev.set_down(0, Point::new(10.0, 50.0));
+        prev.set_down(3, Point::new(30.0, 50.0));
+        prev.set_down(4, Point::new(0.0, 0.0));
+        cur.set_down(0, Point::new(25.0, 70.0));
+        cur.set_down(3, Point::new(25.0, 30.0));
+
+        let s = fit_similarity(&prev, &cur);
+        assert!((s.translation - Point::new(5.0, 0.0)).length() < 1e-9);
+        assert!((s.rotation - Angle::from_degrees(90.0)).abs().to_degrees() < 1e-9);
+        assert!((s.scale - 2.0).abs() < 1e-9);
+
+        let s = fit_similarity(&prev, &Snapshot::new());
+        assert_eq!(s.scale, 1.0);
+    }
 }
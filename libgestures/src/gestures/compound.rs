@@ -1,6 +1,10 @@
-use geom::{ Angle, Direction, Point, UAngle };
+use edge::{Edge, EdgeZones};
+use geom::{ Angle, Axis, Direction, DirectionThresholds, Point, Similarity };
 use filters::*;
 use gestures::primitive::*;
+use params::{DwellParams, Params};
+use progress::Normalizer;
+use recognizer::Sequence;
 use { Recognizer, RecResult };
 
 pub struct SwipeResult {
@@ -14,40 +18,577 @@ pub fn angle_swipe() -> impl Recognizer<In=(), Out=Angle> {
 			.and_then(StraightSwipe::new())
 			.constrain(NoRelativeMovement::new())
 			.filter_outcome(|x| x.reason == StraightSwipeReason::LiftedFinger))
-		.and_then(FingersUp::new()
-			.split_input(|x: StraightSwipeOutcome| (x, ()))
-			.map_outcome(|(x, _)| x.angle))
+		.then_with(FingersUp::new())
+		.map_outcome(|(x, _)| x.angle)
 }
 
-pub fn direction_swipe(num_fingers: u8) -> impl Recognizer<In=(), Out=Direction> {
-    fn round_angle((pt, a): (Point, Angle)) -> RecResult<(Point, Direction)> {
-        match Direction::from_angle(a, UAngle::from_degrees(25.0)) {
-            Some(d) => RecResult::Succeeded((pt, d)),
-            None => RecResult::Failed,
-        }
+/// Recognizes `num_fingers` fingers coming down and staying still.
+///
+/// This is the common prefix of all the multi-finger gestures in this module. If you want to
+/// recognize several gestures with the same number of fingers, it is more efficient to run this
+/// once and [`branch`](../../trait.Recognizer.html#method.branch) off it than to run each gesture
+/// separately.
+pub fn fingers_down(num_fingers: u8) -> impl Recognizer<In=(), Out=()> {
+    fingers_down_tuned(num_fingers, &Params::default())
+}
+
+/// Like [`fingers_down`](fn.fingers_down.html), with the thresholds in `params`.
+///
+/// The other `_tuned` functions in this module are the same: they take their thresholds from
+/// `params` instead of using the defaults.
+pub fn fingers_down_tuned(num_fingers: u8, params: &Params) -> impl Recognizer<In=(), Out=()> {
+    NFingers::new(num_fingers).constrain(NoMovement::with_params(&params.filter))
+}
+
+fn round_angle(thresholds: DirectionThresholds)
+-> impl Fn((Point, Angle)) -> RecResult<(Point, Direction)> {
+    move |(pt, a)| match Direction::from_angle_thresholds(a, &thresholds) {
+        Some(d) => RecResult::Succeeded((pt, d)),
+        None => RecResult::Failed,
     }
+}
+
+/// Recognizes a swipe in one of the four cardinal directions, and reports its progress while it
+/// happens.
+///
+/// The progress (and the final outcome) is the distance travelled in the swipe's direction,
+/// normalized by `norm`.
+pub fn progressive_swipe(num_fingers: u8, norm: Normalizer) -> impl Recognizer<In=(), Out=(Direction, f64)> {
+    let drag = InitialAngle::new()
+        .flat_map_outcome(round_angle(DirectionThresholds::default()))
+        .and_then(
+            Drag::new()
+            .split_input(|(pt, d): (Point, Direction)| (d, (pt, d.to_angle())))
+        )
+        .constrain(NoRelativeMovement::new())
+        .map_outcome(move |(d, dist)| (d, norm.normalize(dist)));
+
+    fingers_down(num_fingers).and_then(drag)
+}
+
+/// Recognizes the part of a [`progressive_swipe`](fn.progressive_swipe.html) that happens after
+/// the fingers are down, with the directions limited by `thresholds` and the other thresholds in
+/// `params`.
+///
+/// The progress is the distance travelled in the swipe's direction, divided by
+/// `params.swipe.slide_mm`.
+pub fn progressive_swipe_after_down_tuned(thresholds: DirectionThresholds, params: &Params)
+-> impl Recognizer<In=(), Out=(Direction, f64)> {
+    let norm = Normalizer::new(params.swipe.slide_mm);
+    InitialAngle::with_params(&params.swipe)
+        .flat_map_outcome(round_angle(thresholds))
+        .and_then(
+            Drag::new()
+            .split_input(|(pt, d): (Point, Direction)| (d, (pt, d.to_angle())))
+        )
+        .constrain(NoRelativeMovement::with_params(&params.filter))
+        .map_outcome(move |(d, dist)| (d, norm.normalize(dist)))
+}
+
+/// Recognizes a swipe in one of the four cardinal directions.
+pub fn direction_swipe(num_fingers: u8) -> impl Recognizer<In=(), Out=Direction> {
+    fingers_down(num_fingers).and_then(direction_swipe_after_down())
+}
+
+/// Recognizes the part of a [`direction_swipe`](fn.direction_swipe.html) that happens after the
+/// fingers are down.
+pub fn direction_swipe_after_down() -> impl Recognizer<In=(), Out=Direction> {
+    direction_swipe_after_down_with(DirectionThresholds::default())
+}
+
+/// Recognizes a swipe in one of the four cardinal directions, where `thresholds` says how far the
+/// start of the swipe may stray from each direction.
+///
+/// [`direction_swipe`](fn.direction_swipe.html) allows 25 degrees in every direction. On wide
+/// surfaces, it can help to be more lenient for up and down swipes.
+pub fn direction_swipe_with(num_fingers: u8, thresholds: DirectionThresholds)
+-> impl Recognizer<In=(), Out=Direction> {
+    fingers_down(num_fingers).and_then(direction_swipe_after_down_with(thresholds))
+}
+
+/// Recognizes the part of a [`direction_swipe_with`](fn.direction_swipe_with.html) that happens
+/// after the fingers are down.
+pub fn direction_swipe_after_down_with(thresholds: DirectionThresholds)
+-> impl Recognizer<In=(), Out=Direction> {
+    direction_swipe_after_down_tuned(thresholds, &Params::default())
+}
+
+/// Like [`direction_swipe_after_down_with`](fn.direction_swipe_after_down_with.html), with the
+/// thresholds in `params`.
+pub fn direction_swipe_after_down_tuned(thresholds: DirectionThresholds, params: &Params)
+-> impl Recognizer<In=(), Out=Direction> {
+    swipe_after_down(InitialAngle::with_params(&params.swipe), thresholds,
+                     NoRelativeMovement::with_params(&params.filter), params)
+}
+
+/// Recognizes a swipe along `axis` (for example, left or right if `axis` is horizontal).
+///
+/// Movement perpendicular to `axis` is ignored when deciding which way the swipe is going, so
+/// this is more reliable than [`direction_swipe`](fn.direction_swipe.html) on surfaces with a poor
+/// resolution in that direction.
+pub fn axis_swipe(num_fingers: u8, axis: Axis) -> impl Recognizer<In=(), Out=Direction> {
+    fingers_down(num_fingers).and_then(axis_swipe_after_down(axis))
+}
 
-    // This is a Recognizer<In=(), Out=Direction>.
-    let swipe =
-        InitialAngle::new()
-        .flat_map_outcome(round_angle)
+/// Recognizes the part of an [`axis_swipe`](fn.axis_swipe.html) that happens after the fingers
+/// are down.
+pub fn axis_swipe_after_down(axis: Axis) -> impl Recognizer<In=(), Out=Direction> {
+    swipe_after_down(InitialAngle::new().lock_axis(axis), DirectionThresholds::default(),
+                     NoRelativeMovement::new(), &Params::default())
+}
+
+/// Recognizes a pinch (or a spread), followed by a swipe without lifting the fingers.
+///
+/// The outcome is the scale of the pinch (see [`Pinch`](../primitive/struct.Pinch.html)) and the
+/// direction of the swipe. Since the fingers were just pinching, they are allowed to keep changing
+/// shape a bit more during the swipe than they are in an ordinary swipe.
+pub fn pinch_then_swipe(num_fingers: u8) -> impl Recognizer<In=(), Out=(f64, Direction)> {
+    let params = Params::default();
+    let shape = NoRelativeMovement::new().threshold(params.filter.loose_shape_mm);
+    let swipe = swipe_after_down(InitialAngle::new(), DirectionThresholds::default(), shape,
+                                 &params);
+    let pinch = Pinch::new().constrain(MaintainAngle::new());
+    fingers_down(num_fingers).and_then(pinch.then_with(swipe))
+}
+
+/// Recognizes a swipe in one of the four cardinal directions, which succeeds as soon as it is
+/// long enough, without waiting for the fingers to lift.
+///
+/// This is quicker than [`direction_swipe`](fn.direction_swipe.html), but there is no taking it
+/// back: a swipe that would have turned into something else (like a path) is already recognized.
+/// To make sure that nothing else is recognized for the rest of the touch, add it to the
+/// `Manager` with [`push_claiming`](../../manager/struct.Manager.html#method.push_claiming).
+pub fn early_swipe(num_fingers: u8) -> impl Recognizer<In=(), Out=Direction> {
+    fingers_down(num_fingers).and_then(early_swipe_after_down_tuned(DirectionThresholds::default(),
+                                                                    &Params::default()))
+}
+
+/// Recognizes the part of an [`early_swipe`](fn.early_swipe.html) that happens after the fingers
+/// are down, with the thresholds in `params`, and where `thresholds` says how far the swipe may
+/// stray from each direction.
+pub fn early_swipe_after_down_tuned(thresholds: DirectionThresholds, params: &Params)
+-> impl Recognizer<In=(), Out=Direction> {
+    swipe_moves(InitialAngle::with_params(&params.swipe), thresholds,
+                NoRelativeMovement::with_params(&params.filter), params, true)
+}
+
+fn swipe_after_down(start: InitialAngle, thresholds: DirectionThresholds,
+                    shape: NoRelativeMovement, params: &Params)
+-> impl Recognizer<In=(), Out=Direction> {
+    swipe_moves(start, thresholds, shape, params, false)
+        .then_with(FingersUp::with_params(&params.lift))
+        .map_outcome(|(d, _)| d)
+}
+
+// The moving part of a swipe. Unless it commits `early`, this finishes when a finger lifts.
+fn swipe_moves(start: InitialAngle, thresholds: DirectionThresholds,
+               shape: NoRelativeMovement, params: &Params, early: bool)
+-> impl Recognizer<In=(), Out=Direction> {
+    let mut straight = StraightSwipe::with_params(&params.swipe).adaptivity(0.0);
+    if early {
+        straight = straight.commit_early();
+    }
+    start
+        .flat_map_outcome(round_angle(thresholds))
         // So far, we have a Recognizer that returns (Point, Direction).
         .and_then(
-            StraightSwipe::new()
-            .adaptivity(0.0)
+            straight
             .split_input(|(pt, d): (Point, Direction)| (d, (pt, d.to_angle())))
         )
         // So far, we have a Recognizer that returns (Direction, StraightSwipeOutcome).
-        .constrain(NoRelativeMovement::new())
-        .filter_outcome(|&(_, ref x)| x.reason == StraightSwipeReason::LiftedFinger)
-        .map_outcome(|x| x.0);
+        .constrain(shape)
+        .constrain(MaintainSpread::if_enabled(&params.filter))
+        .filter_outcome(move |&(_, ref x)| {
+            x.reason == StraightSwipeReason::LiftedFinger
+                || (early && x.reason == StraightSwipeReason::ReachedLength)
+        })
+        .map_outcome(|x| x.0)
+}
+
+/// Recognizes a swipe in one of the four cardinal directions that ends with the fingers holding
+/// still, instead of lifting.
+///
+/// This is an alternative to [`direction_swipe`](fn.direction_swipe.html) for people who find it
+/// hard to flick their fingers: the swipe is recognized once the fingers have stopped (see
+/// [`Dwell`](../primitive/struct.Dwell.html)) at least `min_length_mm` from where they started,
+/// while they are still down. Since the touch surface doesn't send anything while the fingers are
+/// still, this needs [`Manager::tick`](../../manager/struct.Manager.html#method.tick).
+pub fn dwell_swipe(num_fingers: u8) -> impl Recognizer<In=(), Out=Direction> {
+    fingers_down(num_fingers).and_then(dwell_swipe_after_down())
+}
+
+/// Recognizes the part of a [`dwell_swipe`](fn.dwell_swipe.html) that happens after the fingers
+/// are down.
+pub fn dwell_swipe_after_down() -> impl Recognizer<In=(), Out=Direction> {
+    dwell_swipe_after_down_tuned(DirectionThresholds::default(), &Params::default())
+}
+
+/// Like [`dwell_swipe_after_down`](fn.dwell_swipe_after_down.html), with the thresholds in
+/// `params`, and where `thresholds` says how far the swipe may stray from each direction.
+pub fn dwell_swipe_after_down_tuned(thresholds: DirectionThresholds, params: &Params)
+-> impl Recognizer<In=(), Out=Direction> {
+    let min_length = params.swipe.min_length_mm;
+    InitialAngle::with_params(&params.swipe)
+        .flat_map_outcome(round_angle(thresholds))
+        .and_then(
+            Dwell::with_params(&params.dwell)
+            .split_input(|start: (Point, Direction)| (start, ()))
+        )
+        .constrain(NoRelativeMovement::with_params(&params.filter))
+        .constrain(MaintainSpread::if_enabled(&params.filter))
+        // The fingers must have ended up far enough away, in the same direction that they started.
+        .flat_map_outcome(move |((start, d), end): ((Point, Direction), Point)| {
+            let diff = end - start;
+            let angle = Angle::from_radians((-diff.y).atan2(diff.x));
+            if diff.length() >= min_length
+                && Direction::from_angle_thresholds(angle, &thresholds) == Some(d) {
+                RecResult::Succeeded(d)
+            } else {
+                debug!("dwell swipe failed: stopped {} mm away at {:?}", diff.length(), angle);
+                RecResult::Failed
+            }
+        })
+}
+
+/// Recognizes the fingers moving along a path made of straight segments, like an "L" for
+/// `[Direction::Down, Direction::Right]`.
+///
+/// Each segment must go in its given direction, and the fingers must turn sharply from one segment
+/// to the next, without lifting. The gesture is recognized when the fingers are lifted at the end
+/// of the last segment.
+///
+/// # Panics
+/// if `directions` is empty.
+pub fn path(num_fingers: u8, directions: &[Direction]) -> impl Recognizer<In=(), Out=()> {
+    fingers_down(num_fingers).and_then(path_after_down(directions))
+}
+
+/// Recognizes the part of a [`path`](fn.path.html) that happens after the fingers are down.
+pub fn path_after_down(directions: &[Direction]) -> impl Recognizer<In=(), Out=()> {
+    path_after_down_tuned(directions, &Params::default())
+}
+
+/// Like [`path_after_down`](fn.path_after_down.html), with the thresholds in `params`.
+pub fn path_after_down_tuned(directions: &[Direction], params: &Params)
+-> impl Recognizer<In=(), Out=()> {
+    let segments = directions.iter()
+        .enumerate()
+        .map(|(i, &d)| -> Box<Recognizer<In=(), Out=()>> {
+            Box::new(path_segment(d, i + 1 == directions.len(), params))
+        })
+        .collect::<Vec<_>>();
+
+    Sequence::new(segments)
+        .constrain(NoRelativeMovement::with_params(&params.filter))
+        .then_with(FingersUp::with_params(&params.lift))
+        .map_outcome(|_| ())
+}
+
+// One straight segment of a path. Every segment but the last one ends by changing direction.
+fn path_segment(direction: Direction, last: bool, params: &Params)
+-> impl Recognizer<In=(), Out=()> {
+    let reason = if last {
+        StraightSwipeReason::LiftedFinger
+    } else {
+        StraightSwipeReason::ChangedAngle
+    };
+    InitialAngle::with_params(&params.swipe)
+        .flat_map_outcome(round_angle(DirectionThresholds::default()))
+        .flat_map_outcome(move |(pt, d)| {
+            if d == direction {
+                RecResult::Succeeded((pt, d.to_angle()))
+            } else {
+                debug!("path segment failed: moved {:?} instead of {:?}", d, direction);
+                RecResult::Failed
+            }
+        })
+        .and_then(StraightSwipe::with_params(&params.swipe).adaptivity(0.0))
+        .filter_outcome(move |x| x.reason == reason)
+        .map_outcome(|_| ())
+}
+
+/// Recognizes the fingers moving along any path made of up to `max_segments` straight segments,
+/// and returns the direction of each segment.
+///
+/// This is like [`path`](fn.path.html), except that the directions aren't decided in advance:
+/// whenever the fingers turn sharply, a new segment starts. Consecutive segments that go the same
+/// way are merged, so a plain swipe gives a single direction. The gesture is recognized when the
+/// fingers are lifted at the end of the last segment.
+///
+/// # Panics
+/// if `max_segments` is zero.
+pub fn segmented_swipe(num_fingers: u8, max_segments: usize)
+-> impl Recognizer<In=(), Out=Vec<Direction>> {
+    fingers_down(num_fingers).and_then(segmented_swipe_after_down_tuned(max_segments,
+                                                                        &Params::default()))
+}
 
-    // This is a Recognizer<In=Direction, Out=()>.
-    let up = FingersUp::new()
-        .split_input(|d: Direction| (d, ()))
-        .map_outcome(|(d, _)| d);
+/// Recognizes the part of a [`segmented_swipe`](fn.segmented_swipe.html) that happens after the
+/// fingers are down, with the thresholds in `params`.
+pub fn segmented_swipe_after_down_tuned(max_segments: usize, params: &Params)
+-> impl Recognizer<In=(), Out=Vec<Direction>> {
+    let segment = InitialAngle::with_params(&params.swipe)
+        .flat_map_outcome(round_angle(DirectionThresholds::default()))
+        .and_then(
+            StraightSwipe::with_params(&params.swipe).adaptivity(0.0)
+            .split_input(|(pt, d): (Point, Direction)| (d, (pt, d.to_angle())))
+        )
+        // A sharp turn starts the next segment, and lifting the fingers ends the last one.
+        .flat_map_outcome(|(d, x): (Direction, StraightSwipeOutcome)| match x.reason {
+            StraightSwipeReason::ChangedAngle => RecResult::Succeeded((d, false)),
+            StraightSwipeReason::LiftedFinger => RecResult::Succeeded((d, true)),
+            StraightSwipeReason::ReachedLength => RecResult::Failed,
+        });
+
+    segment.repeat(max_segments)
+        .constrain(NoRelativeMovement::with_params(&params.filter))
+        .then_with(FingersUp::with_params(&params.lift))
+        .map_outcome(|(mut dirs, _)| {
+            dirs.dedup();
+            dirs
+        })
+}
+
+/// Recognizes a one-finger swipe that pulls in from one of the edges of the surface.
+///
+/// The finger must come down in one of the activation zones of `zones`, and then move towards the
+/// middle of the surface. The gesture is recognized as soon as the finger has moved far enough,
+/// without waiting for it to be lifted, which is how compositors' edge swipes behave.
+pub fn edge_swipe(zones: EdgeZones) -> impl Recognizer<In=(), Out=Edge> {
+    edge_swipe_tuned(zones, &Params::default())
+}
+
+/// Like [`edge_swipe`](fn.edge_swipe.html), with the thresholds in `params`.
+pub fn edge_swipe_tuned(zones: EdgeZones, params: &Params) -> impl Recognizer<In=(), Out=Edge> {
+    NFingers::new(1)
+        .and_then(EdgeStart::new(zones))
+        .and_then(
+            InitialAngle::with_params(&params.swipe)
+            .flat_map_outcome(round_angle(DirectionThresholds::default()))
+            .split_input(|e: Edge| (e, ()))
+        )
+        .flat_map_outcome(|(e, (_, d))| {
+            if d == e.inward() {
+                RecResult::Succeeded(e)
+            } else {
+                debug!("edge swipe failed: moved {:?} from the {:?} edge", d, e);
+                RecResult::Failed
+            }
+        })
+}
+
+/// Recognizes the fingers shaking back and forth, either horizontally or vertically.
+///
+/// The outcome is the axis of the shake. Like an edge swipe, this is recognized as soon as the
+/// fingers have turned around often enough (see [`Shake`](../primitive/struct.Shake.html)),
+/// without waiting for them to be lifted.
+pub fn shake(num_fingers: u8) -> impl Recognizer<In=(), Out=Axis> {
+    fingers_down(num_fingers).and_then(shake_after_down())
+}
+
+/// Recognizes the part of a [`shake`](fn.shake.html) that happens after the fingers are down.
+pub fn shake_after_down() -> impl Recognizer<In=(), Out=Axis> {
+    shake_after_down_tuned(&Params::default())
+}
+
+/// Like [`shake_after_down`](fn.shake_after_down.html), with the thresholds in `params`.
+pub fn shake_after_down_tuned(params: &Params) -> impl Recognizer<In=(), Out=Axis> {
+    // Quick movements tend to distort the hand a bit, so we're more lenient about its shape.
+    let shape = NoRelativeMovement::with_params(&params.filter)
+        .threshold(params.filter.loose_shape_mm);
+    Shake::with_params(&params.shake).constrain(shape)
+}
+
+/// Recognizes `num_fingers` fingers moving, rotating and pinching, and reports how they move on
+/// every frame.
+///
+/// Unlike most of the gestures in this module, the fingers don't need to stay still before the
+/// gesture starts: it starts tracking them as soon as they are all down. See
+/// [`Transform`](../primitive/struct.Transform.html) for the progress and the outcome.
+pub fn transform(num_fingers: u8) -> impl Recognizer<In=(), Out=Similarity> {
+    NFingers::new(num_fingers).and_then(Transform::new())
+}
+
+/// Recognizes the fingers coming down and going up again without moving, like a click.
+pub fn tap(num_fingers: u8) -> impl Recognizer<In=(), Out=()> {
+    fingers_down(num_fingers).and_then(tap_after_down())
+}
+
+/// Recognizes the part of a [`tap`](fn.tap.html) that happens after the fingers are down.
+pub fn tap_after_down() -> impl Recognizer<In=(), Out=()> {
+    tap_after_down_tuned(&Params::default())
+}
+
+/// Like [`tap_after_down`](fn.tap_after_down.html), with the thresholds in `params`.
+///
+/// The fingers must all be lifted within `params.tap.max_ms` of coming down.
+pub fn tap_after_down_tuned(params: &Params) -> impl Recognizer<In=(), Out=()> {
+    FingersUp::with_params(&params.lift)
+        .constrain(NoMovement::with_params(&params.filter))
+        .constrain(MaxDuration::new(params.tap.max_ms))
+}
+
+/// Recognizes the fingers coming down and staying still, like a long press.
+///
+/// This is recognized while the fingers are still down, so it needs
+/// [`Manager::tick`](../../manager/struct.Manager.html#method.tick) (see
+/// [`Dwell`](../primitive/struct.Dwell.html)).
+pub fn hold(num_fingers: u8) -> impl Recognizer<In=(), Out=()> {
+    fingers_down(num_fingers).and_then(hold_after_down())
+}
+
+/// Recognizes the part of a [`hold`](fn.hold.html) that happens after the fingers are down.
+pub fn hold_after_down() -> impl Recognizer<In=(), Out=()> {
+    hold_after_down_tuned(&Params::default())
+}
+
+/// Like [`hold_after_down`](fn.hold_after_down.html), with the thresholds in `params`.
+///
+/// The fingers must stay down for `params.tap.hold_ms`, without moving more than
+/// `params.filter.still_mm`.
+pub fn hold_after_down_tuned(params: &Params) -> impl Recognizer<In=(), Out=()> {
+    let dwell = DwellParams {
+        hold_ms: params.tap.hold_ms,
+        still_mm: params.filter.still_mm,
+    };
+    Dwell::with_params(&dwell)
+        .constrain(NoMovement::with_params(&params.filter))
+        .map_outcome(|_| ())
+}
+
+/// Recognizes all the fingers spreading apart, like an opening hand.
+pub fn spread(num_fingers: u8) -> impl Recognizer<In=(), Out=()> {
+    fingers_down(num_fingers).and_then(spread_after_down())
+}
+
+/// Recognizes the part of a [`spread`](fn.spread.html) that happens after the fingers are down.
+pub fn spread_after_down() -> impl Recognizer<In=(), Out=()> {
+    spread_after_down_tuned(&Params::default())
+}
+
+/// Like [`spread_after_down`](fn.spread_after_down.html), with the thresholds in `params`.
+pub fn spread_after_down_tuned(params: &Params) -> impl Recognizer<In=(), Out=()> {
+    Radial::new(RadialDirection::Outward, params.pinch.spread_mm)
+        .then_with(FingersUp::with_params(&params.lift))
+        .map_outcome(|_| ())
+}
+
+/// Like [`spread_after_down_tuned`](fn.spread_after_down_tuned.html), but succeeds as soon as
+/// the fingers have spread far enough, without waiting for them to lift.
+pub fn early_spread_after_down_tuned(params: &Params) -> impl Recognizer<In=(), Out=()> {
+    Radial::new(RadialDirection::Outward, params.pinch.spread_mm).map_outcome(|_| ())
+}
+
+/// Recognizes all the fingers coming together, like a grabbing hand.
+///
+/// Every finger must move at least `min_distance_mm` millimeters towards the others.
+pub fn grab(num_fingers: u8, min_distance_mm: f64) -> impl Recognizer<In=(), Out=()> {
+    fingers_down(num_fingers).and_then(grab_after_down(min_distance_mm))
+}
+
+/// Recognizes the part of a [`grab`](fn.grab.html) that happens after the fingers are down.
+pub fn grab_after_down(min_distance_mm: f64) -> impl Recognizer<In=(), Out=()> {
+    Radial::new(RadialDirection::Inward, min_distance_mm)
+        .then_with(FingersUp::new())
+        .map_outcome(|_| ())
+}
+
+/// Like [`grab_after_down`](fn.grab_after_down.html), with the thresholds in `params`.
+///
+/// Every finger must move at least `params.pinch.grab_mm` millimeters towards the others.
+pub fn grab_after_down_tuned(params: &Params) -> impl Recognizer<In=(), Out=()> {
+    Radial::new(RadialDirection::Inward, params.pinch.grab_mm)
+        .then_with(FingersUp::with_params(&params.lift))
+        .map_outcome(|_| ())
+}
+
+/// Like [`grab_after_down_tuned`](fn.grab_after_down_tuned.html), but succeeds as soon as the
+/// fingers have come together far enough, without waiting for them to lift.
+pub fn early_grab_after_down_tuned(params: &Params) -> impl Recognizer<In=(), Out=()> {
+    Radial::new(RadialDirection::Inward, params.pinch.grab_mm).map_outcome(|_| ())
+}
+
+/// Recognizes the fingers pinching together or spreading apart as a whole hand (see
+/// [`Pinch`](../primitive/struct.Pinch.html)), and then lifting.
+///
+/// The outcome is the scale of the pinch, which is less than one if the fingers came together.
+/// Unlike a [`grab`](fn.grab.html) or a [`spread`](fn.spread.html), the fingers don't each have
+/// to move straight towards or away from the others.
+pub fn pinch(num_fingers: u8) -> impl Recognizer<In=(), Out=f64> {
+    fingers_down(num_fingers).and_then(pinch_after_down())
+}
+
+/// Recognizes the part of a [`pinch`](fn.pinch.html) that happens after the fingers are down.
+pub fn pinch_after_down() -> impl Recognizer<In=(), Out=f64> {
+    pinch_after_down_tuned(&Params::default())
+}
+
+/// Like [`pinch_after_down`](fn.pinch_after_down.html), with the thresholds in `params`.
+pub fn pinch_after_down_tuned(params: &Params) -> impl Recognizer<In=(), Out=f64> {
+    Pinch::with_params(&params.pinch)
+        .then_with(FingersUp::with_params(&params.lift))
+        .map_outcome(|(scale, _)| scale)
+}
+
+/// Recognizes the fingers turning around their middle (see
+/// [`Rotation`](../primitive/struct.Rotation.html)), and then lifting.
+///
+/// The outcome is how far the fingers turned, in degrees, which is negative for a clockwise turn.
+pub fn rotate(num_fingers: u8) -> impl Recognizer<In=(), Out=f64> {
+    fingers_down(num_fingers).and_then(rotate_after_down())
+}
+
+/// Recognizes the part of a [`rotate`](fn.rotate.html) that happens after the fingers are down.
+pub fn rotate_after_down() -> impl Recognizer<In=(), Out=f64> {
+    rotate_after_down_tuned(&Params::default())
+}
+
+/// Like [`rotate_after_down`](fn.rotate_after_down.html), with the thresholds in `params`.
+///
+/// The fingers must turn at least `params.pinch.min_rotation_deg` degrees.
+pub fn rotate_after_down_tuned(params: &Params) -> impl Recognizer<In=(), Out=f64> {
+    Rotation::with_params(&params.pinch)
+        .then_with(FingersUp::with_params(&params.lift))
+        .map_outcome(|(degrees, _)| degrees)
+}
+
+#[cfg(test)]
+mod tests {
+    use event::Event;
+    use geom::{Direction, Point};
+    use manager::Manager;
+    use super::segmented_swipe;
 
-    NFingers::new(num_fingers).constrain(NoMovement::new())
-        .and_then(swipe)
-        .and_then(up)
+    #[test]
+    fn segmented_swipes() {
+        let mut man = Manager::new();
+        man.push(segmented_swipe(2, 4));
+        let mut time = 0;
+        let mut frame = |man: &mut Manager<Vec<Direction>>, evs: Vec<Event>| {
+            for ev in evs {
+                man.update_event(&ev);
+            }
+            time += 10;
+            man.update_event(&Event::Frame { time: time })
+        };
+        let fingers = |x: f64, y: f64| vec![
+            Event::Motion { slot: 0, pos: Point::new(x, y) },
+            Event::Motion { slot: 1, pos: Point::new(x + 20.0, y) },
+        ];
+
+        frame(&mut man, vec![
+            Event::Down { slot: 0, pos: Point::new(50.0, 50.0) },
+            Event::Down { slot: 1, pos: Point::new(70.0, 50.0) },
+        ]);
+        // Down, and then right.
+        for i in 1..9 {
+            assert_eq!(frame(&mut man, fingers(50.0, 50.0 + 4.0 * i as f64)), None);
+        }
+        for i in 1..9 {
+            assert_eq!(frame(&mut man, fingers(50.0 + 4.0 * i as f64, 82.0)), None);
+        }
+        assert_eq!(frame(&mut man, vec![Event::Up { slot: 0 }]), None);
+        assert_eq!(frame(&mut man, vec![Event::Up { slot: 1 }]),
+                   Some(vec![Direction::Down, Direction::Right]));
+    }
 }
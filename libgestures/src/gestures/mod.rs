@@ -1,2 +1,754 @@
+//! Recognizers for specific gestures.
+//!
+//! The [`primitive`](primitive/index.html) and [`compound`](compound/index.html) modules contain
+//! the building blocks. This module also has a [`Gesture`](enum.Gesture.html) type naming all the
+//! gestures that can be built out of them, and a [`GestureSet`](struct.GestureSet.html) for
+//! recognizing several of them at once.
+
 pub mod primitive;
 pub mod compound;
+pub mod spec;
+
+use edge::{Edge, EdgeZones};
+use geom::{Direction, DirectionThresholds};
+use manager::Manager;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+use {Recognizer, RecResult};
+use params::Params;
+use self::compound::{direction_swipe_after_down_tuned, dwell_swipe_after_down_tuned,
+                     early_grab_after_down_tuned, early_spread_after_down_tuned,
+                     early_swipe_after_down_tuned, edge_swipe_tuned, fingers_down_tuned,
+                     grab_after_down_tuned, hold_after_down_tuned, pinch_after_down_tuned,
+                     progressive_swipe_after_down_tuned, rotate_after_down_tuned,
+                     segmented_swipe_after_down_tuned, shake_after_down_tuned,
+                     spread_after_down_tuned, tap_after_down_tuned};
+use self::primitive::EdgeStart;
+
+/// A gesture that was made on a touch surface.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Gesture {
+    /// Moving all the fingers in the same direction, and then lifting them.
+    Swipe {
+        num_fingers: u8,
+        direction: Direction,
+    },
+    /// Moving all the fingers apart.
+    Spread {
+        num_fingers: u8,
+    },
+    /// Moving all the fingers together.
+    Grab {
+        num_fingers: u8,
+    },
+    /// Moving all the fingers quickly back and forth.
+    Shake {
+        num_fingers: u8,
+    },
+    /// Moving all the fingers along a path made of straight segments, and then lifting them.
+    Path {
+        num_fingers: u8,
+        path: Path,
+    },
+    /// Dragging one finger in from the edge of the surface.
+    EdgeSwipe {
+        edge: Edge,
+    },
+    /// Putting the fingers down and lifting them again without moving them.
+    Tap {
+        num_fingers: u8,
+    },
+    /// Putting the fingers down and leaving them there without moving them.
+    Hold {
+        num_fingers: u8,
+    },
+    /// Moving the fingers closer together or further apart as a whole hand, and then lifting them.
+    ///
+    /// Unlike a grab or a spread, the fingers don't each have to move straight towards or away
+    /// from the others, so this also covers two fingers pinching.
+    Pinch {
+        num_fingers: u8,
+        direction: PinchDirection,
+    },
+    /// Turning the fingers around their middle, like turning a knob, and then lifting them.
+    Rotate {
+        num_fingers: u8,
+        direction: RotateDirection,
+    },
+}
+
+impl Gesture {
+    /// Returns the number of fingers that make this gesture.
+    pub fn num_fingers(&self) -> u8 {
+        match *self {
+            Gesture::Swipe { num_fingers, .. } | Gesture::Spread { num_fingers }
+                | Gesture::Grab { num_fingers } | Gesture::Shake { num_fingers }
+                | Gesture::Path { num_fingers, .. } | Gesture::Tap { num_fingers }
+                | Gesture::Hold { num_fingers } | Gesture::Pinch { num_fingers, .. }
+                | Gesture::Rotate { num_fingers, .. } => num_fingers,
+            Gesture::EdgeSwipe { .. } => 1,
+        }
+    }
+}
+
+impl Gesture {
+    /// Returns when this gesture is reported, unless it is told otherwise.
+    ///
+    /// Holds, shakes and edge swipes are reported as soon as they are recognized, and the others
+    /// once the fingers lift.
+    pub fn default_fire(&self) -> Fire {
+        match *self {
+            Gesture::Hold { .. } | Gesture::Shake { .. } | Gesture::EdgeSwipe { .. } =>
+                Fire::Threshold,
+            _ => Fire::Release,
+        }
+    }
+
+    /// Returns true if this gesture can be reported at `fire`.
+    ///
+    /// Swipes can be reported any way. Spreads and grabs can be reported at the threshold or on
+    /// release, and the others only at their [`default_fire`](#method.default_fire).
+    pub fn can_fire(&self, fire: Fire) -> bool {
+        match *self {
+            Gesture::Swipe { .. } => true,
+            Gesture::Spread { .. } | Gesture::Grab { .. } => fire != Fire::Continuously,
+            _ => fire == self.default_fire(),
+        }
+    }
+}
+
+/// Which way the fingers move in a [`Gesture::Pinch`](enum.Gesture.html#variant.Pinch).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum PinchDirection {
+    /// Closer together.
+    In,
+    /// Further apart.
+    Out,
+}
+
+impl PinchDirection {
+    /// Returns the direction of a pinch that changed the spread of the fingers by `scale`.
+    pub fn from_scale(scale: f64) -> PinchDirection {
+        if scale < 1.0 { PinchDirection::In } else { PinchDirection::Out }
+    }
+}
+
+impl fmt::Display for PinchDirection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            PinchDirection::In => "in",
+            PinchDirection::Out => "out",
+        })
+    }
+}
+
+impl FromStr for PinchDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<PinchDirection, String> {
+        match s {
+            "in" => Ok(PinchDirection::In),
+            "out" => Ok(PinchDirection::Out),
+            _ => Err(format!("unknown pinch direction {:?}", s)),
+        }
+    }
+}
+
+/// Which way the fingers turn in a [`Gesture::Rotate`](enum.Gesture.html#variant.Rotate), as
+/// seen on the surface.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum RotateDirection {
+    Clockwise,
+    Counterclockwise,
+}
+
+impl RotateDirection {
+    /// Returns the direction of a rotation by `degrees`, which are counter-clockwise.
+    pub fn from_degrees(degrees: f64) -> RotateDirection {
+        if degrees < 0.0 { RotateDirection::Clockwise } else { RotateDirection::Counterclockwise }
+    }
+}
+
+impl fmt::Display for RotateDirection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            RotateDirection::Clockwise => "clockwise",
+            RotateDirection::Counterclockwise => "counterclockwise",
+        })
+    }
+}
+
+impl FromStr for RotateDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<RotateDirection, String> {
+        match s {
+            "clockwise" => Ok(RotateDirection::Clockwise),
+            "counterclockwise" => Ok(RotateDirection::Counterclockwise),
+            _ => Err(format!("unknown rotation {:?}", s)),
+        }
+    }
+}
+
+/// When a gesture is reported.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Fire {
+    /// As soon as the fingers have moved far enough. A gesture that fires like this claims the
+    /// rest of the touch (see
+    /// [`Manager::push_claiming`](../manager/struct.Manager.html#method.push_claiming)).
+    Threshold,
+    /// Once the fingers lift, which gives a gesture the chance to turn into a more specific one
+    /// first.
+    Release,
+    /// On every frame while the fingers move, as a progress update, and then once more when
+    /// they lift. The [`progress`](../manager/struct.Recognized.html#structfield.progress) of
+    /// each update says how far the fingers have gone (see
+    /// [`progressive_swipe`](compound/fn.progressive_swipe.html)), so this is for driving
+    /// something like a slider.
+    Continuously,
+}
+
+impl fmt::Display for Fire {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Fire::Threshold => "threshold",
+            Fire::Release => "release",
+            Fire::Continuously => "continuously",
+        })
+    }
+}
+
+impl FromStr for Fire {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Fire, String> {
+        match s {
+            "threshold" => Ok(Fire::Threshold),
+            "release" => Ok(Fire::Release),
+            "continuously" => Ok(Fire::Continuously),
+            _ => Err(format!("unknown firing point {:?}", s)),
+        }
+    }
+}
+
+/// The longest [`Path`](struct.Path.html) that can be recognized.
+pub const MAX_PATH_LEN: usize = 4;
+
+/// The directions of the segments in a path gesture.
+///
+/// A path has at least two segments, and at most [`MAX_PATH_LEN`](constant.MAX_PATH_LEN.html).
+/// Two consecutive segments can't go in the same direction, because then there would be no corner
+/// between them.
+///
+/// # Examples
+/// ```
+/// use libgestures::geom::Direction;
+/// use libgestures::gestures::Path;
+///
+/// let l = Path::new(&[Direction::Down, Direction::Right]).unwrap();
+/// assert_eq!(l.directions(), &[Direction::Down, Direction::Right]);
+/// assert_eq!(l.to_string(), "down right");
+///
+/// assert!(Path::new(&[Direction::Down]).is_none());
+/// assert!(Path::new(&[Direction::Down, Direction::Down]).is_none());
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Path {
+    len: u8,
+    // Only the first `len` of these are used; the rest are always `Up`, so that the derived
+    // comparisons work.
+    directions: [Direction; MAX_PATH_LEN],
+}
+
+impl Path {
+    /// Creates a path with segments in the given directions, or returns `None` if they don't make
+    /// a valid path.
+    pub fn new(directions: &[Direction]) -> Option<Path> {
+        if directions.len() < 2 || directions.len() > MAX_PATH_LEN {
+            return None;
+        }
+        if directions.windows(2).any(|w| w[0] == w[1]) {
+            return None;
+        }
+
+        let mut ret = Path {
+            len: directions.len() as u8,
+            directions: [Direction::Up; MAX_PATH_LEN],
+        };
+        ret.directions[..directions.len()].copy_from_slice(directions);
+        Some(ret)
+    }
+
+    /// The directions of the segments, in order.
+    pub fn directions(&self) -> &[Direction] {
+        &self.directions[..self.len as usize]
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = self.directions().iter().map(|d| d.name()).collect::<Vec<_>>();
+        f.write_str(&names.join(" "))
+    }
+}
+
+/// Writes a gesture in the same way that it is named in the `gestures` configuration file.
+///
+/// # Examples
+/// ```
+/// use libgestures::edge::Edge;
+/// use libgestures::geom::Direction;
+/// use libgestures::gestures::Gesture;
+///
+/// let swipe = Gesture::Swipe { num_fingers: 3, direction: Direction::Up };
+/// assert_eq!(swipe.to_string(), "swipe 3 up");
+/// assert_eq!(Gesture::EdgeSwipe { edge: Edge::Left }.to_string(), "edge left");
+/// ```
+impl fmt::Display for Gesture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Gesture::Swipe { num_fingers, direction } =>
+                write!(f, "swipe {} {}", num_fingers, direction),
+            Gesture::Spread { num_fingers } => write!(f, "spread {}", num_fingers),
+            Gesture::Grab { num_fingers } => write!(f, "grab {}", num_fingers),
+            Gesture::Shake { num_fingers } => write!(f, "shake {}", num_fingers),
+            Gesture::Path { num_fingers, ref path } => write!(f, "path {} {}", num_fingers, path),
+            Gesture::EdgeSwipe { edge } => write!(f, "edge {}", edge),
+            Gesture::Tap { num_fingers } => write!(f, "tap {}", num_fingers),
+            Gesture::Hold { num_fingers } => write!(f, "hold {}", num_fingers),
+            Gesture::Pinch { num_fingers, direction } =>
+                write!(f, "pinch {} {}", num_fingers, direction),
+            Gesture::Rotate { num_fingers, direction } =>
+                write!(f, "rotate {} {}", num_fingers, direction),
+        }
+    }
+}
+
+/// Where gestures with one finger may start.
+///
+/// On a touchscreen, one finger is also how people use their apps. A program that only watches
+/// the touches (instead of taking them away from the apps) can't stop a one-finger swipe from
+/// also scrolling whatever is under the finger, so it should only recognize one-finger gestures
+/// that people don't make by accident.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OneFinger {
+    /// Anywhere, like gestures with more fingers.
+    Anywhere,
+    /// Anywhere, but only after the finger has held still for `ms` milliseconds. Ordinary taps,
+    /// scrolls, and drags don't do that. Everything counts from the end of the wait, so a tap
+    /// becomes a long press, and a hold becomes even longer.
+    AfterHold {
+        ms: u32,
+    },
+    /// Only if the finger comes down in one of the edge zones (see
+    /// [`GestureSet::edge_zones`](struct.GestureSet.html#method.edge_zones)).
+    FromEdges,
+}
+
+// Edge swipes are recognized before the finger lifts, and they should win over any ordinary swipe
+// that starts at the same place, so they get a higher priority than any finger count.
+const EDGE_PRIORITY: i32 = 256;
+
+/// A collection of gestures to recognize.
+///
+/// This knows how to arrange the recognizers for its gestures in a
+/// [`Manager`](../manager/struct.Manager.html) so that they share as much work as possible, and so
+/// that the more specific gestures take priority.
+///
+/// # Examples
+/// ```
+/// use libgestures::geom::Direction;
+/// use libgestures::gestures::{Gesture, GestureSet};
+/// use libgestures::manager::Manager;
+///
+/// let mut set = GestureSet::new().grab_distance(20.0);
+/// set.insert(Gesture::Swipe { num_fingers: 3, direction: Direction::Up });
+/// set.insert(Gesture::Grab { num_fingers: 5 });
+///
+/// let mut man = Manager::<Gesture>::new();
+/// let names = set.push_to(&mut man);
+/// assert_eq!(names, vec!["3-finger gestures", "5-finger gestures"]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct GestureSet {
+    swipes: BTreeSet<u8>,
+    spreads: BTreeSet<u8>,
+    grabs: BTreeSet<u8>,
+    shakes: BTreeSet<u8>,
+    paths: Vec<(u8, Path)>,
+    taps: BTreeSet<u8>,
+    holds: BTreeSet<u8>,
+    pinches: BTreeSet<u8>,
+    rotations: BTreeSet<u8>,
+    edges: bool,
+    edge_zones: Option<EdgeZones>,
+    swipe_angles: DirectionThresholds,
+    dwell_swipes: bool,
+    early_swipes: bool,
+    // The gestures that were inserted to fire at the threshold, instead of when the fingers lift.
+    early: Vec<Gesture>,
+    // The swipes that were inserted to fire continuously.
+    sliding: Vec<Gesture>,
+    one_finger: OneFinger,
+    params: Params,
+}
+
+impl GestureSet {
+    /// Creates an empty `GestureSet`.
+    pub fn new() -> GestureSet {
+        GestureSet {
+            swipes: BTreeSet::new(),
+            spreads: BTreeSet::new(),
+            grabs: BTreeSet::new(),
+            shakes: BTreeSet::new(),
+            paths: Vec::new(),
+            taps: BTreeSet::new(),
+            holds: BTreeSet::new(),
+            pinches: BTreeSet::new(),
+            rotations: BTreeSet::new(),
+            edges: false,
+            edge_zones: None,
+            swipe_angles: DirectionThresholds::default(),
+            dwell_swipes: false,
+            early_swipes: false,
+            early: Vec::new(),
+            sliding: Vec::new(),
+            one_finger: OneFinger::Anywhere,
+            params: Params::default(),
+        }
+    }
+
+    /// Sets how far swipes may stray from each direction.
+    pub fn swipe_angles(mut self, thresholds: DirectionThresholds) -> GestureSet {
+        self.swipe_angles = thresholds;
+        self
+    }
+
+    /// Sets whether swipes may also end with the fingers holding still, instead of lifting (see
+    /// [`dwell_swipe`](compound/fn.dwell_swipe.html)). Either way, they are recognized as the
+    /// same swipe.
+    pub fn dwell_swipes(mut self, enabled: bool) -> GestureSet {
+        self.dwell_swipes = enabled;
+        self
+    }
+
+    /// Sets whether swipes are recognized as soon as they are long enough, instead of when the
+    /// fingers lift (see [`early_swipe`](compound/fn.early_swipe.html)).
+    ///
+    /// Early swipes claim the rest of the touch, so nothing else is recognized until every finger
+    /// has lifted. They also take the place of the swipes that end by holding still (see
+    /// [`dwell_swipes`](#method.dwell_swipes)), since they would always come first.
+    pub fn early_swipes(mut self, enabled: bool) -> GestureSet {
+        self.early_swipes = enabled;
+        self
+    }
+
+    /// Sets where gestures with one finger (apart from edge swipes) may start. The default is
+    /// `OneFinger::Anywhere`.
+    pub fn one_finger(mut self, policy: OneFinger) -> GestureSet {
+        self.one_finger = policy;
+        self
+    }
+
+    /// Sets how far (in millimeters) each finger must move in a grab.
+    ///
+    /// This is the same as setting `params.pinch.grab_mm`.
+    pub fn grab_distance(mut self, mm: f64) -> GestureSet {
+        self.params.pinch.grab_mm = mm;
+        self
+    }
+
+    /// Sets the thresholds for all the recognizers.
+    ///
+    /// This replaces any earlier call to [`grab_distance`](#method.grab_distance).
+    pub fn params(mut self, params: Params) -> GestureSet {
+        self.params = params;
+        self
+    }
+
+    /// Sets where edge swipes can start. Without this, edge swipes are never recognized.
+    pub fn edge_zones(mut self, zones: EdgeZones) -> GestureSet {
+        self.edge_zones = Some(zones);
+        self
+    }
+
+    /// Adds a gesture to the set.
+    ///
+    /// Since a swipe is recognized along with its direction, adding a swipe in any direction
+    /// means that swipes in every direction (with the same number of fingers) are recognized.
+    /// The same goes for edge swipes, pinches and rotations.
+    pub fn insert(&mut self, g: Gesture) {
+        match g {
+            Gesture::Swipe { num_fingers, .. } => { self.swipes.insert(num_fingers); },
+            Gesture::Spread { num_fingers } => { self.spreads.insert(num_fingers); },
+            Gesture::Grab { num_fingers } => { self.grabs.insert(num_fingers); },
+            Gesture::Shake { num_fingers } => { self.shakes.insert(num_fingers); },
+            Gesture::Path { num_fingers, path } => if !self.paths.contains(&(num_fingers, path)) {
+                self.paths.push((num_fingers, path));
+            },
+            Gesture::EdgeSwipe { .. } => self.edges = true,
+            Gesture::Tap { num_fingers } => { self.taps.insert(num_fingers); },
+            Gesture::Hold { num_fingers } => { self.holds.insert(num_fingers); },
+            Gesture::Pinch { num_fingers, .. } => { self.pinches.insert(num_fingers); },
+            Gesture::Rotate { num_fingers, .. } => { self.rotations.insert(num_fingers); },
+        }
+    }
+
+    /// Adds a gesture to the set, which is reported at `fire`.
+    ///
+    /// If `g` [can't be reported](enum.Gesture.html#method.can_fire) at `fire`, this is the same
+    /// as [`insert`](#method.insert). Gestures that fire at the threshold are recognized by a
+    /// separate recognizer, which claims the rest of the touch when it succeeds. For swipes, this
+    /// only applies to the direction of `g`: the other directions still fire when the fingers
+    /// lift, unless they are inserted like this too. The same goes for swipes that fire
+    /// continuously, which also have a recognizer of their own.
+    pub fn insert_firing(&mut self, g: Gesture, fire: Fire) {
+        self.insert(g);
+        if !g.can_fire(fire) {
+            warn!("{} can't fire {:?}", g, fire.to_string());
+        } else if fire == Fire::Threshold && g.default_fire() == Fire::Release
+            && !self.early.contains(&g) {
+            self.early.push(g);
+        } else if fire == Fire::Continuously && !self.sliding.contains(&g) {
+            self.sliding.push(g);
+        }
+    }
+
+    /// Adds recognizers for all the gestures in this set to `man`.
+    ///
+    /// The outcomes are converted to `T`, so that the gestures can share a `Manager` with other
+    /// kinds of recognizers. Returns a description of each of the recognizers that was added, in
+    /// the order that they were added.
+    pub fn push_to<T: From<Gesture> + 'static>(&self, man: &mut Manager<T>) -> Vec<String> {
+        self.push_to_with_fingers(man).into_iter().map(|(name, _)| name).collect()
+    }
+
+    /// Like [`push_to`](#method.push_to), but also returns the number of fingers that make the
+    /// gestures of each recognizer.
+    pub fn push_to_with_fingers<T: From<Gesture> + 'static>(&self, man: &mut Manager<T>)
+    -> Vec<(String, u8)> {
+        let mut names = Vec::new();
+        if self.edges {
+            match self.edge_zones {
+                Some(ref zones) => {
+                    let rec = edge_swipe_tuned(zones.clone(), &self.params)
+                        .map_outcome(|edge| Gesture::EdgeSwipe { edge })
+                        .into_outcome::<T>();
+                    man.push_with_priority(rec, EDGE_PRIORITY);
+                    names.push(("edge swipes".to_owned(), 1));
+                },
+                None => warn!("not recognizing edge swipes, because there are no edge zones"),
+            }
+        }
+
+        let fingers = self.swipes.iter()
+            .chain(&self.spreads)
+            .chain(&self.grabs)
+            .chain(&self.shakes)
+            .chain(self.paths.iter().map(|p| &p.0))
+            .chain(&self.taps)
+            .chain(&self.holds)
+            .chain(&self.pinches)
+            .chain(&self.rotations)
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        for num_fingers in fingers {
+            // All the gestures with the same number of fingers share the same prefix, so we only
+            // evaluate it once.
+            let mut branches: Vec<Box<Recognizer<In=(), Out=T>>> = Vec::new();
+            // The gestures that fire at the threshold, which get their own recognizer.
+            let mut early: Vec<Box<Recognizer<In=(), Out=T>>> = Vec::new();
+            let all_dirs = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+            let slide_dirs = all_dirs.iter()
+                .cloned()
+                .filter(|&direction| {
+                    self.sliding.contains(&Gesture::Swipe { num_fingers, direction })
+                })
+                .collect::<Vec<_>>();
+            let early_dirs = all_dirs.iter()
+                .cloned()
+                .filter(|&direction| {
+                    (self.early_swipes
+                        || self.early.contains(&Gesture::Swipe { num_fingers, direction }))
+                        && !slide_dirs.contains(&direction)
+                })
+                .collect::<Vec<_>>();
+            let is_early = |g: Gesture| self.early.contains(&g);
+            if self.swipes.contains(&num_fingers) && !early_dirs.is_empty() {
+                let dirs = early_dirs.clone();
+                early.push(Box::new(early_swipe_after_down_tuned(self.swipe_angles, &self.params)
+                    .flat_map_outcome(move |direction| {
+                        if dirs.contains(&direction) {
+                            RecResult::Succeeded(Gesture::Swipe { num_fingers, direction })
+                        } else {
+                            RecResult::Failed
+                        }
+                    })
+                    .into_outcome::<T>()));
+            }
+            // Once every direction fires early or continuously, there is nothing left for the
+            // ordinary swipes.
+            if self.swipes.contains(&num_fingers) && early_dirs.len() + slide_dirs.len() < 4 {
+                let dirs = slide_dirs.clone();
+                branches.push(Box::new(direction_swipe_after_down_tuned(self.swipe_angles,
+                                                                        &self.params)
+                    .flat_map_outcome(move |direction| plain_swipe(num_fingers, direction, &dirs))
+                    .into_outcome::<T>()));
+                if self.dwell_swipes {
+                    let dirs = slide_dirs.clone();
+                    branches.push(Box::new(dwell_swipe_after_down_tuned(self.swipe_angles,
+                                                                        &self.params)
+                        .flat_map_outcome(move |direction| {
+                            plain_swipe(num_fingers, direction, &dirs)
+                        })
+                        .into_outcome::<T>()));
+                }
+            }
+            if is_early(Gesture::Spread { num_fingers }) {
+                early.push(Box::new(early_spread_after_down_tuned(&self.params)
+                    .map_outcome(move |_| Gesture::Spread { num_fingers })
+                    .into_outcome::<T>()));
+            } else if self.spreads.contains(&num_fingers) {
+                branches.push(Box::new(spread_after_down_tuned(&self.params)
+                    .map_outcome(move |_| Gesture::Spread { num_fingers })
+                    .into_outcome::<T>()));
+            }
+            if is_early(Gesture::Grab { num_fingers }) {
+                early.push(Box::new(early_grab_after_down_tuned(&self.params)
+                    .map_outcome(move |_| Gesture::Grab { num_fingers })
+                    .into_outcome::<T>()));
+            } else if self.grabs.contains(&num_fingers) {
+                branches.push(Box::new(grab_after_down_tuned(&self.params)
+                    .map_outcome(move |_| Gesture::Grab { num_fingers })
+                    .into_outcome::<T>()));
+            }
+            if self.shakes.contains(&num_fingers) {
+                branches.push(Box::new(shake_after_down_tuned(&self.params)
+                    .map_outcome(move |_| Gesture::Shake { num_fingers })
+                    .into_outcome::<T>()));
+            }
+            // All the paths share one recognizer, which follows the fingers wherever they go and
+            // then checks whether they made one of the paths.
+            let paths = self.paths.iter()
+                .filter(|p| p.0 == num_fingers)
+                .map(|p| p.1)
+                .collect::<Vec<_>>();
+            if !paths.is_empty() {
+                branches.push(Box::new(segmented_swipe_after_down_tuned(MAX_PATH_LEN, &self.params)
+                    .flat_map_outcome(move |dirs| match Path::new(&dirs) {
+                        Some(path) if paths.contains(&path) => {
+                            RecResult::Succeeded(Gesture::Path { num_fingers, path })
+                        },
+                        _ => {
+                            debug!("path {:?} isn't bound", dirs);
+                            RecResult::Failed
+                        },
+                    })
+                    .into_outcome::<T>()));
+            }
+            if self.taps.contains(&num_fingers) {
+                branches.push(Box::new(tap_after_down_tuned(&self.params)
+                    .map_outcome(move |_| Gesture::Tap { num_fingers })
+                    .into_outcome::<T>()));
+            }
+            if self.holds.contains(&num_fingers) {
+                branches.push(Box::new(hold_after_down_tuned(&self.params)
+                    .map_outcome(move |_| Gesture::Hold { num_fingers })
+                    .into_outcome::<T>()));
+            }
+            if self.pinches.contains(&num_fingers) {
+                branches.push(Box::new(pinch_after_down_tuned(&self.params)
+                    .map_outcome(move |scale| Gesture::Pinch {
+                        num_fingers,
+                        direction: PinchDirection::from_scale(scale),
+                    })
+                    .into_outcome::<T>()));
+            }
+            if self.rotations.contains(&num_fingers) {
+                branches.push(Box::new(rotate_after_down_tuned(&self.params)
+                    .map_outcome(move |degrees| Gesture::Rotate {
+                        num_fingers,
+                        direction: RotateDirection::from_degrees(degrees),
+                    })
+                    .into_outcome::<T>()));
+            }
+            // Gestures with more fingers are more specific, so they get priority.
+            if self.swipes.contains(&num_fingers) && !slide_dirs.is_empty() {
+                if let Some(prefix) = self.prefix(num_fingers) {
+                    let dirs = slide_dirs.clone();
+                    let rec = prefix
+                        .and_then(progressive_swipe_after_down_tuned(self.swipe_angles,
+                                                                     &self.params))
+                        .flat_map_outcome(move |(direction, p)| if dirs.contains(&direction) {
+                            RecResult::Succeeded((Gesture::Swipe { num_fingers, direction }, p))
+                        } else {
+                            RecResult::Failed
+                        })
+                        .report_progress()
+                        .into_outcome::<T>();
+                    man.push_with_priority(rec, num_fingers as i32);
+                    names.push((format!("{}-finger continuous swipes", num_fingers), num_fingers));
+                }
+            }
+            if !early.is_empty() {
+                if let Some(prefix) = self.prefix(num_fingers) {
+                    man.push_claiming(prefix.branch(early), num_fingers as i32);
+                    names.push((format!("{}-finger early gestures", num_fingers), num_fingers));
+                }
+            }
+            if branches.is_empty() {
+                continue;
+            }
+            let prefix = match self.prefix(num_fingers) {
+                Some(p) => p,
+                None => continue,
+            };
+            man.push_with_priority(prefix.branch(branches), num_fingers as i32);
+            names.push((format!("{}-finger gestures", num_fingers), num_fingers));
+        }
+        names
+    }
+
+    // The beginning of all the gestures with `num_fingers` fingers, or `None` if they can't be
+    // recognized at all.
+    fn prefix(&self, num_fingers: u8) -> Option<Box<Recognizer<In=(), Out=()>>> {
+        let down = fingers_down_tuned(num_fingers, &self.params);
+        if num_fingers != 1 {
+            return Some(Box::new(down));
+        }
+        match self.one_finger {
+            OneFinger::Anywhere => Some(Box::new(down)),
+            OneFinger::AfterHold { ms } => {
+                let mut params = self.params;
+                params.tap.hold_ms = ms;
+                Some(Box::new(down.and_then(hold_after_down_tuned(&params))))
+            },
+            OneFinger::FromEdges => match self.edge_zones {
+                Some(ref zones) => {
+                    let start = EdgeStart::new(zones.clone()).map_outcome(|_| ());
+                    Some(Box::new(down.and_then(start)))
+                },
+                None => {
+                    warn!("not recognizing one-finger gestures, because there are no edge zones");
+                    None
+                },
+            },
+        }
+    }
+}
+
+// The outcome of an ordinary swipe, which fails if swipes in its direction fire continuously
+// (because they have a recognizer of their own).
+fn plain_swipe(num_fingers: u8, direction: Direction, sliding: &[Direction])
+-> RecResult<Gesture> {
+    if sliding.contains(&direction) {
+        RecResult::Failed
+    } else {
+        RecResult::Succeeded(Gesture::Swipe { num_fingers, direction })
+    }
+}
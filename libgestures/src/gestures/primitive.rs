@@ -1,8 +1,11 @@
 use euclid::vec2;
 use std;
 
-use frame::Frame;
-use geom::{Angle, Point};
+use edge::{Edge, EdgeZones};
+use frame::{Frame, Snapshot};
+use geom::{adaptation, Angle, Axis, Point, Similarity};
+use graph::Graph;
+use params::{DwellParams, LiftParams, PinchParams, ShakeParams, SwipeParams};
 use {Recognizer, RecResult};
 
 /// A recognizer that detects when a certain number of fingers are down.
@@ -40,38 +43,107 @@ impl Recognizer for NFingers {
             RecResult::Continuing
         }
     }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        // Nothing can happen until a finger comes down.
+        false
+    }
 }
 
 /// A recognizer that succeeds when all the fingers have gone up.
 ///
-/// This recognizer will fail if any fingers go down.
+/// By default, this recognizer will fail if any fingers go down. With
+/// [`with_params`](#method.with_params), it can put up with a contact that only brushes the
+/// surface while the hand is lifting (like a knuckle at the end of a swipe), and it can give up
+/// on fingers that stay down too long after the others have lifted.
 #[derive(Clone, Debug)]
 pub struct FingersUp {
+    extra_ms: u32,
+    window_ms: u32,
+    // The slots of the fingers that were down when this started, and haven't gone up yet.
+    fingers: Vec<bool>,
+    // The slots of the contacts that came down since this started, and when they came down.
+    extra: Vec<(usize, u32)>,
+    // When the first of `fingers` went up.
+    first_lift: Option<u32>,
 }
 
 impl FingersUp {
-	pub fn new() -> FingersUp {
-		FingersUp { }
-	}
+    /// Creates a new recognizer that succeeds when all the fingers have gone up, and fails if any
+    /// fingers go down.
+    pub fn new() -> FingersUp {
+        FingersUp::with_params(&LiftParams::default())
+    }
+
+    /// Creates a new recognizer with the tolerances in `params`.
+    pub fn with_params(params: &LiftParams) -> FingersUp {
+        FingersUp {
+            extra_ms: params.extra_contact_ms,
+            window_ms: params.window_ms,
+            fingers: Vec::new(),
+            extra: Vec::new(),
+            first_lift: None,
+        }
+    }
 }
 
 impl Recognizer for FingersUp {
-	type In = ();
-	type Out = ();
+    type In = ();
+    type Out = ();
+
+    fn init(&mut self, _: (), frame: &Frame) {
+        self.fingers = frame.cur.down.clone();
+        self.extra.clear();
+        self.first_lift = None;
+    }
 
-	fn init(&mut self, _: (), _: &Frame) {}
+    fn update(&mut self, frame: &Frame) -> RecResult<()> {
+        if frame.touch_down && self.extra_ms == 0 {
+            debug!("FingersUp failed");
+            return RecResult::Failed;
+        }
+
+        let mut lifted = false;
+        for (i, down) in self.fingers.iter_mut().enumerate() {
+            if *down && !frame.cur.is_down(i) {
+                *down = false;
+                lifted = true;
+            }
+        }
+        if lifted && self.first_lift.is_none() {
+            self.first_lift = Some(frame.time);
+        }
+
+        self.extra.retain(|&(i, _)| frame.cur.is_down(i));
+        for i in 0..frame.cur.down.len() {
+            let known = self.fingers.get(i).cloned().unwrap_or(false)
+                || self.extra.iter().any(|&(j, _)| j == i);
+            if frame.cur.down[i] && !known {
+                self.extra.push((i, frame.time));
+            }
+        }
+        if self.extra.iter().any(|&(_, t)| frame.time.wrapping_sub(t) > self.extra_ms) {
+            debug!("FingersUp failed: a finger came down and stayed down");
+            return RecResult::Failed;
+        }
 
-	fn update(&mut self, frame: &Frame) -> RecResult<()> {
-		if frame.touch_down {
-			debug!("FingersUp failed");
-			RecResult::Failed
-		} else if frame.cur.num_down == 0 {
-			debug!("FingersUp succeeded");
-			RecResult::Succeeded(())
-		} else {
-			RecResult::Continuing
-		}
-	}
+        let in_window = match self.first_lift {
+            Some(t) => self.window_ms == 0 || frame.time.wrapping_sub(t) <= self.window_ms,
+            None => true,
+        };
+        let all_up = self.fingers.iter().all(|&d| !d);
+        if !in_window && (lifted || !all_up) {
+            debug!("FingersUp failed: the fingers didn't all lift within {} ms", self.window_ms);
+            return RecResult::Failed;
+        }
+        // Any contacts that are left are the brief ones that we are putting up with. If the
+        // fingers lifted together, they don't have to go up before we finish.
+        if all_up && (self.extra.is_empty() || self.window_ms > 0) {
+            debug!("FingersUp succeeded");
+            return RecResult::Succeeded(());
+        }
+        RecResult::Continuing
+    }
 }
 
 /// A recognizer that detects when the average finger position starts to move.
@@ -85,15 +157,26 @@ impl Recognizer for FingersUp {
 pub struct InitialAngle {
     threshold: f64,
     init_pos: Point,
+    // The preferred axis, and how much the movement perpendicular to it counts.
+    bias: Option<(Axis, f64)>,
+    // How many fingers to follow, or zero to follow all of them.
+    leading: usize,
+    // Where the fingers that we follow have got to.
+    pos: Point,
+    // How far the fingers have moved so far.
+    moved: f64,
 }
 
 impl InitialAngle {
     /// Creates a new recognizer for detecting when the average finger position starts to move.
     pub fn new() -> InitialAngle {
-        InitialAngle {
-            threshold: 5.0,
-            init_pos: vec2(0.0, 0.0),
-        }
+        InitialAngle::with_params(&SwipeParams::default())
+    }
+
+    /// Creates a new recognizer for detecting when the average finger position has moved by
+    /// `params.start_mm`.
+    pub fn with_params(params: &SwipeParams) -> InitialAngle {
+        InitialAngle::with_threshold_mm(params.start_mm).leading_fingers(params.leading_fingers)
     }
 
     /// Creates a new recognizer for detecting when the average finger position starts to move,
@@ -105,8 +188,39 @@ impl InitialAngle {
         InitialAngle {
             threshold: mm,
             init_pos: vec2(0.0, 0.0),
+            bias: None,
+            leading: 0,
+            pos: vec2(0.0, 0.0),
+            moved: 0.0,
+        }
+    }
+
+    /// Only follows the `k` fingers that move furthest on each frame, instead of the middle of all
+    /// of them (see [`Frame::leading_motion`](../../struct.Frame.html#method.leading_motion)).
+    /// Zero follows all of them.
+    pub fn leading_fingers(self, k: usize) -> InitialAngle {
+        InitialAngle {
+            leading: k,
+            ..self
         }
     }
+
+    /// Favors movement along `axis`.
+    ///
+    /// The part of the movement that is perpendicular to `axis` is scaled by `weight` (which
+    /// should be between zero and one) before it is compared to the threshold or used to compute
+    /// the angle. This helps on touchpads whose resolution is poor in one direction.
+    pub fn axis_bias(mut self, axis: Axis, weight: f64) -> InitialAngle {
+        self.bias = Some((axis, weight));
+        self
+    }
+
+    /// Only recognizes movement along `axis`, ignoring any perpendicular movement.
+    ///
+    /// The outcome will always be one of the two angles along `axis`.
+    pub fn lock_axis(self, axis: Axis) -> InitialAngle {
+        self.axis_bias(axis, 0.0)
+    }
 }
 
 impl Recognizer for InitialAngle {
@@ -114,7 +228,9 @@ impl Recognizer for InitialAngle {
     type Out = (Point, Angle);
 
     fn init(&mut self, _: (), frame: &Frame) {
-        self.init_pos = frame.cur.mean_pos();
+        self.init_pos = frame.delta.mean_pos;
+        self.pos = frame.delta.mean_pos;
+        self.moved = 0.0;
     }
 
     fn update(&mut self, frame: &Frame) -> RecResult<(Point, Angle)> {
@@ -122,16 +238,650 @@ impl Recognizer for InitialAngle {
 			debug!("InitialAngle failed");
             RecResult::Failed
         } else {
-            let pos = frame.cur.mean_pos();
-            let diff = pos - self.init_pos;
+            self.pos = follow(self.leading, self.pos, frame);
+            let mut diff = self.pos - self.init_pos;
+            if let Some((axis, weight)) = self.bias {
+                diff = axis.bias(diff, weight);
+            }
+            self.moved = diff.length();
             if diff.length() > self.threshold {
 				debug!("InitialAngle succeeded: {:?} radians", (-diff.y).atan2(diff.x));
-                RecResult::Succeeded((self.init_pos, Angle::from_radians((-diff.y).atan2(diff.x))))
+                // The next stage starts from the middle of all the fingers, so we tell it where
+                // that would have started if it had moved as far as the ones we follow.
+                let start = frame.delta.mean_pos - (self.pos - self.init_pos);
+                RecResult::Succeeded((start, Angle::from_radians((-diff.y).atan2(diff.x))))
             } else {
                 RecResult::Continuing
             }
         }
     }
+
+    fn progress(&self) -> f64 {
+        (self.moved / self.threshold).min(1.0)
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        match self.bias {
+            Some((axis, weight)) => {
+                g.node(&format!("InitialAngle (threshold {} mm, {:?} bias {})",
+                                self.threshold, axis, weight))
+            },
+            None => g.node(&format!("InitialAngle (threshold {} mm)", self.threshold)),
+        }
+    }
+}
+
+// Where the fingers that we follow have got to, given that they were at `pos` before `frame`.
+fn follow(leading: usize, pos: Point, frame: &Frame) -> Point {
+    if leading == 0 {
+        frame.delta.mean_pos
+    } else {
+        pos + frame.leading_motion(leading)
+    }
+}
+
+/// A progressive recognizer that tracks how far the fingers move in a given direction.
+///
+/// It starts from an initial position and angle (like the ones produced by
+/// [`InitialAngle`](struct.InitialAngle.html)). On every frame where the fingers move, it reports
+/// progress: the distance (in millimeters) that the average finger position has moved along the
+/// angle, which is negative if the fingers went backwards. It succeeds with the final distance
+/// when a finger goes up, and fails if a finger comes down.
+#[derive(Clone, Debug)]
+pub struct Drag {
+    init_pos: Point,
+    angle: Angle,
+}
+
+impl Drag {
+    pub fn new() -> Drag {
+        Drag {
+            init_pos: vec2(0.0, 0.0),
+            angle: Angle::from_radians(0.0),
+        }
+    }
+
+    fn distance(&self, frame: &Frame) -> f64 {
+        let diff = frame.delta.mean_pos - self.init_pos;
+        let a = self.angle.to_radians();
+        // The y axis points down, but angles are measured counter-clockwise.
+        diff.x * a.cos() - diff.y * a.sin()
+    }
+}
+
+impl Recognizer for Drag {
+    type In = (Point, Angle);
+    type Out = f64;
+
+    fn init(&mut self, init: (Point, Angle), _: &Frame) {
+        self.init_pos = init.0;
+        self.angle = init.1;
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<f64> {
+        if frame.touch_down {
+            debug!("Drag failed because of a touch down");
+            RecResult::Failed
+        } else if frame.touch_up {
+            debug!("Drag succeeded after a lifted finger");
+            RecResult::Succeeded(self.distance(frame))
+        } else if frame.delta.mean_pos != frame.delta.last_mean_pos {
+            RecResult::Progressing(self.distance(frame))
+        } else {
+            RecResult::Continuing
+        }
+    }
+}
+
+/// A recognizer that detects the fingers pinching together or spreading apart.
+///
+/// It measures how spread out the fingers are (see
+/// [`Snapshot::spread`](../../frame/struct.Snapshot.html#method.spread)) when it starts recognizing.
+/// It succeeds once the spread has changed by a large enough factor, returning the ratio of the
+/// current spread to the initial one; this is less than one for a pinch and more than one for a
+/// spread. It fails if fingers go up or come down.
+#[derive(Clone, Debug)]
+pub struct Pinch {
+    min_scale: f64,
+    init_spread: f64,
+}
+
+impl Pinch {
+    /// Creates a new recognizer that succeeds when the fingers are a quarter closer together, or a
+    /// quarter further apart.
+    pub fn new() -> Pinch {
+        Pinch::with_params(&PinchParams::default())
+    }
+
+    /// Creates a new recognizer that succeeds when the spread of the fingers changes by a factor
+    /// of `params.min_scale`.
+    pub fn with_params(params: &PinchParams) -> Pinch {
+        Pinch {
+            min_scale: 1.0,
+            init_spread: 0.0,
+        }.min_scale(params.min_scale)
+    }
+
+    /// Sets how much the spread of the fingers must change. For example, with a `factor` of `1.5`
+    /// this succeeds when the spread shrinks to two thirds of its initial value, or grows by a half.
+    pub fn min_scale(self, factor: f64) -> Pinch {
+        assert!(factor > 1.0);
+        Pinch {
+            min_scale: factor,
+            ..self
+        }
+    }
+}
+
+impl Recognizer for Pinch {
+    type In = ();
+    type Out = f64;
+
+    fn init(&mut self, _: (), frame: &Frame) {
+        self.init_spread = frame.delta.spread;
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<f64> {
+        if frame.touch_up || frame.touch_down {
+            debug!("Pinch failed");
+            return RecResult::Failed;
+        }
+        if self.init_spread <= 0.0 {
+            debug!("Pinch failed: the fingers started in the same place");
+            return RecResult::Failed;
+        }
+
+        let scale = frame.delta.spread / self.init_spread;
+        if scale >= self.min_scale || scale * self.min_scale <= 1.0 {
+            debug!("Pinch succeeded: scale {}", scale);
+            RecResult::Succeeded(scale)
+        } else {
+            RecResult::Continuing
+        }
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        g.node(&format!("Pinch (min scale {})", self.min_scale))
+    }
+}
+
+/// A progressive recognizer that tracks the fingers moving, rotating and pinching all at once, like
+/// when manipulating a map or a photo.
+///
+/// On every frame where the fingers move, it reports progress: the transform (see
+/// [`fit_similarity`](../../geom/fn.fit_similarity.html)) that best explains how the fingers moved
+/// since the previous frame. When a finger goes up, it succeeds with the transform that combines
+/// all of those steps. It fails if a finger comes down.
+///
+/// The steps and the total have the same type, so a [`Manager`](../../manager/struct.Manager.html)
+/// only reports the steps from
+/// [`update_event_recognized`](../../manager/struct.Manager.html#method.update_event_recognized),
+/// where their `finished` is false.
+#[derive(Clone, Debug)]
+pub struct Transform {
+    total: Similarity,
+}
+
+impl Transform {
+    pub fn new() -> Transform {
+        Transform {
+            total: Similarity::identity(),
+        }
+    }
+}
+
+impl Recognizer for Transform {
+    type In = ();
+    type Out = Similarity;
+
+    fn init(&mut self, _: (), _: &Frame) {
+        self.total = Similarity::identity();
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<Similarity> {
+        if frame.touch_down {
+            debug!("Transform failed because of a touch down");
+            RecResult::Failed
+        } else if frame.touch_up {
+            debug!("Transform succeeded after a lifted finger: {:?}", self.total);
+            RecResult::Succeeded(self.total)
+        } else if frame.delta.mean_dist > 0.0 {
+            let step = frame.delta.motion;
+            self.total = self.total.then(&step);
+            RecResult::Progressing(step)
+        } else {
+            RecResult::Continuing
+        }
+    }
+}
+
+/// A recognizer that detects the fingers turning around their middle, like a hand turning a knob.
+///
+/// It adds up how much the fingers rotate on every frame (see
+/// [`FrameDelta::motion`](../../frame/struct.FrameDelta.html#structfield.motion)), and succeeds
+/// once they have turned at least `min_angle_deg` degrees either way, returning the total in
+/// degrees. This is positive for a counter-clockwise turn and negative for a clockwise one. It
+/// fails if fingers go up or come down.
+#[derive(Clone, Debug)]
+pub struct Rotation {
+    min_angle: f64,
+    // The total rotation so far, in degrees. Unlike an `Angle`, this doesn't wrap around.
+    total: f64,
+}
+
+impl Rotation {
+    /// Creates a new recognizer that succeeds when the fingers have turned 30 degrees.
+    pub fn new() -> Rotation {
+        Rotation::with_params(&PinchParams::default())
+    }
+
+    /// Creates a new recognizer that succeeds when the fingers have turned
+    /// `params.min_rotation_deg` degrees.
+    pub fn with_params(params: &PinchParams) -> Rotation {
+        assert!(params.min_rotation_deg > 0.0);
+        Rotation {
+            min_angle: params.min_rotation_deg,
+            total: 0.0,
+        }
+    }
+}
+
+impl Recognizer for Rotation {
+    type In = ();
+    type Out = f64;
+
+    fn init(&mut self, _: (), _: &Frame) {
+        self.total = 0.0;
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<f64> {
+        if frame.touch_up || frame.touch_down {
+            debug!("Rotation failed");
+            return RecResult::Failed;
+        }
+
+        // The steps are small, so anything past half a turn is really a clockwise step.
+        let step = frame.delta.motion.rotation.to_degrees();
+        self.total += if step > 180.0 { step - 360.0 } else { step };
+        if self.total.abs() >= self.min_angle {
+            debug!("Rotation succeeded: {} degrees", self.total);
+            RecResult::Succeeded(self.total)
+        } else {
+            RecResult::Continuing
+        }
+    }
+
+    fn progress(&self) -> f64 {
+        (self.total.abs() / self.min_angle).min(1.0)
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        g.node(&format!("Rotation (min angle {} degrees)", self.min_angle))
+    }
+}
+
+/// Which way the fingers should move in a [`Radial`](struct.Radial.html) gesture.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RadialDirection {
+    /// Away from the middle of the fingers.
+    Outward,
+    /// Towards the middle of the fingers.
+    Inward,
+}
+
+/// A recognizer that detects every finger moving straight towards, or straight away from, the
+/// middle of the fingers.
+///
+/// The middle is the average position of the fingers when this recognizer starts. Each finger's
+/// movement is measured from its own starting position, and it must stay within 45 degrees of the
+/// line between the middle and that starting position. This succeeds once every finger has moved
+/// at least `min_distance` millimeters in the right direction, returning the average distance
+/// moved. It fails if a finger moves the wrong way, or if fingers go up or come down.
+#[derive(Clone, Debug)]
+pub struct Radial {
+    direction: RadialDirection,
+    min_distance: f64,
+    init_pos: Snapshot,
+    center: Point,
+    // How far the finger that is furthest behind has moved in the right direction.
+    slowest: f64,
+}
+
+impl Radial {
+    pub fn new(direction: RadialDirection, min_distance_mm: f64) -> Radial {
+        Radial {
+            direction: direction,
+            min_distance: min_distance_mm,
+            init_pos: Snapshot::new(),
+            center: vec2(0.0, 0.0),
+            slowest: 0.0,
+        }
+    }
+}
+
+impl Recognizer for Radial {
+    type In = ();
+    type Out = f64;
+
+    fn init(&mut self, _: (), frame: &Frame) {
+        self.init_pos = frame.cur.clone();
+        self.center = frame.delta.mean_pos;
+        self.slowest = 0.0;
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<f64> {
+        if frame.touch_up || frame.touch_down {
+            debug!("Radial failed");
+            return RecResult::Failed;
+        }
+
+        let sign = match self.direction {
+            RadialDirection::Outward => 1.0,
+            RadialDirection::Inward => -1.0,
+        };
+        let mut total = 0.0;
+        let mut slowest = std::f64::INFINITY;
+        for (i, pos) in frame.cur.fingers() {
+            let start = self.init_pos.pos[i];
+            let out = start - self.center;
+            if out.length() == 0.0 {
+                debug!("Radial failed: finger {} started in the middle", i);
+                return RecResult::Failed;
+            }
+            let disp = pos - start;
+            // How far the finger moved in the right direction, and how far it moved sideways.
+            let radial = sign * disp.dot(out) / out.length();
+            let sideways = (disp.length() * disp.length() - radial * radial).max(0.0).sqrt();
+            if disp.length() > self.min_distance / 2.0 && sideways > radial {
+                debug!("Radial failed: finger {} moved the wrong way", i);
+                return RecResult::Failed;
+            }
+            slowest = slowest.min(radial);
+            total += radial;
+        }
+
+        self.slowest = slowest;
+        if slowest >= self.min_distance {
+            let mean = total / (frame.cur.num_down as f64);
+            debug!("Radial succeeded: {} mm", mean);
+            RecResult::Succeeded(mean)
+        } else {
+            RecResult::Continuing
+        }
+    }
+
+    fn progress(&self) -> f64 {
+        (self.slowest / self.min_distance).max(0.0).min(1.0)
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        g.node(&format!("Radial ({:?}, min distance {} mm)", self.direction, self.min_distance))
+    }
+}
+
+/// A recognizer that detects the fingers shaking back and forth.
+///
+/// The fingers must move at least `stroke` millimeters in some direction, and then turn around and
+/// move at least `stroke` millimeters back, and so on. The first stroke decides whether the shake
+/// is horizontal or vertical, and after that any movement along the other axis is ignored. This
+/// succeeds as soon as the fingers have turned around `min_reversals` times within `window_ms`
+/// milliseconds, returning the axis of the shake. It fails if fingers go up or come down.
+#[derive(Clone, Debug)]
+pub struct Shake {
+    min_reversals: usize,
+    window_ms: u32,
+    stroke: f64,
+    axis: Option<Axis>,
+    // Whether the current stroke is moving towards increasing coordinates.
+    forward: bool,
+    // The furthest position reached in the current stroke.
+    extreme: Point,
+    // The times of the recent reversals, oldest first.
+    reversals: Vec<u32>,
+}
+
+impl Shake {
+    /// Creates a new recognizer that succeeds after three reversals of at least 8 millimeters each,
+    /// all within 800 milliseconds.
+    pub fn new() -> Shake {
+        Shake::with_params(&ShakeParams::default())
+    }
+
+    /// Creates a new recognizer with the thresholds in `params`.
+    pub fn with_params(params: &ShakeParams) -> Shake {
+        assert!(params.min_reversals > 0);
+        Shake {
+            min_reversals: params.min_reversals,
+            window_ms: params.window_ms,
+            stroke: params.stroke_mm,
+            axis: None,
+            forward: true,
+            extreme: vec2(0.0, 0.0),
+            reversals: Vec::new(),
+        }
+    }
+
+    /// Sets how many times the fingers need to turn around.
+    pub fn min_reversals(self, n: usize) -> Shake {
+        assert!(n > 0);
+        Shake {
+            min_reversals: n,
+            ..self
+        }
+    }
+
+    /// Sets how quickly the reversals must happen.
+    pub fn window_ms(self, ms: u32) -> Shake {
+        Shake {
+            window_ms: ms,
+            ..self
+        }
+    }
+
+    /// Sets how far (in millimeters) the fingers must move between reversals.
+    pub fn stroke_mm(self, mm: f64) -> Shake {
+        Shake {
+            stroke: mm,
+            ..self
+        }
+    }
+}
+
+// The coordinate of `p` along `axis`.
+fn along(axis: Axis, p: Point) -> f64 {
+    match axis {
+        Axis::Horizontal => p.x,
+        Axis::Vertical => p.y,
+    }
+}
+
+impl Recognizer for Shake {
+    type In = ();
+    type Out = Axis;
+
+    fn init(&mut self, _: (), frame: &Frame) {
+        self.axis = None;
+        self.extreme = frame.delta.mean_pos;
+        self.reversals.clear();
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<Axis> {
+        if frame.touch_up || frame.touch_down {
+            debug!("Shake failed");
+            return RecResult::Failed;
+        }
+
+        let pos = frame.delta.mean_pos;
+        let axis = match self.axis {
+            Some(axis) => axis,
+            None => {
+                // We're waiting for the first stroke, which decides the axis.
+                let diff = pos - self.extreme;
+                if diff.length() < self.stroke {
+                    return RecResult::Continuing;
+                }
+                let axis = if diff.x.abs() >= diff.y.abs() {
+                    Axis::Horizontal
+                } else {
+                    Axis::Vertical
+                };
+                debug!("Shake started along the {:?} axis", axis);
+                self.axis = Some(axis);
+                self.forward = along(axis, diff) > 0.0;
+                self.extreme = pos;
+                return RecResult::Continuing;
+            },
+        };
+
+        let sign = if self.forward { 1.0 } else { -1.0 };
+        let ahead = sign * (along(axis, pos) - along(axis, self.extreme));
+        if ahead > 0.0 {
+            self.extreme = pos;
+        } else if -ahead >= self.stroke {
+            self.forward = !self.forward;
+            self.extreme = pos;
+            self.reversals.push(frame.time);
+        }
+
+        let window = self.window_ms;
+        self.reversals.retain(|&t| frame.time.wrapping_sub(t) <= window);
+        if self.reversals.len() >= self.min_reversals {
+            debug!("Shake succeeded after {} reversals", self.reversals.len());
+            RecResult::Succeeded(axis)
+        } else {
+            RecResult::Continuing
+        }
+    }
+
+    fn progress(&self) -> f64 {
+        (self.reversals.len() as f64 / self.min_reversals as f64).min(1.0)
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        g.node(&format!("Shake ({} reversals in {} ms, stroke {} mm)",
+                        self.min_reversals, self.window_ms, self.stroke))
+    }
+}
+
+/// A recognizer that succeeds when the fingers hold still for a while.
+///
+/// The middle of the fingers must stay within `still_mm` millimeters of one place for `hold_ms`
+/// milliseconds. If the fingers move further than that, the wait starts over wherever they are
+/// now, so this can be started while the fingers are still moving, and succeeds once they stop.
+/// It returns the middle of the fingers where they stopped, and fails if fingers go up or come
+/// down.
+///
+/// Touch surfaces don't usually send anything while the fingers are still, so the time needs to
+/// come from somewhere else: see [`Manager::tick`](../../manager/struct.Manager.html#method.tick).
+#[derive(Clone, Debug)]
+pub struct Dwell {
+    hold_ms: u32,
+    still: f64,
+    // Where the fingers are holding still, and since when.
+    anchor: Point,
+    since: u32,
+    // The time of the latest frame.
+    now: u32,
+}
+
+impl Dwell {
+    /// Creates a new recognizer that succeeds when the fingers have stayed within 2 millimeters
+    /// of one place for 600 milliseconds.
+    pub fn new() -> Dwell {
+        Dwell::with_params(&DwellParams::default())
+    }
+
+    /// Creates a new recognizer with the thresholds in `params`.
+    pub fn with_params(params: &DwellParams) -> Dwell {
+        Dwell {
+            hold_ms: params.hold_ms,
+            still: params.still_mm,
+            anchor: vec2(0.0, 0.0),
+            since: 0,
+            now: 0,
+        }
+    }
+}
+
+impl Recognizer for Dwell {
+    type In = ();
+    type Out = Point;
+
+    fn init(&mut self, _: (), frame: &Frame) {
+        self.anchor = frame.delta.mean_pos;
+        self.since = frame.time;
+        self.now = frame.time;
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<Point> {
+        if frame.touch_up || frame.touch_down {
+            debug!("Dwell failed");
+            return RecResult::Failed;
+        }
+
+        self.now = frame.time;
+        let pos = frame.delta.mean_pos;
+        if (pos - self.anchor).length() > self.still {
+            self.anchor = pos;
+            self.since = frame.time;
+            RecResult::Continuing
+        } else if frame.time.wrapping_sub(self.since) >= self.hold_ms {
+            debug!("Dwell succeeded");
+            RecResult::Succeeded(self.anchor)
+        } else {
+            RecResult::Continuing
+        }
+    }
+
+    fn progress(&self) -> f64 {
+        (self.now.wrapping_sub(self.since) as f64 / self.hold_ms as f64).min(1.0)
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        g.node(&format!("Dwell ({} ms within {} mm)", self.hold_ms, self.still))
+    }
+}
+
+/// A recognizer that checks whether the fingers came down at the edge of the surface.
+///
+/// It looks at the average finger position at the time that it starts recognizing, so it should
+/// be started as soon as the fingers are down. On its first update, it succeeds with the edge
+/// whose activation zone contains that position, or fails if there is no such edge.
+#[derive(Clone, Debug)]
+pub struct EdgeStart {
+    zones: EdgeZones,
+    edge: Option<Edge>,
+}
+
+impl EdgeStart {
+    pub fn new(zones: EdgeZones) -> EdgeStart {
+        EdgeStart {
+            zones: zones,
+            edge: None,
+        }
+    }
+}
+
+impl Recognizer for EdgeStart {
+    type In = ();
+    type Out = Edge;
+
+    fn init(&mut self, _: (), frame: &Frame) {
+        self.edge = self.zones.edge_at(frame.delta.mean_pos);
+    }
+
+    fn update(&mut self, _: &Frame) -> RecResult<Edge> {
+        match self.edge {
+            Some(e) => {
+                debug!("EdgeStart succeeded: {:?}", e);
+                RecResult::Succeeded(e)
+            },
+            None => {
+                debug!("EdgeStart failed: not in any edge zone");
+                RecResult::Failed
+            },
+        }
+    }
 }
 
 /// The possible reasons that a `StraightSwipe` finished recognizing.
@@ -141,6 +891,9 @@ pub enum StraightSwipeReason {
     ChangedAngle,
 	/// The straight swipe finished because a finger was lifted.
     LiftedFinger,
+    /// The straight swipe finished as soon as it was long enough, because it was made with
+    /// [`commit_early`](struct.StraightSwipe.html#method.commit_early).
+    ReachedLength,
 }
 
 /// The outcome of a successful `StraightSwipe`.
@@ -172,6 +925,10 @@ pub struct StraightSwipeOutcome {
 /// 
 /// If fingers go up, or if the angle changes, then we will succeed provided that
 /// the mean position moved a reasonable distance in a straight line first.
+///
+/// The angle is checked each time the fingers have moved another `step` millimeters, however many
+/// frames that took, so a touchpad that reports 60 frames per second and a touchscreen that
+/// reports 250 see the same checks.
 #[derive(Clone, Debug)]
 pub struct StraightSwipe {
     init_pos: Point,
@@ -181,18 +938,34 @@ pub struct StraightSwipe {
     adaptivity: f64,
     angle: Angle,
     angle_tolerance: f64,
+    early: bool,
+    // How many fingers to follow, or zero to follow all of them.
+    leading: usize,
+    // Where the fingers that we follow have got to.
+    pos: Point,
+    // How far the fingers are from where they started.
+    length: f64,
 }
 
 impl StraightSwipe {
     pub fn new() -> StraightSwipe {
+        StraightSwipe::with_params(&SwipeParams::default())
+    }
+
+    /// Creates a new recognizer with the thresholds in `params`.
+    pub fn with_params(params: &SwipeParams) -> StraightSwipe {
         StraightSwipe {
             init_pos: vec2(0.0, 0.0),
             last_pos: vec2(0.0, 0.0),
-            min_length: 10.0,
-            step: 3.0,
-            adaptivity: 0.01,
+            min_length: params.min_length_mm,
+            step: params.step_mm,
+            adaptivity: params.adaptivity,
             angle: Angle::from_radians(0.0),
-            angle_tolerance: 20.0 * std::f64::consts::PI / 180.0,
+            angle_tolerance: params.angle_tolerance_deg * std::f64::consts::PI / 180.0,
+            early: false,
+            leading: params.leading_fingers,
+            pos: vec2(0.0, 0.0),
+            length: 0.0,
         }
     }
 
@@ -210,11 +983,31 @@ impl StraightSwipe {
         }
     }
 
-    fn outcome(&self, reason: StraightSwipeReason, frame: &Frame) -> StraightSwipeOutcome {
+    /// Only follows the `k` fingers that move furthest on each frame, instead of the middle of all
+    /// of them (see [`Frame::leading_motion`](../../struct.Frame.html#method.leading_motion)).
+    /// Zero follows all of them.
+    pub fn leading_fingers(self, k: usize) -> StraightSwipe {
+        StraightSwipe {
+            leading: k,
+            ..self
+        }
+    }
+
+    /// Succeeds as soon as the fingers have moved more than the minimum length, as long as the
+    /// last step was still going in the same direction, instead of waiting for a finger to lift or
+    /// for the angle to change.
+    pub fn commit_early(self) -> StraightSwipe {
+        StraightSwipe {
+            early: true,
+            ..self
+        }
+    }
+
+    fn outcome(&self, reason: StraightSwipeReason) -> StraightSwipeOutcome {
         StraightSwipeOutcome {
             reason: reason,
             init_pos: self.init_pos,
-            final_pos: frame.cur.mean_pos(),
+            final_pos: self.pos,
             angle: self.angle,
         }
     }
@@ -224,10 +1017,12 @@ impl Recognizer for StraightSwipe {
     type In = (Point, Angle);
     type Out = StraightSwipeOutcome;
 
-    fn init(&mut self, init: (Point, Angle), _: &Frame) {
+    fn init(&mut self, init: (Point, Angle), frame: &Frame) {
         self.init_pos = init.0;
         self.last_pos = init.0;
         self.angle = init.1;
+        self.pos = frame.delta.mean_pos;
+        self.length = 0.0;
     }
 
     fn update(&mut self, frame: &Frame) -> RecResult<Self::Out> {
@@ -235,37 +1030,179 @@ impl Recognizer for StraightSwipe {
 			debug!("StraightSwipe failed because of a touch down");
             RecResult::Failed
         } else if frame.touch_up {
-            let diff = frame.cur.mean_pos() - self.init_pos;
+            self.pos = follow(self.leading, self.pos, frame);
+            let diff = self.pos - self.init_pos;
             if diff.length() > self.min_length {
 				debug!("StraightSwipe succeeded after a lifted finger");
-                RecResult::Succeeded(self.outcome(StraightSwipeReason::LiftedFinger, frame))
+                RecResult::Succeeded(self.outcome(StraightSwipeReason::LiftedFinger))
             } else {
 				debug!("StraightSwipe failed because of a premature lift");
                 RecResult::Failed
             }
         } else {
-            let diff = frame.cur.mean_pos() - self.last_pos;
+            self.pos = follow(self.leading, self.pos, frame);
+            self.length = (self.pos - self.init_pos).length();
+            let diff = self.pos - self.last_pos;
             if diff.length() >= self.step {
-                self.last_pos = frame.cur.mean_pos();
+                self.last_pos = self.pos;
                 let angle = Angle::from_radians((-diff.y).atan2(diff.x));
                 debug!("angle {:?}, self.angle {:?}", angle, self.angle);
                 debug!("diff {:?}", (angle - self.angle).abs().to_radians());
                 if (angle - self.angle).abs().to_radians() > self.angle_tolerance {
-                    if (frame.cur.mean_pos() - self.init_pos).length() > self.min_length {
+                    if self.length > self.min_length {
                         debug!("StraightSwipe succeeded after an angle change");
-                        return RecResult::Succeeded(self.outcome(StraightSwipeReason::ChangedAngle, frame));
+                        return RecResult::Succeeded(self.outcome(StraightSwipeReason::ChangedAngle));
                     } else {
                         debug!("StraightSwipe failed because of a premature angle change");
                         return RecResult::Failed;
                     }
                 }
 
-                let lambda = (diff.length() * self.adaptivity).min(1.0);
+                let lambda = adaptation(self.adaptivity, diff.length());
                 self.angle = self.angle.interpolate(angle, lambda);
+                if self.early && self.length > self.min_length {
+                    debug!("StraightSwipe succeeded early");
+                    return RecResult::Succeeded(self.outcome(StraightSwipeReason::ReachedLength));
+                }
             }
             RecResult::Continuing
         }
     }
+
+    fn progress(&self) -> f64 {
+        (self.length / self.min_length).min(1.0)
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        g.node(&format!("StraightSwipe (min length {} mm, step {} mm, adaptivity {}, tolerance {} rad)",
+                        self.min_length, self.step, self.adaptivity, self.angle_tolerance))
+    }
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use event::Event;
+    use geom::Point;
+    use manager::Manager;
+    use params::{LiftParams, SwipeParams};
+    use super::{FingersUp, InitialAngle, NFingers, Rotation, StraightSwipe, Transform};
+    use Recognizer;
+
+    #[test]
+    fn transform_steps_are_unfinished() {
+        let mut man = Manager::new();
+        man.push(NFingers::new(2).and_then(Transform::new()));
+        let pos = |slot, x| Point::new(x + slot as f64 * 20.0, 0.0);
+        man.update_event(&Event::Down { slot: 0, pos: pos(0, 0.0) });
+        man.update_event(&Event::Down { slot: 1, pos: pos(1, 0.0) });
+        assert!(man.update_event(&Event::Frame { time: 0 }).is_none());
+
+        man.update_event(&Event::Motion { slot: 0, pos: pos(0, 10.0) });
+        man.update_event(&Event::Motion { slot: 1, pos: pos(1, 10.0) });
+        let step = man.update_event_recognized(&Event::Frame { time: 10 }).unwrap();
+        assert!(!step.finished);
+
+        man.update_event(&Event::Motion { slot: 0, pos: pos(0, 20.0) });
+        man.update_event(&Event::Motion { slot: 1, pos: pos(1, 20.0) });
+        // A step isn't an outcome.
+        assert!(man.update_event(&Event::Frame { time: 20 }).is_none());
+
+        man.update_event(&Event::Up { slot: 0 });
+        man.update_event(&Event::Up { slot: 1 });
+        let total = man.update_event_recognized(&Event::Frame { time: 30 }).unwrap();
+        assert!(total.finished);
+    }
+
+    #[test]
+    fn rotation_adds_up_the_steps() {
+        let mut man = Manager::new();
+        man.push(NFingers::new(2).and_then(Rotation::new()));
+        // Two fingers on opposite sides of a circle, turned `deg` degrees clockwise (on the
+        // surface, y points down).
+        let pos = |slot, deg: f64| {
+            let a = (deg + slot as f64 * 180.0).to_radians();
+            Point::new(50.0 + 20.0 * a.cos(), 50.0 + 20.0 * a.sin())
+        };
+        man.update_event(&Event::Down { slot: 0, pos: pos(0, 0.0) });
+        man.update_event(&Event::Down { slot: 1, pos: pos(1, 0.0) });
+        assert!(man.update_event(&Event::Frame { time: 0 }).is_none());
+
+        // No single step is anywhere near enough, but they add up.
+        let mut outcome = None;
+        for i in 1..8 {
+            man.update_event(&Event::Motion { slot: 0, pos: pos(0, 5.0 * i as f64) });
+            man.update_event(&Event::Motion { slot: 1, pos: pos(1, 5.0 * i as f64) });
+            outcome = man.update_event(&Event::Frame { time: 10 * i });
+            if outcome.is_some() {
+                break;
+            }
+        }
+        // Clockwise turns are negative.
+        let total = outcome.unwrap();
+        assert!(total <= -30.0 && total > -36.0);
+    }
+
+    // Puts two fingers down, and then lifts them with a knuckle brushing the surface in between.
+    // Returns the frames in which something was recognized.
+    fn lift_with_knuckle(params: LiftParams) -> Vec<u32> {
+        let mut man = Manager::new();
+        man.push(NFingers::new(2).then_with(FingersUp::with_params(&params)).map_outcome(|_| 1));
+        let down = |slot| Event::Down { slot: slot, pos: Point::new(slot as f64 * 20.0, 0.0) };
+        let events = vec![
+            (vec![down(0), down(1)], 0),
+            (vec![Event::Up { slot: 0 }], 10),
+            (vec![down(2)], 20),
+            (vec![Event::Up { slot: 1 }], 30),
+            (vec![Event::Up { slot: 2 }], 40),
+        ];
+        let mut ret = Vec::new();
+        for (evs, time) in events {
+            for ev in evs {
+                man.update_event(&ev);
+            }
+            if man.update_event(&Event::Frame { time: time }).is_some() {
+                ret.push(time);
+            }
+        }
+        ret
+    }
+
+    #[test]
+    fn fingers_up_tolerance() {
+        assert_eq!(lift_with_knuckle(LiftParams::default()), Vec::<u32>::new());
+        let brief = LiftParams { extra_contact_ms: 50, window_ms: 0 };
+        assert_eq!(lift_with_knuckle(brief), vec![40]);
+        assert_eq!(lift_with_knuckle(LiftParams { window_ms: 30, ..brief }), vec![30]);
+        // The knuckle stays down too long.
+        assert_eq!(lift_with_knuckle(LiftParams { extra_contact_ms: 5, ..brief }),
+                   Vec::<u32>::new());
+        // The second finger lifts too late.
+        assert_eq!(lift_with_knuckle(LiftParams { window_ms: 15, ..brief }), Vec::<u32>::new());
+    }
+
+    // Swipes two fingers to the right, except that one of them sticks. Returns the frame in which
+    // the swipe was recognized.
+    fn swipe_with_sticky_finger(leading: usize) -> Option<u32> {
+        let params = SwipeParams { leading_fingers: leading, ..SwipeParams::default() };
+        let mut man = Manager::new();
+        man.push(NFingers::new(2)
+                 .and_then(InitialAngle::with_params(&params))
+                 .and_then(StraightSwipe::with_params(&params).commit_early())
+                 .map_outcome(|_| 1));
+        man.update_event(&Event::Down { slot: 0, pos: Point::new(0.0, 0.0) });
+        man.update_event(&Event::Down { slot: 1, pos: Point::new(20.0, 0.0) });
+        man.update_event(&Event::Frame { time: 0 });
+        (1..9).find(|&i| {
+            man.update_event(&Event::Motion { slot: 0, pos: Point::new(2.0 * i as f64, 0.0) });
+            man.update_event(&Event::Frame { time: i }).is_some()
+        })
+    }
+
+    #[test]
+    fn leading_fingers() {
+        assert_eq!(swipe_with_sticky_finger(0), None);
+        assert_eq!(swipe_with_sticky_finger(1), Some(6));
+    }
+}
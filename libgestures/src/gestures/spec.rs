@@ -0,0 +1,520 @@
+//! Gestures described by a few settings, instead of by a pipeline of recognizers.
+//!
+//! The recognizers in [`compound`](../compound/index.html) are built by chaining together the
+//! pieces in [`primitive`](../primitive/index.html) and [`filters`](../../filters/index.html),
+//! which takes some knowledge of how those pieces fit together. A [`Spec`](struct.Spec.html) says
+//! what the gesture looks like (how many fingers, how they move, how far, and how the gesture
+//! ends) and builds the pipeline for it. Specs can be written in code, parsed from a string (for
+//! example, one that came from a config file), or written with the
+//! [`gesture!`](../../macro.gesture.html) macro.
+//!
+//! # Examples
+//! ```
+//! #[macro_use]
+//! extern crate libgestures;
+//!
+//! use libgestures::geom::Direction;
+//! use libgestures::gestures::spec::{End, Motion, Spec};
+//!
+//! # fn main() {
+//! let spec = gesture!{ fingers: 3, move: straight, direction: up, min_len: 10mm, end: lift };
+//! assert_eq!(spec, Spec {
+//!     direction: Some(Direction::Up),
+//!     min_len_mm: Some(10.0),
+//!     ..Spec::new(3, Motion::Straight)
+//! });
+//! assert_eq!(spec.end, End::Lift);
+//!
+//! let rec = spec.build(&Default::default());
+//! # }
+//! ```
+
+use classify::Kinds;
+use filters::{MaintainSpread, MaxDuration, NoRelativeMovement};
+use geom::{Angle, Direction, DirectionThresholds, Point};
+use gestures::Path;
+use gestures::compound::{fingers_down_tuned, path_after_down_tuned};
+use gestures::primitive::*;
+use params::Params;
+use std::fmt;
+use std::str::FromStr;
+use { Recognizer, RecResult };
+
+/// Builds a [`Spec`](gestures/spec/struct.Spec.html) out of `key: value` pairs.
+///
+/// This takes the same pairs as [`Spec::parse`](gestures/spec/struct.Spec.html#method.parse),
+/// without the quotes, and panics if they don't describe a gesture.
+///
+/// # Examples
+/// ```
+/// #[macro_use]
+/// extern crate libgestures;
+///
+/// # fn main() {
+/// let spread = gesture!{ fingers: 4, move: spread, max_duration: 800ms };
+/// assert_eq!(spread.max_duration_ms, Some(800));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! gesture {
+    ($($t:tt)*) => {
+        $crate::gestures::spec::Spec::parse(stringify!($($t)*))
+            .unwrap_or_else(|e| panic!("invalid gesture: {}", e))
+    }
+}
+
+/// How the fingers move in a gesture.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Motion {
+    /// All the fingers move together along a straight line.
+    Straight,
+    /// The fingers move apart.
+    Spread,
+    /// The fingers move together.
+    Grab,
+    /// The fingers move quickly back and forth.
+    Shake,
+    /// All the fingers move together along a [`Path`](../struct.Path.html), like
+    /// `path down right`.
+    Path(Path),
+}
+
+impl Motion {
+    /// The name of this kind of motion, as it is written in a spec.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Motion::Straight => "straight",
+            Motion::Spread => "spread",
+            Motion::Grab => "grab",
+            Motion::Shake => "shake",
+            Motion::Path(_) => "path",
+        }
+    }
+}
+
+impl fmt::Display for Motion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Motion::Path(ref path) => write!(f, "path {}", path),
+            _ => f.write_str(self.name()),
+        }
+    }
+}
+
+impl FromStr for Motion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Motion, String> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        match words.first().cloned() {
+            Some("straight") if words.len() == 1 => Ok(Motion::Straight),
+            Some("spread") if words.len() == 1 => Ok(Motion::Spread),
+            Some("grab") if words.len() == 1 => Ok(Motion::Grab),
+            Some("shake") if words.len() == 1 => Ok(Motion::Shake),
+            Some("path") => {
+                let directions = words[1..].iter()
+                    .map(|w| w.parse::<Direction>())
+                    .collect::<Result<Vec<_>, _>>()?;
+                Path::new(&directions)
+                    .map(Motion::Path)
+                    .ok_or(format!("{:?} is not a valid path", s))
+            },
+            _ => Err(format!("unknown motion {:?}", s)),
+        }
+    }
+}
+
+/// When a gesture is recognized.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum End {
+    /// Once the fingers have moved far enough and then been lifted.
+    Lift,
+    /// As soon as the fingers have moved far enough, while they are still down.
+    Move,
+}
+
+impl End {
+    /// The name of this ending, as it is written in a spec.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            End::Lift => "lift",
+            End::Move => "move",
+        }
+    }
+}
+
+impl FromStr for End {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<End, String> {
+        match s {
+            "lift" => Ok(End::Lift),
+            "move" => Ok(End::Move),
+            _ => Err(format!("unknown end {:?}", s)),
+        }
+    }
+}
+
+/// A description of a gesture, which can be turned into a recognizer.
+///
+/// The settings that are `None` use the thresholds in the
+/// [`Params`](../../params/struct.Params.html) passed to [`build`](#method.build).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spec {
+    /// The number of fingers.
+    pub fingers: u8,
+    /// How the fingers move.
+    pub motion: Motion,
+    /// Which way a straight motion must go. If this is `None`, it may go any way.
+    pub direction: Option<Direction>,
+    /// How far (in millimeters) the fingers must move. For spreads and grabs, this is how far
+    /// each finger must move; for shakes, it is the length of each stroke.
+    pub min_len_mm: Option<f64>,
+    /// How long (in milliseconds) the fingers may take to move, counting from when they are all
+    /// down and still. This is stretched by the `duration_scale` of the
+    /// [`FilterParams`](../../params/struct.FilterParams.html).
+    pub max_duration_ms: Option<u32>,
+    /// When the gesture is recognized.
+    pub end: End,
+    /// Which kinds of contact count as fingers (see [`classify`](../../classify/index.html)). If
+    /// this is `None`, every contact that the `Manager` doesn't leave out counts.
+    pub contacts: Option<Kinds>,
+}
+
+impl Spec {
+    /// Creates a spec for `fingers` fingers making `motion`, and then lifting.
+    pub fn new(fingers: u8, motion: Motion) -> Spec {
+        Spec {
+            fingers: fingers,
+            motion: motion,
+            direction: None,
+            min_len_mm: None,
+            max_duration_ms: None,
+            end: End::Lift,
+            contacts: None,
+        }
+    }
+
+    /// Reads a spec from comma-separated `key: value` pairs.
+    ///
+    /// The keys are `fingers`, `move` (`straight`, `spread`, `grab`, `shake`, or `path` followed
+    /// by some directions), `direction`,
+    /// `min_len` (like `10mm`), `max_duration` (like `500ms`), `end` (`lift` or `move`) and
+    /// `contacts` (some kinds of contact, like `finger unknown`). The first two are required.
+    /// Keys may be written with dashes instead of underscores, as they are in config files.
+    ///
+    /// # Examples
+    /// ```
+    /// use libgestures::gestures::spec::{Motion, Spec};
+    ///
+    /// let spec = Spec::parse("fingers: 2, move: grab, min-len: 12 mm").unwrap();
+    /// assert_eq!(spec.motion, Motion::Grab);
+    /// assert_eq!(spec.min_len_mm, Some(12.0));
+    ///
+    /// assert!(Spec::parse("fingers: 2").is_err());
+    /// assert!(Spec::parse("fingers: 2, move: grab, direction: up").is_err());
+    /// ```
+    pub fn parse(s: &str) -> Result<Spec, String> {
+        let mut fingers = None;
+        let mut motion = None;
+        let mut direction = None;
+        let mut min_len_mm = None;
+        let mut max_duration_ms = None;
+        let mut end = None;
+        let mut contacts = None;
+
+        for pair in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let mut parts = pair.splitn(2, ':');
+            let key = parts.next().unwrap_or("").trim().replace('-', "_");
+            let value = match parts.next() {
+                Some(v) => v.trim(),
+                None => return Err(format!("expected `key: value`, found {:?}", pair)),
+            };
+            match key.as_ref() {
+                "fingers" => set(&mut fingers, parse_fingers(value)?, &key)?,
+                "move" => set(&mut motion, value.parse::<Motion>()?, &key)?,
+                "direction" => set(&mut direction, value.parse::<Direction>()?, &key)?,
+                "min_len" => set(&mut min_len_mm, parse_unit(value, "mm")?, &key)?,
+                "max_duration" => {
+                    set(&mut max_duration_ms, parse_unit(value, "ms")? as u32, &key)?
+                },
+                "end" => set(&mut end, value.parse::<End>()?, &key)?,
+                "contacts" => set(&mut contacts, value.parse::<Kinds>()?, &key)?,
+                _ => return Err(format!("unknown key {:?}", key)),
+            }
+        }
+
+        let spec = Spec {
+            fingers: fingers.ok_or("missing `fingers`")?,
+            motion: motion.ok_or("missing `move`")?,
+            direction: direction,
+            min_len_mm: min_len_mm,
+            max_duration_ms: max_duration_ms,
+            end: end.unwrap_or(End::Lift),
+            contacts: contacts,
+        };
+        spec.check()?;
+        Ok(spec)
+    }
+
+    // Checks the settings that don't make sense together.
+    fn check(&self) -> Result<(), String> {
+        if self.contacts == Some(Kinds::none()) {
+            return Err("`contacts` needs at least one kind of contact".to_owned());
+        }
+        if self.direction.is_some() && self.motion != Motion::Straight {
+            return Err(format!("a {} motion has no direction", self.motion.name()));
+        }
+        if self.motion == Motion::Shake && self.end == End::Lift {
+            // A shake is over once the fingers have turned around often enough, and they tend to
+            // keep shaking for a while after that.
+            return Err("a shake ends with `end: move`".to_owned());
+        }
+        if let Motion::Path(_) = self.motion {
+            // Every segment but the last one ends by turning, so only the last one could end
+            // early, and its length is already decided by the swipe thresholds.
+            if self.end == End::Move || self.min_len_mm.is_some() {
+                return Err("a path ends with `end: lift`, and has no `min_len`".to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds a recognizer for this gesture, using the thresholds in `params` for anything that
+    /// this spec doesn't set.
+    pub fn build(&self, params: &Params) -> Box<Recognizer<In=(), Out=()>> {
+        let rec = fingers_down_tuned(self.fingers, params).and_then(self.build_after_down(params));
+        match self.contacts {
+            Some(kinds) => Box::new(rec.counting(kinds)),
+            None => Box::new(rec),
+        }
+    }
+
+    /// Builds a recognizer for the part of this gesture that happens after the fingers are
+    /// down and still.
+    ///
+    /// Like the `_after_down` functions in [`compound`](../compound/index.html), this is for
+    /// sharing the beginning of the gesture with others that have the same number of fingers.
+    pub fn build_after_down(&self, params: &Params) -> Box<Recognizer<In=(), Out=()>> {
+        let mut rec = self.motion_recognizer(params);
+        if let Some(ms) = self.max_duration_ms {
+            rec = Box::new(rec.constrain(MaxDuration::with_params(ms, &params.filter)));
+        }
+        let rec: Box<Recognizer<In=(), Out=()>> = match (self.end, self.motion) {
+            // A path already waits for the fingers to lift.
+            (End::Lift, Motion::Path(_)) => rec,
+            (End::Lift, _) => {
+                let up = FingersUp::with_params(&params.lift);
+                Box::new(rec.then_with(up).map_outcome(|_| ()))
+            },
+            (End::Move, _) => rec,
+        };
+        match self.contacts {
+            Some(kinds) => Box::new(rec.counting(kinds)),
+            None => rec,
+        }
+    }
+
+    // Recognizes the fingers moving, but not lifting.
+    fn motion_recognizer(&self, params: &Params) -> Box<Recognizer<In=(), Out=()>> {
+        let shape = NoRelativeMovement::with_params(&params.filter);
+        match self.motion {
+            Motion::Straight => {
+                let min_len = self.min_len_mm.unwrap_or(params.swipe.min_length_mm);
+                let direction = self.direction;
+                let start = match self.end {
+                    End::Lift => InitialAngle::with_params(&params.swipe),
+                    End::Move => {
+                        InitialAngle::with_threshold_mm(min_len)
+                            .leading_fingers(params.swipe.leading_fingers)
+                    },
+                };
+                let start = start.flat_map_outcome(move |(pt, a)| {
+                    check_direction(pt, a, direction)
+                });
+                match self.end {
+                    End::Lift => {
+                        // A swipe with a fixed direction has to stay close to it, but one that may
+                        // go any way is allowed to curve gently.
+                        let adaptivity = if direction.is_some() {
+                            0.0
+                        } else {
+                            params.swipe.adaptivity
+                        };
+                        let swipe = StraightSwipe::with_params(&params.swipe)
+                            .min_length(min_len)
+                            .adaptivity(adaptivity);
+                        Box::new(start.and_then(swipe)
+                            .constrain(shape)
+                            .constrain(MaintainSpread::if_enabled(&params.filter))
+                            .filter_outcome(|x| x.reason == StraightSwipeReason::LiftedFinger)
+                            .map_outcome(|_| ()))
+                    },
+                    End::Move => Box::new(start.constrain(shape).map_outcome(|_| ())),
+                }
+            },
+            Motion::Spread => {
+                let min_len = self.min_len_mm.unwrap_or(params.pinch.spread_mm);
+                Box::new(Radial::new(RadialDirection::Outward, min_len).map_outcome(|_| ()))
+            },
+            Motion::Grab => {
+                let min_len = self.min_len_mm.unwrap_or(params.pinch.grab_mm);
+                Box::new(Radial::new(RadialDirection::Inward, min_len).map_outcome(|_| ()))
+            },
+            Motion::Shake => {
+                let stroke = self.min_len_mm.unwrap_or(params.shake.stroke_mm);
+                let shape = shape.threshold(params.filter.loose_shape_mm);
+                Box::new(Shake::with_params(&params.shake)
+                    .stroke_mm(stroke)
+                    .constrain(shape)
+                    .map_outcome(|_| ()))
+            },
+            Motion::Path(ref path) => Box::new(path_after_down_tuned(path.directions(), params)),
+        }
+    }
+}
+
+// If the swipe must go in `direction`, checks that it started that way and snaps its angle to it.
+fn check_direction(pt: Point, a: Angle, direction: Option<Direction>)
+-> RecResult<(Point, Angle)> {
+    match direction {
+        None => RecResult::Succeeded((pt, a)),
+        Some(d) => {
+            if Direction::from_angle_thresholds(a, &DirectionThresholds::default()) == Some(d) {
+                RecResult::Succeeded((pt, d.to_angle()))
+            } else {
+                debug!("spec failed: moved at {:?} instead of {:?}", a, d);
+                RecResult::Failed
+            }
+        },
+    }
+}
+
+// Fills in a setting, unless it was already given.
+fn set<T>(slot: &mut Option<T>, value: T, key: &str) -> Result<(), String> {
+    if slot.is_some() {
+        return Err(format!("{:?} is given twice", key));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+fn parse_fingers(s: &str) -> Result<u8, String> {
+    match s.parse::<u8>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("expected a number of fingers, found {:?}", s)),
+    }
+}
+
+// Parses a non-negative number, optionally followed by `unit`.
+fn parse_unit(s: &str, unit: &str) -> Result<f64, String> {
+    let num = s.trim_right_matches(unit).trim();
+    match num.parse::<f64>() {
+        Ok(x) if x >= 0.0 => Ok(x),
+        _ => Err(format!("expected a number of {}, found {:?}", unit, s)),
+    }
+}
+
+impl FromStr for Spec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Spec, String> {
+        Spec::parse(s)
+    }
+}
+
+/// Writes a spec in the form that [`parse`](struct.Spec.html#method.parse) reads.
+impl fmt::Display for Spec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "fingers: {}, move: {}", self.fingers, self.motion)?;
+        if let Some(d) = self.direction {
+            write!(f, ", direction: {}", d)?;
+        }
+        if let Some(mm) = self.min_len_mm {
+            write!(f, ", min_len: {}mm", mm)?;
+        }
+        if let Some(ms) = self.max_duration_ms {
+            write!(f, ", max_duration: {}ms", ms)?;
+        }
+        write!(f, ", end: {}", self.end.name())?;
+        if let Some(kinds) = self.contacts {
+            write!(f, ", contacts: {}", kinds)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use classify::ContactKind;
+    use geom::Direction;
+    use manager::Manager;
+    use params::Params;
+    use testing::Synth;
+    use super::*;
+
+    fn recognizes(spec: &Spec, events: &[::event::Event]) -> bool {
+        let mut man = Manager::<()>::new();
+        man.push(spec.build(&Params::default()));
+        events.iter().filter_map(|ev| man.update_event(ev)).count() == 1
+    }
+
+    #[test]
+    fn macro_matches_parse() {
+        let spec = gesture!{ fingers: 3, move: straight, direction: up, min_len: 10mm, end: lift };
+        assert_eq!(Ok(spec), "fingers: 3, move: straight, direction: up, min-len: 10 mm".parse());
+        assert_eq!(Ok(spec), spec.to_string().parse());
+    }
+
+    #[test]
+    fn bad_specs() {
+        assert!(Spec::parse("fingers: 0, move: straight").is_err());
+        assert!(Spec::parse("fingers: 3, move: wiggle").is_err());
+        assert!(Spec::parse("fingers: 3, fingers: 4, move: straight").is_err());
+        assert!(Spec::parse("fingers: 3, move: straight, min_len: -1mm").is_err());
+        assert!(Spec::parse("fingers: 3, move: shake").is_err());
+        assert!(Spec::parse("fingers: 3 move: straight").is_err());
+        assert!(Spec::parse("fingers: 1, move: path down down").is_err());
+        assert!(Spec::parse("fingers: 1, move: path down right, end: move").is_err());
+        assert!(Spec::parse("fingers: 2, move: spread, contacts: elbow").is_err());
+        assert!(Spec::parse("fingers: 2, move: spread, contacts: ").is_err());
+    }
+
+    #[test]
+    fn straight_swipe() {
+        let up = gesture!{ fingers: 3, move: straight, direction: up, min_len: 20mm };
+        let swipe = |d: Direction, len| Synth::new(3).swipe(d.to_angle(), len);
+        assert!(recognizes(&up, &swipe(Direction::Up, 30.0)));
+        assert!(!recognizes(&up, &swipe(Direction::Up, 15.0)));
+        assert!(!recognizes(&up, &swipe(Direction::Left, 30.0)));
+
+        let any = gesture!{ fingers: 3, move: straight };
+        assert!(recognizes(&any, &swipe(Direction::Left, 30.0)));
+    }
+
+    #[test]
+    fn paths() {
+        let l = gesture!{ fingers: 1, move: path down right };
+        assert_eq!(Ok(l), l.to_string().parse());
+    }
+
+    #[test]
+    fn contacts() {
+        let spec = gesture!{ fingers: 2, move: spread, contacts: finger unknown };
+        assert_eq!(spec.contacts, Some(Kinds::of(&[ContactKind::Finger, ContactKind::Unknown])));
+        assert_eq!(Ok(spec), spec.to_string().parse());
+
+        // Nothing labels the contacts, so they are all unknown.
+        let spread = Synth::new(2).spacing(40.0).pinch(2.2, 4.0);
+        assert!(recognizes(&spec, &spread));
+        assert!(!recognizes(&gesture!{ fingers: 2, move: spread, contacts: finger }, &spread));
+    }
+
+    #[test]
+    fn max_duration() {
+        let quick = gesture!{ fingers: 2, move: spread, max_duration: 300ms, end: move };
+        let spread = |rate| Synth::new(2).spacing(40.0).pinch(2.2, rate);
+        assert!(recognizes(&quick, &spread(8.0)));
+        assert!(!recognizes(&quick, &spread(2.0)));
+    }
+}
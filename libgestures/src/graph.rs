@@ -0,0 +1,60 @@
+//! Describing the structure of recognizers as graphs.
+//!
+//! Recognizers built out of lots of combinators can be hard to understand by looking at the code
+//! that built them. Every [`Recognizer`](../trait.Recognizer.html) and
+//! [`Filter`](../trait.Filter.html) knows how to add itself to a [`Graph`](struct.Graph.html),
+//! which can then be written out in Graphviz's DOT format.
+
+use std::fmt::Write;
+
+/// A directed graph with labelled nodes and edges.
+#[derive(Clone, Debug, Default)]
+pub struct Graph {
+    nodes: Vec<String>,
+    edges: Vec<(usize, usize, String)>,
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl Graph {
+    pub fn new() -> Graph {
+        Graph::default()
+    }
+
+    /// Adds a node with the given label, and returns its index.
+    pub fn node(&mut self, label: &str) -> usize {
+        self.nodes.push(label.to_owned());
+        self.nodes.len() - 1
+    }
+
+    /// Adds an edge between two nodes.
+    pub fn edge(&mut self, from: usize, to: usize, label: &str) {
+        self.edges.push((from, to, label.to_owned()));
+    }
+
+    /// Writes this graph in Graphviz's DOT format.
+    ///
+    /// # Examples
+    /// ```
+    /// use libgestures::graph::Graph;
+    ///
+    /// let mut g = Graph::new();
+    /// let a = g.node("a");
+    /// let b = g.node("b");
+    /// g.edge(a, b, "then");
+    /// assert_eq!(g.to_dot(), "digraph {\n  n0 [label=\"a\"];\n  n1 [label=\"b\"];\n  n0 -> n1 [label=\"then\"];\n}\n");
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut ret = String::from("digraph {\n");
+        for (i, label) in self.nodes.iter().enumerate() {
+            let _ = writeln!(ret, "  n{} [label=\"{}\"];", i, escape(label));
+        }
+        for &(from, to, ref label) in &self.edges {
+            let _ = writeln!(ret, "  n{} -> n{} [label=\"{}\"];", from, to, escape(label));
+        }
+        ret.push_str("}\n");
+        ret
+    }
+}
@@ -0,0 +1,179 @@
+//! Kinetic scrolling: keeping things moving after the fingers have gone up.
+//!
+//! When a progressive gesture (like a [`Drag`](../gestures/primitive/struct.Drag.html)) ends with
+//! the fingers still moving quickly, it often feels natural for whatever it controls to keep
+//! going for a little while, and gradually slow down. The [`Fling`](struct.Fling.html) adapter
+//! measures how fast a progressive recognizer was moving when it finished, and hands back a
+//! [`Kinetic`](struct.Kinetic.html) model that says where things should be at each moment after
+//! that.
+
+use frame::Frame;
+use graph::Graph;
+use {Recognizer, RecResult};
+
+// How long (in milliseconds) it takes for a velocity measurement to count for half as much as it
+// did. This is about one frame on a 60 Hz touchpad.
+const VELOCITY_HALF_LIFE_MS: f64 = 16.0;
+
+/// A simple model of something that keeps moving after being let go, and slows down because of
+/// friction.
+///
+/// The velocity decays exponentially: after `t` milliseconds, the velocity is
+/// `v * exp(-friction * t)`.
+///
+/// # Examples
+/// ```
+/// use libgestures::kinetic::Kinetic;
+///
+/// // Let go at 100 mm, moving at 1 mm per millisecond.
+/// let k = Kinetic::new(100.0, 1.0, 0.01);
+/// assert_eq!(k.position_at(0.0), 100.0);
+/// assert!(k.position_at(100.0) > 150.0);
+///
+/// // It never goes further than 100 mm past where it was let go.
+/// assert!(k.updates(16.0).all(|x| x > 100.0 && x <= 200.0));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Kinetic {
+    start: f64,
+    velocity: f64,
+    friction: f64,
+    min_velocity: f64,
+}
+
+impl Kinetic {
+    /// Creates a new model of something that was let go at position `start`, moving at `velocity`
+    /// (in units per millisecond).
+    ///
+    /// # Panics
+    /// if `friction` is not positive.
+    pub fn new(start: f64, velocity: f64, friction: f64) -> Kinetic {
+        assert!(friction > 0.0);
+        Kinetic {
+            start: start,
+            velocity: velocity,
+            friction: friction,
+            min_velocity: 0.01,
+        }
+    }
+
+    /// Sets the speed below which we consider the motion to have stopped.
+    pub fn min_velocity(self, min_velocity: f64) -> Kinetic {
+        Kinetic {
+            min_velocity: min_velocity,
+            ..self
+        }
+    }
+
+    /// Where is it, `t` milliseconds after being let go?
+    pub fn position_at(&self, t: f64) -> f64 {
+        self.start + self.velocity / self.friction * (1.0 - (-self.friction * t).exp())
+    }
+
+    /// How fast is it moving, `t` milliseconds after being let go?
+    pub fn velocity_at(&self, t: f64) -> f64 {
+        self.velocity * (-self.friction * t).exp()
+    }
+
+    /// How many milliseconds until it has slowed down to `min_velocity`?
+    pub fn duration(&self) -> f64 {
+        if self.velocity.abs() <= self.min_velocity {
+            0.0
+        } else {
+            (self.velocity.abs() / self.min_velocity).ln() / self.friction
+        }
+    }
+
+    /// Returns the positions at regular intervals (every `step_ms` milliseconds), until it has
+    /// slowed down.
+    pub fn updates(&self, step_ms: f64) -> impl Iterator<Item=f64> {
+        assert!(step_ms > 0.0);
+        let k = *self;
+        let steps = (k.duration() / step_ms).ceil() as u64;
+        (1..=steps).map(move |i| k.position_at(i as f64 * step_ms))
+    }
+}
+
+/// A recognizer that measures the speed of a progressive recognizer, so that it can keep going
+/// after it finishes.
+///
+/// This struct is usually created by the [fling](../trait.Recognizer.html#method.fling) method on
+/// [Recognizer](../trait.Recognizer.html). The wrapped recognizer should report its progress as a
+/// distance. While it is in progress, the output is that distance paired with `None`; when it
+/// succeeds, the distance is paired with a `Kinetic` model, starting from the final distance and
+/// the speed it was moving at.
+#[derive(Clone, Debug)]
+pub struct Fling<Rec> {
+    rec: Rec,
+    friction: f64,
+    last: Option<(u32, f64)>,
+    velocity: f64,
+}
+
+impl<Rec: Recognizer<Out=f64>> Fling<Rec> {
+    pub fn new(rec: Rec, friction: f64) -> Fling<Rec> {
+        Fling {
+            rec: rec,
+            friction: friction,
+            last: None,
+            velocity: 0.0,
+        }
+    }
+
+    /// Updates the velocity estimate with a new distance.
+    fn track(&mut self, time: u32, dist: f64) {
+        if let Some((last_time, last_dist)) = self.last {
+            if time > last_time {
+                let dt = (time - last_time) as f64;
+                let v = (dist - last_dist) / dt;
+                // Smooth things out a little, because individual frames can be noisy. The old
+                // estimate fades according to the time that passed, not the number of frames, so
+                // that the smoothing is the same at any frame rate.
+                let keep = 0.5f64.powf(dt / VELOCITY_HALF_LIFE_MS);
+                self.velocity = keep * self.velocity + (1.0 - keep) * v;
+            }
+        }
+        self.last = Some((time, dist));
+    }
+}
+
+impl<Rec: Recognizer<Out=f64>> Recognizer for Fling<Rec> {
+    type In = Rec::In;
+    type Out = (f64, Option<Kinetic>);
+
+    fn init(&mut self, input: Self::In, frame: &Frame) {
+        self.rec.init(input, frame);
+        self.last = None;
+        self.velocity = 0.0;
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<Self::Out> {
+        match self.rec.update(frame) {
+            RecResult::Continuing => RecResult::Continuing,
+            RecResult::Failed => RecResult::Failed,
+            RecResult::Progressing(d) => {
+                self.track(frame.time, d);
+                RecResult::Progressing((d, None))
+            },
+            RecResult::Succeeded(d) => {
+                self.track(frame.time, d);
+                RecResult::Succeeded((d, Some(Kinetic::new(d, self.velocity, self.friction))))
+            },
+        }
+    }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        self.rec.wants_frames_while_idle()
+    }
+
+    fn progress(&self) -> f64 {
+        self.rec.progress()
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        let n = g.node(&format!("Fling (friction {})", self.friction));
+        let r = self.rec.to_graph(g);
+        g.edge(n, r, "");
+        n
+    }
+}
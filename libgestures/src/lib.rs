@@ -1,16 +1,38 @@
 #![feature(conservative_impl_trait, inclusive_range_syntax, range_contains)]
 
 extern crate euclid;
+#[cfg(feature = "input")]
 extern crate input;
 
 #[macro_use]
 extern crate log;
 
+#[cfg(feature = "serialize")]
+extern crate serde;
+#[cfg(feature = "serialize")]
+#[macro_use]
+extern crate serde_derive;
+
+pub mod classify;
+pub mod cluster;
+pub mod edge;
+pub mod event;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod filters;
 pub mod frame;
 pub mod geom;
 pub mod gestures;
+pub mod graph;
+pub mod kinetic;
 pub mod manager;
+pub mod params;
+pub mod progress;
 pub mod recognizer;
+pub mod testing;
+pub mod trace;
 
-pub use recognizer::{Filter, FilterResult, Recognizer, RecResult};
+pub use event::Event;
+pub use frame::{Frame, FrameDelta, Snapshot};
+pub use manager::{Candidate, Manager, ManagerState, Phase, Recognized};
+pub use recognizer::{Either, Filter, FilterResult, Recognizer, RecResult};
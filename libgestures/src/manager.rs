@@ -1,14 +1,322 @@
+use euclid::vec2;
+#[cfg(feature = "input")]
+use input::event::EventTrait;
+#[cfg(feature = "input")]
 use input::event::touch::TouchEvent;
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
 
-use frame::Frame;
+use classify::{Classifier, Kinds};
+use event::Event;
+use frame::{Frame, Overflow, Snapshot};
+use geom::Point;
+use graph::Graph;
 use {Recognizer, RecResult};
 
+// With the `spans` feature, each frame and each recognizer update gets a span, which lasts until
+// the end of the enclosing block. Its start and its end (with how long it took) are logged at the
+// trace level, and the name is only formatted when trace logging is on. Without the feature, these
+// expand to nothing.
+#[cfg(feature = "spans")]
+macro_rules! enter_span {
+    ($($arg:tt)+) => {
+        let _span = Span::enter(if log_enabled!(::log::LogLevel::Trace) {
+            Some(format!($($arg)+))
+        } else {
+            None
+        });
+    }
+}
+
+#[cfg(not(feature = "spans"))]
+macro_rules! enter_span {
+    ($($arg:tt)+) => {}
+}
+
+#[cfg(feature = "spans")]
+macro_rules! trace_transition {
+    ($id:expr, $t:expr) => {
+        trace!("recognizer={} transition={:?}", $id, $t);
+    }
+}
+
+#[cfg(not(feature = "spans"))]
+macro_rules! trace_transition {
+    ($id:expr, $t:expr) => {}
+}
+
+#[cfg(feature = "spans")]
+struct Span {
+    name: Option<String>,
+    started: ::std::time::Instant,
+}
+
+#[cfg(feature = "spans")]
+impl Span {
+    fn enter(name: Option<String>) -> Span {
+        if let Some(ref name) = name {
+            trace!("> {}", name);
+        }
+        Span { name: name, started: ::std::time::Instant::now() }
+    }
+}
+
+#[cfg(feature = "spans")]
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(ref name) = self.name {
+            let t = self.started.elapsed();
+            trace!("< {} us={}", name, t.as_secs() * 1_000_000 + (t.subsec_nanos() / 1000) as u64);
+        }
+    }
+}
+
+/// Something that happened to one of the recognizers in a `Manager`.
+///
+/// These are reported to the observer that was passed to
+/// [`Manager::set_observer`](struct.Manager.html#method.set_observer).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Transition {
+    /// The recognizer was initialized, because fingers started coming down.
+    Started,
+    /// The recognizer failed.
+    Failed,
+    /// The recognizer succeeded.
+    Succeeded,
+    /// The recognizer was stopped because a higher-priority recognizer succeeded.
+    Cancelled,
+    /// The recognizer panicked, so it was disabled for good.
+    Panicked,
+}
+
+/// When a `Manager` starts recognizing again, after it has recognized a gesture.
+///
+/// See [`Manager::set_rearm_policy`](struct.Manager.html#method.set_rearm_policy).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Rearm {
+    /// As soon as a finger comes down after the gesture. This is the default.
+    Immediately,
+    /// Once every finger has been up for `ms` milliseconds. A touch that starts sooner is ignored,
+    /// and the wait starts over when it ends.
+    AfterLift { ms: u32 },
+    /// When a finger comes down at least `ms` milliseconds after the gesture was recognized.
+    AfterDelay { ms: u32 },
+}
+
+/// Where a `Manager` is in the current touch.
+///
+/// See [`Manager::phase`](struct.Manager.html#method.phase).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Phase {
+    /// No fingers are down.
+    Idle,
+    /// Fingers are down, and some of the recognizers are still watching them.
+    Recognizing,
+    /// Fingers are down, but none of the recognizers are running: either one of them succeeded,
+    /// or they all failed. Nothing more is recognized until every finger goes up.
+    Waiting,
+}
+
+/// A gesture that was recognized by a `Manager`, together with some context about it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recognized<T> {
+    /// The outcome of the recognizer that succeeded, or the progress of one that hasn't finished.
+    pub value: T,
+    /// False if `value` is a progress update from a progressive recognizer, and the gesture
+    /// isn't over yet.
+    pub finished: bool,
+    /// How far along the gesture is (see
+    /// [`Recognizer::progress`](../trait.Recognizer.html#method.progress)), for a recognizer that
+    /// reported progress while it happened. For the other recognizers, this is always 1.
+    pub progress: f64,
+    /// The system name of the device that the gesture came from, if the `Manager` was given
+    /// libinput events.
+    pub device: Option<String>,
+    /// The most fingers that were down at once during the gesture.
+    pub fingers: u8,
+    /// The time (in milliseconds) of the frame in which the first finger came down.
+    pub start_time: u32,
+    /// The average position of the fingers in the frame in which the first finger came down.
+    pub start_pos: Point,
+    /// The time (in milliseconds) of the frame in which the gesture was recognized.
+    pub end_time: u32,
+    /// The recognizers with higher priorities that were still running, but were cancelled
+    /// because this gesture claimed the touch (see
+    /// [`push_claiming`](struct.Manager.html#method.push_claiming)). If the same ones keep
+    /// showing up here, this gesture might be getting in the way of theirs.
+    pub shadowed: Vec<usize>,
+}
+
+impl<T> Recognized<T> {
+    /// Changes the value of the recognized gesture, keeping its context.
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Recognized<U> {
+        Recognized {
+            value: f(self.value),
+            finished: self.finished,
+            progress: self.progress,
+            device: self.device,
+            fingers: self.fingers,
+            start_time: self.start_time,
+            start_pos: self.start_pos,
+            end_time: self.end_time,
+            shadowed: self.shadowed,
+        }
+    }
+}
+
+/// A recognizer that might still recognize the current touch.
+///
+/// See [`Manager::candidates`](struct.Manager.html#method.candidates).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Candidate {
+    /// The recognizer's id, as returned by [`push`](struct.Manager.html#method.push).
+    pub id: usize,
+    pub priority: i32,
+    /// Roughly how far along the gesture is, between 0 and 1. See
+    /// [`Recognizer::progress`](../trait.Recognizer.html#method.progress).
+    pub progress: f64,
+}
+
+/// The state of a `Manager`, which can be put into another `Manager` with the same recognizers.
+///
+/// See [`Manager::snapshot`](struct.Manager.html#method.snapshot). With the `serialize` feature,
+/// this can be saved (to a file, for example) and read back later.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ManagerState {
+    num_recognizers: usize,
+    // How the `Manager` was when it was last at rest.
+    rest: Rest,
+    device: Option<String>,
+    // Everything that happened since then.
+    events: Vec<Event>,
+}
+
+impl ManagerState {
+    /// Returns the events that have to be replayed to restore this state.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+}
+
+// How a `Manager` was the last time that it was at rest: no fingers were down, no gesture was
+// pending, and none of the recognizers was running. At that point, the recognizers' own state
+// doesn't matter, because they get initialized when the next touch starts.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+struct Rest {
+    // The ids of the active, inactive and disabled recognizers, in order. This is `None` if the
+    // `Manager` hasn't been at rest since it was built, and so its recognizers are still as they
+    // were pushed.
+    order: Option<(Vec<usize>, Vec<usize>, Vec<usize>)>,
+    enabled: bool,
+    last_update: Option<u32>,
+    disarmed_since: Option<u32>,
+}
+
+// The most events that are kept for a snapshot. A touch that goes on for longer than this can't
+// be captured.
+const MAX_HISTORY: usize = 100_000;
+
 #[derive(Debug)]
+struct Entry<T> {
+    rec: Box<Recognizer<In=(), Out=T>>,
+    priority: i32,
+    id: usize,
+    // If set, this recognizer's outcome is returned straight away, and stops all the others.
+    claims: bool,
+    // True if this recognizer has reported progress since it was last started.
+    progressed: bool,
+}
+
+// An outcome that hasn't been returned yet.
+#[derive(Debug)]
+struct Success<T> {
+    value: T,
+    progress: f64,
+    priority: i32,
+    id: usize,
+    claims: bool,
+}
+
+// What came out of a frame.
+enum Outcome<T> {
+    Nothing,
+    // A progressive recognizer reported progress (and how far along it is), and nothing
+    // succeeded.
+    Progress(T, f64),
+    // Everything that succeeded since the last outcome was returned, in the order that it
+    // happened, the index of the one that wins, and the recognizers that it shadowed.
+    Released(Vec<Success<T>>, usize, Vec<usize>),
+}
+
 pub struct Manager<T> {
-    active: Vec<Box<Recognizer<In=(), Out=T>>>,
-    inactive: Vec<Box<Recognizer<In=(), Out=T>>>,
-    buf: Vec<Box<Recognizer<In=(), Out=T>>>,
+    active: Vec<Entry<T>>,
+    inactive: Vec<Entry<T>>,
+    // Recognizers that panicked, and will never run again.
+    disabled: Vec<Entry<T>>,
+    buf: Vec<Entry<T>>,
+    // Gestures that were recognized, but are waiting for higher-priority recognizers to finish.
+    pending: Vec<Success<T>>,
     frame: Frame,
+    next_id: usize,
+    observer: Option<Box<FnMut(&Frame, usize, Transition)>>,
+    coalesce_ms: Option<u32>,
+    // The time of the last frame that the recognizers saw.
+    last_update: Option<u32>,
+    // The device that the last touch event came from.
+    device: Option<String>,
+    // When the first finger of the current touch came down.
+    start_time: u32,
+    // Where the fingers were when the current touch started.
+    start_pos: Point,
+    // The most fingers that have been down at once during the current touch.
+    peak_fingers: u8,
+    // The last snapshot that was passed to `update_snapshot`.
+    last_snapshot: Snapshot,
+    rearm: Rearm,
+    // If a gesture was recognized and we haven't re-armed yet, the time that the wait started.
+    disarmed_since: Option<u32>,
+    // False if the recognizers were paused with `set_enabled`.
+    enabled: bool,
+    classifiers: Vec<Box<Classifier>>,
+    // The last time that we were at rest, and the events since then (unless there were too many
+    // of them, or something happened that they can't account for).
+    rest: Rest,
+    history: Option<Vec<Event>>,
+}
+
+impl<T: fmt::Debug> fmt::Debug for Manager<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Manager")
+            .field("active", &self.active)
+            .field("inactive", &self.inactive)
+            .field("pending", &self.pending)
+            .field("frame", &self.frame)
+            .finish()
+    }
+}
+
+// The size and pressure of the contact in `slot`, or zero for the ones that aren't known.
+fn shape(s: &Snapshot, slot: usize) -> (f64, f64) {
+    (s.size.get(slot).cloned().unwrap_or(0.0), s.pressure.get(slot).cloned().unwrap_or(0.0))
+}
+
+// The value of a gesture, unless it is only a progress update.
+fn finished<T>(r: Recognized<T>) -> Option<T> {
+    if r.finished { Some(r.value) } else { None }
+}
+
+// Describes the value that a panic was started with.
+fn panic_message(payload: &Box<Any + Send>) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_owned()
+    }
 }
 
 impl<T> Manager<T> {
@@ -16,42 +324,949 @@ impl<T> Manager<T> {
         Manager {
             active: vec![],
             inactive: vec![],
+            disabled: vec![],
             buf: vec![],
+            pending: vec![],
             frame: Frame::new(),
+            next_id: 0,
+            observer: None,
+            coalesce_ms: None,
+            last_update: None,
+            device: None,
+            start_time: 0,
+            start_pos: vec2(0.0, 0.0),
+            peak_fingers: 0,
+            last_snapshot: Snapshot::new(),
+            rearm: Rearm::Immediately,
+            disarmed_since: None,
+            enabled: true,
+            classifiers: vec![],
+            rest: Rest {
+                order: None,
+                enabled: true,
+                last_update: None,
+                disarmed_since: None,
+            },
+            history: Some(Vec::new()),
+        }
+    }
+
+    /// Adds a new recognizer, with the default priority of zero.
+    ///
+    /// Returns an id for the recognizer. The ids are assigned in order, starting from zero.
+    pub fn push<R: Recognizer<In=(), Out=T> + 'static>(&mut self, r: R) -> usize {
+        self.push_with_priority(r, 0)
+    }
+
+    /// Adds a new recognizer with the given priority.
+    ///
+    /// Higher numbers mean higher priority. When a recognizer succeeds, its outcome is held back
+    /// until every active recognizer with a higher priority has either succeeded or failed. If one
+    /// of them succeeds, its outcome replaces the held-back one (although
+    /// [`update_all`](#method.update_all) returns both of them). Once an outcome is returned, all
+    /// the active recognizers with lower priorities are stopped until the next time fingers come
+    /// down.
+    ///
+    /// The typical use of this is to give more specific gestures (like a four-finger swipe)
+    /// higher priority than less specific ones (like a three-finger swipe).
+    ///
+    /// Returns an id for the recognizer, like [`push`](#method.push).
+    pub fn push_with_priority<R>(&mut self, r: R, priority: i32) -> usize
+    where R: Recognizer<In=(), Out=T> + 'static {
+        self.push_entry(Box::new(r), priority, false)
+    }
+
+    /// Adds a new recognizer that claims the whole touch when it succeeds.
+    ///
+    /// Its outcome is returned as soon as it succeeds, without waiting for the recognizers with
+    /// higher priorities, and every other active recognizer is stopped until the next time
+    /// fingers come down. This is for gestures that are recognized before the fingers lift (like
+    /// an [`early_swipe`](../gestures/compound/fn.early_swipe.html)), so that the rest of the
+    /// touch can't also be recognized as something else.
+    ///
+    /// Apart from that, `priority` works as in [`push_with_priority`](#method.push_with_priority).
+    pub fn push_claiming<R>(&mut self, r: R, priority: i32) -> usize
+    where R: Recognizer<In=(), Out=T> + 'static {
+        self.push_entry(Box::new(r), priority, true)
+    }
+
+    fn push_entry(&mut self, rec: Box<Recognizer<In=(), Out=T>>, priority: i32, claims: bool)
+    -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.active.push(Entry {
+            rec: rec,
+            priority: priority,
+            id: id,
+            claims: claims,
+            progressed: false,
+        });
+        id
+    }
+
+    /// Sets a function that will be told whenever one of the recognizers starts, fails, or
+    /// succeeds.
+    ///
+    /// The function receives the current frame, the id of the recognizer (as returned by
+    /// [`push`](#method.push)), and what happened to it. This is mainly useful for debugging.
+    pub fn set_observer<F: FnMut(&Frame, usize, Transition) + 'static>(&mut self, f: F) {
+        self.observer = Some(Box::new(f));
+    }
+
+    /// Returns true if there is nothing for this `Manager` to do until a finger comes down.
+    ///
+    /// That is, no fingers are down, no gesture is waiting to be reported, and none of the running
+    /// recognizers [want frames while idle](../trait.Recognizer.html#method.wants_frames_while_idle).
+    pub fn is_idle(&self) -> bool {
+        self.frame.cur.num_down == 0 && self.pending.is_empty()
+            && self.active.iter().all(|e| !e.rec.wants_frames_while_idle())
+    }
+
+    /// Returns true if the recognizer with the given id (as returned by [`push`](#method.push)) is
+    /// running, because the current touch might still turn out to be its gesture.
+    pub fn is_active(&self, id: usize) -> bool {
+        self.active.iter().any(|e| e.id == id)
+    }
+
+    /// Returns the recognizers that are still running, with how far along each one is.
+    ///
+    /// This can be used to get ready for a gesture before it is recognized: for example, once the
+    /// only candidate left is a swipe, a compositor could start moving to the next workspace. The
+    /// candidates are in the order that they were pushed.
+    pub fn candidates(&self) -> Vec<Candidate> {
+        let mut ret = self.active.iter()
+            .map(|e| Candidate {
+                id: e.id,
+                priority: e.priority,
+                progress: e.rec.progress().max(0.0).min(1.0),
+            })
+            .collect::<Vec<_>>();
+        ret.sort_by_key(|c| c.id);
+        ret
+    }
+
+    /// Returns where this `Manager` is in the current touch.
+    pub fn phase(&self) -> Phase {
+        if self.frame.cur.num_down == 0 {
+            Phase::Idle
+        } else if self.active.is_empty() && self.pending.is_empty() {
+            Phase::Waiting
+        } else {
+            Phase::Recognizing
+        }
+    }
+
+    /// Returns the number of recognizers in this `Manager`, including the disabled ones.
+    pub fn num_recognizers(&self) -> usize {
+        self.active.len() + self.inactive.len() + self.disabled.len()
+    }
+
+    /// Returns the ids of the recognizers that were disabled because they panicked.
+    ///
+    /// A recognizer that panics (for example, because of a bug in a closure passed to
+    /// [`map_outcome`](../trait.Recognizer.html#method.map_outcome)) is stopped and never started
+    /// again, so that the other recognizers can keep working.
+    pub fn disabled(&self) -> Vec<usize> {
+        self.disabled.iter().map(|e| e.id).collect()
+    }
+
+    /// Describes all the recognizers in this `Manager`, in Graphviz's DOT format.
+    ///
+    /// Each recognizer gets a root node labelled with its id and priority.
+    pub fn to_dot(&self) -> String {
+        let mut entries = self.active.iter()
+            .chain(self.inactive.iter())
+            .chain(self.disabled.iter())
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|e| e.id);
+
+        let mut g = Graph::new();
+        for e in entries {
+            let root = g.node(&format!("#{} (priority {})", e.id, e.priority));
+            let rec = e.rec.to_graph(&mut g);
+            g.edge(root, rec, "");
+        }
+        g.to_dot()
+    }
+
+    /// Limits the number of fingers that are tracked at once.
+    ///
+    /// See [`Frame::set_contact_limit`](../frame/struct.Frame.html#method.set_contact_limit).
+    pub fn set_contact_limit(&mut self, max: Option<u8>, policy: Overflow) {
+        self.frame.set_contact_limit(max, policy);
+    }
+
+    /// Merges frames that arrive in quick succession.
+    ///
+    /// Some touchscreens send hundreds of frames per second, which is more than the recognizers
+    /// need. If this is set, a frame that ends less than `interval_ms` milliseconds after the last
+    /// frame that the recognizers saw is held back and merged with the following one: the
+    /// recognizers see the latest positions, and `touch_down` or `touch_up` if either happened in
+    /// any of the merged frames. The frames in which the first finger comes down and the last one
+    /// goes up are never held back.
+    pub fn set_coalesce_interval(&mut self, interval_ms: Option<u32>) {
+        self.coalesce_ms = interval_ms;
+    }
+
+    /// Sets when the recognizers start again after a gesture is recognized.
+    ///
+    /// By default, they start with the next touch. This can be a problem when a gesture finishes
+    /// with fingers resting on the surface: lifting some of them and putting them down again is a
+    /// new touch, and it might be recognized as another gesture.
+    pub fn set_rearm_policy(&mut self, policy: Rearm) {
+        self.rearm = policy;
+        if policy == Rearm::Immediately {
+            self.disarmed_since = None;
+        }
+    }
+
+    /// Pauses or resumes all the recognizers.
+    ///
+    /// While the `Manager` is disabled, touches are still tracked but nothing is recognized.
+    /// Disabling it stops the touch that is going on, if any. Enabling it takes effect from the
+    /// next touch, so that a touch that started while it was disabled can't end up as a gesture.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if !enabled && self.enabled {
+            self.reset();
+        }
+        if enabled != self.enabled {
+            self.enabled = enabled;
+            // A snapshot can only replay events, so it can't account for this in the middle of a
+            // touch.
+            if self.history.as_ref().map_or(false, |h| h.is_empty()) {
+                self.settle();
+            } else {
+                self.history = None;
+            }
+        }
+    }
+
+    /// Returns false if the recognizers were paused with
+    /// [`set_enabled`](#method.set_enabled).
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Adds a stage that labels the contacts before the recognizers see them.
+    ///
+    /// The classifiers run in the order that they were added, at the end of every frame. When one
+    /// of them labels a contact as something that is left out (see
+    /// [`set_left_out`](#method.set_left_out)), whatever was going on fails and the recognizers
+    /// start again with the contacts that are left. See the [`classify`](../classify/index.html)
+    /// module.
+    pub fn add_classifier<C: Classifier + 'static>(&mut self, c: C) {
+        self.classifiers.push(Box::new(c));
+    }
+
+    /// Sets the kinds of contact that are left out altogether, once a classifier labels them.
+    ///
+    /// See [`Frame::set_left_out`](../frame/struct.Frame.html#method.set_left_out).
+    pub fn set_left_out(&mut self, kinds: Kinds) {
+        self.frame.set_left_out(kinds);
+    }
+
+    // Updates the re-arming state at the end of a frame, and returns true if the recognizers
+    // should start in this frame.
+    fn check_armed(&mut self) -> bool {
+        let f = &self.frame;
+        let since = match self.disarmed_since {
+            Some(t) => t,
+            None => return true,
+        };
+        let starting = f.last.num_down == 0 && f.cur.num_down > 0;
+        let ending = f.last.num_down > 0 && f.cur.num_down == 0;
+        let (wait, since) = match self.rearm {
+            Rearm::Immediately => (0, since),
+            // The wait for a lift starts over every time the last finger goes up.
+            Rearm::AfterLift { ms } => (ms, if ending { f.time } else { since }),
+            Rearm::AfterDelay { ms } => (ms, since),
+        };
+        self.disarmed_since = Some(since);
+
+        if starting {
+            if f.time.wrapping_sub(since) >= wait {
+                self.disarmed_since = None;
+                return true;
+            }
+            debug!("ignoring a touch {} ms after the last gesture", f.time.wrapping_sub(since));
         }
+        false
     }
 
-    pub fn push<R: Recognizer<In=(), Out=T> + 'static>(&mut self, r: R) {
-        self.active.push(Box::new(r));
+    // Should the frame that just ended be merged with the next one?
+    fn should_coalesce(&self) -> bool {
+        let interval = match self.coalesce_ms {
+            Some(i) => i,
+            None => return false,
+        };
+        let f = &self.frame;
+        let starting = f.last.num_down == 0 && f.cur.num_down > 0;
+        let ending = f.last.num_down > 0 && f.cur.num_down == 0;
+        let recent = self.last_update.map_or(false, |t| f.time.wrapping_sub(t) < interval);
+        recent && !starting && !ending && f.overflow.is_none() && !f.inconsistent && !f.excluded
     }
 
+    fn notify(&mut self, id: usize, t: Transition) {
+        trace_transition!(id, t);
+        if let Some(ref mut obs) = self.observer {
+            (**obs)(&self.frame, id, t);
+        }
+    }
+
+    // Disables a recognizer that panicked.
+    fn disable(&mut self, e: Entry<T>, payload: Box<Any + Send>) {
+        error!("recognizer #{} panicked, so it is disabled: {}", e.id, panic_message(&payload));
+        self.disabled.push(e);
+    }
+
+    // Remembers the current state as the one that snapshots start from.
+    fn settle(&mut self) {
+        let ids = |entries: &[Entry<T>]| entries.iter().map(|e| e.id).collect::<Vec<_>>();
+        self.rest = Rest {
+            order: Some((ids(&self.active[..]), ids(&self.inactive[..]), ids(&self.disabled[..]))),
+            enabled: self.enabled,
+            last_update: self.last_update,
+            disarmed_since: self.disarmed_since,
+        };
+        self.history = Some(Vec::new());
+    }
+
+    // Adds an event to the history that snapshots replay.
+    fn record(&mut self, ev: &Event) {
+        let full = match self.history {
+            Some(ref mut h) => {
+                h.push(*ev);
+                h.len() > MAX_HISTORY
+            },
+            None => false,
+        };
+        if full {
+            self.history = None;
+        }
+    }
+
+    /// Captures the state of this `Manager`, so that it can be [restored](#method.restore) later.
+    ///
+    /// The recognizers can't be captured directly, so the state is how this `Manager` was the
+    /// last time that it was at rest (with no fingers down, and no recognizers running), together
+    /// with all the events since then. Restoring it replays those events, which puts the
+    /// recognizers back exactly where they were, as long as they (and the classifiers) only
+    /// depend on the events that they see.
+    ///
+    /// Returns `None` if the state can't be captured until the current touch ends: that is, if
+    /// the touch has gone on for too long, or the `Manager` was disabled or enabled during it.
+    pub fn snapshot(&self) -> Option<ManagerState> {
+        self.history.as_ref().map(|h| ManagerState {
+            num_recognizers: self.num_recognizers(),
+            rest: self.rest.clone(),
+            device: self.device.clone(),
+            events: h.clone(),
+        })
+    }
+
+    /// Puts this `Manager` into the state that [`snapshot`](#method.snapshot) captured.
+    ///
+    /// This `Manager` must have been built in the same way as the one that the state came from
+    /// (with the same recognizers, in the same order, and the same settings), and it must not
+    /// have been given any events yet. The observer isn't told about anything that happens while
+    /// the events are replayed.
+    pub fn restore(&mut self, state: &ManagerState) -> Result<(), String> {
+        let n = self.num_recognizers();
+        if state.num_recognizers != n {
+            return Err(format!("the state has {} recognizers, but the Manager has {}",
+                               state.num_recognizers, n));
+        }
+        if self.last_update.is_some() || !self.frame.is_empty() {
+            return Err("the Manager was already given some events".to_owned());
+        }
+        if let Some((ref active, ref inactive, ref disabled)) = state.rest.order {
+            let mut ids = active.iter().chain(inactive).chain(disabled).cloned()
+                .collect::<Vec<_>>();
+            ids.sort();
+            if ids != (0..n).collect::<Vec<_>>() {
+                return Err("the state's recognizers don't match the Manager's".to_owned());
+            }
+            let mut entries = (0..n).map(|_| None).collect::<Vec<Option<Entry<T>>>>();
+            for e in self.active.drain(..).chain(self.inactive.drain(..))
+                .chain(self.disabled.drain(..)) {
+                let id = e.id;
+                entries[id] = Some(e);
+            }
+            let mut take = |order: &[usize]| {
+                order.iter().map(|&id| entries[id].take().unwrap()).collect::<Vec<_>>()
+            };
+            self.active = take(&active[..]);
+            self.inactive = take(&inactive[..]);
+            self.disabled = take(&disabled[..]);
+        }
+        self.enabled = state.rest.enabled;
+        self.last_update = state.rest.last_update;
+        self.disarmed_since = state.rest.disarmed_since;
+        self.rest = state.rest.clone();
+        self.history = Some(Vec::new());
+
+        let observer = self.observer.take();
+        for ev in &state.events {
+            self.update_event_recognized(ev);
+        }
+        self.observer = observer;
+        self.device = state.device.clone();
+        // For `update_snapshot`, which works out what changed since the last snapshot.
+        self.last_snapshot = self.frame.cur.clone();
+        Ok(())
+    }
+
+    // Makes all the active recognizers fail.
+    fn reset(&mut self) {
+        self.pending.clear();
+        let mut failed = Vec::new();
+        for e in self.active.drain(..) {
+            failed.push(e.id);
+            self.inactive.push(e);
+        }
+        for id in failed {
+            self.notify(id, Transition::Failed);
+        }
+    }
+
+    #[cfg(feature = "input")]
     pub fn update(&mut self, ev: &TouchEvent) -> Option<T> {
-        self.frame.update(ev);
-        if let &TouchEvent::Frame(_) = ev {
+        self.update_recognized(ev).and_then(finished)
+    }
+
+    /// Updates all the recognizers with a new event.
+    ///
+    /// Returns the outcome of a recognizer that succeeded. Progress updates from progressive
+    /// recognizers aren't returned, because they couldn't be told apart from the outcomes; use
+    /// [`update_event_recognized`](#method.update_event_recognized) to get those too.
+    pub fn update_event(&mut self, ev: &Event) -> Option<T> {
+        self.update_event_recognized(ev).and_then(finished)
+    }
+
+    /// Updates all the recognizers with the complete state of the fingers at the end of a frame.
+    ///
+    /// This is an alternative to [`update_event`](#method.update_event) for programs that already
+    /// keep track of the fingers themselves. `snapshot` describes all the fingers that are down at
+    /// `time` (in milliseconds), indexed by slot; the `Manager` works out which fingers came down,
+    /// went up or moved since the previous snapshot. The `num_down` and `kind` fields of
+    /// `snapshot` are ignored.
+    ///
+    /// Don't mix this with `update_event` during a single touch, because the two of them keep
+    /// track of the fingers separately.
+    pub fn update_snapshot(&mut self, snapshot: &Snapshot, time: u32) -> Option<T> {
+        let mut events = Vec::new();
+        let len = snapshot.down.len().max(self.last_snapshot.down.len());
+        for slot in 0..len {
+            match (self.last_snapshot.is_down(slot), snapshot.is_down(slot)) {
+                (false, true) => events.push(Event::Down { slot: slot, pos: snapshot.pos[slot] }),
+                (true, false) => events.push(Event::Up { slot: slot }),
+                (true, true) if snapshot.pos[slot] != self.last_snapshot.pos[slot] => {
+                    events.push(Event::Motion { slot: slot, pos: snapshot.pos[slot] });
+                },
+                _ => {},
+            }
+            let (size, pressure) = shape(snapshot, slot);
+            let reshaped = !self.last_snapshot.is_down(slot)
+                || shape(&self.last_snapshot, slot) != (size, pressure);
+            if snapshot.is_down(slot) && reshaped && (size, pressure) != (0.0, 0.0) {
+                events.push(Event::Shape { slot: slot, size: size, pressure: pressure });
+            }
+        }
+        self.last_snapshot = snapshot.clone();
+
+        for ev in &events {
+            self.record(ev);
+            self.frame.update_event(ev);
+        }
+        self.update_event(&Event::Frame { time: time })
+    }
+
+    /// Tells the recognizers that it is now `time` (in milliseconds), even though the fingers
+    /// haven't done anything.
+    ///
+    /// A touch surface usually sends nothing while the fingers are still, so the recognizers that
+    /// wait for some time to pass (like [`Dwell`](../gestures/primitive/struct.Dwell.html)) can only
+    /// succeed if this is called every so often while fingers are down. `time` must be on the same
+    /// clock as the events, and it shouldn't be called in the middle of a frame. This does nothing
+    /// if no fingers are down, or if `time` is no later than the last frame.
+    pub fn tick(&mut self, time: u32) -> Option<Recognized<T>> {
+        let later = self.last_update.map_or(true, |t| (time.wrapping_sub(t) as i32) > 0);
+        if self.frame.cur.num_down == 0 || !later {
+            return None;
+        }
+        self.update_event_recognized(&Event::Frame { time: time })
+    }
+
+    /// Like [`update`](#method.update), but also says where the gesture came from.
+    #[cfg(feature = "input")]
+    pub fn update_recognized(&mut self, ev: &TouchEvent) -> Option<Recognized<T>> {
+        self.device = Some(ev.device().sysname().to_owned());
+        self.update_event_recognized(&Event::from(ev))
+    }
+
+    /// Like [`update_event`](#method.update_event), but also says where the gesture came from
+    /// and how long it took.
+    ///
+    /// If no recognizer succeeded, but a progressive one reported progress, this returns that
+    /// instead, with `finished` set to false.
+    ///
+    /// Since an `Event` doesn't know which device it came from, the `device` of the result is the
+    /// device of the last event that was passed to [`update_recognized`](#method.update_recognized).
+    pub fn update_event_recognized(&mut self, ev: &Event) -> Option<Recognized<T>> {
+        match self.update_frame(ev) {
+            Outcome::Nothing => None,
+            Outcome::Progress(g, p) => Some(self.recognized(g, false, p, vec![])),
+            Outcome::Released(mut succeeded, winner, shadowed) => {
+                let s = succeeded.swap_remove(winner);
+                Some(self.recognized(s.value, true, s.progress, shadowed))
+            },
+        }
+    }
+
+    /// Like [`update_all`](#method.update_all), but for an `Event`.
+    pub fn update_event_all(&mut self, ev: &Event) -> Vec<Recognized<T>> {
+        match self.update_frame(ev) {
+            Outcome::Nothing => vec![],
+            Outcome::Progress(g, p) => vec![self.recognized(g, false, p, vec![])],
+            Outcome::Released(mut succeeded, winner, shadowed) => {
+                let first = succeeded.remove(winner);
+                succeeded.sort_by_key(|s| (-(s.priority as i64), s.id));
+                let mut ret = vec![self.recognized(first.value, true, first.progress, shadowed)];
+                for s in succeeded {
+                    ret.push(self.recognized(s.value, true, s.progress, vec![]));
+                }
+                ret
+            },
+        }
+    }
+
+    /// Like [`update_recognized`](#method.update_recognized), but returns every gesture that was
+    /// recognized, instead of only the one that wins.
+    ///
+    /// When a gesture is reported, everything else that succeeded since the last one was
+    /// reported is returned along with it (including the ones that were held back for a
+    /// recognizer with a higher priority). The gesture that [`update_recognized`] would have
+    /// returned always comes first. The rest come after it, from the highest priority to the
+    /// lowest, and in the order that their recognizers were pushed if their priorities are the
+    /// same. If nothing succeeded, this returns the progress of a progressive recognizer, if
+    /// there is any, just like [`update_recognized`].
+    ///
+    /// [`update_recognized`]: #method.update_recognized
+    #[cfg(feature = "input")]
+    pub fn update_all(&mut self, ev: &TouchEvent) -> Vec<Recognized<T>> {
+        self.device = Some(ev.device().sysname().to_owned());
+        self.update_event_all(&Event::from(ev))
+    }
+
+    // Gives a gesture the details of the current touch.
+    fn recognized(&self, value: T, finished: bool, progress: f64, shadowed: Vec<usize>)
+    -> Recognized<T> {
+        Recognized {
+            value: value,
+            finished: finished,
+            progress: progress,
+            device: self.device.clone(),
+            fingers: self.peak_fingers,
+            start_time: self.start_time,
+            start_pos: self.start_pos,
+            end_time: self.frame.time,
+            shadowed: shadowed,
+        }
+    }
+
+    fn update_frame(&mut self, ev: &Event) -> Outcome<T> {
+        self.record(ev);
+        self.frame.update_event(ev);
+        if let &Event::Frame { .. } = ev {
+            for c in &mut self.classifiers {
+                self.frame.classify(&mut **c);
+            }
+            if self.should_coalesce() {
+                // Since the frame doesn't advance, the next frame's events pile on top of this one.
+                return Outcome::Nothing;
+            }
+            self.last_update = Some(self.frame.time);
+            enter_span!("frame time={} fingers={}", self.frame.time, self.frame.cur.num_down);
             if self.frame.last.num_down == 0 && self.frame.cur.num_down > 0 {
-                for r in &mut self.inactive {
-                    r.init((), &self.frame);
+                self.start_time = self.frame.time;
+                self.start_pos = self.frame.delta.mean_pos;
+                self.peak_fingers = 0;
+            }
+            self.peak_fingers = self.peak_fingers.max(self.frame.cur.num_down);
+
+            let overflowed = self.frame.overflow.map_or(false, |o| o != Overflow::Ignore);
+            if overflowed {
+                // There were too many fingers, so whatever was going on is not a gesture after all.
+                self.reset();
+            } else if self.frame.inconsistent {
+                // We can't trust the state of the recognizers, so start over with the next touch.
+                warn!("inconsistent touch events; resetting all the recognizers");
+                self.reset();
+            } else if self.frame.excluded {
+                // The recognizers were counting a contact that isn't there any more, so they start
+                // again without it.
+                self.reset();
+            }
+
+            let armed = self.check_armed();
+            if self.frame.last.num_down == 0 && self.frame.cur.num_down > 0 && !overflowed
+                && armed && self.enabled {
+                let mut transitions = Vec::new();
+                let mut panicked = Vec::new();
+                for mut e in self.inactive.drain(..) {
+                    let frame = &self.frame;
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| e.rec.init((), frame)));
+                    match result {
+                        Ok(()) => {
+                            transitions.push((e.id, Transition::Started));
+                            e.progressed = false;
+                            self.active.push(e);
+                        },
+                        Err(payload) => {
+                            transitions.push((e.id, Transition::Panicked));
+                            panicked.push((e, payload));
+                        },
+                    }
+                }
+                for (e, payload) in panicked {
+                    self.disable(e, payload);
+                }
+                for (id, t) in transitions {
+                    self.notify(id, t);
                 }
-                self.active.extend(self.inactive.drain(..));
             }
 
-            let mut ret = None;
-            for mut rec in self.active.drain(..) {
-                match rec.update(&self.frame) {
-                    RecResult::Continuing => self.buf.push(rec),
-                    RecResult::Failed => self.inactive.push(rec),
+            // Everything that succeeded since the last outcome was returned, in order.
+            let mut succeeded = ::std::mem::replace(&mut self.pending, Vec::new());
+            let mut progress = None;
+            let mut transitions = Vec::new();
+            let mut panicked = Vec::new();
+            // A frame without any fingers, in which nothing happened.
+            let idle = self.frame.cur.num_down == 0 && !self.frame.touch_down
+                && !self.frame.touch_up;
+            for mut e in self.active.drain(..) {
+                if idle && !e.rec.wants_frames_while_idle() {
+                    self.buf.push(e);
+                    continue;
+                }
+                let result = {
+                    enter_span!("recognizer id={} priority={}", e.id, e.priority);
+                    let frame = &self.frame;
+                    panic::catch_unwind(AssertUnwindSafe(|| e.rec.update(frame)))
+                };
+                let result = match result {
+                    Ok(r) => r,
+                    Err(payload) => {
+                        transitions.push((e.id, Transition::Panicked));
+                        panicked.push((e, payload));
+                        continue;
+                    },
+                };
+                match result {
+                    RecResult::Continuing => self.buf.push(e),
+                    RecResult::Progressing(g) => {
+                        e.progressed = true;
+                        progress = Some((g, e.rec.progress()));
+                        self.buf.push(e);
+                    },
+                    RecResult::Failed => {
+                        transitions.push((e.id, Transition::Failed));
+                        self.inactive.push(e);
+                    },
                     RecResult::Succeeded(g) => {
-                        ret = Some(g);
-                        self.inactive.push(rec);
+                        succeeded.push(Success {
+                            value: g,
+                            progress: if e.progressed { e.rec.progress() } else { 1.0 },
+                            priority: e.priority,
+                            id: e.id,
+                            claims: e.claims,
+                        });
+                        transitions.push((e.id, Transition::Succeeded));
+                        self.inactive.push(e);
                     }
                 }
             }
             ::std::mem::swap(&mut self.buf, &mut self.active);
+            for (e, payload) in panicked {
+                self.disable(e, payload);
+            }
+
+            // A recognizer that claims the touch beats everything else. Otherwise, the one with
+            // the highest priority wins, and the later one wins a tie.
+            let claimed = succeeded.iter().any(|s| s.claims);
+            let mut winner = None;
+            for (i, s) in succeeded.iter().enumerate() {
+                let better = winner.map_or(true, |w: usize| s.priority >= succeeded[w].priority);
+                if (s.claims || !claimed) && better {
+                    winner = Some(i);
+                }
+            }
+            let ret = match winner {
+                Some(w) => {
+                    let p = succeeded[w].priority;
+                    if !claimed && self.active.iter().any(|e| e.priority > p) {
+                        self.pending = succeeded;
+                        Outcome::Nothing
+                    } else {
+                        // This gesture claims the current sequence, so the less important ones
+                        // (or all of them, if it insists) don't get to finish.
+                        let (lower, rest): (Vec<_>, Vec<_>) = self.active.drain(..)
+                            .partition(|e| claimed || e.priority < p);
+                        self.active = rest;
+                        let shadowed = lower.iter()
+                            .filter(|e| e.priority > p)
+                            .map(|e| e.id)
+                            .collect();
+                        for e in lower {
+                            transitions.push((e.id, Transition::Cancelled));
+                            self.inactive.push(e);
+                        }
+                        if self.rearm != Rearm::Immediately {
+                            self.disarmed_since = Some(self.frame.time);
+                        }
+                        Outcome::Released(succeeded, w, shadowed)
+                    }
+                },
+                // Progress updates only get reported if nothing finished on this frame.
+                None => progress.map_or(Outcome::Nothing, |(g, p)| Outcome::Progress(g, p)),
+            };
+
+            for (id, t) in transitions {
+                self.notify(id, t);
+            }
             self.frame.advance();
+            if self.active.is_empty() && self.pending.is_empty() && self.frame.is_empty() {
+                self.settle();
+            }
             ret
         } else {
-            None
+            Outcome::Nothing
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use classify::ShapeClassifier;
+    use event::Event;
+    use geom::Point;
+    use gestures::primitive::{Dwell, InitialAngle, NFingers, StraightSwipe};
+    use params::ShapeParams;
+    use super::{Manager, Phase, Rearm};
+    use Recognizer;
+
+    #[test]
+    fn panicking_recognizers_are_disabled() {
+        let mut man = Manager::new();
+        man.push(NFingers::new(1).map_outcome(|_| -> u32 { panic!("oops") }));
+        man.push(NFingers::new(1).map_outcome(|_| 2));
+
+        man.update_event(&Event::Down { slot: 0, pos: Point::new(0.0, 0.0) });
+        assert_eq!(man.update_event(&Event::Frame { time: 0 }), Some(2));
+        assert_eq!(man.disabled(), vec![0]);
+
+        man.update_event(&Event::Up { slot: 0 });
+        man.update_event(&Event::Frame { time: 10 });
+        man.update_event(&Event::Down { slot: 0, pos: Point::new(0.0, 0.0) });
+        assert_eq!(man.update_event(&Event::Frame { time: 20 }), Some(2));
+        assert_eq!(man.num_recognizers(), 2);
+    }
+
+    #[test]
+    fn palms_are_left_out() {
+        let mut man = Manager::new();
+        man.push(NFingers::new(2).map_outcome(|_| 2));
+        man.add_classifier(ShapeClassifier::new(ShapeParams::default()));
+
+        man.update_event(&Event::Down { slot: 0, pos: Point::new(0.0, 0.0) });
+        man.update_event(&Event::Shape { slot: 0, size: 40.0, pressure: 0.5 });
+        assert_eq!(man.update_event(&Event::Frame { time: 0 }), None);
+        man.update_event(&Event::Down { slot: 1, pos: Point::new(50.0, 0.0) });
+        man.update_event(&Event::Down { slot: 2, pos: Point::new(60.0, 0.0) });
+        assert_eq!(man.update_event(&Event::Frame { time: 10 }), Some(2));
+    }
+
+    #[test]
+    fn ticks_let_time_pass() {
+        let mut man = Manager::new();
+        man.push(NFingers::new(1).and_then(Dwell::new()).map_outcome(|_| 1));
+        assert!(man.tick(0).is_none());
+
+        man.update_event(&Event::Down { slot: 0, pos: Point::new(0.0, 0.0) });
+        assert_eq!(man.update_event(&Event::Frame { time: 0 }), None);
+        assert!(man.tick(300).is_none());
+        assert!(man.tick(200).is_none());
+        assert_eq!(man.tick(700).map(|r| r.value), Some(1));
+    }
+
+    #[test]
+    fn disabled_managers_recognize_nothing() {
+        let mut man = Manager::new();
+        man.push(NFingers::new(1).map_outcome(|_| 1));
+        let touch = |man: &mut Manager<i32>, time| {
+            man.update_event(&Event::Down { slot: 0, pos: Point::new(0.0, 0.0) });
+            let ret = man.update_event(&Event::Frame { time: time });
+            man.update_event(&Event::Up { slot: 0 });
+            man.update_event(&Event::Frame { time: time + 10 });
+            ret
+        };
+
+        man.set_enabled(false);
+        assert!(!man.is_enabled());
+        assert_eq!(touch(&mut man, 0), None);
+        man.set_enabled(true);
+        assert_eq!(touch(&mut man, 100), Some(1));
+    }
+
+    #[test]
+    fn rearm_policies() {
+        // A one-finger touch that is held from `down` to `up`. Returns what it was recognized as.
+        let touch = |man: &mut Manager<i32>, down, up| {
+            man.update_event(&Event::Down { slot: 0, pos: Point::new(0.0, 0.0) });
+            let ret = man.update_event(&Event::Frame { time: down });
+            man.update_event(&Event::Up { slot: 0 });
+            man.update_event(&Event::Frame { time: up });
+            ret
+        };
+        // The first touch rests on the surface for a while, and the next ones are quick taps.
+        let touches = |policy| {
+            let mut man = Manager::new();
+            man.push(NFingers::new(1).map_outcome(|_| 1));
+            man.set_rearm_policy(policy);
+            vec![touch(&mut man, 0, 300), touch(&mut man, 350, 360), touch(&mut man, 450, 460),
+                 touch(&mut man, 700, 710)]
+        };
+
+        assert_eq!(touches(Rearm::Immediately), vec![Some(1), Some(1), Some(1), Some(1)]);
+        // The second touch comes too soon after the first one lifted, and the third too soon
+        // after the second.
+        assert_eq!(touches(Rearm::AfterLift { ms: 200 }), vec![Some(1), None, None, Some(1)]);
+        // The second touch comes long enough after the first gesture, but the third doesn't come
+        // long enough after the second.
+        assert_eq!(touches(Rearm::AfterDelay { ms: 200 }), vec![Some(1), Some(1), None, Some(1)]);
+    }
+
+    #[test]
+    fn phases() {
+        let mut man = Manager::new();
+        man.push(NFingers::new(1).and_then(Dwell::new()).map_outcome(|_| 1));
+        assert_eq!(man.phase(), Phase::Idle);
+
+        man.update_event(&Event::Down { slot: 0, pos: Point::new(0.0, 0.0) });
+        man.update_event(&Event::Frame { time: 0 });
+        assert_eq!(man.phase(), Phase::Recognizing);
+        assert_eq!(man.tick(700).map(|r| r.value), Some(1));
+        assert_eq!(man.phase(), Phase::Waiting);
+
+        man.update_event(&Event::Up { slot: 0 });
+        man.update_event(&Event::Frame { time: 800 });
+        assert_eq!(man.phase(), Phase::Idle);
+    }
+
+    #[test]
+    fn candidates() {
+        let mut man = Manager::new();
+        let tap = man.push(NFingers::new(1).and_then(Dwell::new()).map_outcome(|_| 1));
+        let swipe = man.push(NFingers::new(2).and_then(InitialAngle::with_threshold_mm(10.0))
+                             .and_then(StraightSwipe::new().min_length(20.0))
+                             .map_outcome(|_| 2));
+        assert_eq!(man.candidates().len(), 2);
+
+        man.update_event(&Event::Down { slot: 0, pos: Point::new(0.0, 0.0) });
+        man.update_event(&Event::Down { slot: 1, pos: Point::new(10.0, 0.0) });
+        man.update_event(&Event::Frame { time: 0 });
+        man.update_event(&Event::Motion { slot: 0, pos: Point::new(5.0, 0.0) });
+        man.update_event(&Event::Motion { slot: 1, pos: Point::new(15.0, 0.0) });
+        man.update_event(&Event::Frame { time: 10 });
+        let c = man.candidates();
+        assert_eq!(c.len(), 1);
+        assert_eq!(c[0].id, swipe);
+        assert!(c[0].progress > 0.25 && c[0].progress < 0.5);
+        assert!(!man.is_active(tap));
+    }
+
+    #[test]
+    fn claiming_recognizers_win_straight_away() {
+        let mut man = Manager::new();
+        let slow = man.push_with_priority(NFingers::new(1).and_then(Dwell::new())
+                                          .map_outcome(|_| 1), 5);
+        man.push_claiming(NFingers::new(1).map_outcome(|_| 2), 0);
+
+        man.update_event(&Event::Down { slot: 0, pos: Point::new(0.0, 0.0) });
+        let r = man.update_event_recognized(&Event::Frame { time: 0 }).unwrap();
+        assert_eq!(r.value, 2);
+        assert_eq!(r.shadowed, vec![slow]);
+        assert!(!man.is_active(slow));
+        assert!(man.tick(700).is_none());
+    }
+
+    #[test]
+    fn all_outcomes_are_returned() {
+        let mut man = Manager::new();
+        man.push(NFingers::new(1).map_outcome(|_| 1));
+        man.push_with_priority(NFingers::new(1).map_outcome(|_| 2), 5);
+        man.push(NFingers::new(1).map_outcome(|_| 3));
+
+        man.update_event(&Event::Down { slot: 0, pos: Point::new(0.0, 0.0) });
+        let all = man.update_event_all(&Event::Frame { time: 0 });
+        // The winner comes first, and then the rest by priority and id.
+        assert_eq!(all.iter().map(|r| r.value).collect::<Vec<_>>(), vec![2, 1, 3]);
+        assert_eq!(all[1].fingers, 1);
+
+        // An outcome that is held back for a higher priority isn't lost when it is replaced.
+        let mut man = Manager::new();
+        man.push(NFingers::new(1).map_outcome(|_| 1));
+        man.push_with_priority(NFingers::new(1).and_then(Dwell::new()).map_outcome(|_| 2), 5);
+        man.update_event(&Event::Down { slot: 0, pos: Point::new(0.0, 0.0) });
+        assert!(man.update_event_all(&Event::Frame { time: 0 }).is_empty());
+        let all = man.update_event_all(&Event::Frame { time: 700 });
+        assert_eq!(all.iter().map(|r| r.value).collect::<Vec<_>>(), vec![2, 1]);
+    }
+
+    #[test]
+    fn snapshots_restore_touches_in_progress() {
+        let build = || {
+            let mut man = Manager::new();
+            man.push(NFingers::new(1).and_then(Dwell::new()).map_outcome(|_| 1));
+            man.push(NFingers::new(2).and_then(InitialAngle::with_threshold_mm(10.0))
+                     .and_then(StraightSwipe::new().min_length(20.0))
+                     .map_outcome(|_| 2));
+            man
+        };
+        let down = |slot, x| Event::Down { slot: slot, pos: Point::new(x, 0.0) };
+        let motion = |slot, x| Event::Motion { slot: slot, pos: Point::new(x, 0.0) };
+        let before = vec![
+            // A quick tap, after which the recognizers are at rest.
+            down(0, 0.0), Event::Frame { time: 0 },
+            Event::Up { slot: 0 }, Event::Frame { time: 10 },
+            // The start of a swipe.
+            down(0, 0.0), down(1, 10.0), Event::Frame { time: 100 },
+            motion(0, 5.0), motion(1, 15.0), Event::Frame { time: 110 },
+        ];
+        let after = vec![
+            motion(0, 30.0), motion(1, 40.0), Event::Frame { time: 120 },
+            Event::Up { slot: 0 }, Event::Up { slot: 1 }, Event::Frame { time: 130 },
+        ];
+
+        let mut man = build();
+        for ev in &before {
+            man.update_event(ev);
+        }
+        let state = man.snapshot().unwrap();
+        assert_eq!(state.events(), &before[4..]);
+
+        let mut restored = build();
+        restored.restore(&state).unwrap();
+        assert_eq!(restored.snapshot(), Some(state.clone()));
+        let expected = after.iter().map(|ev| man.update_event(ev)).collect::<Vec<_>>();
+        let got = after.iter().map(|ev| restored.update_event(ev)).collect::<Vec<_>>();
+        assert!(expected.contains(&Some(2)));
+        assert_eq!(got, expected);
+
+        // Only a new `Manager` with the same recognizers can be restored.
+        assert!(restored.restore(&state).is_err());
+        let mut other = Manager::new();
+        other.push(NFingers::new(1).map_outcome(|_| 1));
+        assert!(other.restore(&state).is_err());
+    }
+}
@@ -0,0 +1,354 @@
+//! The numbers that decide how the recognizers behave.
+//!
+//! Every threshold used by the recognizers in [`gestures`](../gestures/index.html) and the
+//! filters in [`filters`](../filters/index.html) lives in one of the structs in this module, so
+//! that they can be tuned for a particular device (or found by a program) instead of being
+//! scattered through the code. Their `Default` implementations are the values that the
+//! recognizers use when they are created with `new()`.
+//!
+//! With the `serialize` feature, these can be read from a config file. Any field that is missing
+//! keeps its default value.
+//!
+//! # Examples
+//! ```
+//! use libgestures::gestures::primitive::StraightSwipe;
+//! use libgestures::params::SwipeParams;
+//!
+//! let params = SwipeParams { min_length_mm: 20.0, ..SwipeParams::default() };
+//! let swipe = StraightSwipe::with_params(&params);
+//! ```
+
+/// The numbers used by [`InitialAngle`](../gestures/primitive/struct.InitialAngle.html) and
+/// [`StraightSwipe`](../gestures/primitive/struct.StraightSwipe.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default, rename_all = "kebab-case"))]
+pub struct SwipeParams {
+    /// How far (in millimeters) the middle of the fingers must move before we decide which way
+    /// it is going.
+    pub start_mm: f64,
+    /// How far (in millimeters) the middle of the fingers must move for a swipe to count.
+    pub min_length_mm: f64,
+    /// How far (in millimeters) the middle of the fingers must move before we check the angle of
+    /// the swipe again.
+    pub step_mm: f64,
+    /// How quickly (per millimeter) the expected angle of a swipe follows the fingers, so that
+    /// gently curved swipes still count.
+    pub adaptivity: f64,
+    /// How far (in degrees) a swipe may turn before it stops being straight.
+    pub angle_tolerance_deg: f64,
+    /// How many fingers a swipe follows. On each frame, only the ones that moved furthest count,
+    /// so that a finger that sticks to the surface doesn't make the swipe look shorter. Zero
+    /// follows the middle of all the fingers.
+    pub leading_fingers: usize,
+    /// How far (in millimeters) the middle of the fingers must move for a swipe that fires
+    /// continuously to go all the way.
+    pub slide_mm: f64,
+}
+
+impl Default for SwipeParams {
+    fn default() -> SwipeParams {
+        SwipeParams {
+            start_mm: 5.0,
+            min_length_mm: 10.0,
+            step_mm: 3.0,
+            adaptivity: 0.01,
+            angle_tolerance_deg: 20.0,
+            leading_fingers: 0,
+            slide_mm: 50.0,
+        }
+    }
+}
+
+/// The numbers used by [`Pinch`](../gestures/primitive/struct.Pinch.html),
+/// [`Radial`](../gestures/primitive/struct.Radial.html) and
+/// [`Rotation`](../gestures/primitive/struct.Rotation.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default, rename_all = "kebab-case"))]
+pub struct PinchParams {
+    /// How much the spread of the fingers must change in a pinch.
+    pub min_scale: f64,
+    /// How far (in millimeters) each finger must move in a spread.
+    pub spread_mm: f64,
+    /// How far (in millimeters) each finger must move in a grab.
+    pub grab_mm: f64,
+    /// How far (in degrees) the fingers must turn in a rotation.
+    pub min_rotation_deg: f64,
+}
+
+impl Default for PinchParams {
+    fn default() -> PinchParams {
+        PinchParams {
+            min_scale: 1.25,
+            spread_mm: 20.0,
+            grab_mm: 15.0,
+            min_rotation_deg: 30.0,
+        }
+    }
+}
+
+/// The numbers used by [`Shake`](../gestures/primitive/struct.Shake.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default, rename_all = "kebab-case"))]
+pub struct ShakeParams {
+    /// How many times the fingers must turn around.
+    pub min_reversals: usize,
+    /// How quickly (in milliseconds) the reversals must happen.
+    pub window_ms: u32,
+    /// How far (in millimeters) the fingers must move between reversals.
+    pub stroke_mm: f64,
+}
+
+impl Default for ShakeParams {
+    fn default() -> ShakeParams {
+        ShakeParams {
+            min_reversals: 3,
+            window_ms: 800,
+            stroke_mm: 8.0,
+        }
+    }
+}
+
+/// The numbers used by the filters in [`filters`](../filters/index.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default, rename_all = "kebab-case"))]
+pub struct FilterParams {
+    /// How far (in millimeters) fingers may move while they are supposed to be still.
+    pub still_mm: f64,
+    /// How far (in millimeters) the fingers may move relative to one another during a swipe.
+    pub shape_mm: f64,
+    /// Like `shape_mm`, for the gestures where the hand tends to change shape more, like shakes.
+    pub loose_shape_mm: f64,
+    /// How quickly (per millimeter) the expected shape of the hand follows the fingers.
+    pub shape_adaptivity: f64,
+    /// Whether swipes fail if the fingers pinch or spread during them (see
+    /// [`MaintainSpread`](../filters/struct.MaintainSpread.html)). This is off unless you ask for
+    /// it, because some people's fingers drift apart or together when they swipe.
+    pub maintain_spread: bool,
+    /// How much the spread of the fingers may change during a swipe, if `maintain_spread` is set.
+    pub max_spread_scale: f64,
+    /// How far (in degrees) the fingers may rotate during a pinch.
+    pub max_rotation_deg: f64,
+    /// How many times longer than their stated limit (see
+    /// [`MaxDuration`](../filters/struct.MaxDuration.html)) gestures may take.
+    pub duration_scale: f64,
+}
+
+impl Default for FilterParams {
+    fn default() -> FilterParams {
+        FilterParams {
+            still_mm: 1.0,
+            shape_mm: 5.0,
+            loose_shape_mm: 10.0,
+            shape_adaptivity: 0.02,
+            maintain_spread: false,
+            max_spread_scale: 1.2,
+            max_rotation_deg: 30.0,
+            duration_scale: 1.0,
+        }
+    }
+}
+
+/// The numbers used by [`tap`](../gestures/compound/fn.tap.html) and
+/// [`hold`](../gestures/compound/fn.hold.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default, rename_all = "kebab-case"))]
+pub struct TapParams {
+    /// How soon (in milliseconds) the fingers must be lifted in a tap.
+    pub max_ms: u32,
+    /// How long (in milliseconds) the fingers must stay down in a hold.
+    pub hold_ms: u32,
+}
+
+impl Default for TapParams {
+    fn default() -> TapParams {
+        TapParams {
+            max_ms: 250,
+            hold_ms: 500,
+        }
+    }
+}
+
+/// The tolerances used by [`FingersUp`](../gestures/primitive/struct.FingersUp.html), at the end
+/// of most gestures.
+///
+/// Both are off by default, so that any finger coming down while the hand lifts makes the gesture
+/// fail.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default, rename_all = "kebab-case"))]
+pub struct LiftParams {
+    /// How long (in milliseconds) a contact that comes down while the fingers are lifting may
+    /// stay down without spoiling the gesture. Zero means that it may not come down at all.
+    pub extra_contact_ms: u32,
+    /// How soon (in milliseconds) after the first finger lifts the others must follow. When they
+    /// do, the gesture succeeds even if a brief extra contact is still down; when they don't, it
+    /// fails. Zero means that there is no limit.
+    pub window_ms: u32,
+}
+
+impl Default for LiftParams {
+    fn default() -> LiftParams {
+        LiftParams {
+            extra_contact_ms: 0,
+            window_ms: 0,
+        }
+    }
+}
+
+/// The numbers used by [`Dwell`](../gestures/primitive/struct.Dwell.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default, rename_all = "kebab-case"))]
+pub struct DwellParams {
+    /// How long (in milliseconds) the fingers must hold still.
+    pub hold_ms: u32,
+    /// How far (in millimeters) the fingers may wander while they hold still.
+    pub still_mm: f64,
+}
+
+impl Default for DwellParams {
+    fn default() -> DwellParams {
+        DwellParams {
+            hold_ms: 600,
+            still_mm: 2.0,
+        }
+    }
+}
+
+/// The numbers used by [`ThumbDetector`](../classify/struct.ThumbDetector.html).
+///
+/// Thumb detection is off by default, because it needs to know how big the touch surface is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default, rename_all = "kebab-case"))]
+pub struct ThumbParams {
+    /// How close (in millimeters) to the bottom edge a contact must come down to be a thumb. Zero
+    /// turns thumb detection off.
+    pub edge_mm: f64,
+    /// How far (in millimeters) a resting thumb may move.
+    pub max_move_mm: f64,
+    /// How long (in milliseconds) a contact must rest before the other fingers come down for it
+    /// to count as a thumb.
+    pub min_rest_ms: u32,
+}
+
+impl Default for ThumbParams {
+    fn default() -> ThumbParams {
+        ThumbParams {
+            edge_mm: 0.0,
+            max_move_mm: 2.0,
+            min_rest_ms: 300,
+        }
+    }
+}
+
+/// The numbers used by [`ShapeClassifier`](../classify/struct.ShapeClassifier.html).
+///
+/// Only some devices say how big their contacts are, or how hard they press. For the others,
+/// these do nothing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default, rename_all = "kebab-case"))]
+pub struct ShapeParams {
+    /// How big (in millimeters, across the long side) a contact must be to count as a palm. Zero
+    /// turns this check off.
+    pub palm_size_mm: f64,
+    /// How hard (from 0 to 1) a contact must press to count as a palm. Zero turns this check off.
+    pub palm_pressure: f64,
+    /// How small (in millimeters, across the long side) a contact must be to count as a pen.
+    /// Zero turns this check off.
+    pub pen_size_mm: f64,
+}
+
+impl Default for ShapeParams {
+    fn default() -> ShapeParams {
+        ShapeParams {
+            palm_size_mm: 25.0,
+            palm_pressure: 0.0,
+            pen_size_mm: 3.0,
+        }
+    }
+}
+
+/// All of the tunable numbers together.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default))]
+pub struct Params {
+    pub swipe: SwipeParams,
+    pub pinch: PinchParams,
+    pub shake: ShakeParams,
+    pub filter: FilterParams,
+    pub dwell: DwellParams,
+    pub tap: TapParams,
+    pub lift: LiftParams,
+    pub thumb: ThumbParams,
+    pub shape: ShapeParams,
+}
+
+impl Params {
+    /// Returns thresholds for people who find the default gestures hard to make, for example
+    /// because their hands shake or move slowly.
+    ///
+    /// Swipes may be less straight, the hand may change shape more, the fingers may drift further
+    /// while they are supposed to be still, and everything that has to happen within a time limit
+    /// gets twice as long. Holding still (see [`Dwell`](../gestures/primitive/struct.Dwell.html))
+    /// also gets longer, so that slow movements aren't mistaken for it. A contact that brushes the
+    /// surface while the hand lifts doesn't spoil the gesture.
+    ///
+    /// # Examples
+    /// ```
+    /// use libgestures::params::Params;
+    ///
+    /// let params = Params::accessible();
+    /// assert!(params.swipe.angle_tolerance_deg > Params::default().swipe.angle_tolerance_deg);
+    /// assert!(params.shake.window_ms > Params::default().shake.window_ms);
+    /// ```
+    pub fn accessible() -> Params {
+        let d = Params::default();
+        Params {
+            swipe: SwipeParams {
+                adaptivity: 0.03,
+                angle_tolerance_deg: 35.0,
+                ..d.swipe
+            },
+            pinch: PinchParams {
+                min_scale: 1.15,
+                min_rotation_deg: 20.0,
+                ..d.pinch
+            },
+            shake: ShakeParams {
+                window_ms: d.shake.window_ms * 2,
+                ..d.shake
+            },
+            filter: FilterParams {
+                still_mm: 3.0,
+                shape_mm: 10.0,
+                loose_shape_mm: 15.0,
+                max_spread_scale: 1.5,
+                max_rotation_deg: 45.0,
+                duration_scale: 2.0,
+                ..d.filter
+            },
+            dwell: DwellParams {
+                hold_ms: 900,
+                still_mm: 3.0,
+            },
+            tap: TapParams {
+                max_ms: d.tap.max_ms * 2,
+                ..d.tap
+            },
+            lift: LiftParams {
+                extra_contact_ms: 150,
+                window_ms: 0,
+            },
+            thumb: d.thumb,
+            shape: d.shape,
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Turning distances into progress values.
+//!
+//! Progressive gestures (like a [`Drag`](../gestures/primitive/struct.Drag.html)) report how far
+//! the fingers have travelled, in millimeters. Most consumers want to drive something like an
+//! animation with that, for which they need a number between zero and one. A
+//! [`Normalizer`](struct.Normalizer.html) does the conversion.
+
+/// A curve for shaping progress values.
+///
+/// All of these map `0.0` to `0.0` and `1.0` to `1.0`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Easing {
+    /// Progress is proportional to distance.
+    Linear,
+    /// Progress starts slow and speeds up.
+    EaseIn,
+    /// Progress starts fast and slows down.
+    EaseOut,
+    /// Progress starts slow, speeds up, and then slows down again.
+    EaseInOut,
+}
+
+impl Easing {
+    /// Applies this easing curve to a number between zero and one.
+    pub fn apply(&self, t: f64) -> f64 {
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// Converts distances in millimeters to progress values.
+///
+/// # Examples
+/// ```
+/// use libgestures::progress::{Easing, Normalizer};
+///
+/// let n = Normalizer::new(40.0);
+/// assert_eq!(n.normalize(10.0), 0.25);
+/// assert_eq!(n.normalize(50.0), 1.0);
+/// assert_eq!(n.normalize(-5.0), 0.0);
+///
+/// let n = Normalizer::new(40.0).overshoot(true);
+/// assert_eq!(n.normalize(50.0), 1.25);
+///
+/// let n = Normalizer::new(40.0).easing(Easing::EaseOut);
+/// assert_eq!(n.normalize(20.0), 0.75);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Normalizer {
+    full_travel: f64,
+    overshoot: bool,
+    easing: Easing,
+}
+
+impl Normalizer {
+    /// Creates a new `Normalizer`, for which `full_travel_mm` millimeters corresponds to a progress
+    /// of `1.0`.
+    ///
+    /// By default, progress is linear and it is clamped to be between zero and one.
+    pub fn new(full_travel_mm: f64) -> Normalizer {
+        assert!(full_travel_mm > 0.0);
+        Normalizer {
+            full_travel: full_travel_mm,
+            overshoot: false,
+            easing: Easing::Linear,
+        }
+    }
+
+    /// Sets whether progress values are allowed to go below zero or above one.
+    ///
+    /// The easing curve only applies between zero and one; outside of that, progress is linear.
+    pub fn overshoot(self, allow: bool) -> Normalizer {
+        Normalizer {
+            overshoot: allow,
+            ..self
+        }
+    }
+
+    /// Sets the easing curve.
+    pub fn easing(self, easing: Easing) -> Normalizer {
+        Normalizer {
+            easing: easing,
+            ..self
+        }
+    }
+
+    /// Converts a distance in millimeters to a progress value.
+    pub fn normalize(&self, distance_mm: f64) -> f64 {
+        let t = distance_mm / self.full_travel;
+        if t < 0.0 {
+            if self.overshoot { t } else { 0.0 }
+        } else if t > 1.0 {
+            if self.overshoot { t } else { 1.0 }
+        } else {
+            self.easing.apply(t)
+        }
+    }
+}
@@ -1,4 +1,7 @@
+use classify::Kinds;
 use frame::Frame;
+use graph::Graph;
+use kinetic::Fling;
 use std::fmt::Debug;
 use std;
 
@@ -11,23 +14,140 @@ pub enum RecResult<T> {
     Succeeded(T),
     /// The gesture was not recognized.
     Failed,
+    /// The gesture isn't finished, but here is an update on how it's going.
+    ///
+    /// This is only produced by progressive recognizers (like a drag, which reports how far the
+    /// fingers have moved while they are still moving). Otherwise, it means the same thing as
+    /// `Continuing`.
+    Progressing(T),
 }
 
 impl<T> RecResult<T> {
     /// Changes the output of a `RecResult` by applying a function to it.
+    ///
+    /// The function is applied to both final outcomes and progress updates.
     pub fn map<U, F: FnMut(T) -> U>(self, mut f: F) -> RecResult<U> {
 		self.and_then(|x| RecResult::Succeeded(f(x)))
     }
 
+    /// Changes a `RecResult` by applying a function to its output.
+    ///
+    /// If `self` is a progress update, the result of `f` is also treated as a progress update:
+    /// a success becomes `Progressing`, while a failure just drops the update, and becomes
+    /// `Continuing`.
 	pub fn and_then<U, F: FnMut(T) -> RecResult<U>>(self, mut f: F) -> RecResult<U> {
         match self {
             RecResult::Continuing => RecResult::Continuing,
             RecResult::Failed => RecResult::Failed,
             RecResult::Succeeded(t) => f(t),
+            RecResult::Progressing(t) => match f(t) {
+                RecResult::Succeeded(u) | RecResult::Progressing(u) => RecResult::Progressing(u),
+                RecResult::Continuing | RecResult::Failed => RecResult::Continuing,
+            },
         }
 	}
 }
 
+/// A value that is one of two possible types.
+///
+/// This is the output of recognizers that can recognize one of two different gestures, like the
+/// one created by [race](trait.Recognizer.html#method.race).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+impl<L, R> Either<L, R> {
+    /// Applies `f` to a `Left` value, and `g` to a `Right` one.
+    pub fn either<T, F: FnOnce(L) -> T, G: FnOnce(R) -> T>(self, f: F, g: G) -> T {
+        match self {
+            Either::Left(l) => f(l),
+            Either::Right(r) => g(r),
+        }
+    }
+
+    /// Changes a `Left` value by applying a function to it.
+    pub fn map_left<M, F: FnOnce(L) -> M>(self, f: F) -> Either<M, R> {
+        self.either(|l| Either::Left(f(l)), Either::Right)
+    }
+
+    /// Changes a `Right` value by applying a function to it.
+    pub fn map_right<S, F: FnOnce(R) -> S>(self, f: F) -> Either<L, S> {
+        self.either(Either::Left, |r| Either::Right(f(r)))
+    }
+
+    /// Converts either kind of value into a common type.
+    ///
+    /// # Examples
+    /// ```
+    /// use libgestures::Either;
+    ///
+    /// let x: Either<u8, u16> = Either::Left(3);
+    /// assert_eq!(x.into_inner::<u32>(), 3);
+    /// ```
+    pub fn into_inner<T>(self) -> T where L: Into<T>, R: Into<T> {
+        self.either(Into::into, Into::into)
+    }
+}
+
+/// Defines an enum for the outcomes of several different recognizers.
+///
+/// Each variant wraps the output type of one kind of recognizer, and the enum gets a `From`
+/// implementation for each of them. Then recognizers with different output types can be turned
+/// into recognizers for the enum with [`into_outcome`](trait.Recognizer.html#method.into_outcome),
+/// and put in the same [`Manager`](manager/struct.Manager.html). Since the conversions are
+/// chosen by type, each variant must wrap a different type.
+///
+/// # Examples
+/// ```
+/// #[macro_use] extern crate libgestures;
+///
+/// use libgestures::Recognizer;
+/// use libgestures::geom::Direction;
+/// use libgestures::gestures::compound::{direction_swipe, pinch_then_swipe};
+/// use libgestures::manager::Manager;
+///
+/// outcome_enum! {
+///     #[derive(Debug)]
+///     pub enum Outcome {
+///         Swipe(Direction),
+///         PinchThenSwipe((f64, Direction)),
+///     }
+/// }
+///
+/// fn main() {
+///     let mut man = Manager::<Outcome>::new();
+///     man.push(direction_swipe(3).into_outcome());
+///     man.push(pinch_then_swipe(2).into_outcome());
+/// }
+/// ```
+#[macro_export]
+macro_rules! outcome_enum {
+    ($(#[$attr:meta])* pub enum $name:ident { $($variant:ident($ty:ty)),* $(,)* }) => {
+        $(#[$attr])*
+        pub enum $name {
+            $($variant($ty)),*
+        }
+
+        $(
+            impl From<$ty> for $name {
+                fn from(x: $ty) -> $name {
+                    $name::$variant(x)
+                }
+            }
+        )*
+    };
+}
+
+fn tuple_first<A, B>((a, _): (A, B)) -> A {
+    a
+}
+
+fn tuple_second<A, B>((_, b): (A, B)) -> B {
+    b
+}
+
 /// A `Recognizer` is the main trait involved in recognizing gestures.
 ///
 /// TODO: more documentation, and examples
@@ -44,6 +164,39 @@ pub trait Recognizer: Debug {
     /// recognizing a gesture.
     fn update(&mut self, frame: &Frame) -> RecResult<Self::Out>;
 
+    /// Adds a description of this `Recognizer` to a graph, and returns the index of the node that
+    /// represents it.
+    ///
+    /// The default implementation adds a single node, labelled with the `Debug` representation of
+    /// `self`. Recognizers that are built out of other recognizers should override this to add a
+    /// node for themselves, with edges to the nodes for their parts.
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        g.node(&format!("{:?}", self))
+    }
+
+    /// Does this `Recognizer` need to see frames in which no fingers are down and none went up or
+    /// came down?
+    ///
+    /// Most recognizers are waiting for the fingers to do something, and such a frame can't change
+    /// their minds. If this returns false, the [`Manager`](manager/struct.Manager.html) may skip
+    /// updating this `Recognizer` on those frames. The default implementation returns true, which
+    /// is always safe. Recognizers that are built out of other recognizers should ask whichever
+    /// parts are currently running.
+    fn wants_frames_while_idle(&self) -> bool {
+        true
+    }
+
+    /// Roughly how far along is the gesture, as a number between 0 and 1?
+    ///
+    /// This is only a hint, for things like starting an animation before the gesture is
+    /// recognized; there is no promise that the gesture will succeed when it reaches 1. The
+    /// default implementation returns 0. Recognizers that wait for the fingers to move some
+    /// distance, or for some time to pass, should override it. Recognizers that are built out of
+    /// other recognizers should combine the progress of their parts.
+    fn progress(&self) -> f64 {
+        0.0
+    }
+
     /// Takes a closure and returns a `Recognizer` that recognizes exactly the same gesture as this
     /// one, but has a different output type.
     fn flat_map_outcome<U, F>(self, f: F) -> FlatMapOutcome<Self, F>
@@ -71,7 +224,39 @@ pub trait Recognizer: Debug {
 		}
 	}
 
-	fn split_input<A, B, F: FnMut(A) -> (B, Self::In)>(self, f: F) -> SplitInput<Self, A, B, F> where Self: Sized {
+    /// Returns a `Recognizer` that converts this one's outcome into `U`, for example to put it in
+    /// the same [`Manager`](manager/struct.Manager.html) as recognizers with other outcomes (see
+    /// [`outcome_enum!`](macro.outcome_enum.html)).
+    fn into_outcome<U>(self) -> MapOutcome<Self, fn(Self::Out) -> U>
+    where
+    Self: Sized,
+    Self::Out: Into<U>,
+    {
+        self.map_outcome(Into::into as fn(Self::Out) -> U)
+    }
+
+    /// For a `Recognizer` whose outcome is a pair, returns one that keeps only the first part.
+    fn first<A, B>(self) -> MapOutcome<Self, fn((A, B)) -> A>
+    where
+    Self: Sized + Recognizer<Out=(A, B)>,
+    {
+        self.map_outcome(tuple_first as fn((A, B)) -> A)
+    }
+
+    /// For a `Recognizer` whose outcome is a pair, returns one that keeps only the second part.
+    fn second<A, B>(self) -> MapOutcome<Self, fn((A, B)) -> B>
+    where
+    Self: Sized + Recognizer<Out=(A, B)>,
+    {
+        self.map_outcome(tuple_second as fn((A, B)) -> B)
+    }
+
+	/// Returns a `Recognizer` that takes a different input type, and splits it in two.
+	///
+	/// The closure `f` splits the new input into a part `B` that is held back and a part that is
+	/// passed on to this `Recognizer`. When this `Recognizer` succeeds, the held-back part is
+	/// returned alongside its output.
+	fn split_input<A, B: Clone, F: FnMut(A) -> (B, Self::In)>(self, f: F) -> SplitInput<Self, A, B, F> where Self: Sized {
 		SplitInput {
 			rec: self,
 			f: f,
@@ -90,12 +275,142 @@ pub trait Recognizer: Debug {
         Composition::new(self, other)
     }
 
+    /// Composes this `Recognizer` with another one that doesn't need any input, to create a
+    /// `Recognizer` that recognizes our gesture first and then the other one.
+    ///
+    /// Unlike [`and_then`](#method.and_then), the output of the first `Recognizer` is not consumed
+    /// by the second one. Instead, it is returned alongside the output of the second one. This is
+    /// handy for carrying information across a stage that doesn't care about it, like waiting for
+    /// the fingers to go up at the end of a swipe.
+    fn then_with<U, R: Recognizer<In=(), Out=U>>(self, other: R)
+    -> ThenWith<Self, R, Self::Out> where Self: Sized, Self::Out: Clone {
+        ThenWith::new(self, other)
+    }
+
+    /// Composes this `Recognizer` with another one that is optional.
+    ///
+    /// This is like [`and_then`](#method.and_then), except that if the second `Recognizer` fails,
+    /// the composition still succeeds. The output is the output of `self`, together with the output
+    /// of `other` if it succeeded, or `None` if it failed.
+    fn and_maybe_then<U, R: Recognizer<In=Self::Out, Out=U>>(self, other: R)
+    -> MaybeThen<Self, R, Self::Out> where Self: Sized, Self::Out: Clone {
+        MaybeThen::new(self, other)
+    }
+
+    /// Runs this `Recognizer` and another one side by side, on the same input.
+    ///
+    /// The resulting `Recognizer` succeeds as soon as either of the two succeeds, returning
+    /// `Either::Left` with our output or `Either::Right` with the other one's. If one of them
+    /// fails, the other one keeps going; the race only fails when both have failed. If both
+    /// succeed on the same frame, `self` wins.
+    fn race<R: Recognizer<In=Self::In>>(self, other: R)
+    -> Race<Self, R> where Self: Sized, Self::In: Clone {
+        Race::new(self, other)
+    }
+
+    /// Composes this `Recognizer` with several alternative continuations.
+    ///
+    /// This is like calling [`and_then`](#method.and_then) once for each element of `branches`,
+    /// except that `self` is only run once: when it succeeds, all of the branches are initialized
+    /// with (a copy of) its output and they proceed side by side. The first branch to succeed
+    /// wins, and the whole thing fails once every branch has failed.
+    ///
+    /// This is useful for gestures that share a common prefix (for example, everything that starts
+    /// with three fingers coming down), because the prefix only needs to be evaluated once per
+    /// frame.
+    fn branch<U>(self, branches: Vec<Box<Recognizer<In=Self::Out, Out=U>>>)
+    -> Branch<Self, Self::Out, U> where Self: Sized, Self::Out: Clone {
+        Branch::new(self, branches)
+    }
+
+    /// Returns a `Recognizer` that recognizes this gesture over and over, without the fingers
+    /// lifting in between, and collects the outputs.
+    ///
+    /// Each output says whether it was the last one: while it's `(x, false)`, this `Recognizer` is
+    /// initialized again on the same frame to recognize the next one. The whole thing succeeds
+    /// with all the outputs once one of them is `(x, true)`, and fails if any of them fails, or if
+    /// there would be more than `max` of them.
+    ///
+    /// # Panics
+    /// if `max` is zero.
+    fn repeat<T>(self, max: usize) -> Repeat<Self, T>
+    where Self: Sized + Recognizer<In=(), Out=(T, bool)> {
+        Repeat::new(self, max)
+    }
+
+    /// Wraps a progressive `Recognizer` that reports distances, so that when it finishes it also
+    /// returns a model of how it should keep moving afterwards.
+    ///
+    /// `friction` controls how quickly the motion decays; see
+    /// [`Kinetic`](kinetic/struct.Kinetic.html) for details.
+    fn fling(self, friction: f64) -> Fling<Self> where Self: Sized + Recognizer<Out=f64> {
+        Fling::new(self, friction)
+    }
+
+    /// Wraps a progressive `Recognizer` whose outputs come with a progress value (like the ones
+    /// from [`progressive_swipe`](gestures/compound/fn.progressive_swipe.html)), so that the
+    /// progress value becomes its [`progress`](#method.progress) instead.
+    ///
+    /// The [`Manager`](manager/struct.Manager.html) only looks at the `progress` of the
+    /// recognizers that it runs, so this needs to be the outermost part of the recognizer.
+    fn report_progress<T>(self) -> ReportProgress<Self>
+    where Self: Sized + Recognizer<Out=(T, f64)> {
+        ReportProgress { rec: self, progress: 0.0 }
+    }
+
     /// Takes a `Filter` and returns a new `Recognizer` that succeeds if and only if the original
     /// `Recognizer` recognized something *and* the `Filter` didn't abort.
     fn constrain<F: Filter>(self, f: F)
     -> Constraint<Self, F> where Self: Sized {
         Constraint { rec: self, fil: f }
     }
+
+    /// Returns a `Recognizer` that only counts the contacts whose kinds are in `kinds`, and
+    /// ignores all the others.
+    ///
+    /// The contacts are labelled by the [`Classifier`](classify/trait.Classifier.html)s that were
+    /// added to the `Manager`. Without any, every contact is `Unknown`, so it's usually best to
+    /// count those too.
+    ///
+    /// # Examples
+    /// ```
+    /// use libgestures::Recognizer;
+    /// use libgestures::classify::{ContactKind, Kinds};
+    /// use libgestures::gestures::primitive::NFingers;
+    ///
+    /// let kinds = Kinds::of(&[ContactKind::Finger, ContactKind::Unknown]);
+    /// let rec = NFingers::new(2).counting(kinds);
+    /// ```
+    fn counting(self, kinds: Kinds) -> Counting<Self> where Self: Sized {
+        Counting { rec: self, kinds: kinds }
+    }
+}
+
+/// A boxed `Recognizer` is a `Recognizer` too, so that recognizers that are chosen at runtime can
+/// still be combined with the methods above.
+impl<R: Recognizer + ?Sized> Recognizer for Box<R> {
+    type In = R::In;
+    type Out = R::Out;
+
+    fn init(&mut self, input: Self::In, frame: &Frame) {
+        (**self).init(input, frame);
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<Self::Out> {
+        (**self).update(frame)
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        (**self).to_graph(g)
+    }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        (**self).wants_frames_while_idle()
+    }
+
+    fn progress(&self) -> f64 {
+        (**self).progress()
+    }
 }
 
 /// A recognizer that maps the output value by applying a function.
@@ -129,6 +444,21 @@ F: FnMut(T) -> RecResult<Out>,
     fn update(&mut self, frame: &Frame) -> RecResult<Self::Out> {
         self.rec.update(frame).and_then(&mut self.f)
     }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        self.rec.wants_frames_while_idle()
+    }
+
+    fn progress(&self) -> f64 {
+        self.rec.progress()
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        let n = g.node("FlatMapOutcome");
+        let r = self.rec.to_graph(g);
+        g.edge(n, r, "");
+        n
+    }
 }
 
 // TODO: This should really reuse code from FlatMapOutcome. The problem is that
@@ -161,6 +491,21 @@ F: FnMut(T) -> Out,
     fn update(&mut self, frame: &Frame) -> RecResult<Self::Out> {
         self.rec.update(frame).map(&mut self.f)
     }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        self.rec.wants_frames_while_idle()
+    }
+
+    fn progress(&self) -> f64 {
+        self.rec.progress()
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        let n = g.node("MapOutcome");
+        let r = self.rec.to_graph(g);
+        g.edge(n, r, "");
+        n
+    }
 }
 
 #[derive(Clone)]
@@ -196,8 +541,33 @@ F: FnMut(&Out) -> bool,
 			}
 		})
     }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        self.rec.wants_frames_while_idle()
+    }
+
+    fn progress(&self) -> f64 {
+        self.rec.progress()
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        let n = g.node("FilterOutcome");
+        let r = self.rec.to_graph(g);
+        g.edge(n, r, "");
+        n
+    }
 }
 
+/// A recognizer that splits its input in two, passing one part to the wrapped recognizer and
+/// attaching the other part to the wrapped recognizer's output.
+///
+/// This struct is usually created by the
+/// [split_input](trait.Recognizer.html#method.split_input) method on
+/// [Recognizer](trait.Recognizer.html). See that method for more.
+///
+/// The part of the input that is held back is cloned into every outcome, so it is fine for the
+/// wrapped recognizer to succeed more than once after a single call to `init`. If `update` is
+/// called before `init`, there is nothing to attach and the recognizer fails.
 #[derive(Clone)]
 pub struct SplitInput<Rec, A, B, F> {
     rec: Rec,
@@ -215,6 +585,7 @@ impl<Rec: Debug, A, B, F> Debug for SplitInput<Rec, A, B, F> {
 impl<Rec, A, B, F> Recognizer for SplitInput<Rec, A, B, F>
 where
 Rec: Recognizer,
+B: Clone,
 F: FnMut(A) -> (B, Rec::In),
 {
 	type In = A;
@@ -227,8 +598,29 @@ F: FnMut(A) -> (B, Rec::In),
 	}
 
 	fn update(&mut self, frame: &Frame) -> RecResult<Self::Out> {
-		self.rec.update(frame)
-			.map(|x| (self.cached.take().unwrap(), x))
+		let res = self.rec.update(frame);
+		match self.cached {
+			Some(ref cached) => res.map(|x| (cached.clone(), x)),
+			None => {
+				debug!("SplitInput updated before being initialized");
+				RecResult::Failed
+			}
+		}
+	}
+
+	fn wants_frames_while_idle(&self) -> bool {
+		self.rec.wants_frames_while_idle()
+	}
+
+	fn progress(&self) -> f64 {
+		self.rec.progress()
+	}
+
+	fn to_graph(&self, g: &mut Graph) -> usize {
+		let n = g.node("SplitInput");
+		let r = self.rec.to_graph(g);
+		g.edge(n, r, "");
+		n
 	}
 }
 
@@ -268,7 +660,7 @@ impl<T, Rec1: Recognizer<Out=T>, Rec2: Recognizer<In=T>> Recognizer for Composit
         } else {
             match self.rec1.update(frame) {
                 RecResult::Failed => RecResult::Failed,
-                RecResult::Continuing => RecResult::Continuing,
+                RecResult::Continuing | RecResult::Progressing(_) => RecResult::Continuing,
                 RecResult::Succeeded(x) => {
                     self.on_rec2 = true;
                     self.rec2.init(x, frame);
@@ -277,6 +669,604 @@ impl<T, Rec1: Recognizer<Out=T>, Rec2: Recognizer<In=T>> Recognizer for Composit
             }
         }
     }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        if self.on_rec2 {
+            self.rec2.wants_frames_while_idle()
+        } else {
+            self.rec1.wants_frames_while_idle()
+        }
+    }
+
+    fn progress(&self) -> f64 {
+        if self.on_rec2 {
+            0.5 + self.rec2.progress() / 2.0
+        } else {
+            self.rec1.progress() / 2.0
+        }
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        let n = g.node("Composition");
+        let r1 = self.rec1.to_graph(g);
+        let r2 = self.rec2.to_graph(g);
+        g.edge(n, r1, "first");
+        g.edge(n, r2, "then");
+        n
+    }
+}
+
+/// A recognizer that recognizes one gesture and then another, returning both outputs.
+///
+/// This struct is usually created by the [then_with](trait.Recognizer.html#method.then_with)
+/// method on [Recognizer](trait.Recognizer.html). See that method for more.
+#[derive(Clone, Debug)]
+pub struct ThenWith<Rec1, Rec2, T> {
+    rec1: Rec1,
+    rec2: Rec2,
+    first: Option<T>,
+}
+
+impl<T: Clone, Rec1: Recognizer<Out=T>, Rec2: Recognizer<In=()>> ThenWith<Rec1, Rec2, T> {
+    fn new(rec1: Rec1, rec2: Rec2) -> ThenWith<Rec1, Rec2, T> {
+        ThenWith {
+            rec1: rec1,
+            rec2: rec2,
+            first: None,
+        }
+    }
+}
+
+impl<T, Rec1, Rec2> Recognizer for ThenWith<Rec1, Rec2, T>
+where
+T: Clone,
+Rec1: Recognizer<Out=T>,
+Rec2: Recognizer<In=()>,
+{
+    type In = Rec1::In;
+    type Out = (T, Rec2::Out);
+
+    fn init(&mut self, input: Self::In, frame: &Frame) {
+        self.rec1.init(input, frame);
+        self.first = None;
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<Self::Out> {
+        if let Some(ref first) = self.first {
+            return self.rec2.update(frame).map(|x| (first.clone(), x));
+        }
+
+        match self.rec1.update(frame) {
+            RecResult::Failed => RecResult::Failed,
+            RecResult::Continuing | RecResult::Progressing(_) => RecResult::Continuing,
+            RecResult::Succeeded(x) => {
+                self.rec2.init((), frame);
+                self.first = Some(x.clone());
+                // Once the last finger has lifted, there might not be another frame until the
+                // next touch, so this is the second recognizer's only chance (to see that the
+                // fingers are all up, for example).
+                if frame.cur.num_down == 0 {
+                    self.rec2.update(frame).map(|y| (x, y))
+                } else {
+                    RecResult::Continuing
+                }
+            }
+        }
+    }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        if self.first.is_some() {
+            self.rec2.wants_frames_while_idle()
+        } else {
+            self.rec1.wants_frames_while_idle()
+        }
+    }
+
+    fn progress(&self) -> f64 {
+        if self.first.is_some() {
+            0.5 + self.rec2.progress() / 2.0
+        } else {
+            self.rec1.progress() / 2.0
+        }
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        let n = g.node("ThenWith");
+        let r1 = self.rec1.to_graph(g);
+        let r2 = self.rec2.to_graph(g);
+        g.edge(n, r1, "first");
+        g.edge(n, r2, "then");
+        n
+    }
+}
+
+/// A recognizer that recognizes one gesture and then, optionally, another.
+///
+/// This struct is usually created by the
+/// [and_maybe_then](trait.Recognizer.html#method.and_maybe_then) method on
+/// [Recognizer](trait.Recognizer.html). See that method for more.
+#[derive(Clone, Debug)]
+pub struct MaybeThen<Rec1, Rec2, T> {
+    rec1: Rec1,
+    rec2: Rec2,
+    first: Option<T>,
+}
+
+impl<T: Clone, Rec1: Recognizer<Out=T>, Rec2: Recognizer<In=T>> MaybeThen<Rec1, Rec2, T> {
+    fn new(rec1: Rec1, rec2: Rec2) -> MaybeThen<Rec1, Rec2, T> {
+        MaybeThen {
+            rec1: rec1,
+            rec2: rec2,
+            first: None,
+        }
+    }
+}
+
+impl<T, Rec1, Rec2> Recognizer for MaybeThen<Rec1, Rec2, T>
+where
+T: Clone,
+Rec1: Recognizer<Out=T>,
+Rec2: Recognizer<In=T>,
+{
+    type In = Rec1::In;
+    type Out = (T, Option<Rec2::Out>);
+
+    fn init(&mut self, input: Self::In, frame: &Frame) {
+        self.rec1.init(input, frame);
+        self.first = None;
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<Self::Out> {
+        if let Some(ref first) = self.first {
+            return match self.rec2.update(frame) {
+                RecResult::Continuing => RecResult::Continuing,
+                RecResult::Failed => RecResult::Succeeded((first.clone(), None)),
+                RecResult::Succeeded(x) => RecResult::Succeeded((first.clone(), Some(x))),
+                RecResult::Progressing(x) => RecResult::Progressing((first.clone(), Some(x))),
+            };
+        }
+
+        match self.rec1.update(frame) {
+            RecResult::Failed => RecResult::Failed,
+            RecResult::Continuing | RecResult::Progressing(_) => RecResult::Continuing,
+            RecResult::Succeeded(x) => {
+                self.rec2.init(x.clone(), frame);
+                self.first = Some(x);
+                RecResult::Continuing
+            }
+        }
+    }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        if self.first.is_some() {
+            self.rec2.wants_frames_while_idle()
+        } else {
+            self.rec1.wants_frames_while_idle()
+        }
+    }
+
+    fn progress(&self) -> f64 {
+        if self.first.is_some() {
+            0.5 + self.rec2.progress() / 2.0
+        } else {
+            self.rec1.progress() / 2.0
+        }
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        let n = g.node("MaybeThen");
+        let r1 = self.rec1.to_graph(g);
+        let r2 = self.rec2.to_graph(g);
+        g.edge(n, r1, "first");
+        g.edge(n, r2, "maybe then");
+        n
+    }
+}
+
+/// A recognizer that runs two recognizers side by side and returns whichever succeeds first.
+///
+/// This struct is usually created by the [race](trait.Recognizer.html#method.race) method on
+/// [Recognizer](trait.Recognizer.html). See that method for more.
+#[derive(Clone, Debug)]
+pub struct Race<Rec1, Rec2> {
+    rec1: Rec1,
+    rec2: Rec2,
+    failed1: bool,
+    failed2: bool,
+}
+
+impl<Rec1: Recognizer, Rec2: Recognizer<In=Rec1::In>> Race<Rec1, Rec2> {
+    fn new(rec1: Rec1, rec2: Rec2) -> Race<Rec1, Rec2> {
+        Race {
+            rec1: rec1,
+            rec2: rec2,
+            failed1: false,
+            failed2: false,
+        }
+    }
+}
+
+impl<Rec1, Rec2> Recognizer for Race<Rec1, Rec2>
+where
+Rec1: Recognizer,
+Rec1::In: Clone,
+Rec2: Recognizer<In=Rec1::In>,
+{
+    type In = Rec1::In;
+    type Out = Either<Rec1::Out, Rec2::Out>;
+
+    fn init(&mut self, input: Self::In, frame: &Frame) {
+        self.rec1.init(input.clone(), frame);
+        self.rec2.init(input, frame);
+        self.failed1 = false;
+        self.failed2 = false;
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<Self::Out> {
+        let mut progress = None;
+        if !self.failed1 {
+            match self.rec1.update(frame) {
+                RecResult::Succeeded(x) => return RecResult::Succeeded(Either::Left(x)),
+                RecResult::Failed => self.failed1 = true,
+                RecResult::Continuing => {},
+                RecResult::Progressing(x) => progress = Some(Either::Left(x)),
+            }
+        }
+        if !self.failed2 {
+            match self.rec2.update(frame) {
+                RecResult::Succeeded(x) => return RecResult::Succeeded(Either::Right(x)),
+                RecResult::Failed => self.failed2 = true,
+                RecResult::Continuing => {},
+                RecResult::Progressing(x) => if progress.is_none() {
+                    progress = Some(Either::Right(x));
+                },
+            }
+        }
+
+        if let Some(p) = progress {
+            RecResult::Progressing(p)
+        } else if self.failed1 && self.failed2 {
+            RecResult::Failed
+        } else {
+            RecResult::Continuing
+        }
+    }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        (!self.failed1 && self.rec1.wants_frames_while_idle())
+            || (!self.failed2 && self.rec2.wants_frames_while_idle())
+    }
+
+    fn progress(&self) -> f64 {
+        let p1 = if self.failed1 { 0.0 } else { self.rec1.progress() };
+        let p2 = if self.failed2 { 0.0 } else { self.rec2.progress() };
+        p1.max(p2)
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        let n = g.node("Race");
+        let r1 = self.rec1.to_graph(g);
+        let r2 = self.rec2.to_graph(g);
+        g.edge(n, r1, "left");
+        g.edge(n, r2, "right");
+        n
+    }
+}
+
+/// A recognizer that recognizes a common prefix, followed by one of several continuations.
+///
+/// This struct is usually created by the [branch](trait.Recognizer.html#method.branch) method on
+/// [Recognizer](trait.Recognizer.html). See that method for more.
+pub struct Branch<Rec, T, U> {
+    prefix: Rec,
+    branches: Vec<Box<Recognizer<In=T, Out=U>>>,
+    live: Vec<bool>,
+    on_branches: bool,
+}
+
+impl<Rec: Debug, T, U> Debug for Branch<Rec, T, U> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "Branch<{:?}, {:?}>", self.prefix, self.branches)
+    }
+}
+
+impl<T: Clone, U, Rec: Recognizer<Out=T>> Branch<Rec, T, U> {
+    fn new(prefix: Rec, branches: Vec<Box<Recognizer<In=T, Out=U>>>) -> Branch<Rec, T, U> {
+        let n = branches.len();
+        Branch {
+            prefix: prefix,
+            branches: branches,
+            live: vec![false; n],
+            on_branches: false,
+        }
+    }
+}
+
+impl<T, U, Rec> Recognizer for Branch<Rec, T, U>
+where
+T: Clone,
+Rec: Recognizer<Out=T>,
+{
+    type In = Rec::In;
+    type Out = U;
+
+    fn init(&mut self, input: Self::In, frame: &Frame) {
+        self.prefix.init(input, frame);
+        self.on_branches = false;
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<U> {
+        if !self.on_branches {
+            return match self.prefix.update(frame) {
+                RecResult::Failed => RecResult::Failed,
+                RecResult::Continuing | RecResult::Progressing(_) => RecResult::Continuing,
+                RecResult::Succeeded(x) => {
+                    self.on_branches = true;
+                    for (b, live) in self.branches.iter_mut().zip(self.live.iter_mut()) {
+                        b.init(x.clone(), frame);
+                        *live = true;
+                    }
+                    RecResult::Continuing
+                }
+            };
+        }
+
+        let mut progress = None;
+        for (b, live) in self.branches.iter_mut().zip(self.live.iter_mut()) {
+            if *live {
+                match b.update(frame) {
+                    RecResult::Succeeded(x) => return RecResult::Succeeded(x),
+                    RecResult::Failed => *live = false,
+                    RecResult::Continuing => {},
+                    RecResult::Progressing(x) => if progress.is_none() {
+                        progress = Some(x);
+                    },
+                }
+            }
+        }
+
+        if let Some(p) = progress {
+            RecResult::Progressing(p)
+        } else if self.live.iter().any(|&l| l) {
+            RecResult::Continuing
+        } else {
+            RecResult::Failed
+        }
+    }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        if self.on_branches {
+            self.branches.iter().zip(&self.live).any(|(b, &l)| l && b.wants_frames_while_idle())
+        } else {
+            self.prefix.wants_frames_while_idle()
+        }
+    }
+
+    fn progress(&self) -> f64 {
+        if self.on_branches {
+            let best = self.branches.iter().zip(&self.live)
+                .filter(|&(_, &l)| l)
+                .map(|(b, _)| b.progress())
+                .fold(0.0, f64::max);
+            0.5 + best / 2.0
+        } else {
+            self.prefix.progress() / 2.0
+        }
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        let n = g.node("Branch");
+        let p = self.prefix.to_graph(g);
+        g.edge(n, p, "prefix");
+        for (i, b) in self.branches.iter().enumerate() {
+            let b = b.to_graph(g);
+            g.edge(n, b, &format!("branch {}", i));
+        }
+        n
+    }
+}
+
+/// A recognizer that recognizes several gestures, one after another.
+///
+/// Each stage is initialized on the frame where the previous one succeeded, and the whole sequence
+/// succeeds when the last stage does, returning the outputs of all the stages in order. If any
+/// stage fails, so does the sequence.
+///
+/// This is like chaining the stages with [`then_with`](trait.Recognizer.html#method.then_with),
+/// except that the number of stages doesn't need to be known at compile time.
+pub struct Sequence<T> {
+    stages: Vec<Box<Recognizer<In=(), Out=T>>>,
+    current: usize,
+    outcomes: Vec<T>,
+}
+
+impl<T> Debug for Sequence<T> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "Sequence<{:?}>", self.stages)
+    }
+}
+
+impl<T> Sequence<T> {
+    /// Creates a new `Sequence` out of some stages.
+    ///
+    /// # Panics
+    /// if there are no stages.
+    pub fn new(stages: Vec<Box<Recognizer<In=(), Out=T>>>) -> Sequence<T> {
+        assert!(!stages.is_empty());
+        Sequence {
+            stages: stages,
+            current: 0,
+            outcomes: Vec::new(),
+        }
+    }
+}
+
+impl<T> Recognizer for Sequence<T> {
+    type In = ();
+    type Out = Vec<T>;
+
+    fn init(&mut self, _: (), frame: &Frame) {
+        self.current = 0;
+        self.outcomes.clear();
+        self.stages[0].init((), frame);
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<Vec<T>> {
+        if self.current >= self.stages.len() {
+            return RecResult::Failed;
+        }
+
+        match self.stages[self.current].update(frame) {
+            RecResult::Failed => RecResult::Failed,
+            RecResult::Continuing | RecResult::Progressing(_) => RecResult::Continuing,
+            RecResult::Succeeded(x) => {
+                self.outcomes.push(x);
+                self.current += 1;
+                if self.current == self.stages.len() {
+                    RecResult::Succeeded(std::mem::replace(&mut self.outcomes, Vec::new()))
+                } else {
+                    self.stages[self.current].init((), frame);
+                    RecResult::Continuing
+                }
+            }
+        }
+    }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        self.stages.get(self.current).map_or(true, |s| s.wants_frames_while_idle())
+    }
+
+    fn progress(&self) -> f64 {
+        let stage = self.stages.get(self.current).map_or(1.0, |s| s.progress());
+        (self.current as f64 + stage) / self.stages.len() as f64
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        let n = g.node("Sequence");
+        for (i, s) in self.stages.iter().enumerate() {
+            let s = s.to_graph(g);
+            g.edge(n, s, &format!("step {}", i));
+        }
+        n
+    }
+}
+
+/// A recognizer that recognizes the same gesture several times in a row.
+///
+/// This struct is created by the [`repeat`](trait.Recognizer.html#method.repeat) method of
+/// `Recognizer`. See that method for more.
+#[derive(Clone)]
+pub struct Repeat<Rec, T> {
+    rec: Rec,
+    max: usize,
+    outcomes: Vec<T>,
+}
+
+impl<Rec: Debug, T> Debug for Repeat<Rec, T> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "Repeat<{:?}, {}>", self.rec, self.max)
+    }
+}
+
+impl<Rec, T> Repeat<Rec, T> {
+    fn new(rec: Rec, max: usize) -> Repeat<Rec, T> {
+        assert!(max > 0);
+        Repeat {
+            rec: rec,
+            max: max,
+            outcomes: Vec::new(),
+        }
+    }
+}
+
+impl<Rec, T> Recognizer for Repeat<Rec, T>
+where
+Rec: Recognizer<In=(), Out=(T, bool)>,
+{
+    type In = ();
+    type Out = Vec<T>;
+
+    fn init(&mut self, _: (), frame: &Frame) {
+        self.outcomes.clear();
+        self.rec.init((), frame);
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<Vec<T>> {
+        match self.rec.update(frame) {
+            RecResult::Failed => RecResult::Failed,
+            RecResult::Continuing | RecResult::Progressing(_) => RecResult::Continuing,
+            RecResult::Succeeded((x, last)) => {
+                self.outcomes.push(x);
+                if last {
+                    RecResult::Succeeded(std::mem::replace(&mut self.outcomes, Vec::new()))
+                } else if self.outcomes.len() == self.max {
+                    debug!("Repeat failed: more than {} repetitions", self.max);
+                    RecResult::Failed
+                } else {
+                    self.rec.init((), frame);
+                    RecResult::Continuing
+                }
+            }
+        }
+    }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        self.rec.wants_frames_while_idle()
+    }
+
+    fn progress(&self) -> f64 {
+        self.rec.progress()
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        let n = g.node(&format!("Repeat (at most {})", self.max));
+        let r = self.rec.to_graph(g);
+        g.edge(n, r, "");
+        n
+    }
+}
+
+/// A recognizer that takes its progress from the outputs of `Rec`.
+///
+/// This struct is created by the [`report_progress`](trait.Recognizer.html#method.report_progress)
+/// method of `Recognizer`.
+#[derive(Clone, Debug)]
+pub struct ReportProgress<Rec> {
+    rec: Rec,
+    progress: f64,
+}
+
+impl<Rec, T> Recognizer for ReportProgress<Rec> where Rec: Recognizer<Out=(T, f64)> {
+    type In = Rec::In;
+    type Out = T;
+
+    fn init(&mut self, input: Self::In, frame: &Frame) {
+        self.progress = 0.0;
+        self.rec.init(input, frame);
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<T> {
+        let progress = &mut self.progress;
+        self.rec.update(frame).map(|(x, p)| {
+            *progress = p;
+            x
+        })
+    }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        self.rec.wants_frames_while_idle()
+    }
+
+    fn progress(&self) -> f64 {
+        self.progress
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        let n = g.node("ReportProgress");
+        let r = self.rec.to_graph(g);
+        g.edge(n, r, "");
+        n
+    }
 }
 
 /// The result of a [Filter](trait.Filter.html).
@@ -300,6 +1290,37 @@ pub enum FilterResult {
 pub trait Filter: Debug {
     fn init(&mut self, frame: &Frame);
     fn update(&mut self, frame: &Frame) -> FilterResult;
+
+    /// Adds a description of this `Filter` to a graph, and returns the index of the node that
+    /// represents it.
+    ///
+    /// See [`Recognizer::to_graph`](trait.Recognizer.html#method.to_graph).
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        g.node(&format!("{:?}", self))
+    }
+}
+
+/// A filter that may be turned off: `None` always passes.
+impl<F: Filter> Filter for Option<F> {
+    fn init(&mut self, frame: &Frame) {
+        if let Some(ref mut f) = *self {
+            f.init(frame);
+        }
+    }
+
+    fn update(&mut self, frame: &Frame) -> FilterResult {
+        match *self {
+            Some(ref mut f) => f.update(frame),
+            None => FilterResult::Passed,
+        }
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        match *self {
+            Some(ref f) => f.to_graph(g),
+            None => g.node("No filter"),
+        }
+    }
 }
 
 /// A recognizer that recognizes the same gestures as `Rec`, but fails if `Fil` tells it to.
@@ -329,5 +1350,257 @@ impl<Rec: Recognizer, Fil: Filter> Recognizer for Constraint<Rec, Fil> {
             self.rec.update(frame)
         }
     }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        self.rec.wants_frames_while_idle()
+    }
+
+    fn progress(&self) -> f64 {
+        self.rec.progress()
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        let n = g.node("Constraint");
+        let r = self.rec.to_graph(g);
+        let f = self.fil.to_graph(g);
+        g.edge(n, r, "recognizer");
+        g.edge(n, f, "filter");
+        n
+    }
+}
+
+/// A recognizer that recognizes the same gestures as `Rec`, but only counts some kinds of
+/// contact.
+///
+/// This struct is created by the [`counting`](trait.Recognizer.html#method.counting) method of
+/// `Recognizer`.
+#[derive(Clone, Debug)]
+pub struct Counting<Rec> {
+    rec: Rec,
+    kinds: Kinds,
+}
+
+impl<Rec: Recognizer> Recognizer for Counting<Rec> {
+    type In = Rec::In;
+    type Out = Rec::Out;
+
+    fn init(&mut self, input: Self::In, frame: &Frame) {
+        self.rec.init(input, &frame.only(self.kinds));
+    }
+
+    fn update(&mut self, frame: &Frame) -> RecResult<Self::Out> {
+        self.rec.update(&frame.only(self.kinds))
+    }
+
+    fn wants_frames_while_idle(&self) -> bool {
+        self.rec.wants_frames_while_idle()
+    }
+
+    fn progress(&self) -> f64 {
+        self.rec.progress()
+    }
+
+    fn to_graph(&self, g: &mut Graph) -> usize {
+        let n = g.node(&format!("Counting({})", self.kinds));
+        let r = self.rec.to_graph(g);
+        g.edge(n, r, "");
+        n
+    }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use event::Event;
+    use frame::Frame;
+    use geom::Point;
+    use super::Sequence;
+    use {Either, Recognizer, RecResult};
+
+    /// A recognizer that succeeds on every frame.
+    #[derive(Clone, Debug)]
+    struct Always;
+
+    impl Recognizer for Always {
+        type In = ();
+        type Out = ();
+
+        fn init(&mut self, _: (), _: &Frame) {}
+
+        fn update(&mut self, _: &Frame) -> RecResult<()> {
+            RecResult::Succeeded(())
+        }
+    }
+
+    /// A recognizer that never finishes, and doesn't care about idle frames.
+    #[derive(Clone, Debug)]
+    struct Sleepy;
+
+    impl Recognizer for Sleepy {
+        type In = ();
+        type Out = ();
+
+        fn init(&mut self, _: (), _: &Frame) {}
+
+        fn update(&mut self, _: &Frame) -> RecResult<()> {
+            RecResult::Continuing
+        }
+
+        fn wants_frames_while_idle(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn idle_hint_follows_the_running_stage() {
+        let frame = Frame::new();
+        let mut rec = Always.and_then(Sleepy);
+        rec.init((), &frame);
+        assert!(rec.wants_frames_while_idle());
+        assert_eq!(rec.update(&frame), RecResult::Continuing);
+        assert!(!rec.wants_frames_while_idle());
+
+        let mut rec = Sleepy.map_outcome(|_| 1).then_with(Always);
+        rec.init((), &frame);
+        assert!(!rec.wants_frames_while_idle());
+        assert!(Sleepy.race(Always).wants_frames_while_idle());
+    }
+
+    #[test]
+    fn tuple_and_either_helpers() {
+        let frame = Frame::new();
+        let mut rec = Always.map_outcome(|_| (1, "a")).second();
+        rec.init((), &frame);
+        assert_eq!(rec.update(&frame), RecResult::Succeeded("a"));
+
+        let mut rec = Always.map_outcome(|_| 7u8).into_outcome::<u32>();
+        rec.init((), &frame);
+        assert_eq!(rec.update(&frame), RecResult::Succeeded(7u32));
+
+        let x: Either<u8, &str> = Either::Right("abc");
+        assert_eq!(x.map_right(|s| s.len()).either(|l| l as usize, |r| r), 3);
+    }
+
+    #[test]
+    fn split_input_multiple_successes() {
+        let frame = Frame::new();
+        let mut rec = Always.split_input(|x: u32| (x, ()));
+
+        rec.init(7, &frame);
+        assert_eq!(rec.update(&frame), RecResult::Succeeded((7, ())));
+        assert_eq!(rec.update(&frame), RecResult::Succeeded((7, ())));
+
+        rec.init(8, &frame);
+        assert_eq!(rec.update(&frame), RecResult::Succeeded((8, ())));
+    }
+
+    #[test]
+    fn then_with_keeps_first_output() {
+        let mut frame = Frame::new();
+        frame.update_event(&Event::Down { slot: 0, pos: Point::new(0.0, 0.0) });
+        frame.update_event(&Event::Frame { time: 0 });
+        let mut rec = Always.map_outcome(|_| 3).then_with(Always);
+
+        rec.init((), &frame);
+        assert_eq!(rec.update(&frame), RecResult::Continuing);
+        assert_eq!(rec.update(&frame), RecResult::Succeeded((3, ())));
+
+        // Without any fingers, the second recognizer runs straight away.
+        let frame = Frame::new();
+        rec.init((), &frame);
+        assert_eq!(rec.update(&frame), RecResult::Succeeded((3, ())));
+    }
+
+    #[test]
+    fn and_maybe_then_tolerates_failure() {
+        let frame = Frame::new();
+        let mut rec = Always.and_maybe_then(Always.filter_outcome(|_| false));
+
+        rec.init((), &frame);
+        assert_eq!(rec.update(&frame), RecResult::Continuing);
+        assert_eq!(rec.update(&frame), RecResult::Succeeded(((), None)));
+
+        let mut rec = Always.and_maybe_then(Always);
+        rec.init((), &frame);
+        assert_eq!(rec.update(&frame), RecResult::Continuing);
+        assert_eq!(rec.update(&frame), RecResult::Succeeded(((), Some(()))));
+    }
+
+    #[test]
+    fn race_survives_one_failure() {
+        let frame = Frame::new();
+        let mut rec = Always.filter_outcome(|_| false).race(Always.map_outcome(|_| 5));
+
+        rec.init((), &frame);
+        assert_eq!(rec.update(&frame), RecResult::Succeeded(Either::Right(5)));
+
+        let mut rec = Always.filter_outcome(|_| false).race(Always.filter_outcome(|_| false));
+        rec.init((), &frame);
+        assert_eq!(rec.update(&frame), RecResult::Failed);
+    }
+
+    #[test]
+    fn branch_first_success_wins() {
+        let frame = Frame::new();
+        let branches: Vec<Box<Recognizer<In=(), Out=u32>>> = vec![
+            Box::new(Always.filter_outcome(|_| false).map_outcome(|_| 1)),
+            Box::new(Always.map_outcome(|_| 2)),
+            Box::new(Always.map_outcome(|_| 3)),
+        ];
+        let mut rec = Always.branch(branches);
+
+        rec.init((), &frame);
+        assert_eq!(rec.update(&frame), RecResult::Continuing);
+        assert_eq!(rec.update(&frame), RecResult::Succeeded(2));
+    }
+
+    #[test]
+    fn sequence_runs_each_stage_in_turn() {
+        let frame = Frame::new();
+        let stages: Vec<Box<Recognizer<In=(), Out=u32>>> = vec![
+            Box::new(Always.map_outcome(|_| 1)),
+            Box::new(Always.map_outcome(|_| 2)),
+        ];
+        let mut rec = Sequence::new(stages);
+
+        rec.init((), &frame);
+        assert_eq!(rec.update(&frame), RecResult::Continuing);
+        assert_eq!(rec.update(&frame), RecResult::Succeeded(vec![1, 2]));
+
+        rec.init((), &frame);
+        assert_eq!(rec.update(&frame), RecResult::Continuing);
+        assert_eq!(rec.update(&frame), RecResult::Succeeded(vec![1, 2]));
+
+        let stages: Vec<Box<Recognizer<In=(), Out=()>>> = vec![
+            Box::new(Always),
+            Box::new(Always.filter_outcome(|_| false)),
+        ];
+        let mut rec = Sequence::new(stages);
+        rec.init((), &frame);
+        assert_eq!(rec.update(&frame), RecResult::Continuing);
+        assert_eq!(rec.update(&frame), RecResult::Failed);
+    }
+
+    #[test]
+    fn repeat_until_the_last_one() {
+        let frame = Frame::new();
+        let mut count = 0;
+        let mut rec = Always.map_outcome(move |_| { count += 1; (count, count == 3) }).repeat(3);
+        rec.init((), &frame);
+        assert_eq!(rec.update(&frame), RecResult::Continuing);
+        assert_eq!(rec.update(&frame), RecResult::Continuing);
+        assert_eq!(rec.update(&frame), RecResult::Succeeded(vec![1, 2, 3]));
+
+        let mut rec = Always.map_outcome(|_| ((), false)).repeat(2);
+        rec.init((), &frame);
+        assert_eq!(rec.update(&frame), RecResult::Continuing);
+        assert_eq!(rec.update(&frame), RecResult::Failed);
+    }
+
+    #[test]
+    fn split_input_without_init() {
+        let frame = Frame::new();
+        let mut rec = Always.split_input(|x: u32| (x, ()));
+        assert_eq!(rec.update(&frame), RecResult::Failed);
+    }
+}
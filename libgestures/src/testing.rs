@@ -0,0 +1,286 @@
+//! Synthetic gestures, for testing and benchmarking recognizers without a touch device.
+//!
+//! A [`Synth`](struct.Synth.html) describes a hand: how many fingers it has, where they come down,
+//! how far apart they are, and how shaky they are. It then produces the stream of
+//! [`Event`](../event/enum.Event.html)s for a swipe, a pinch, or a tap with that hand, which can
+//! be fed to a [`Manager`](../manager/struct.Manager.html) in the same way as a recorded
+//! [trace](../trace/index.html).
+//!
+//! The noise comes from a simple pseudo-random number generator with a fixed seed, so the same
+//! `Synth` always produces the same events. To check that a gesture is recognized reliably, try
+//! it with a few different seeds.
+//!
+//! # Examples
+//! ```
+//! use libgestures::geom::{Direction, Point};
+//! use libgestures::gestures::{Gesture, GestureSet};
+//! use libgestures::manager::Manager;
+//! use libgestures::testing::Synth;
+//!
+//! let mut set = GestureSet::new();
+//! set.insert(Gesture::Swipe { num_fingers: 3, direction: Direction::Up });
+//! let mut man = Manager::new();
+//! set.push_to(&mut man);
+//!
+//! let events = Synth::new(3).at(Point::new(100.0, 80.0)).noise(1.0).seed(7)
+//!     .swipe(Direction::Up.to_angle(), 30.0);
+//! let recognized = events.iter().filter_map(|ev| man.update_event(ev)).collect::<Vec<_>>();
+//! assert_eq!(recognized, vec![Gesture::Swipe { num_fingers: 3, direction: Direction::Up }]);
+//! ```
+
+use event::Event;
+use geom::{Angle, Point};
+
+// A xorshift generator. It isn't good for much, but it's plenty for making fingers wobble.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        // Xorshift gets stuck at zero, and takes a while to get going from small seeds.
+        Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    // A number between 0 and 1.
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // A vector whose coordinates are between `-amount` and `amount`.
+    fn jitter(&mut self, amount: f64) -> Point {
+        let x = (2.0 * self.next() - 1.0) * amount;
+        let y = (2.0 * self.next() - 1.0) * amount;
+        Point::new(x, y)
+    }
+}
+
+/// Produces the events for synthetic gestures.
+///
+/// The fingers start out in a horizontal row, centered on the starting point. They come down one
+/// after the other, stay still for a little while (so that the recognizers that wait for the
+/// fingers to settle get a chance to), do the gesture, and then all go up together.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Synth {
+    fingers: usize,
+    center: Point,
+    spacing: f64,
+    frame_ms: u32,
+    settle_ms: u32,
+    hold_ms: u32,
+    speed: f64,
+    noise: f64,
+    seed: u64,
+}
+
+impl Synth {
+    /// Creates a hand with `fingers` fingers, 15 millimeters apart, that come down at
+    /// (100, 100) and move at 150 millimeters per second without any noise. There is a frame every
+    /// 10 milliseconds, the fingers come down one frame apart, and they stay still for 50
+    /// milliseconds before they start moving.
+    pub fn new(fingers: usize) -> Synth {
+        Synth {
+            fingers: fingers,
+            center: Point::new(100.0, 100.0),
+            spacing: 15.0,
+            frame_ms: 10,
+            settle_ms: 10,
+            hold_ms: 50,
+            speed: 150.0,
+            noise: 0.0,
+            seed: 0,
+        }
+    }
+
+    /// Sets where (in millimeters) the middle of the fingers comes down.
+    pub fn at(mut self, center: Point) -> Synth {
+        self.center = center;
+        self
+    }
+
+    /// Sets how far apart (in millimeters) neighboring fingers are.
+    pub fn spacing(mut self, mm: f64) -> Synth {
+        self.spacing = mm;
+        self
+    }
+
+    /// Sets the time (in milliseconds) between frames.
+    pub fn frame_interval(mut self, ms: u32) -> Synth {
+        self.frame_ms = ms.max(1);
+        self
+    }
+
+    /// Sets the time (in milliseconds) between one finger coming down and the next. If this is
+    /// zero, all the fingers come down in the same frame. In between, the fingers that are already
+    /// down keep shaking, if there is any noise.
+    pub fn settle(mut self, ms: u32) -> Synth {
+        self.settle_ms = ms;
+        self
+    }
+
+    /// Sets how long (in milliseconds) the fingers stay still after they all come down.
+    pub fn hold(mut self, ms: u32) -> Synth {
+        self.hold_ms = ms;
+        self
+    }
+
+    /// Sets how fast (in millimeters per second) the fingers move during a swipe or a pinch.
+    pub fn speed(mut self, mm_per_sec: f64) -> Synth {
+        self.speed = mm_per_sec;
+        self
+    }
+
+    /// Sets how far (in millimeters) each finger may stray from where it should be, in each
+    /// direction. Each finger strays by a different amount in each frame.
+    pub fn noise(mut self, mm: f64) -> Synth {
+        self.noise = mm;
+        self
+    }
+
+    /// Sets the seed for the noise.
+    pub fn seed(mut self, seed: u64) -> Synth {
+        self.seed = seed;
+        self
+    }
+
+    /// A swipe of `length` millimeters in the direction `angle`.
+    ///
+    /// As everywhere else, angles go counter-clockwise from the right, so that an angle of 90
+    /// degrees is an upwards swipe.
+    pub fn swipe(&self, angle: Angle, length: f64) -> Vec<Event> {
+        let rad = angle.to_radians();
+        // The y coordinate increases downwards.
+        let dir = Point::new(rad.cos(), -rad.sin());
+        self.gesture(length / self.speed, &|t| (dir * (length * t), 1.0))
+    }
+
+    /// A pinch, in which the distances between the fingers grow by a factor of `scale` (so a
+    /// `scale` of less than one is a grab). `rate` is the change in scale per second; for example,
+    /// with a `rate` of 2, a pinch with a `scale` of 1.5 takes a quarter of a second.
+    pub fn pinch(&self, scale: f64, rate: f64) -> Vec<Event> {
+        self.gesture((scale - 1.0).abs() / rate, &|t| (Point::zero(), 1.0 + (scale - 1.0) * t))
+    }
+
+    /// A tap, in which the fingers stay down for `ms` milliseconds without moving (apart from the
+    /// noise).
+    pub fn tap(&self, ms: u32) -> Vec<Event> {
+        let held = Synth { hold_ms: ms, ..self.clone() };
+        held.gesture(0.0, &|_| (Point::zero(), 1.0))
+    }
+
+    // The position of finger `i` when the hand has moved by `offset` and the spread of the
+    // fingers has changed by a factor of `scale`.
+    fn position(&self, i: usize, offset: Point, scale: f64, rng: &mut Rng) -> Point {
+        let x = (i as f64 - (self.fingers as f64 - 1.0) / 2.0) * self.spacing * scale;
+        let pos = self.center + offset + Point::new(x, 0.0);
+        if self.noise > 0.0 {
+            pos + rng.jitter(self.noise)
+        } else {
+            pos
+        }
+    }
+
+    // Produces the events of a gesture that lasts `secs` seconds. `motion` takes the fraction of
+    // the gesture that is done, and returns how far the hand has moved and how much its spread
+    // has changed.
+    fn gesture(&self, secs: f64, motion: &Fn(f64) -> (Point, f64)) -> Vec<Event> {
+        let mut rng = Rng::new(self.seed);
+        let mut ret = Vec::new();
+        let mut time = 0;
+
+        for i in 0..self.fingers {
+            if i > 0 && self.settle_ms > 0 {
+                ret.push(Event::Frame { time: time });
+                // Wait for the next finger, in frames of at most `frame_ms`.
+                let next = time + self.settle_ms;
+                while time + self.frame_ms < next {
+                    time += self.frame_ms;
+                    if self.noise > 0.0 {
+                        for j in 0..i {
+                            let pos = self.position(j, Point::zero(), 1.0, &mut rng);
+                            ret.push(Event::Motion { slot: j, pos: pos });
+                        }
+                        ret.push(Event::Frame { time: time });
+                    }
+                }
+                time = next;
+            }
+            let pos = self.position(i, Point::zero(), 1.0, &mut rng);
+            ret.push(Event::Down { slot: i, pos: pos });
+        }
+        ret.push(Event::Frame { time: time });
+
+        let moves = (secs * 1000.0 / self.frame_ms as f64).ceil() as u32;
+        let holds = self.hold_ms / self.frame_ms;
+        for step in 0..(holds + moves) {
+            time += self.frame_ms;
+            let done = if step < holds { 0.0 } else { (step - holds + 1) as f64 / moves as f64 };
+            let (offset, scale) = motion(done);
+            // Without noise or movement, nothing changes while the fingers hold still.
+            if step >= holds || self.noise > 0.0 {
+                for i in 0..self.fingers {
+                    let pos = self.position(i, offset, scale, &mut rng);
+                    ret.push(Event::Motion { slot: i, pos: pos });
+                }
+            }
+            ret.push(Event::Frame { time: time });
+        }
+
+        time += self.frame_ms;
+        for i in 0..self.fingers {
+            ret.push(Event::Up { slot: i });
+        }
+        ret.push(Event::Frame { time: time });
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use event::Event;
+    use geom::{Direction, Point};
+    use super::Synth;
+
+    fn last_positions(events: &[Event], fingers: usize) -> Vec<Point> {
+        let mut ret = vec![Point::zero(); fingers];
+        for ev in events {
+            match *ev {
+                Event::Down { slot, pos } | Event::Motion { slot, pos } => ret[slot] = pos,
+                _ => {},
+            }
+        }
+        ret
+    }
+
+    #[test]
+    fn swipe_ends_in_the_right_place() {
+        let events = Synth::new(2).at(Point::new(50.0, 50.0)).spacing(10.0)
+            .swipe(Direction::Up.to_angle(), 30.0);
+        let end = last_positions(&events, 2);
+        assert!((end[0] - Point::new(45.0, 20.0)).length() < 1e-6);
+        assert!((end[1] - Point::new(55.0, 20.0)).length() < 1e-6);
+        assert_eq!(events.last(), Some(&Event::Frame { time: 270 }));
+    }
+
+    #[test]
+    fn pinch_scales_the_spread() {
+        let events = Synth::new(2).spacing(10.0).pinch(2.0, 4.0);
+        let end = last_positions(&events, 2);
+        assert!(((end[1] - end[0]).length() - 20.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn noise_is_bounded_and_repeatable() {
+        let synth = Synth::new(3).noise(0.5).seed(3);
+        let events = synth.tap(200);
+        assert_eq!(events, synth.tap(200));
+        assert!(events != synth.clone().seed(4).tap(200));
+        for ev in &events {
+            if let Event::Motion { slot, pos } = *ev {
+                let expected = Point::new(85.0 + 15.0 * slot as f64, 100.0);
+                assert!((pos - expected).x.abs() <= 0.5 && (pos - expected).y.abs() <= 0.5);
+            }
+        }
+    }
+}
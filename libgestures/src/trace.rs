@@ -0,0 +1,134 @@
+//! Reading and writing streams of touch events.
+//!
+//! A trace file is a plain text file with one [`Event`](../event/enum.Event.html) per line:
+//!
+//! ```text
+//! # Comments and blank lines are ignored.
+//! down 0 10.5 20
+//! down 1 30 20.25
+//! frame 1200
+//! motion 0 11 20
+//! frame 1208
+//! up 0
+//! up 1
+//! frame 1230
+//! ```
+//!
+//! Positions are in millimeters, and frame times are in milliseconds. Devices that know how big
+//! their contacts are also have lines like `shape 0 9.5 0.25`, which give a contact's size (in
+//! millimeters) and pressure (from 0 to 1).
+//!
+//! A `frame` line can be followed by a line like `check 5d4c3b2a`, which is the
+//! [`checksum`](fn.checksum.html) of the frame's events. Traces that are recorded with
+//! [`write_frame`](fn.write_frame.html) have one after every frame, so that a trace that was
+//! edited by hand (or damaged) can't silently replay differently from the way it was recorded.
+//! Traces without them can still be read.
+
+use event::Event;
+use std::io;
+use std::io::{BufRead, Write};
+
+/// Computes the checksum of the events in a frame (including the `frame` event at the end).
+///
+/// This is the 32-bit FNV-1a hash of the frame's lines in the trace file.
+pub fn checksum(frame: &[Event]) -> u32 {
+    let mut hash = 0x811c_9dc5u32;
+    for ev in frame {
+        for b in format!("{}\n", ev).bytes() {
+            hash = (hash ^ b as u32).wrapping_mul(0x0100_0193);
+        }
+    }
+    hash
+}
+
+/// Reads all of the events in a trace, checking the checksums of the frames that have them.
+pub fn read<R: BufRead>(r: R) -> Result<Vec<Event>, String> {
+    let mut ret = Vec::new();
+    // Where the current frame starts in `ret`.
+    let mut start = 0;
+    // The events of the frame that ended on the previous line, if one did.
+    let mut ended = None;
+    let mut num_frames = 0;
+    for (i, line) in r.lines().enumerate() {
+        let line = line.map_err(|e| format!("error reading line {}: {}", i + 1, e))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with("check ") {
+            let expected = u32::from_str_radix(line["check ".len()..].trim(), 16)
+                .map_err(|e| format!("line {}: bad checksum: {}", i + 1, e))?;
+            let frame = ended.take()
+                .ok_or(format!("line {}: a checksum must come right after a frame", i + 1))?;
+            if checksum(&ret[frame]) != expected {
+                return Err(format!("line {}: frame {} doesn't match its checksum", i + 1,
+                                   num_frames));
+            }
+            continue;
+        }
+        let ev = line.parse().map_err(|e| format!("line {}: {}", i + 1, e))?;
+        ret.push(ev);
+        ended = None;
+        if let Event::Frame { .. } = ev {
+            ended = Some(start..ret.len());
+            start = ret.len();
+            num_frames += 1;
+        }
+    }
+    Ok(ret)
+}
+
+/// Splits a trace into its frames. Each frame ends with a `frame` event.
+///
+/// Events after the last `frame` event (which a trace that was cut short might have) aren't part
+/// of any frame, so they are left out.
+pub fn frames(events: &[Event]) -> Vec<&[Event]> {
+    let mut ret = Vec::new();
+    let mut start = 0;
+    for (i, ev) in events.iter().enumerate() {
+        if let Event::Frame { .. } = *ev {
+            ret.push(&events[start..(i + 1)]);
+            start = i + 1;
+        }
+    }
+    ret
+}
+
+/// Writes a single event to a trace.
+pub fn write_event<W: Write>(w: &mut W, ev: &Event) -> io::Result<()> {
+    writeln!(w, "{}", ev)
+}
+
+/// Writes a whole frame to a trace, followed by its checksum.
+pub fn write_frame<W: Write>(w: &mut W, frame: &[Event]) -> io::Result<()> {
+    for ev in frame {
+        write_event(w, ev)?;
+    }
+    writeln!(w, "check {:08x}", checksum(frame))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use euclid::vec2;
+
+    #[test]
+    fn checksums() {
+        let events = vec![
+            Event::Down { slot: 0, pos: vec2(10.5, 20.0) },
+            Event::Frame { time: 1200 },
+            Event::Motion { slot: 0, pos: vec2(11.0, 20.0) },
+            Event::Frame { time: 1208 },
+            Event::Up { slot: 0 },
+        ];
+        let mut out = Vec::new();
+        for f in frames(&events) {
+            write_frame(&mut out, f).unwrap();
+        }
+        assert_eq!(frames(&events).len(), 2);
+        assert_eq!(read(&out[..]), Ok(events[..4].to_vec()));
+
+        let edited = String::from_utf8(out).unwrap().replace("motion 0 11", "motion 0 12");
+        assert!(read(edited.as_bytes()).unwrap_err().contains("frame 2"));
+    }
+}
@@ -0,0 +1,229 @@
+//! Checks how reliably gestures are recognized when the fingers are shaky.
+//!
+//! Each gesture is made with [synthetic](../libgestures/testing/index.html) fingers, over a grid
+//! of noise amplitudes, speeds, and delays between the fingers coming down, and with several
+//! different seeds for the noise. For each amount of noise, the fraction of the attempts that
+//! were recognized correctly must be at least the minimum in `MIN_RATES`.
+//!
+//! When a change to the recognizers (or to their default parameters) makes one of these tests
+//! fail, the message shows the whole grid. If the change is worth it, lower the minimum; if it
+//! makes things better, raise it, so that the improvement doesn't quietly get lost later.
+
+extern crate libgestures;
+
+use libgestures::event::Event;
+use libgestures::geom::Direction;
+use libgestures::gestures::{Fire, Gesture, GestureSet, PinchDirection};
+use libgestures::manager::Manager;
+use libgestures::testing::Synth;
+
+// How far (in millimeters) each finger may stray, in each direction.
+const NOISE: [f64; 4] = [0.0, 0.25, 0.5, 1.0];
+// How fast (in millimeters per second) the fingers move.
+const SPEEDS: [f64; 3] = [50.0, 150.0, 400.0];
+// How long (in milliseconds) it takes for the next finger to come down.
+const SETTLE: [u32; 3] = [0, 10, 50];
+// The number of seeds to try for each combination of the above.
+const SEEDS: u64 = 10;
+
+// The smallest acceptable recognition rate for each amount of noise in `NOISE`. Shaking while the
+// fingers come down looks like moving before the gesture started, so the long `SETTLE` delays
+// pull these down.
+const MIN_RATES: [f64; 4] = [1.0, 0.95, 0.75, 0.5];
+
+fn manager() -> Manager<Gesture> {
+    let mut set = GestureSet::new();
+    for num_fingers in 2..5u8 {
+        for &direction in &[Direction::Up, Direction::Down, Direction::Left, Direction::Right] {
+            set.insert(Gesture::Swipe { num_fingers, direction });
+        }
+        set.insert(Gesture::Spread { num_fingers });
+        set.insert(Gesture::Grab { num_fingers });
+    }
+    let mut man = Manager::new();
+    set.push_to(&mut man);
+    man
+}
+
+fn recognize(events: &[Event]) -> Vec<Gesture> {
+    let mut man = manager();
+    events.iter().filter_map(|ev| man.update_event(ev)).collect()
+}
+
+// Tries the gesture made by `make` all over the grid, and checks the recognition rates. `make`
+// gets the hand, and the speed to move it at.
+fn check<F>(expected: Gesture, make: F)
+where F: Fn(Synth, f64) -> Vec<Event> {
+    let mut rows = Vec::new();
+    let mut failed = false;
+    for (&noise, &min_rate) in NOISE.iter().zip(MIN_RATES.iter()) {
+        let mut cells = Vec::new();
+        let mut total = 0;
+        for &speed in &SPEEDS {
+            for &settle in &SETTLE {
+                let hits = (0..SEEDS)
+                    .filter(|&seed| {
+                        let synth = Synth::new(expected.num_fingers() as usize)
+                            .noise(noise)
+                            .settle(settle)
+                            .seed(seed);
+                        recognize(&make(synth, speed)) == vec![expected]
+                    })
+                    .count();
+                cells.push(format!("{:3}", hits));
+                total += hits;
+            }
+        }
+        let rate = total as f64 / (SEEDS as usize * SPEEDS.len() * SETTLE.len()) as f64;
+        failed = failed || rate < min_rate;
+        rows.push(format!("noise {:4} mm: {:.2} (min {:.2}) | {}",
+                          noise, rate, min_rate, cells.join(" ")));
+    }
+    assert!(!failed, "recognition rates for {} out of {} per speed and settle delay:\n{}",
+            expected, SEEDS, rows.join("\n"));
+}
+
+#[test]
+fn swipes() {
+    for &direction in &[Direction::Up, Direction::Down, Direction::Left, Direction::Right] {
+        check(Gesture::Swipe { num_fingers: 3, direction },
+              |s, speed| s.speed(speed).swipe(direction.to_angle(), 30.0));
+    }
+}
+
+// The pinches use two fingers far apart, because every finger has to move towards (or away from)
+// the middle, and a finger that starts in the middle can't. With 40 millimeters between them,
+// each finger moves 20 millimeters for every change of 1 in the scale, so these move a bit more
+// than the default `spread_mm` and `grab_mm`.
+#[test]
+fn spreads() {
+    check(Gesture::Spread { num_fingers: 2 }, |s, speed| s.spacing(40.0).pinch(2.2, speed / 20.0));
+}
+
+#[test]
+fn grabs() {
+    check(Gesture::Grab { num_fingers: 2 }, |s, speed| s.spacing(40.0).pinch(0.2, speed / 20.0));
+}
+
+// Pinches get a set of their own, since a pinch with the fingers moving straight at each other is
+// also a grab or a spread.
+#[test]
+fn pinches() {
+    let mut set = GestureSet::new();
+    set.insert(Gesture::Pinch { num_fingers: 2, direction: PinchDirection::In });
+    let mut man = Manager::new();
+    set.push_to(&mut man);
+    for &(scale, direction) in &[(0.5, PinchDirection::In), (1.6, PinchDirection::Out)] {
+        let events = Synth::new(2).spacing(40.0).noise(0.25).pinch(scale, 2.0);
+        let out = events.iter().filter_map(|ev| man.update_event(ev)).collect::<Vec<_>>();
+        assert_eq!(out, vec![Gesture::Pinch { num_fingers: 2, direction }]);
+    }
+}
+
+// The same gestures should be recognized whatever the frame rate, from a sluggish 30 Hz up to a
+// 250 Hz touchscreen.
+#[test]
+fn frame_rates() {
+    let gestures: Vec<(Gesture, Box<Fn(Synth) -> Vec<Event>>)> = vec![
+        (Gesture::Swipe { num_fingers: 3, direction: Direction::Up },
+         Box::new(|s: Synth| s.swipe(Direction::Up.to_angle(), 30.0))),
+        (Gesture::Swipe { num_fingers: 4, direction: Direction::Left },
+         Box::new(|s: Synth| s.speed(400.0).swipe(Direction::Left.to_angle(), 30.0))),
+        (Gesture::Spread { num_fingers: 2 }, Box::new(|s: Synth| s.spacing(40.0).pinch(2.2, 4.0))),
+        (Gesture::Grab { num_fingers: 2 }, Box::new(|s: Synth| s.spacing(40.0).pinch(0.2, 4.0))),
+    ];
+    let mut failures = Vec::new();
+    for &(expected, ref make) in &gestures {
+        for &interval in &[4, 8, 16, 33] {
+            let misses = (0..SEEDS)
+                .filter(|&seed| {
+                    let synth = Synth::new(expected.num_fingers() as usize)
+                        .frame_interval(interval)
+                        .settle(interval)
+                        .noise(0.25)
+                        .seed(seed);
+                    recognize(&make(synth)) != vec![expected]
+                })
+                .count();
+            if misses > 0 {
+                failures.push(format!("{} every {} ms: missed {} out of {}",
+                                      expected, interval, misses, SEEDS));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+// When all the fingers lift in the same frame (as the synthetic ones do), the swipe is recognized
+// on that frame, even though nothing comes after it.
+#[test]
+fn fingers_lift_together() {
+    let events = Synth::new(3).swipe(Direction::Left.to_angle(), 30.0);
+    let lifted = events.iter()
+        .rev()
+        .skip(1)
+        .take_while(|ev| match **ev { Event::Up { .. } => true, _ => false })
+        .count();
+    assert_eq!(lifted, 3);
+    let left = Gesture::Swipe { num_fingers: 3, direction: Direction::Left };
+    assert_eq!(recognize(&events), vec![left]);
+}
+
+// An early swipe is recognized before any of the fingers lift, and the rest of the touch isn't
+// recognized as anything else.
+#[test]
+fn early_swipes() {
+    let mut set = GestureSet::new().early_swipes(true);
+    set.insert(Gesture::Swipe { num_fingers: 3, direction: Direction::Right });
+    set.insert(Gesture::Spread { num_fingers: 3 });
+    let mut man = Manager::new();
+    set.push_to(&mut man);
+
+    let events = Synth::new(3).swipe(Direction::Right.to_angle(), 30.0);
+    let first_up = events.iter()
+        .position(|ev| match *ev { Event::Up { .. } => true, _ => false })
+        .unwrap();
+    let recognized = events.iter()
+        .enumerate()
+        .filter_map(|(i, ev)| man.update_event(ev).map(|g| (i, g)))
+        .collect::<Vec<_>>();
+    assert_eq!(recognized.len(), 1);
+    assert_eq!(recognized[0].1, Gesture::Swipe { num_fingers: 3, direction: Direction::Right });
+    assert!(recognized[0].0 < first_up);
+}
+
+// A swipe that fires continuously reports how far it has gone on the way, and finishes when the
+// fingers lift, at the fraction of a full slide that it travelled.
+#[test]
+fn continuous_swipes() {
+    let mut set = GestureSet::new();
+    set.insert_firing(Gesture::Swipe { num_fingers: 2, direction: Direction::Up },
+                      Fire::Continuously);
+    set.insert(Gesture::Swipe { num_fingers: 2, direction: Direction::Down });
+    let mut man = Manager::new();
+    set.push_to(&mut man);
+
+    let up = Gesture::Swipe { num_fingers: 2, direction: Direction::Up };
+    let events = Synth::new(2).swipe(Direction::Up.to_angle(), 30.0);
+    let recognized = events.iter()
+        .filter_map(|ev| man.update_event_recognized(ev))
+        .collect::<Vec<_>>();
+    let (last, updates) = recognized.split_last().unwrap();
+    assert!(updates.len() > 3);
+    assert!(updates.iter().all(|r| !r.finished && r.value == up));
+    assert!(updates.windows(2).all(|w| w[0].progress <= w[1].progress));
+    assert!(last.finished);
+    assert_eq!(last.value, up);
+    // The default slide is 50 mm.
+    assert!((last.progress - 0.6).abs() < 0.05, "progress {}", last.progress);
+
+    // The other directions still fire on release, without any progress.
+    let down = Gesture::Swipe { num_fingers: 2, direction: Direction::Down };
+    let events = Synth::new(2).swipe(Direction::Down.to_angle(), 30.0);
+    let recognized = events.iter()
+        .filter_map(|ev| man.update_event_recognized(ev))
+        .collect::<Vec<_>>();
+    assert_eq!(recognized.len(), 1);
+    assert_eq!(recognized[0].value, down);
+    assert_eq!(recognized[0].progress, 1.0);
+}
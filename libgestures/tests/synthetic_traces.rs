@@ -0,0 +1,138 @@
+//! Replays the synthetic traces in `tests/synthetic_traces/` and checks that the right gestures
+//! are recognized.
+//!
+//! The traces weren't recorded from a real touchscreen: they were made up to look like one, so
+//! they catch changes to the thresholds or the combinators that break the gestures as we imagine
+//! them, but not the ones that only break on real hardware. Each of them says so, with a
+//! `# source: synthetic` line.
+//!
+//! Each trace says what it should be recognized as, with comment lines like
+//!
+//! ```text
+//! # expect: swipe 3 up
+//! ```
+//!
+//! in the order that the gestures should be recognized. A trace that shouldn't be recognized as
+//! anything says `# expect: nothing`.
+
+extern crate libgestures;
+
+use libgestures::edge::{Edge, EdgeZones};
+use libgestures::event::Event;
+use libgestures::frame::FrameTracker;
+use libgestures::geom::Direction;
+use libgestures::gestures::{Gesture, GestureSet};
+use libgestures::manager::Manager;
+use libgestures::trace;
+use std::fs;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+// The size of the touchscreen that the traces pretend to come from, in millimeters.
+const SURFACE_WIDTH: f64 = 290.0;
+const SURFACE_HEIGHT: f64 = 165.0;
+
+/// Builds a `Manager` that recognizes every gesture that can be bound in the `gestures` daemon,
+/// in the same way that the daemon does.
+fn manager() -> Manager<Gesture> {
+    let mut set = GestureSet::new()
+        .edge_zones(EdgeZones::new(SURFACE_WIDTH, SURFACE_HEIGHT));
+    set.insert(Gesture::EdgeSwipe { edge: Edge::Top });
+    for num_fingers in 1..6u8 {
+        set.insert(Gesture::Swipe { num_fingers, direction: Direction::Up });
+        set.insert(Gesture::Spread { num_fingers });
+        set.insert(Gesture::Grab { num_fingers });
+    }
+    let mut man = Manager::new();
+    set.push_to(&mut man);
+    man
+}
+
+fn expectations(path: &Path) -> Vec<String> {
+    let file = BufReader::new(File::open(path).unwrap());
+    file.lines()
+        .map(|l| l.unwrap())
+        .filter(|l| l.starts_with("# expect:"))
+        .map(|l| l["# expect:".len()..].trim().to_owned())
+        .filter(|e| e != "nothing")
+        .collect()
+}
+
+// Where a trace came from, according to its `# source:` line.
+fn source(path: &Path) -> Option<String> {
+    let file = BufReader::new(File::open(path).unwrap());
+    file.lines()
+        .map(|l| l.unwrap())
+        .find(|l| l.starts_with("# source:"))
+        .map(|l| l["# source:".len()..].trim().to_owned())
+}
+
+fn trace_paths() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("synthetic_traces");
+    let mut paths = fs::read_dir(&dir).unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().map_or(false, |e| e == "trace"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(!paths.is_empty(), "no traces in {:?}", dir);
+    paths
+}
+
+fn read_trace(path: &Path) -> Vec<Event> {
+    trace::read(BufReader::new(File::open(path).unwrap())).unwrap()
+}
+
+#[test]
+fn traces_are_marked_synthetic() {
+    let unmarked = trace_paths().into_iter()
+        .filter(|p| source(p).map_or(true, |s| s != "synthetic"))
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>();
+    assert!(unmarked.is_empty(), "these traces don't say that they are synthetic: {}",
+            unmarked.join(", "));
+}
+
+#[test]
+fn synthetic_traces() {
+    let mut failures = Vec::new();
+    for path in &trace_paths() {
+        let events = read_trace(path);
+        let mut man = manager();
+        let recognized = events.iter()
+            .filter_map(|ev| man.update_event(ev))
+            .map(|g| g.to_string())
+            .collect::<Vec<_>>();
+        let expected = expectations(path);
+        if recognized != expected {
+            failures.push(format!("{}: expected {:?}, recognized {:?}",
+                                  path.display(), expected, recognized));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+// Feeding in the state of the fingers at the end of each frame should give the same results as
+// feeding in the events one by one.
+#[test]
+fn synthetic_traces_as_snapshots() {
+    let mut failures = Vec::new();
+    for path in &trace_paths() {
+        let mut tracker = FrameTracker::new();
+        let mut man = manager();
+        let mut recognized = Vec::new();
+        for ev in &read_trace(path) {
+            tracker.push_event(ev);
+            if let Event::Frame { time } = *ev {
+                let snapshot = &tracker.frame().cur;
+                recognized.extend(man.update_snapshot(snapshot, time).map(|g| g.to_string()));
+            }
+        }
+        let expected = expectations(path);
+        if recognized != expected {
+            failures.push(format!("{}: expected {:?}, recognized {:?}",
+                                  path.display(), expected, recognized));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
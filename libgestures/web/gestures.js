@@ -0,0 +1,252 @@
+// JavaScript bindings for the WebAssembly build of libgestures.
+//
+// Build the library with
+//
+//     cargo build --release --target wasm32-unknown-unknown --no-default-features --features ffi
+//
+// and serve `target/wasm32-unknown-unknown/release/libgestures.wasm` next to this file. Then:
+//
+//     const lib = await Gestures.load('libgestures.wasm');
+//     const rec = lib.recognizer([{ kind: 'swipe', fingers: 3, direction: 'up' }]);
+//     rec.attach(element, g => console.log(g));
+//
+// This talks to the C interface in `include/libgestures.h`, reading and writing its structs
+// directly in the WebAssembly memory. The offsets below are the wasm32 layouts of those structs.
+
+(function (global) {
+    'use strict';
+
+    const KINDS = [
+        'swipe', 'spread', 'grab', 'shake', 'path', 'edge', 'tap', 'hold', 'pinch', 'rotate',
+    ];
+    const DIRECTIONS = ['up', 'down', 'left', 'right'];
+    // Pinches and rotations keep their directions in the same field as swipes.
+    const PINCH_DIRECTIONS = ['in', 'out'];
+    const ROTATE_DIRECTIONS = ['clockwise', 'counterclockwise'];
+    const EDGES = ['top', 'bottom', 'left', 'right'];
+
+    const EVENT_DOWN = 0;
+    const EVENT_UP = 1;
+    const EVENT_MOTION = 2;
+    const EVENT_CANCEL = 3;
+    const EVENT_FRAME = 4;
+
+    // Browsers measure touches in CSS pixels, which are 1/96 of an inch.
+    const MM_PER_PX = 25.4 / 96;
+    // How often (in milliseconds) the manager hears about the time passing while fingers are
+    // down. The browser doesn't send any events while they hold still, and holds (and taps that
+    // wait to see whether they are held) need to know.
+    const TICK_MS = 20;
+
+    // The names of the directions that a kind of gesture can have.
+    function directionsOf(kind) {
+        if (kind === 'pinch') {
+            return PINCH_DIRECTIONS;
+        } else if (kind === 'rotate') {
+            return ROTATE_DIRECTIONS;
+        }
+        return DIRECTIONS;
+    }
+
+    class Gestures {
+        // Fetches and instantiates the WebAssembly module at `url`.
+        static load(url) {
+            return fetch(url)
+                .then(response => response.arrayBuffer())
+                .then(bytes => WebAssembly.instantiate(bytes, {}))
+                .then(result => new Gestures(result.instance.exports));
+        }
+
+        constructor(exports) {
+            this.exports = exports;
+        }
+
+        // A DataView of the module's memory. This has to be made again after the memory grows.
+        view() {
+            return new DataView(this.exports.memory.buffer);
+        }
+
+        writeGesture(ptr, g) {
+            const v = this.view();
+            v.setUint32(ptr, KINDS.indexOf(g.kind), true);
+            v.setUint8(ptr + 4, g.fingers || 0);
+            v.setUint32(ptr + 8, directionsOf(g.kind).indexOf(g.direction), true);
+            v.setUint32(ptr + 12, EDGES.indexOf(g.edge), true);
+            const path = g.path || [];
+            path.slice(0, 4).forEach((d, i) => {
+                v.setUint32(ptr + 16 + 4 * i, DIRECTIONS.indexOf(d), true);
+            });
+            v.setUint32(ptr + 32, path.length, true);
+        }
+
+        readGesture(ptr) {
+            const v = this.view();
+            const g = { kind: KINDS[v.getUint32(ptr, true)] };
+            if (g.kind !== 'edge') {
+                g.fingers = v.getUint8(ptr + 4);
+            }
+            if (g.kind === 'swipe' || g.kind === 'pinch' || g.kind === 'rotate') {
+                g.direction = directionsOf(g.kind)[v.getUint32(ptr + 8, true)];
+            } else if (g.kind === 'edge') {
+                g.edge = EDGES[v.getUint32(ptr + 12, true)];
+            } else if (g.kind === 'path') {
+                g.path = [];
+                for (let i = 0; i < v.getUint32(ptr + 32, true); i++) {
+                    g.path.push(DIRECTIONS[v.getUint32(ptr + 16 + 4 * i, true)]);
+                }
+            }
+            return g;
+        }
+
+        // Creates a recognizer for a list of gestures, which look like the objects passed to the
+        // callback in `Recognizer.attach`. Edge swipes need the size of the surface, in pixels.
+        recognizer(gestures, size) {
+            const e = this.exports;
+            const set = e.gestures_set_new();
+            const g = e.gestures_gesture_new();
+            for (const gesture of gestures) {
+                this.writeGesture(g, gesture);
+                if (e.gestures_set_add(set, g) !== 0) {
+                    throw new Error('not a valid gesture: ' + JSON.stringify(gesture));
+                }
+            }
+            if (size) {
+                e.gestures_set_surface_size(set, size.width * MM_PER_PX, size.height * MM_PER_PX);
+            }
+            const man = e.gestures_manager_new(set);
+            e.gestures_set_free(set);
+            return new Recognizer(this, man, g);
+        }
+    }
+
+    class Recognizer {
+        constructor(lib, man, gesture) {
+            this.lib = lib;
+            this.man = man;
+            this.gesture = gesture;
+            this.event = lib.exports.gestures_event_new();
+            // The slot of each touch, by its identifier.
+            this.slots = new Map();
+            // The interval that ticks while fingers are down.
+            this.timer = null;
+        }
+
+        // Feeds an event to the manager. Returns the gesture that was recognized, if any.
+        send(kind, slot, x, y, time) {
+            const e = this.lib.exports;
+            const v = this.lib.view();
+            v.setUint32(this.event, kind, true);
+            v.setUint32(this.event + 4, slot, true);
+            v.setFloat64(this.event + 8, x * MM_PER_PX, true);
+            v.setFloat64(this.event + 16, y * MM_PER_PX, true);
+            v.setUint32(this.event + 24, time >>> 0, true);
+            e.gestures_manager_update(this.man, this.event);
+            return this.take();
+        }
+
+        // Returns the gesture that was recognized, if any.
+        take() {
+            const e = this.lib.exports;
+            if (e.gestures_manager_take_gesture(this.man, this.gesture)) {
+                return this.lib.readGesture(this.gesture);
+            }
+            return null;
+        }
+
+        // Lets the manager know that it's `time` now, even if nothing moved. Returns the gesture
+        // that was recognized, if any.
+        tick(time) {
+            this.lib.exports.gestures_manager_tick(this.man, time >>> 0);
+            return this.take();
+        }
+
+        isIdle() {
+            return this.lib.exports.gestures_manager_is_idle(this.man) !== 0;
+        }
+
+        freeSlot() {
+            const used = new Set(this.slots.values());
+            let slot = 0;
+            while (used.has(slot)) {
+                slot++;
+            }
+            return slot;
+        }
+
+        // Feeds a browser TouchEvent to the manager, and returns the gesture that was recognized,
+        // if any. Positions are relative to `rect`, a DOMRect.
+        handle(ev, rect) {
+            const time = Math.round(ev.timeStamp);
+            if (ev.type === 'touchcancel') {
+                this.slots.clear();
+                this.send(EVENT_CANCEL, 0, 0, 0, time);
+                return this.send(EVENT_FRAME, 0, 0, 0, time);
+            }
+            let found = null;
+            const remember = g => { found = found || g; };
+            for (const t of Array.from(ev.changedTouches)) {
+                const x = t.clientX - rect.left;
+                const y = t.clientY - rect.top;
+                if (ev.type === 'touchstart') {
+                    const slot = this.freeSlot();
+                    this.slots.set(t.identifier, slot);
+                    remember(this.send(EVENT_DOWN, slot, x, y, time));
+                } else if (this.slots.has(t.identifier)) {
+                    const slot = this.slots.get(t.identifier);
+                    if (ev.type === 'touchmove') {
+                        remember(this.send(EVENT_MOTION, slot, x, y, time));
+                    } else {
+                        this.slots.delete(t.identifier);
+                        remember(this.send(EVENT_UP, slot, x, y, time));
+                    }
+                }
+            }
+            remember(this.send(EVENT_FRAME, 0, 0, 0, time));
+            return found;
+        }
+
+        // Listens to the touches on `element`, calling `callback` with each gesture.
+        attach(element, callback) {
+            // Touch events and `performance.now()` use the same clock.
+            const tick = () => {
+                const g = this.tick(Math.round(performance.now()));
+                if (this.isIdle()) {
+                    this.stopTicking();
+                }
+                if (g) {
+                    callback(g);
+                }
+            };
+            const listener = ev => {
+                ev.preventDefault();
+                const g = this.handle(ev, element.getBoundingClientRect());
+                if (!this.isIdle() && this.timer === null) {
+                    this.timer = setInterval(tick, TICK_MS);
+                }
+                if (g) {
+                    callback(g);
+                }
+            };
+            for (const type of ['touchstart', 'touchmove', 'touchend', 'touchcancel']) {
+                element.addEventListener(type, listener, { passive: false });
+            }
+        }
+
+        stopTicking() {
+            if (this.timer !== null) {
+                clearInterval(this.timer);
+                this.timer = null;
+            }
+        }
+
+        free() {
+            this.stopTicking();
+            const e = this.lib.exports;
+            e.gestures_manager_free(this.man);
+            e.gestures_gesture_free(this.gesture);
+            e.gestures_event_free(this.event);
+        }
+    }
+
+    global.Gestures = Gestures;
+})(this);